@@ -0,0 +1,93 @@
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::atomic::AtomicUsize;
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use sparking_lot_core as slc;
+
+fn addr<T>(x: &'static T) -> *const () {
+    x as *const _ as *const _
+}
+
+/// The calls which don't sleep, where the lot's own overhead is all there is.
+fn uncontended(c: &mut Criterion) {
+    static ADDR: u8 = 0;
+    c.bench_function("unpark_one, nobody parked", |b| {
+        b.iter(|| slc::unpark_one(addr(&ADDR)))
+    });
+    c.bench_function("unpark_all, nobody parked", |b| {
+        b.iter(|| slc::unpark_all(addr(&ADDR)))
+    });
+    c.bench_function("park, not expected", |b| {
+        b.iter(|| unsafe { slc::park(addr(&ADDR), || false) })
+    });
+}
+
+/// Two threads taking turns, so every iteration parks and unparks once each.
+fn ping_pong(c: &mut Criterion) {
+    static TURN: AtomicUsize = AtomicUsize::new(0);
+    static STOP: AtomicBool = AtomicBool::new(false);
+
+    fn wait_for(turn: usize) {
+        while TURN.load(Acquire) != turn {
+            unsafe {
+                slc::park(addr(&TURN), || {
+                    TURN.load(Acquire) != turn && !STOP.load(Acquire)
+                })
+            };
+            if STOP.load(Acquire) {
+                return;
+            }
+        }
+    }
+
+    fn pass(turn: usize) {
+        TURN.store(turn, Release);
+        slc::unpark_one(addr(&TURN));
+    }
+
+    let other = thread::spawn(|| {
+        while !STOP.load(Acquire) {
+            wait_for(1);
+            pass(0);
+        }
+    });
+    c.bench_function("ping-pong", |b| {
+        b.iter(|| {
+            pass(1);
+            wait_for(0);
+        })
+    });
+    STOP.store(true, Release);
+    slc::unpark_all(addr(&TURN));
+    other.join().unwrap();
+}
+
+/// Threads unparking an address nobody is parked on, so they only fight over
+/// its bucket lock. Build with different `SPARKING_LOT_CORE_SPIN` values
+/// to compare how long contended bucket locks should spin.
+fn contended_bucket(c: &mut Criterion) {
+    static ADDR: u8 = 0;
+    static STOP: AtomicBool = AtomicBool::new(false);
+
+    let others: Vec<_> = (0..3)
+        .map(|_| {
+            thread::spawn(|| {
+                while !STOP.load(Acquire) {
+                    slc::unpark_one(addr(&ADDR));
+                }
+            })
+        })
+        .collect();
+    c.bench_function("contended bucket", |b| {
+        b.iter(|| slc::unpark_one(addr(&ADDR)))
+    });
+    STOP.store(true, Release);
+    for other in others {
+        other.join().unwrap();
+    }
+}
+
+criterion_group!(benches, uncontended, ping_pong, contended_bucket);
+criterion_main!(benches);
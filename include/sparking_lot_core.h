@@ -0,0 +1,59 @@
+/* C declarations of the `ffi` feature of sparking-lot-core.
+ *
+ * The functions park and wake threads on addresses, in the same lot as the
+ * Rust API of the crate. See the docs of `sparking_lot_core::ffi` and of the
+ * functions they're named after for the details.
+ */
+#ifndef SPARKING_LOT_CORE_H
+#define SPARKING_LOT_CORE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Called with the bucket of the address locked, with the `context` given
+ * to the park function. It must not call any of these functions, and the
+ * thread only parks if it returns true. A null `expected` always parks.
+ */
+typedef bool (*slc_expected)(void *context);
+
+typedef enum slc_park_result {
+    /* The thread was woken by an unpark function. */
+    SLC_UNPARKED = 0,
+    /* `expected` returned false, so the thread didn't park. */
+    SLC_INVALID = 1,
+    /* The timeout passed before the thread was woken. */
+    SLC_TIMED_OUT = 2,
+} slc_park_result;
+
+/* Parks the current thread on `addr` if `expected(context)` returns true,
+ * and returns whether it parked. */
+bool slc_park(const void *addr, slc_expected expected, void *context);
+
+/* Like `slc_park`, but writes the token of the unpark function which woke
+ * the thread to `token`, unless it's null. */
+bool slc_park_with_token(const void *addr, slc_expected expected, void *context, uintptr_t *token);
+
+/* Like `slc_park_with_token`, but stops waiting after `timeout_ns` nanoseconds.
+ * Only available with `std`, and not with the `freertos` or `zephyr` parkers. */
+slc_park_result slc_park_timeout(const void *addr, slc_expected expected, void *context,
+                                 uint64_t timeout_ns, uintptr_t *token);
+
+/* The unpark functions return how many threads they woke. */
+size_t slc_unpark_one(const void *addr);
+size_t slc_unpark_one_with_token(const void *addr, uintptr_t token);
+size_t slc_unpark_some(const void *addr, size_t count);
+size_t slc_unpark_all(const void *addr);
+
+/* Returns false if no thread is parked on `addr`, without locking. */
+bool slc_has_waiters(const void *addr);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SPARKING_LOT_CORE_H */
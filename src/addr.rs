@@ -0,0 +1,80 @@
+use core::ptr::NonNull;
+
+/// Anything that can be used as the address to park on, so the
+/// functions of this crate can be called without pointer casts.
+///
+/// It's implemented for raw pointers, [`NonNull`], references and
+/// `usize`. Only the address is used: pointers and references are never
+/// dereferenced, their provenance isn't used, and wide pointers are parked
+/// on the address of their data. A `usize` is the address itself, which
+/// is useful for addresses that don't point to anything, e.g. in `loom`
+/// tests. The same address parks the same way, whatever type it's given as.
+///
+/// # Note
+///
+/// A reference parks on what it points to, so passing a reference to a
+/// pointer (`&ptr`) parks on the pointer variable, not on the pointee.
+/// The same goes for `&n` with `n: usize`, except with method syntax:
+/// `(&n).park_addr()` returns `n`, since it resolves to the impl of `usize`.
+///
+/// # Example
+///
+/// ```
+/// use core::ptr::NonNull;
+/// use core::sync::atomic::AtomicBool;
+///
+/// use sparking_lot_core::AsParkAddr;
+///
+/// static READY: AtomicBool = AtomicBool::new(false);
+///
+/// let addr = (&READY).park_addr();
+/// assert_eq!((&READY as *const AtomicBool).park_addr(), addr);
+/// assert_eq!(NonNull::from(&READY).park_addr(), addr);
+/// assert_eq!(addr.park_addr(), addr);
+/// ```
+pub trait AsParkAddr {
+    /// Returns the address to park on.
+    fn park_addr(&self) -> usize;
+}
+
+impl<T: ?Sized> AsParkAddr for *const T {
+    #[inline(always)]
+    fn park_addr(&self) -> usize {
+        self.cast::<()>().addr()
+    }
+}
+
+impl<T: ?Sized> AsParkAddr for *mut T {
+    #[inline(always)]
+    fn park_addr(&self) -> usize {
+        self.cast::<()>().addr()
+    }
+}
+
+impl<T: ?Sized> AsParkAddr for NonNull<T> {
+    #[inline(always)]
+    fn park_addr(&self) -> usize {
+        self.as_ptr().park_addr()
+    }
+}
+
+impl<T: ?Sized> AsParkAddr for &T {
+    #[inline(always)]
+    fn park_addr(&self) -> usize {
+        (*self as *const T).park_addr()
+    }
+}
+
+impl<T: ?Sized> AsParkAddr for &mut T {
+    #[inline(always)]
+    fn park_addr(&self) -> usize {
+        (&**self as *const T).park_addr()
+    }
+}
+
+impl AsParkAddr for usize {
+    #[inline(always)]
+    fn park_addr(&self) -> usize {
+        *self
+    }
+}
@@ -0,0 +1,53 @@
+//! Drop-in replacements for the functions of the [`atomic-wait`] crate,
+//! so code written against it can park in the lot instead, e.g. on
+//! platforms without futexes.
+//!
+//! Like with [`atomic-wait`], [`wait`] may return spuriously, and the wake
+//! functions take pointers, so they can be called after the atomic is gone.
+//! Other code which parks on the same atomic (with the functions of this
+//! crate) shares the queue with them, which only causes spurious wake-ups.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::atomic::{AtomicU32, Ordering::Relaxed};
+//! use std::thread;
+//!
+//! use sparking_lot_core::atomic_wait::{wait, wake_all};
+//!
+//! static STATE: AtomicU32 = AtomicU32::new(0);
+//!
+//! let waiter = thread::spawn(|| {
+//!     while STATE.load(Relaxed) == 0 {
+//!         wait(&STATE, 0);
+//!     }
+//! });
+//! STATE.store(1, Relaxed);
+//! wake_all(&STATE);
+//! waiter.join().unwrap();
+//! ```
+//!
+//! [`atomic-wait`]: https://crates.io/crates/atomic-wait
+
+use core::sync::atomic::AtomicU32;
+
+use crate::{park_if_eq, unpark_all, unpark_one};
+
+/// Blocks the current thread while `atomic` is `value`, until it's woken
+/// by [`wake_one`] or [`wake_all`], or spuriously.
+#[inline]
+pub fn wait(atomic: &AtomicU32, value: u32) {
+    park_if_eq(atomic, value);
+}
+
+/// Wakes one thread blocked in [`wait`] on `atomic`.
+#[inline]
+pub fn wake_one(atomic: *const AtomicU32) {
+    unpark_one(atomic);
+}
+
+/// Wakes every thread blocked in [`wait`] on `atomic`.
+#[inline]
+pub fn wake_all(atomic: *const AtomicU32) {
+    unpark_all(atomic);
+}
@@ -0,0 +1,181 @@
+use core::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+
+#[cfg(not(loom))]
+use core::sync::atomic::AtomicUsize;
+#[cfg(loom)]
+use loom::sync::atomic::AtomicUsize;
+
+use crate::{park, unpark_all};
+
+/// A single-use latch: threads [`wait`](Latch::wait) until it's been
+/// [`counted down`](Latch::count_down) as many times as it was created with.
+///
+/// Unlike a [`Barrier`], the threads which count it down don't have to wait
+/// for it, and once it's released it stays released. It's a word in size
+/// and parks on its own address.
+///
+/// Only available with the `barrier` feature.
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+///
+/// use sparking_lot_core::Latch;
+///
+/// let workers = 4;
+/// let done = Latch::new(workers);
+/// thread::scope(|s| {
+///     for _ in 0..workers {
+///         s.spawn(|| {
+///             // ...
+///             done.count_down();
+///         });
+///     }
+///     done.wait();
+/// });
+/// assert!(done.is_released());
+/// ```
+#[derive(Debug)]
+pub struct Latch {
+    count: AtomicUsize,
+}
+
+impl Latch {
+    /// Creates a latch which is released after `count` calls to
+    /// [`count_down`](Latch::count_down), or right away if it's 0.
+    #[cfg(not(loom))]
+    pub const fn new(count: usize) -> Self {
+        Self {
+            count: AtomicUsize::new(count),
+        }
+    }
+
+    /// Creates a latch which is released after `count` calls to
+    /// [`count_down`](Latch::count_down), or right away if it's 0.
+    #[cfg(loom)]
+    pub fn new(count: usize) -> Self {
+        Self {
+            count: AtomicUsize::new(count),
+        }
+    }
+
+    /// Counts the latch down by one, releasing the waiting threads
+    /// if it reaches 0.
+    ///
+    /// # Panics
+    ///
+    /// If the latch is already released.
+    pub fn count_down(&self) {
+        let count = self
+            .count
+            .fetch_update(AcqRel, Relaxed, |count| count.checked_sub(1))
+            .expect("sparking-lot-core: `Latch` counted down after its release");
+        if count == 1 {
+            unpark_all(&self.count);
+        }
+    }
+
+    /// Blocks the current thread until the latch is released.
+    pub fn wait(&self) {
+        while !self.is_released() {
+            //SAFETY: `expected` only loads an atomic and the address is private
+            unsafe { park(&self.count, || !self.is_released()) };
+        }
+    }
+
+    /// Returns whether the latch is released, without blocking.
+    pub fn is_released(&self) -> bool {
+        self.count.load(Acquire) == 0
+    }
+}
+
+/// A reusable barrier: every round, [`wait`](Barrier::wait) blocks until
+/// as many threads as the barrier was created for have called it.
+///
+/// Rounds are told apart by a generation counter, so threads which start
+/// the next round before the others have woken up from the last one don't
+/// mix them up. It's three words in size and parks on its own address.
+///
+/// Only available with the `barrier` feature.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+/// use std::thread;
+///
+/// use sparking_lot_core::Barrier;
+///
+/// let threads = 4;
+/// let barrier = Barrier::new(threads);
+/// let leaders = AtomicUsize::new(0);
+/// thread::scope(|s| {
+///     for _ in 0..threads {
+///         s.spawn(|| {
+///             for _ in 0..10 {
+///                 if barrier.wait() {
+///                     leaders.fetch_add(1, Relaxed);
+///                 }
+///             }
+///         });
+///     }
+/// });
+/// // one leader per round
+/// assert_eq!(leaders.load(Relaxed), 10);
+/// ```
+#[derive(Debug)]
+pub struct Barrier {
+    threads: usize,
+    /// How many threads have called `wait` this round.
+    arrived: AtomicUsize,
+    /// Bumped when a round ends, which releases its threads.
+    generation: AtomicUsize,
+}
+
+impl Barrier {
+    /// Creates a barrier which releases threads in groups of `threads`.
+    /// A barrier for 0 threads behaves like one for 1 thread.
+    #[cfg(not(loom))]
+    pub const fn new(threads: usize) -> Self {
+        Self {
+            threads: if threads == 0 { 1 } else { threads },
+            arrived: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
+        }
+    }
+
+    /// Creates a barrier which releases threads in groups of `threads`.
+    /// A barrier for 0 threads behaves like one for 1 thread.
+    #[cfg(loom)]
+    pub fn new(threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            arrived: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
+        }
+    }
+
+    /// Blocks the current thread until every thread of this round has
+    /// called it, and returns true in exactly one of them (the last to arrive).
+    pub fn wait(&self) -> bool {
+        // loaded before arriving, so the round can't end in between
+        let generation = self.generation.load(Acquire);
+        if self.arrived.fetch_add(1, AcqRel) + 1 == self.threads {
+            // nobody arrives for the next round until this one ends
+            self.arrived.store(0, Relaxed);
+            self.generation.store(generation.wrapping_add(1), Release);
+            unpark_all(&self.generation);
+            return true;
+        }
+        while self.generation.load(Acquire) == generation {
+            //SAFETY: `expected` only loads an atomic and the address is private
+            unsafe {
+                park(&self.generation, || {
+                    self.generation.load(Acquire) == generation
+                })
+            };
+        }
+        false
+    }
+}
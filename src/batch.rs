@@ -0,0 +1,123 @@
+use crate::{unpark_many, AsParkAddr};
+
+/// As many requests as [`unpark_many`] groups by bucket at once.
+const DEFAULT_CAPACITY: usize = usize::BITS as usize;
+
+/// Wake-ups recorded to be done later, all at once, with [`unpark_many`].
+///
+/// Each request is handled like the unpark function of the same name, but
+/// nothing is woken until the batch is [flushed](UnparkBatch::flush). Then
+/// the requests whose addresses map to the same bucket are handled under
+/// one lock, and the threads are woken after all the buckets are unlocked,
+/// which is cheaper for e.g. schedulers which wake many waiters at once.
+///
+/// The batch holds up to `CAPACITY` requests without allocating, and
+/// recording one more flushes the ones before it. Dropping the batch
+/// flushes it too, so no recorded wake-up is lost.
+///
+/// Since threads aren't woken when the request is recorded, the usual
+/// rule of making `expected` return false before unparking applies to
+/// the flush, which is then enough for every request in the batch.
+///
+/// # Example
+///
+/// ```
+/// use sparking_lot_core::UnparkBatch;
+///
+/// let (a, b) = (0u8, 0u8);
+/// let mut batch = UnparkBatch::<8>::new();
+/// batch.unpark_one(&a as *const u8);
+/// batch.unpark_some(&b as *const u8, 3);
+/// assert_eq!(batch.len(), 2);
+/// // nothing is parked on `a` or `b`
+/// assert_eq!(batch.flush(), 0);
+/// assert!(batch.is_empty());
+/// ```
+pub struct UnparkBatch<const CAPACITY: usize = DEFAULT_CAPACITY> {
+    requests: [(*const (), usize); CAPACITY],
+    len: usize,
+}
+
+impl<const CAPACITY: usize> UnparkBatch<CAPACITY> {
+    const NOT_EMPTY: () = assert!(CAPACITY != 0, "an `UnparkBatch` needs room for a request");
+
+    /// Creates an empty batch.
+    ///
+    /// # Panics
+    ///
+    /// At compile time, if `CAPACITY` is 0.
+    pub const fn new() -> Self {
+        let () = Self::NOT_EMPTY;
+        Self {
+            requests: [(core::ptr::null(), 0); CAPACITY],
+            len: 0,
+        }
+    }
+
+    /// Records waking one thread parked on `addr`, like [`unpark_one`](crate::unpark_one).
+    #[inline]
+    pub fn unpark_one(&mut self, addr: impl AsParkAddr) {
+        self.push(addr.park_addr(), 1);
+    }
+
+    /// Records waking up to `count` threads parked on `addr`,
+    /// like [`unpark_some`](crate::unpark_some).
+    #[inline]
+    pub fn unpark_some(&mut self, addr: impl AsParkAddr, count: usize) {
+        self.push(addr.park_addr(), count);
+    }
+
+    /// Records waking every thread parked on `addr`, like [`unpark_all`](crate::unpark_all).
+    #[inline]
+    pub fn unpark_all(&mut self, addr: impl AsParkAddr) {
+        self.push(addr.park_addr(), usize::MAX);
+    }
+
+    /// Does the recorded wake-ups and returns how many threads were woken.
+    /// The batch is empty afterwards.
+    pub fn flush(&mut self) -> usize {
+        let len = core::mem::take(&mut self.len);
+        unpark_many(&self.requests[..len])
+    }
+
+    /// Returns how many wake-ups are recorded.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no wake-ups are recorded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    fn push(&mut self, addr: usize, count: usize) {
+        if self.len == CAPACITY {
+            self.flush();
+        }
+        self.requests[self.len] = (core::ptr::without_provenance(addr), count);
+        self.len += 1;
+    }
+}
+
+impl<const CAPACITY: usize> Default for UnparkBatch<CAPACITY> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CAPACITY: usize> core::fmt::Debug for UnparkBatch<CAPACITY> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(&self.requests[..self.len]).finish()
+    }
+}
+
+impl<const CAPACITY: usize> Drop for UnparkBatch<CAPACITY> {
+    fn drop(&mut self) {
+        if !self.is_empty() {
+            self.flush();
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! The core API of [`parking_lot_core`], on top of this crate, so lock crates
+//! written against it can try this one by changing an import.
+//!
+//! [`park`], [`unpark_one`], [`unpark_all`] and [`unpark_requeue`] have the
+//! same signatures and semantics as the functions of [`parking_lot_core`],
+//! and the types have the same fields and variants. Keys are addresses,
+//! so they're shared with the other functions of this crate: a thread
+//! parked with [`park`] can be woken by [`crate::unpark_one`] too.
+//!
+//! The differences are:
+//! - [`ParkToken`]s are accepted, but ignored, since only `unpark_filter`
+//!   reads them, and this module doesn't have it.
+//! - [`unpark_all`] with a token other than [`DEFAULT_UNPARK_TOKEN`] wakes
+//!   the threads one at a time, so threads which park meanwhile may be woken too.
+//! - `SpinWait`, `unpark_filter` and the `deadlock` module are missing.
+//!
+//! Only available with the `compat` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::atomic::{AtomicBool, Ordering::{Acquire, Release}};
+//! use std::thread;
+//!
+//! use sparking_lot_core::compat::{
+//!     park, unpark_one, ParkResult, DEFAULT_PARK_TOKEN, DEFAULT_UNPARK_TOKEN,
+//! };
+//!
+//! static READY: AtomicBool = AtomicBool::new(false);
+//! let key = (&READY as *const AtomicBool).addr();
+//!
+//! let waiter = thread::spawn(move || {
+//!     // SAFETY: the callbacks only use an atomic and `key` is private
+//!     unsafe {
+//!         park(key, || !READY.load(Acquire), || {}, |_, _| {}, DEFAULT_PARK_TOKEN, None)
+//!     }
+//! });
+//! READY.store(true, Release);
+//! // SAFETY: the callback doesn't call this crate
+//! unsafe { unpark_one(key, |_| DEFAULT_UNPARK_TOKEN) };
+//! assert!(matches!(
+//!     waiter.join().unwrap(),
+//!     ParkResult::Unparked(DEFAULT_UNPARK_TOKEN) | ParkResult::Invalid
+//! ));
+//! ```
+//!
+//! [`parking_lot_core`]: https://docs.rs/parking_lot_core/0.9
+
+use core::cell::Cell;
+use std::time::Instant;
+
+use crate::{parked_count, parking_lot, unpark_one_with_token};
+
+/// A value passed by a parking thread to the unparkers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParkToken(pub usize);
+
+/// A value passed by an unparker to the threads it wakes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UnparkToken(pub usize);
+
+/// The [`ParkToken`] for parks that don't need one.
+pub const DEFAULT_PARK_TOKEN: ParkToken = ParkToken(0);
+
+/// The [`UnparkToken`] for unparks that don't need one.
+pub const DEFAULT_UNPARK_TOKEN: UnparkToken = UnparkToken(crate::DEFAULT_UNPARK_TOKEN);
+
+/// The result of [`park`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParkResult {
+    /// The thread was woken by an unparker, which passed this token.
+    Unparked(UnparkToken),
+    /// `validate` returned false, so the thread didn't park.
+    Invalid,
+    /// The timeout passed before the thread was woken.
+    TimedOut,
+}
+
+impl ParkResult {
+    /// Returns true if the thread was woken by an unparker.
+    #[inline]
+    pub fn is_unparked(self) -> bool {
+        matches!(self, ParkResult::Unparked(_))
+    }
+}
+
+/// The result of [`unpark_one`] and [`unpark_requeue`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct UnparkResult {
+    /// The number of threads which were woken.
+    pub unparked_threads: usize,
+    /// The number of threads which were requeued.
+    pub requeued_threads: usize,
+    /// Whether threads are still parked on the key.
+    pub have_more_threads: bool,
+    /// Whether the woken thread should be handed the lock, for eventual
+    /// fairness (see [`crate::unpark_one_fair`]). Always false for requeues.
+    pub be_fair: bool,
+}
+
+/// What [`unpark_requeue`] should do, decided by its `validate`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RequeueOp {
+    /// Do nothing, without calling the callback.
+    Abort,
+    /// Wake one thread and requeue the rest.
+    UnparkOneRequeueRest,
+    /// Requeue every thread.
+    RequeueAll,
+    /// Wake one thread and leave the rest.
+    UnparkOne,
+    /// Requeue one thread and leave the rest.
+    RequeueOne,
+}
+
+/// Parks the current thread on `key` if `validate` returns true, until it's
+/// woken or `timeout` passes.
+///
+/// `validate` is called with the bucket of `key` locked, and `before_sleep`
+/// once the thread is queued and the bucket is unlocked, so it can call the
+/// unpark functions (e.g. to unlock a mutex for a condition variable). If
+/// the thread times out, `timed_out` is called with the bucket locked, with
+/// the key the thread was parked on (it may have been requeued) and whether
+/// it was the last thread parked on it.
+///
+/// # Safety
+///
+/// The same as for [`crate::park`](crate::park()), for both `validate` and `timed_out`.
+/// `before_sleep` can't park.
+#[cfg_attr(feature = "watchdog", track_caller)]
+#[inline]
+pub unsafe fn park(
+    key: usize,
+    validate: impl FnOnce() -> bool,
+    before_sleep: impl FnOnce(),
+    timed_out: impl FnOnce(usize, bool),
+    _park_token: ParkToken,
+    timeout: Option<Instant>,
+) -> ParkResult {
+    match parking_lot::park_before_sleep_until(key, validate, before_sleep, timeout, timed_out) {
+        crate::ParkResult::Unparked(token) => ParkResult::Unparked(UnparkToken(token)),
+        crate::ParkResult::Invalid => ParkResult::Invalid,
+        crate::ParkResult::TimedOut => ParkResult::TimedOut,
+    }
+}
+
+/// Wakes the first thread parked on `key`, with the token `callback`
+/// returns. `callback` is called with the bucket of `key` locked, even
+/// if no thread was woken.
+///
+/// # Safety
+///
+/// `callback` has the same restrictions as the `expected` of [`crate::park`](crate::park()).
+#[inline]
+pub unsafe fn unpark_one(
+    key: usize,
+    callback: impl FnOnce(UnparkResult) -> UnparkToken,
+) -> UnparkResult {
+    let mut be_fair = false;
+    let result = crate::unpark_one_fair(key, |result, fair| {
+        be_fair = fair;
+        callback(UnparkResult {
+            unparked_threads: result.unparked,
+            have_more_threads: result.has_more,
+            be_fair: fair,
+            ..UnparkResult::default()
+        })
+        .0
+    });
+    UnparkResult {
+        unparked_threads: result.unparked,
+        have_more_threads: result.has_more,
+        be_fair,
+        ..UnparkResult::default()
+    }
+}
+
+/// Wakes every thread parked on `key` with `unpark_token`, and returns
+/// how many there were.
+///
+/// # Safety
+///
+/// None, it's only `unsafe` like the function it replaces.
+#[inline]
+pub unsafe fn unpark_all(key: usize, unpark_token: UnparkToken) -> usize {
+    if unpark_token == DEFAULT_UNPARK_TOKEN {
+        return crate::unpark_all(key).unparked;
+    }
+    // only the threads which were there to begin with
+    let mut unparked = 0;
+    for _ in 0..parked_count(key) {
+        let result = unpark_one_with_token(key, unpark_token.0);
+        unparked += result.unparked;
+        if !result.has_more {
+            break;
+        }
+    }
+    unparked
+}
+
+/// Calls `validate` with the buckets of `key_from` and `key_to` locked,
+/// and wakes or requeues threads parked on `key_from` as the [`RequeueOp`]
+/// it returns says. Requeued threads are parked on `key_to` from then on.
+///
+/// Unless the op is [`RequeueOp::Abort`], `callback` is called with the
+/// buckets still locked, and returns the token of the woken thread.
+/// [`UnparkResult::have_more_threads`] says if threads are left on `key_from`.
+///
+/// # Safety
+///
+/// `validate` and `callback` have the same restrictions as the `expected`
+/// of [`crate::park`](crate::park()).
+#[inline]
+pub unsafe fn unpark_requeue(
+    key_from: usize,
+    key_to: usize,
+    validate: impl FnOnce() -> RequeueOp,
+    callback: impl FnOnce(RequeueOp, UnparkResult) -> UnparkToken,
+) -> UnparkResult {
+    let op = Cell::new(RequeueOp::Abort);
+    let mut have_more_threads = false;
+    let result = parking_lot::unpark_requeue_with(
+        key_from,
+        key_to,
+        || {
+            op.set(validate());
+            match op.get() {
+                RequeueOp::Abort => (0, 0),
+                RequeueOp::UnparkOneRequeueRest => (1, usize::MAX),
+                RequeueOp::RequeueAll => (0, usize::MAX),
+                RequeueOp::UnparkOne => (1, 0),
+                RequeueOp::RequeueOne => (0, 1),
+            }
+        },
+        |result, has_more| {
+            if op.get() == RequeueOp::Abort {
+                return crate::DEFAULT_UNPARK_TOKEN;
+            }
+            have_more_threads = has_more;
+            callback(
+                op.get(),
+                UnparkResult {
+                    unparked_threads: result.unparked,
+                    requeued_threads: result.requeued,
+                    have_more_threads: has_more,
+                    ..UnparkResult::default()
+                },
+            )
+            .0
+        },
+    );
+    UnparkResult {
+        unparked_threads: result.unparked,
+        requeued_threads: result.requeued,
+        have_more_threads,
+        ..UnparkResult::default()
+    }
+}
@@ -0,0 +1,61 @@
+use core::fmt;
+
+use crate::{parking_lot, ADDRESS_TAG};
+
+/// Writes every address threads (and tasks) are [`parked`](crate::park())
+/// on, and how many of them there are, to `out`, one per line, e.g. to find
+/// out what a hung process is waiting for.
+///
+/// Addresses are written in hex, followed by the number of waiters:
+///
+/// ```text
+/// 0x55d4c2a3e0a8: 3 parked
+/// key 0x2a: 1 parked
+/// 0x55d4c2a3e0b0 (generation 7): 2 parked
+/// ```
+///
+/// Waiters parked with [`park_on_key`](crate::park_on_key) are listed by
+/// their key, and the ones parked with
+/// [`park_versioned`](crate::park_versioned) with their generation, and
+/// the ones parked with [`park_lane`](crate::park_lane) with their lane. The
+/// waiters of [`ParkingLot`](crate::ParkingLot)s aren't included, see
+/// [`ParkingLot::dump`](crate::ParkingLot::dump).
+///
+/// The buckets are locked one at a time, so threads which park or are woken
+/// meanwhile may or may not be included. `out` is called with a bucket
+/// locked, so it can't call the functions of this crate (in debug builds
+/// that panics instead of deadlocking).
+///
+/// Only available with the `debug-introspection` feature.
+///
+/// # Example
+///
+/// ```
+/// let mut out = String::new();
+/// sparking_lot_core::dump(&mut out).unwrap();
+/// assert!(out.is_empty());
+/// ```
+pub fn dump(mut out: impl fmt::Write) -> fmt::Result {
+    parking_lot::dump(|addr, tag, count| write_waiters(&mut out, addr, tag, count))
+}
+
+/// Writes one line of [`dump`].
+pub(crate) fn write_waiters(
+    out: &mut impl fmt::Write,
+    addr: usize,
+    tag: u64,
+    count: usize,
+) -> fmt::Result {
+    match tag >> 32 {
+        _ if tag == ADDRESS_TAG => write!(out, "{addr:#x}")?,
+        // the inverse of `key_parts`
+        1 => {
+            let high = tag as u32 as u64;
+            let low = (addr as u64 ^ high) as u32 as u64;
+            write!(out, "key {:#x}", (high << 32) | low)?
+        }
+        4 => write!(out, "{addr:#x} (lane {})", tag as u8)?,
+        _ => write!(out, "{addr:#x} (generation {})", tag as u32)?,
+    }
+    writeln!(out, ": {count} parked")
+}
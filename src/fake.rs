@@ -6,55 +6,184 @@ pub(super) mod parking_lot {
     use core::ptr;
     use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
     use loom::cell::Cell;
-    use loom::sync::atomic::AtomicBool;
+    use loom::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize};
     use loom::sync::{Mutex, MutexGuard};
     use loom::thread::Thread;
 
+    use crate::{
+        AsParkAddr, LotTag, RequeueResult, UnparkResult, ADDRESS_TAG, DEFAULT_UNPARK_TOKEN,
+    };
+
     struct ThreadData {
         next: Cell<*const ThreadData>,
-        addr: Cell<*const ()>,
+        addr: AtomicUsize,
         parker: Parker,
+        token: Cell<usize>,
+        #[cfg(feature = "async")]
+        waker: Cell<Option<core::task::Waker>>,
+        #[cfg(feature = "async")]
+        notified: AtomicBool,
+        /// Only set for the waiters of `park_any`, see `Group`.
+        group: Cell<*const Group>,
     }
 
     impl ThreadData {
         fn new() -> Self {
             Self {
                 parker: Parker::new(),
-                addr: Cell::new(ptr::null()),
+                addr: AtomicUsize::new(0),
                 next: Cell::new(ptr::null()),
+                token: Cell::new(DEFAULT_UNPARK_TOKEN),
+                #[cfg(feature = "async")]
+                waker: Cell::new(None),
+                #[cfg(feature = "async")]
+                notified: AtomicBool::new(false),
+                group: Cell::new(ptr::null()),
+            }
+        }
+
+        unsafe fn unpark(this: *const Self) {
+            #[cfg(feature = "async")]
+            if let Some(waker) = (*this).waker.take() {
+                (*this).notified.store(true, Release);
+                waker.wake();
+                return;
+            }
+            let group = (*this).group.get();
+            if !group.is_null() {
+                // the group was claimed with `this` in `pop_first`
+                return (*(*group).parker).unpark();
+            }
+            (*this).parker.unpark();
+        }
+    }
+
+    /// Shared by the waiters of one `park_any` call. The first unparker
+    /// to claim it, under the lock of its bucket, wakes the thread. The
+    /// others drop their waiter of it and only count themselves.
+    struct Group {
+        parker: *const Parker,
+        woken: AtomicPtr<ThreadData>,
+        done: AtomicUsize,
+    }
+
+    /// Unlinks the first waiter of `bucket` which can still be woken. The
+    /// waiters of a `park_any` call which was woken through another
+    /// address are dropped from the bucket on the way.
+    ///
+    /// # Safety
+    ///
+    /// - `bucket` must be locked.
+    unsafe fn pop_first(bucket: &Bucket) -> *const ThreadData {
+        loop {
+            let current = bucket.first.get();
+            if current.is_null() {
+                return current;
             }
+            bucket.first.set((*current).next.replace(ptr::null()));
+            if bucket.first.get().is_null() {
+                bucket.last.set(ptr::null());
+            }
+            let group = (*current).group.get();
+            if group.is_null()
+                || (*group)
+                    .woken
+                    .compare_exchange(ptr::null_mut(), current.cast_mut(), Relaxed, Relaxed)
+                    .is_ok()
+            {
+                return current;
+            }
+            // its thread waits for this before it returns
+            (*group).done.fetch_add(1, Release);
+        }
+    }
+
+    /// Moves up to `count` waiters from `bucket` to the end of the list
+    /// from `first` to `last` with `pop_first`, returning how many.
+    ///
+    /// # Safety
+    ///
+    /// - `bucket` must be locked.
+    unsafe fn pop_some(
+        bucket: &Bucket,
+        count: usize,
+        first: &mut *const ThreadData,
+        last: &mut *const ThreadData,
+    ) -> usize {
+        let mut popped = 0;
+        while popped < count {
+            let current = pop_first(bucket);
+            if current.is_null() {
+                break;
+            }
+            if last.is_null() {
+                *first = current;
+            } else {
+                (**last).next.set(current);
+            }
+            *last = current;
+            popped += 1;
         }
+        popped
     }
 
-    fn lock_bucket(addr: *const ()) -> MutexGuard<'static, Bucket> {
+    /// Wakes every thread of a list made by `pop_some`.
+    ///
+    /// # Safety
+    ///
+    /// - the list must have been removed from its buckets.
+    unsafe fn unpark_list(mut current: *const ThreadData) {
+        while !current.is_null() {
+            let node = current;
+            current = (*current).next.get();
+            ThreadData::unpark(node);
+        }
+    }
+
+    fn lock_bucket(addr: usize) -> MutexGuard<'static, Bucket> {
+        lock_tagged(addr, ADDRESS_TAG)
+    }
+
+    fn lock_tagged(addr: usize, tag: u64) -> MutexGuard<'static, Bucket> {
+        bucket_of(addr, tag)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Every address and tag pair has its own bucket.
+    fn bucket_of(addr: usize, tag: u64) -> &'static Mutex<Bucket> {
         const ADDRESS_LIMIT: usize = 64;
         use std::cell::Cell as StdCell;
         use std::sync::atomic::AtomicUsize as StdAtomUsize;
+        /// The address and tag a bucket was assigned to, and the bucket.
+        type Entry = (StdCell<(usize, u64)>, Mutex<Bucket>);
         struct Hashtable {
-            buckets: [(StdCell<*const ()>, Mutex<Bucket>); ADDRESS_LIMIT],
+            // boxed, since the array overflows the stack of `loom` threads
+            buckets: Box<[Entry]>,
             assigned_count: StdAtomUsize,
         }
         loom::lazy_static! {
             static ref HASHTABLE: Hashtable = Hashtable {
                 assigned_count: StdAtomUsize::new(0),
-                buckets: core::array::from_fn(|_| {
+                buckets: (0..ADDRESS_LIMIT).map(|_| {
                     (
-                        StdCell::new(std::ptr::null()),
+                        StdCell::new((0, ADDRESS_TAG)),
                         Mutex::new(
                             Bucket {
                                 first: Cell::new(std::ptr::null()),
                                 last: Cell::new(std::ptr::null()),
+                                fair: Cell::new(false),
                             }
                         ),
                     )
-                })
+                }).collect()
             };
         }
 
         let len = HASHTABLE.assigned_count.load(Relaxed);
         for bucket in &HASHTABLE.buckets[0..len] {
-            if bucket.0.get() == addr {
-                return bucket.1.lock().unwrap();
+            if bucket.0.get() == (addr, tag) {
+                return &bucket.1;
             }
         }
         assert!(
@@ -62,9 +191,9 @@ pub(super) mod parking_lot {
             "can't park on more than {ADDRESS_LIMIT} addresses when doing loom tests"
         );
         let entry = &HASHTABLE.buckets[len];
-        entry.0.set(addr);
+        entry.0.set((addr, tag));
         HASHTABLE.assigned_count.store(len + 1, Relaxed);
-        entry.1.lock().unwrap()
+        &entry.1
     }
 
     #[inline(always)]
@@ -79,15 +208,172 @@ pub(super) mod parking_lot {
         }
     }
 
-    pub(crate) fn park(addr: *const (), expected: impl FnOnce() -> bool) {
-        with_thread_data(|thread_data| {
+    pub(crate) fn park(addr: usize, expected: impl FnOnce() -> bool) -> Option<usize> {
+        park_tagged(addr, ADDRESS_TAG, expected)
+    }
+
+    pub(crate) fn park_tagged(
+        addr: usize,
+        tag: u64,
+        expected: impl FnOnce() -> bool,
+    ) -> Option<usize> {
+        park_queued(addr, tag, expected, |_| (), || true)
+    }
+
+    /// A waiter of `park_with_handle`, which `unpark_handle` unlinks.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) struct Handle(*const ThreadData);
+
+    pub(crate) fn park_with_handle(
+        addr: usize,
+        expected: impl FnOnce() -> bool,
+        queued: impl FnOnce(Handle),
+    ) -> Option<usize> {
+        park_queued(
+            addr,
+            ADDRESS_TAG,
+            expected,
+            |thread_data| queued(Handle(thread_data)),
+            || true,
+        )
+    }
+
+    pub(crate) fn park_before_sleep(
+        addr: usize,
+        expected: impl FnOnce() -> bool,
+        before_sleep: impl FnOnce(),
+    ) -> Option<usize> {
+        park_queued(
+            addr,
+            ADDRESS_TAG,
+            expected,
+            |_| (),
+            || {
+                before_sleep();
+                true
+            },
+        )
+    }
+
+    pub(crate) fn park_then_validate(
+        addr: usize,
+        validate: impl FnOnce() -> bool,
+    ) -> Option<usize> {
+        park_queued(addr, ADDRESS_TAG, || true, |_| (), validate)
+    }
+
+    pub(crate) unsafe fn unpark_handle(handle: Handle, token: usize) -> bool {
+        let thread_data = handle.0;
+        if !unlink(thread_data) {
+            return false;
+        }
+        (*thread_data).token.set(token);
+        ThreadData::unpark(thread_data);
+        true
+    }
+
+    /// Unlinks `thread_data`, unless an unparker already did.
+    unsafe fn unlink(thread_data: *const ThreadData) -> bool {
+        let bucket = loop {
+            let addr = (*thread_data).addr.load(Relaxed);
             let bucket = lock_bucket(addr);
+            if (*thread_data).addr.load(Relaxed) == addr {
+                break bucket;
+            }
+        };
+        let mut current = bucket.first.get();
+        let mut previous = ptr::null::<ThreadData>();
+        while !current.is_null() {
+            let next = (*current).next.get();
+            if current == thread_data {
+                if current == bucket.last.get() {
+                    bucket.last.set(previous);
+                }
+                if previous.is_null() {
+                    bucket.first.set(next);
+                } else {
+                    (*previous).next.set(next);
+                }
+                return true;
+            }
+            previous = current;
+            current = next;
+        }
+        false
+    }
+
+    pub(crate) fn park_any<const N: usize>(
+        addrs: &[usize; N],
+        expected: impl FnOnce() -> bool,
+    ) -> Option<(usize, usize)> {
+        assert!(
+            N != 0,
+            "sparking-lot-core: `park_any` needs at least one address"
+        );
+        let waiters: [ThreadData; N] = core::array::from_fn(|_| ThreadData::new());
+        let group = Group {
+            parker: &waiters[0].parker,
+            woken: AtomicPtr::new(ptr::null_mut()),
+            done: AtomicUsize::new(0),
+        };
+        // addresses are locked in order, so that this can't deadlock
+        let mut sorted = *addrs;
+        sorted.sort_unstable();
+        let mut buckets: Vec<(usize, MutexGuard<'static, Bucket>)> = Vec::new();
+        for addr in sorted {
+            if buckets.last().map(|(locked, _)| *locked) != Some(addr) {
+                buckets.push((addr, lock_bucket(addr)));
+            }
+        }
+        if !expected() {
+            return None;
+        }
+        for (waiter, &addr) in waiters.iter().zip(addrs) {
+            let (_, bucket) = buckets.iter().find(|(locked, _)| *locked == addr).unwrap();
+            waiter.group.set(&group);
+            waiter.addr.store(addr, Relaxed);
+            if bucket.first.get().is_null() {
+                bucket.first.set(waiter);
+            } else {
+                unsafe { (*bucket.last.get()).next.set(waiter) };
+            }
+            bucket.last.set(waiter);
+        }
+        drop(buckets);
+
+        waiters[0].parker.park();
+        let woken = group.woken.load(Relaxed);
+        let (mut index, mut unlinked) = (0, 0);
+        for (idx, waiter) in waiters.iter().enumerate() {
+            if ptr::eq(waiter, woken) {
+                index = idx;
+            } else if !unsafe { unlink(waiter) } {
+                unlinked += 1;
+            }
+        }
+        // the unparkers which unlinked the others may still be using them
+        while group.done.load(Acquire) != unlinked {
+            loom::thread::yield_now();
+        }
+        Some((index, waiters[index].token.get()))
+    }
+
+    fn park_queued(
+        addr: usize,
+        tag: u64,
+        expected: impl FnOnce() -> bool,
+        queued: impl FnOnce(*const ThreadData),
+        sleep_if: impl FnOnce() -> bool,
+    ) -> Option<usize> {
+        with_thread_data(|thread_data| {
+            let bucket = lock_tagged(addr, tag);
             if !expected() {
-                return;
+                return None;
             }
 
             thread_data.next.set(ptr::null());
-            thread_data.addr.set(addr);
+            thread_data.addr.store(addr, Relaxed);
+            thread_data.token.set(DEFAULT_UNPARK_TOKEN);
 
             if bucket.first.get().is_null() {
                 bucket.first.set(thread_data);
@@ -100,102 +386,443 @@ pub(super) mod parking_lot {
                 .set(thread_data);
             }
             bucket.last.set(thread_data);
+            queued(thread_data);
             // not releasing `bucket` lock before parking would deadlock
             drop(bucket);
 
+            // an unparker may have unlinked it first, then it has to wait for it
+            if !sleep_if() && unsafe { unlink(thread_data) } {
+                return None;
+            }
             thread_data.parker.park();
-        });
+            Some(thread_data.token.get())
+        })
     }
 
-    pub(crate) fn unpark_one(addr: *const ()) {
-        let bucket = lock_bucket(addr);
-        let current = bucket.first.get();
+    pub(crate) fn parked_count(addr: usize) -> usize {
+        parked_count_tagged(addr, ADDRESS_TAG)
+    }
+
+    pub(crate) fn may_have_waiters(addr: usize) -> bool {
+        !lock_tagged(addr, ADDRESS_TAG).first.get().is_null()
+    }
+
+    fn parked_count_tagged(addr: usize, tag: u64) -> usize {
+        let bucket = lock_tagged(addr, tag);
+        let mut count = 0;
+        let mut current = bucket.first.get();
+        // every thread in the bucket is parked on `addr`
+        while !current.is_null() {
+            count += 1;
+            current = unsafe { (*current).next.get() };
+        }
+        count
+    }
+
+    pub(crate) fn unpark_one(
+        addr: usize,
+        callback: impl FnOnce(UnparkResult) -> usize,
+    ) -> UnparkResult {
+        unpark_one_in(addr, ADDRESS_TAG, |result, _| callback(result))
+    }
+
+    /// The queue is always FIFO here.
+    pub(crate) fn unpark_one_fifo(addr: usize) -> UnparkResult {
+        unpark_one_tagged(addr, ADDRESS_TAG)
+    }
+
+    pub(crate) fn unpark_one_tagged(addr: usize, tag: u64) -> UnparkResult {
+        unpark_one_in(addr, tag, |_, _| DEFAULT_UNPARK_TOKEN)
+    }
+
+    pub(crate) fn unpark_one_fair(
+        addr: usize,
+        callback: impl FnOnce(UnparkResult, bool) -> usize,
+    ) -> UnparkResult {
+        unpark_one_in(addr, ADDRESS_TAG, |result, bucket| {
+            // every other wake-up is fair, so that tests see both
+            let be_fair = result.unparked != 0 && bucket.fair.replace(!bucket.fair.get());
+            callback(result, be_fair)
+        })
+    }
+
+    pub(crate) fn try_unpark_one(addr: usize) -> Option<UnparkResult> {
+        // `loom`'s `try_lock` doesn't poison
+        let bucket = bucket_of(addr, ADDRESS_TAG).try_lock().ok()?;
+        Some(unpark_one_locked(bucket, |_, _| DEFAULT_UNPARK_TOKEN))
+    }
+
+    fn unpark_one_in(
+        addr: usize,
+        tag: u64,
+        callback: impl FnOnce(UnparkResult, &Bucket) -> usize,
+    ) -> UnparkResult {
+        unpark_one_locked(lock_tagged(addr, tag), callback)
+    }
+
+    fn unpark_one_locked(
+        bucket: MutexGuard<'static, Bucket>,
+        callback: impl FnOnce(UnparkResult, &Bucket) -> usize,
+    ) -> UnparkResult {
+        //SAFETY: the bucket is locked
+        let current = unsafe { pop_first(&bucket) };
         if !current.is_null() {
+            let result = UnparkResult {
+                unparked: 1,
+                // every thread left in the bucket is parked on `addr`
+                has_more: !bucket.first.get().is_null(),
+            };
+            let token = callback(result, &bucket);
+            // the thread to wake has been unlinked, release the lock
+            drop(bucket);
+
             /*SAFETY:
              * - sleeping threads can't destroy their ThreadData.
-             * - the bucket is locked, so threads can't be unlinked by others.
              * - `current` isn't null
              */
             unsafe {
-                // fix tail if needed, goes first to deduce `previous`
-                if current == bucket.last.get() {
-                    bucket.last.set(ptr::null());
-                }
-                // remove `current` from the list
-                bucket.first.set((*current).next.get());
-                // the thread to wake has been unlinked, release the lock
-                drop(bucket);
-
-                (*current).parker.unpark();
+                (*current).token.set(token);
+                ThreadData::unpark(current);
             }
+            return result;
         }
+        callback(UnparkResult::default(), &bucket);
+        UnparkResult::default()
     }
 
-    pub(crate) fn unpark_all(addr: *const ()) {
-        let mut current = {
-            let bucket = lock_bucket(addr);
-            //This isn't needed, but it allows detecting errors
-            bucket.last.set(std::ptr::null());
+    pub(crate) fn unpark_all(addr: usize, callback: impl FnOnce(UnparkResult)) -> UnparkResult {
+        unpark_all_in(addr, ADDRESS_TAG, callback)
+    }
 
-            bucket.first.replace(std::ptr::null())
+    pub(crate) fn unpark_all_tagged(addr: usize, tag: u64) -> UnparkResult {
+        unpark_all_in(addr, tag, |_| ())
+    }
+
+    fn unpark_all_in(addr: usize, tag: u64, callback: impl FnOnce(UnparkResult)) -> UnparkResult {
+        let mut first = ptr::null::<ThreadData>();
+        let mut last = ptr::null::<ThreadData>();
+        let bucket = lock_tagged(addr, tag);
+        //SAFETY: the bucket is locked
+        let woken = unsafe { pop_some(&bucket, usize::MAX, &mut first, &mut last) };
+        let result = UnparkResult {
+            unparked: woken,
+            has_more: false,
         };
+        callback(result);
+        drop(bucket);
         /*SAFETY:
          * - sleeping threads can't destroy their ThreadData.
          * - this list was removed from bucket, so we own it.
          */
-        unsafe {
-            while !current.is_null() {
-                let node = current;
-                current = (*current).next.get();
-                (*node).parker.unpark();
+        unsafe { unpark_list(first) };
+        result
+    }
+
+    pub(crate) fn unpark_some(addr: usize, count: usize) -> UnparkResult {
+        if count == 0 {
+            // like the real lot, nothing is locked or looked up
+            return UnparkResult::default();
+        }
+        unpark_some_tagged(addr, ADDRESS_TAG, count, || ())
+    }
+
+    pub(crate) fn unpark_some_release(
+        addr: usize,
+        count: usize,
+        release: impl FnOnce(),
+    ) -> UnparkResult {
+        unpark_some_tagged(addr, ADDRESS_TAG, count, release)
+    }
+
+    fn unpark_some_tagged(
+        addr: usize,
+        tag: u64,
+        count: usize,
+        release: impl FnOnce(),
+    ) -> UnparkResult {
+        let bucket = lock_tagged(addr, tag);
+        release();
+        if count == 0 {
+            return UnparkResult {
+                unparked: 0,
+                has_more: !bucket.first.get().is_null(),
+            };
+        }
+        let mut first = ptr::null::<ThreadData>();
+        let mut last = ptr::null::<ThreadData>();
+        //SAFETY: the bucket is locked
+        let woken = unsafe { pop_some(&bucket, count, &mut first, &mut last) };
+        let has_more = !bucket.first.get().is_null();
+        drop(bucket);
+        /*SAFETY:
+         * - sleeping threads can't destroy their ThreadData.
+         * - this list was removed from bucket, so we own it.
+         */
+        unsafe { unpark_list(first) };
+        UnparkResult {
+            unparked: woken,
+            has_more,
+        }
+    }
+
+    /// Every address already has its own bucket, so a
+    /// `ParkingLot` only keeps its waiters apart with its own
+    /// tag, whatever its bucket count.
+    pub(crate) struct Lot<const BUCKETS: usize>(LotTag);
+
+    impl<const BUCKETS: usize> Lot<BUCKETS> {
+        pub(crate) const fn new() -> Self {
+            Self(LotTag::new())
+        }
+
+        pub(crate) fn park(&self, addr: usize, expected: impl FnOnce() -> bool) -> Option<usize> {
+            park_tagged(addr, self.0.get(), expected)
+        }
+
+        pub(crate) fn unpark_one(&self, addr: usize) -> UnparkResult {
+            unpark_one_tagged(addr, self.0.get())
+        }
+
+        pub(crate) fn unpark_all(&self, addr: usize) -> UnparkResult {
+            unpark_all_tagged(addr, self.0.get())
+        }
+
+        pub(crate) fn unpark_some(&self, addr: usize, count: usize) -> UnparkResult {
+            if count == 0 {
+                return UnparkResult::default();
             }
+            unpark_some_tagged(addr, self.0.get(), count, || ())
+        }
+
+        pub(crate) fn parked_count(&self, addr: usize) -> usize {
+            parked_count_tagged(addr, self.0.get())
         }
     }
 
-    pub(crate) fn unpark_some(addr: *const (), mut count: usize) {
-        let bucket = lock_bucket(addr);
-        let first = bucket.first.get();
-        let mut current = first;
+    pub(crate) fn unpark_many<A: AsParkAddr>(requests: &[(A, usize)]) -> usize {
+        let mut woken = 0;
+        let mut first = ptr::null::<ThreadData>();
+        let mut last = ptr::null::<ThreadData>();
+        for (i, (addr, _)) in requests.iter().enumerate() {
+            let addr = addr.park_addr();
+            // every address has its own bucket, which is only locked once
+            if requests[..i]
+                .iter()
+                .any(|(other, _)| other.park_addr() == addr)
+            {
+                continue;
+            }
+            let count: usize = requests[i..]
+                .iter()
+                .filter(|(other, _)| other.park_addr() == addr)
+                .fold(0, |sum, &(_, count)| sum.saturating_add(count));
+            let bucket = lock_bucket(addr);
+            //SAFETY: the bucket is locked
+            woken += unsafe { pop_some(&bucket, count, &mut first, &mut last) };
+        }
+
+        //SAFETY: the list was removed from the buckets, so we own it.
+        unsafe { unpark_list(first) };
+        woken
+    }
 
+    pub(crate) fn unpark_requeue(
+        from: usize,
+        to: usize,
+        wake_count: usize,
+        requeue_count: usize,
+    ) -> RequeueResult {
+        // addresses are locked in order, so that this can't deadlock
+        let (from_bucket, to_bucket) = if from < to {
+            let from_bucket = lock_bucket(from);
+            (from_bucket, Some(lock_bucket(to)))
+        } else if from > to {
+            let to_bucket = lock_bucket(to);
+            (lock_bucket(from), Some(to_bucket))
+        } else {
+            (lock_bucket(from), None)
+        };
+        let mut result = RequeueResult::default();
+        let mut first = ptr::null::<ThreadData>();
+        let mut last = ptr::null::<ThreadData>();
         /*SAFETY:
          * - sleeping threads can't destroy their ThreadData.
-         * - the bucket is locked, so threads can't be unlinked by others.
-         * So, if `*const ThreadData` isn't null, then it's safe to dereference.
+         * - the buckets are locked, so threads can't be unlinked by others.
          */
         unsafe {
-            loop {
-                if current.is_null() {
-                    bucket.first.set(std::ptr::null());
-                    break;
+            result.unparked = pop_some(&from_bucket, wake_count, &mut first, &mut last);
+            let mut current = from_bucket.first.get();
+            match &to_bucket {
+                // requeueing to the same address is a no-op, but still counted
+                None => {
+                    let mut rest = current;
+                    while !rest.is_null() && result.requeued < requeue_count {
+                        rest = (*rest).next.get();
+                        result.requeued += 1;
+                    }
                 }
-
-                count -= 1;
-                if count == 0 {
-                    bucket.first.set((*current).next.replace(std::ptr::null()));
-                    break;
+                Some(to_bucket) => {
+                    while !current.is_null() && result.requeued < requeue_count {
+                        let next = (*current).next.get();
+                        (*current).addr.store(to, Relaxed);
+                        (*current).next.set(ptr::null());
+                        if to_bucket.first.get().is_null() {
+                            to_bucket.first.set(current);
+                        } else {
+                            (*to_bucket.last.get()).next.set(current);
+                        }
+                        to_bucket.last.set(current);
+                        current = next;
+                        result.requeued += 1;
+                    }
                 }
-
-                current = (*current).next.get();
+            }
+            from_bucket.first.set(current);
+            if current.is_null() {
+                from_bucket.last.set(ptr::null());
             }
         }
-        drop(bucket);
+        drop(to_bucket);
+        drop(from_bucket);
 
-        current = first;
         /*SAFETY:
          * - sleeping threads can't destroy their ThreadData.
          * - this list was removed from bucket, so we own it.
          */
-        unsafe {
-            while !current.is_null() {
-                let node = current;
-                current = (*current).next.get();
-                (*node).parker.unpark();
+        unsafe { unpark_list(first) };
+        result
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) struct AsyncWaiter {
+        thread_data: ThreadData,
+        registered: Cell<bool>,
+    }
+
+    #[cfg(feature = "async")]
+    unsafe impl Send for AsyncWaiter {}
+    #[cfg(feature = "async")]
+    unsafe impl Sync for AsyncWaiter {}
+
+    #[cfg(feature = "async")]
+    impl AsyncWaiter {
+        pub(crate) fn new() -> Self {
+            Self {
+                thread_data: ThreadData::new(),
+                registered: Cell::new(false),
+            }
+        }
+
+        pub(crate) unsafe fn register(
+            &self,
+            addr: usize,
+            expected: impl FnOnce() -> bool,
+            waker: &core::task::Waker,
+        ) -> bool {
+            let waker = waker.clone();
+            let bucket = lock_bucket(addr);
+            if !expected() {
+                return false;
+            }
+            let thread_data = &self.thread_data;
+            thread_data.next.set(ptr::null());
+            thread_data.addr.store(addr, Relaxed);
+            thread_data.token.set(DEFAULT_UNPARK_TOKEN);
+            thread_data.waker.set(Some(waker));
+            if bucket.first.get().is_null() {
+                bucket.first.set(thread_data);
+            } else {
+                (*bucket.last.get()).next.set(thread_data);
+            }
+            bucket.last.set(thread_data);
+            self.registered.set(true);
+            true
+        }
+
+        pub(crate) fn poll(&self, waker: &core::task::Waker) -> bool {
+            if self.thread_data.notified.load(Acquire) {
+                return true;
+            }
+            match self.lock_if_queued() {
+                Some(bucket) => {
+                    let old = self.thread_data.waker.replace(Some(waker.clone()));
+                    drop(bucket);
+                    drop(old);
+                    false
+                }
+                None => {
+                    self.wait_for_notify();
+                    true
+                }
+            }
+        }
+
+        /// Locks the bucket of the waiter, if it's still queued.
+        fn lock_if_queued(&self) -> Option<MutexGuard<'static, Bucket>> {
+            let thread_data: *const ThreadData = &self.thread_data;
+            loop {
+                let addr = self.thread_data.addr.load(Relaxed);
+                let bucket = lock_bucket(addr);
+                if self.thread_data.addr.load(Relaxed) != addr {
+                    // requeued in the meantime
+                    continue;
+                }
+                let mut current = bucket.first.get();
+                while !current.is_null() {
+                    if current == thread_data {
+                        return Some(bucket);
+                    }
+                    //SAFETY: the bucket is locked
+                    current = unsafe { (*current).next.get() };
+                }
+                return None;
+            }
+        }
+
+        fn wait_for_notify(&self) {
+            while !self.thread_data.notified.load(Acquire) {
+                loom::thread::yield_now();
             }
         }
     }
+
+    #[cfg(feature = "async")]
+    impl Drop for AsyncWaiter {
+        fn drop(&mut self) {
+            if !self.registered.get() || self.thread_data.notified.load(Acquire) {
+                return;
+            }
+            let Some(bucket) = self.lock_if_queued() else {
+                return self.wait_for_notify();
+            };
+            let thread_data: *const ThreadData = &self.thread_data;
+            let mut current = bucket.first.get();
+            let mut previous = ptr::null::<ThreadData>();
+            //SAFETY: the bucket is locked and `thread_data` is in it
+            unsafe {
+                while current != thread_data {
+                    previous = current;
+                    current = (*current).next.get();
+                }
+                let next = (*current).next.get();
+                if current == bucket.last.get() {
+                    bucket.last.set(previous);
+                }
+                if previous.is_null() {
+                    bucket.first.set(next);
+                } else {
+                    (*previous).next.set(next);
+                }
+            }
+            drop(bucket);
+            drop(self.thread_data.waker.take());
+        }
+    }
+
     struct Bucket {
         first: Cell<*const ThreadData>,
         last: Cell<*const ThreadData>,
+        fair: Cell<bool>,
     }
 
     unsafe impl Send for Bucket {}
@@ -0,0 +1,163 @@
+//! `extern "C"` functions, so C and C++ code in the same process can park
+//! its threads in the same lot as Rust code, instead of bringing a wait
+//! queue of its own.
+//!
+//! The declarations are in `include/sparking_lot_core.h`. The functions are
+//! exported from whatever library or executable this crate is linked into
+//! (e.g. a `staticlib` of the Rust components), so only one copy of the
+//! crate in the process can enable this feature.
+//!
+//! Addresses are shared with the Rust API: a C thread parked with
+//! [`slc_park`] is woken by [`unpark_one`](crate::unpark_one) too. The
+//! callbacks are C function pointers with a context pointer, and they have
+//! the same restrictions as `expected` in [`park`](crate::park()). Panics
+//! (e.g. from the debug checks for calls from `expected`) abort the process
+//! instead of unwinding into C.
+//!
+//! Only available with the `ffi` feature.
+
+use core::ffi::c_void;
+
+use crate::park_with_token;
+
+/// The `expected` of the park functions, called with the bucket locked
+/// and the `context` given to them. A null `expected` always parks.
+pub type SlcExpected = Option<unsafe extern "C" fn(context: *mut c_void) -> bool>;
+
+/// The result of [`slc_park_timeout`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlcParkResult {
+    /// The thread was woken by an unpark function.
+    Unparked = 0,
+    /// `expected` returned false, so the thread didn't park.
+    Invalid = 1,
+    /// The timeout passed before the thread was woken.
+    TimedOut = 2,
+}
+
+#[inline(always)]
+fn expected(expected: SlcExpected, context: *mut c_void) -> impl FnOnce() -> bool {
+    move || match expected {
+        //SAFETY: the caller of the park function guarantees it can be called with `context`
+        Some(expected) => unsafe { expected(context) },
+        None => true,
+    }
+}
+
+/// Writes `woken_with` to `token`, unless it's null.
+///
+/// # Safety
+///
+/// `token` must be null or valid for writes.
+#[inline(always)]
+unsafe fn write_token(token: *mut usize, woken_with: usize) {
+    if !token.is_null() {
+        token.write(woken_with);
+    }
+}
+
+/// Parks the current thread on `addr` if `expected(context)` returns true,
+/// and returns whether it parked. See [`park`](crate::park()).
+///
+/// # Safety
+///
+/// The same as for [`park`](crate::park()), and `expected` must be safe to
+/// call with `context`.
+#[no_mangle]
+pub unsafe extern "C" fn slc_park(
+    addr: *const c_void,
+    expected: SlcExpected,
+    context: *mut c_void,
+) -> bool {
+    park_with_token(addr, self::expected(expected, context)).is_some()
+}
+
+/// Like [`slc_park`], but if the thread was woken, the token of the unpark
+/// function which woke it is written to `token`, unless it's null.
+/// See [`park_with_token`].
+///
+/// # Safety
+///
+/// The same as for [`slc_park`], and `token` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn slc_park_with_token(
+    addr: *const c_void,
+    expected: SlcExpected,
+    context: *mut c_void,
+    token: *mut usize,
+) -> bool {
+    match park_with_token(addr, self::expected(expected, context)) {
+        Some(woken_with) => {
+            write_token(token, woken_with);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Like [`slc_park_with_token`], but stops waiting after `timeout_ns`
+/// nanoseconds. See [`park_timeout`](crate::park_timeout).
+///
+/// Only available with `std`, and not with the `freertos`
+/// or `zephyr` parkers.
+///
+/// # Safety
+///
+/// The same as for [`slc_park_with_token`].
+#[cfg(all(feature = "std", not(any(feature = "freertos", feature = "zephyr"))))]
+#[no_mangle]
+pub unsafe extern "C" fn slc_park_timeout(
+    addr: *const c_void,
+    expected: SlcExpected,
+    context: *mut c_void,
+    timeout_ns: u64,
+    token: *mut usize,
+) -> SlcParkResult {
+    use crate::ParkResult;
+
+    let timeout = core::time::Duration::from_nanos(timeout_ns);
+    match crate::park_timeout(addr, self::expected(expected, context), timeout) {
+        ParkResult::Unparked(woken_with) => {
+            write_token(token, woken_with);
+            SlcParkResult::Unparked
+        }
+        ParkResult::Invalid => SlcParkResult::Invalid,
+        ParkResult::TimedOut => SlcParkResult::TimedOut,
+    }
+}
+
+/// Wakes one thread parked on `addr` and returns how many were woken (0 or 1).
+/// See [`unpark_one`](crate::unpark_one).
+#[no_mangle]
+pub extern "C" fn slc_unpark_one(addr: *const c_void) -> usize {
+    crate::unpark_one(addr).unparked
+}
+
+/// Like [`slc_unpark_one`], but the woken thread gets `token`.
+/// See [`unpark_one_with_token`](crate::unpark_one_with_token).
+#[no_mangle]
+pub extern "C" fn slc_unpark_one_with_token(addr: *const c_void, token: usize) -> usize {
+    crate::unpark_one_with_token(addr, token).unparked
+}
+
+/// Wakes at most `count` threads parked on `addr` and returns how many
+/// were woken. See [`unpark_some`](crate::unpark_some).
+#[no_mangle]
+pub extern "C" fn slc_unpark_some(addr: *const c_void, count: usize) -> usize {
+    crate::unpark_some(addr, count).unparked
+}
+
+/// Wakes every thread parked on `addr` and returns how many were woken.
+/// See [`unpark_all`](crate::unpark_all).
+#[no_mangle]
+pub extern "C" fn slc_unpark_all(addr: *const c_void) -> usize {
+    crate::unpark_all(addr).unparked
+}
+
+/// Returns false if no thread is parked on `addr`, without locking.
+/// See [`has_waiters`](crate::has_waiters).
+#[no_mangle]
+pub extern "C" fn slc_has_waiters(addr: *const c_void) -> bool {
+    crate::has_waiters(addr)
+}
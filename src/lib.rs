@@ -1,3 +1,13 @@
+#![cfg_attr(not(any(feature = "std", loom)), no_std)]
+// `memory.atomic.wait32` isn't stable yet, but neither is building with `+atomics`
+#![cfg_attr(
+    all(
+        target_arch = "wasm32",
+        target_feature = "atomics",
+        not(any(loom, feature = "flag-parker", feature = "thread-parker")),
+    ),
+    feature(stdarch_wasm_atomic_wait)
+)]
 #![deny(missing_docs)]
 //! This library provides a low-level API for parking
 //! on addresses.
@@ -8,20 +18,83 @@
 //! can be off-loaded to the parking lot. This allows writing locks that may
 //! even use a single bit. The idea comes from Webkit [`WTF::ParkingLot`],
 //! which in turn was inspired by Linux [`futexes`]. The API provided by this
-//! crate is significantly simpler &mdash; no park/unpark tokens or timeouts
-//! are provided and it also doesn't readjust based on thread count, which
-//! means with large enough thread counts the contention may be worse than
-//! when using other crates.
+//! crate is significantly simpler &mdash; tokens only go one way, from the
+//! unparker to the woken thread (see [`park_with_token`]), parked threads can't
+//! leave any for unparkers to filter on, and timeouts need `std`. The table of
+//! queues also has a fixed size unless the `growable-table` feature is enabled,
+//! which means without it, with large enough thread counts the contention may
+//! be worse than when using other crates.
 //!
 //! The parking lot provides two operations:
 //!
 //! - **Parking** &mdash; pausing a thread and enqueing it in a queue keyed
-//! by an address. This can be done with [`park`].
+//!   by an address. This can be done with [`park`].
 //! - **Unparking** &mdash; unpausing a thread that was queued on an address.
-//! This can be done with [`unpark_one`], [`unpark_some`] and [`unpark_all`].
+//!   This can be done with [`unpark_one`], [`unpark_some`] and [`unpark_all`].
+//!
+//! Addresses can be given as raw pointers, references, [`NonNull`](core::ptr::NonNull)s
+//! or plain `usize`s, see [`AsParkAddr`]. Only the address is used, so the same
+//! address parks the same way whatever type it's given as, and code following
+//! the [strict provenance] rules can pass `ptr.addr()` instead of casting integers
+//! back to pointers.
+//!
+//! The functions share one table of queues with the whole process. A
+//! [`ParkingLot`] has a table of its own, with as many buckets as it's given
+//! at compile time, for code which wants its waiters isolated from everyone else's.
+//! [`WaitQueue`] wraps them into a safe condition variable, [`Semaphore`]
+//! into a counting semaphore and [`Once`] into one-time initialization, for
+//! code which just wants to wait for something without handling addresses.
+//! Code written against the `atomic-wait` crate can switch to the
+//! [`atomic_wait`] module, which has the same functions.
 //!
 //! For more information read the function docs.
 //!
+//! # Wake order
+//!
+//! Threads [`parked`](park) on the same address are woken in the order they
+//! parked (FIFO), by all of [`unpark_one`], [`unpark_some`] and [`unpark_all`].
+//! This is a guarantee which primitives relying on fairness can use. There is no
+//! ordering between threads parked on different addresses. The only exception is
+//! [`unpark_one`] (and its variants) with the `random-wake` feature, which any
+//! crate in the dependency graph can enable. [`unpark_one_fifo`] is FIFO even then,
+//! for primitives which can't work without it.
+//!
+//! # Fairness
+//!
+//! The wake order is part of the public contract, debug builds check it on every
+//! unpark. But a woken thread usually has to compete with running threads for
+//! whatever it waited for, so primitives aren't fair just because the queue is.
+//! Those which want to be can hand off to the woken thread under the bucket lock
+//! with [`unpark_one_with`], always, or with [`unpark_one_fair`], which tells them
+//! when to for eventual fairness, like `parking_lot` does.
+//!
+//! # Allocations
+//!
+//! [`unpark_one`], [`unpark_some`] and [`unpark_all`] never allocate, with any of
+//! the parkers, so they can be called where the allocator can't be used or is too
+//! slow. Parked threads are woken through memory the parking thread already owns.
+//! With `std` the bucket locks are word-sized locks whose waiters sleep on the same
+//! parkers as parked threads, so this relies on those not allocating, which holds
+//! on the same platforms as for [`static-only`](#features).
+//! [`park`] may allocate unless `static-only` is enabled.
+//!
+//! # Synchronization
+//!
+//! The lot provides these happens-before edges, which primitives built on it can
+//! rely on instead of adding their own fences or stronger orderings:
+//!
+//! - Everything before an unpark function happens-before [`park`] returns in the
+//!   threads it wakes.
+//! - `expected`, the `release` closures of [`unpark_one_release`],
+//!   [`unpark_some_release`] and [`unpark_all_release`] and the `callback` of
+//!   [`unpark_one_with`] run with the bucket of their `addr` locked, so for the
+//!   same `addr` each of them happens-before or after every other one. State
+//!   which is only changed in these closures can use `Relaxed` atomics.
+//!
+//! When [`park`] returns because `expected` returned false, the lock above is the
+//! only edge. That's why there is no acquiring variant of [`park`]: what `expected`
+//! reads is already synchronized if it's written in `release`.
+//!
 //! # [`loom`]
 //!
 //! This crate has [`loom 0.7`][`loom`] integrated, which can be enabled with
@@ -55,38 +128,284 @@
 //! > The legacy [`loom`] integration technique has some major drawbacks:
 //! >
 //! > - No more than 2 distinct addresses can be used if you want to properly test the case of
-//! > non-colliding buckets.
+//! >   non-colliding buckets.
 //! > - Requires some extra work to use [`loom`].
 //! > - Dependents of dependents of [`sparking-lot-core`](crate) can't really use loom tests, because
-//! > it can easily become impossible to test the case of non-colliding buckets.
+//! >   it can easily become impossible to test the case of non-colliding buckets.
+//!
+//! # Miri
+//!
+//! When running under [Miri](https://github.com/rust-lang/miri) with the default `std`
+//! feature, a simple lot is used instead: every waiter is kept in one queue behind a
+//! [`std::sync::Mutex`] and blocks on a [`std::sync::Condvar`]. It behaves like the real
+//! one, except that all addresses share the one "bucket", so dependents can run their test
+//! suites with Miri without it checking (or slowing down on) the lot itself. Features which
+//! only change the real lot, such as the parkers, `growable-table`, `watchdog`,
+//! `instrument`, `stats` and `deadlock-detection`, have no effect there.
+//!
+//! # `no_std`
+//!
+//! The queueing logic only needs `core`, so the crate can be used without the
+//! default `std` feature. In that case the buckets are protected by spinlocks,
+//! waiter nodes are kept on the stack of the parking thread instead of in
+//! thread-local storage, and parked threads busy-wait until they are unparked.
+//! To block them instead, use one of the parker features for your target, or
+//! `custom-parker` and install your own parker with `set_raw_parker`.
+//!
+//! # WebAssembly
+//!
+//! On `wasm32` with the `atomics` target feature (which currently needs a
+//! nightly compiler and `-Zbuild-std`), threads are parked with
+//! `memory.atomic.wait32` and unparked with `memory.atomic.notify`, with or
+//! without `std`. Browsers don't allow blocking on the main thread, so it
+//! can't park there. Without `atomics` there's only one thread, so nothing
+//! could unpark a parked thread anyway, and the `flag-parker` is used, which
+//! doesn't need any support for threads from `std`.
+//!
+//! # `panic = "abort"`
+//!
+//! When built with `panic = "abort"`, the unwinding cleanup in [`park`] is
+//! compiled out, since it can never be needed. The `abort-on-panic` feature gives
+//! the same guarantee to the park functions alone: if `expected` (or another
+//! closure of a park function, or the parker) panics, the process is aborted
+//! instead of unwinding out of [`park`], which is useful when [`park`] is called
+//! across FFI boundaries or in drop glue.
+//!
+//! Otherwise a panic in `expected` is simply propagated. The bucket locks don't
+//! poison, so the panicking thread isn't parked and the lot is left exactly as if
+//! it was never called. A panic in the callback of an unpark function (e.g.
+//! [`unpark_one_with`]) is propagated too, after the threads it was going to
+//! wake are woken with [`DEFAULT_UNPARK_TOKEN`], so they aren't left asleep
+//! without being parked on anything. Either way, other threads using the
+//! same bucket are unaffected.
 //!
 //! # Features
 //!
+//! - `std` (default) - enables the [`std`] based parkers and thread-local waiter
+//!   nodes. See [`no_std`](#no_std). On Linux and Android threads are parked with
+//!   `futex(2)` directly, which doesn't allocate, lock or panic, on Windows 8 and later
+//!   with `WaitOnAddress`, and on macOS 11 and iOS 14 or later with `__ulock_wait2`.
+//!   Elsewhere (and on older versions of those) they're parked with a
+//!   [`std::sync::Mutex`] and [`std::sync::Condvar`], except on `wasm`
+//!   (see [WebAssembly](#webassembly)). Contended bucket locks spin for
+//!   `SPARKING_LOT_CORE_SPIN` rounds before sleeping, read at compile time
+//!   (6 by default, at most 16).
+//! - `abort-on-panic` - aborts the process when `expected` (or another closure) panics in a
+//!   park function, instead of propagating the panic. See [`panic = "abort"`](#panic--abort).
+//! - `hardening` - every link of the waiter queues is stored together with an encoded
+//!   copy, which is checked whenever the link is followed. If memory corruption from other
+//!   `unsafe` code changes one without the other, the process is aborted (without `std`,
+//!   it panics) instead of waking or losing arbitrary threads. Costs a word per link.
+//! - `random-wake` - [`unpark_one`] wakes a random thread parked on the address instead
+//!   of the one which parked first, which breaks the [wake order](#wake-order) guarantee
+//!   on purpose. Useful for testing primitives for starvation and for services where the
+//!   wake order is externally observable. The random generators are per bucket and, with
+//!   `std`, randomly seeded. [`unpark_some`] and [`unpark_all`] are unaffected.
+//! - `signal-mask` - adds `park_with_signals_blocked` on Unix, which blocks a set of
+//!   signals while the thread is parked. Implies `std`.
+//! - `watchdog` - adds `set_watchdog`, which reports [`park`] calls that
+//!   held a bucket lock (mostly while running `expected`) for longer than a threshold.
+//!   Since buckets are shared by unrelated addresses, one slow `expected` can stall much
+//!   of the process. Implies `std`.
+//! - `instrument` - adds `set_event_hook`, which reports every thread that parks and wakes
+//!   up and how many threads every unpark call woke, for profiling primitives. Without a
+//!   hook it costs an atomic load per call.
+//! - `stress` - adds `run_stress`, which runs a configurable mix of parks and unparks
+//!   (thread count, address count, unpark ratio, duration) and reports how much got done,
+//!   so features like `more-concurrency` can be compared on the target machine with a
+//!   workload like the application's. Implies `std`.
+//! - `stats` - adds `stats`, which returns counters of parks, failed parks, unparks which
+//!   did or didn't wake a thread, threads woken by `unpark_all` and `unpark_some` and
+//!   bucket lock acquisitions. Costs an atomic increment per call.
+//! - `deadlock-detection` - adds `check_deadlock`, which returns the groups of parked threads
+//!   which wait on each other, through the resources primitives declare with
+//!   `acquire_resource` and `release_resource`. Implies `std`.
+//! - `debug-introspection` - adds `dump`, which writes every address threads are parked
+//!   on and how many of them there are, e.g. to find out what a hung process is waiting for.
+//! - `async` - adds `park_async`, which queues a task's waker on an address instead of
+//!   parking the thread, so async and blocking primitives can share addresses. The unpark
+//!   functions wake both kinds of waiters. Makes every waiter node three words bigger.
+//! - `barrier` - adds `Latch` and `Barrier`, which wait for a number of threads. Unlike
+//!   `WaitQueue` and `Semaphore`, they also work in [`loom`] tests.
+//! - `lock-api` - adds `RawRwLock`, a [`lock_api`] reader-writer lock which parks on the
+//!   lot, for dependents which don't want to write their own.
+//! - `compat` - adds the `compat` module, with the core API of [`parking_lot_core`], for
+//!   lock crates which want to try this one by changing an import. Implies `std`.
+//! - `ffi` - adds the `ffi` module, which exports `extern "C"` functions (`slc_park`,
+//!   `slc_unpark_one`, ...) declared in `include/sparking_lot_core.h`, so C and C++
+//!   code in the same process can park its threads in the same lot.
 //! - `more-concurrency` - increases the number of buckets, which reduces contention,
-//! but requires more memory. This flag is unlikely to produce meaningful results if
-//! thread count is below 100, but it also isn't all that expensive &mdash; in the
-//! worst case it uses 24 extra KiB of RAM (adds ~12 KiB for x86-64).
+//!   but requires more memory. This flag is unlikely to produce meaningful results if
+//!   thread count is below 100, but it also isn't all that expensive &mdash; in the
+//!   worst case it uses 24 extra KiB of RAM (adds ~12 KiB for x86-64).
+//! - `growable-table` - counts the threads which have parked and, once there are more
+//!   than 3 per bucket, replaces the bucket table with one twice as big as needed, like
+//!   `parking_lot` does. The first thread to park also sizes it for
+//!   `available_parallelism`, as if every core ran a thread, so the same build gets a
+//!   fitting table on small and big machines. Makes heavily threaded programs scale past
+//!   the initial table size (which `more-concurrency` and `tiny-footprint` still set), at
+//!   the cost of a thread-local access per [`park`] and allocating the tables, which are
+//!   never freed.
+//!   Implies `std` and can't be combined with `static-only`. Also adds `reserve_buckets`,
+//!   which grows the table to a size chosen at runtime.
+//! - `freertos` - parks tasks with FreeRTOS direct to task notifications (`ulTaskNotifyTake`
+//!   and `xTaskNotifyGive`) instead of the default parker. It calls the C shim of
+//!   [`freertos-rust`], so that has to be linked in. The notification value of a parked
+//!   task is used as a counting semaphore, so it shouldn't be used by other code while
+//!   the task is parked. Unparking works from tasks of any priority.
+//! - `zephyr` - parks threads with Zephyr `k_futex_wait` and `k_futex_wake` instead of
+//!   busy-waiting. Both have to be linkable (they're syscalls, so with `CONFIG_USERSPACE`
+//!   the generated wrappers have to be exported) and `CONFIG_TIMEOUT_64BIT` has to be
+//!   enabled. The futexes are a small set of statics shared by all parked threads.
+//! - `custom-parker` - parked threads block with a `RawParker` the user installs with
+//!   `set_raw_parker` at start-up, instead of busy-waiting. For RTOSes and other targets the
+//!   built-in parkers don't cover. Mutually exclusive with the other parkers and requires
+//!   disabling `std`.
+//! - `single-core` - for single-core `no_std` systems (Arm Cortex-M and RISC-V machine
+//!   mode). Bucket locks mask interrupts instead of spinning, [`park`] sleeps with WFI and
+//!   [`unpark_one`], [`unpark_some`] and [`unpark_all`] can be called from interrupt handlers,
+//!   which makes the lot usable for waiting on interrupts. [`park`] must not be called with
+//!   interrupts disabled. Requires disabling `std`.
+//! - `critical-section` - bucket locks enter a critical section of the [`critical-section`]
+//!   crate instead of spinning, which is smaller and can't deadlock with interrupt handlers
+//!   on single-core targets. A `critical-section` implementation has to be provided, usually
+//!   by the HAL. For single-core targets not supported by `single-core`, with which it can't
+//!   be combined. Requires disabling `std`.
+//! - `node-pool` - when waiter nodes can't be kept in thread-local storage (without `std`,
+//!   with `static-only` or with parkers which are cheap to create), they're borrowed from a
+//!   fixed `static` pool instead of being put on the stack of the parking thread. The pool
+//!   size is read from the `SPARKING_LOT_CORE_NODE_POOL` environment variable at compile
+//!   time and defaults to 16. If all nodes are in use, the stack is used anyway.
+//! - `portable-atomic` - uses [`portable-atomic`] for the atomics of the `no_std` code,
+//!   so targets without native compare and swap (`thumbv6m`, RISC-V without the A extension)
+//!   are supported. See its docs for how to enable it on those targets (e.g. with
+//!   `unsafe-assume-single-core` or `critical-section`).
+//! - `tiny-footprint` - for MCUs with tens of KiB of RAM. Shrinks the bucket table to 4
+//!   buckets, removes the cache line padding between them, leaves out the summary of the
+//!   addresses parked on in each bucket and counts the parked threads in `u16`s. Without
+//!   `std`, a bucket is then a lock word, two pointers and a random generator state (for
+//!   [`unpark_one_fair`]), 16 bytes on 32-bit targets, or 12 with `critical-section` and
+//!   `single-core`, whose locks take no space. With the 4 counts, that's 72 (or 56) bytes
+//!   in total. Debug builds add a word per bucket to check the [wake order](#wake-order),
+//!   and `hardening` doubles the pointers. The diagnostics (`stats`, `instrument`,
+//!   `debug-introspection`, `deadlock-detection` and `watchdog`) are features of their own,
+//!   so none of them are compiled in unless they're enabled. Contention grows quickly with
+//!   thread count, so it's only meant for systems with a handful of threads (fewer than
+//!   65536 parked at once). Mutually exclusive with `more-concurrency`.
+//! - `static-only` - guarantees that the crate never allocates: the bucket table is a
+//!   `static` and waiter nodes always live on the stack of the parking thread, even with `std`.
+//!   Incompatible with `thread-parker`, and with `std` it's only available on platforms where
+//!   [`std::sync::Mutex`] and [`std::sync::Condvar`] don't allocate (Linux, Android, Windows,
+//!   FreeBSD, OpenBSD, DragonFly BSD and Fuchsia).
 //! - `loom-test` - enables better [`loom`] tests. Has no effect without `--cfg loom`.
-//! - `thread-parker` - changes the parking implementation from a [`std::sync::Mutex`]
-//! to a [`std::thread::park`] based one. It may or may not perform better.
+//! - `thread-parker` - changes the parking implementation from the default one
+//!   to a [`std::thread::park`] based one. It may or may not perform better, but it's
+//!   unlikely on Linux, Windows and macOS, which have native parkers. On targets
+//!   where [`std`] has no threads (`wasm` without `atomics`) it falls back to `flag-parker`.
+//! - `flag-parker` - a parker which never uses [`std::thread::current`] or any other thread
+//!   handles: parked threads poll a flag and [yield](std::thread::yield_now) between polls.
+//!   Meant for targets where thread handles are unreliable.
 //!
 //! [`WTF::ParkingLot`]: https://webkit.org/blog/6161/locking-in-webkit/
 //! [`futexes`]: http://man7.org/linux/man-pages/man2/futex.2.html
 //! [`loom`]: https://crates.io/crates/loom/0.7.0
+//! [`freertos-rust`]: https://crates.io/crates/freertos-rust
+//! [`portable-atomic`]: https://crates.io/crates/portable-atomic
+//! [`critical-section`]: https://crates.io/crates/critical-section
 //! [`byte_offset`]: https://doc.rust-lang.org/stable/core/primitive.pointer.html#method.byte_offset
 //! [cast]: https://doc.rust-lang.org/stable/core/primitive.pointer.html#method.cast
 //! [offset]: https://doc.rust-lang.org/stable/core/primitive.pointer.html#method.offset
+//! [strict provenance]: core::ptr#strict-provenance
+//! [`lock_api`]: https://crates.io/crates/lock_api
+//! [`parking_lot_core`]: https://crates.io/crates/parking_lot_core
+
+mod addr;
+pub use addr::AsParkAddr;
 
 #[cfg(not(all(loom, feature = "loom-test")))]
+#[cfg_attr(all(miri, feature = "std", not(loom)), allow(dead_code))]
 mod real;
-#[cfg(not(all(loom, feature = "loom-test")))]
+#[cfg(not(any(
+    all(loom, feature = "loom-test"),
+    all(miri, feature = "std", not(loom))
+)))]
 use real::parking_lot;
 
+#[cfg(all(miri, feature = "std", not(loom)))]
+mod miri;
+#[cfg(all(miri, feature = "std", not(loom)))]
+use miri::parking_lot;
+
 #[cfg(all(loom, feature = "loom-test"))]
 mod fake;
 #[cfg(all(loom, feature = "loom-test"))]
 use fake::parking_lot;
 
+/* Waiters are queued on an address and a tag, and only woken by calls
+ * with both of them. The tag keeps the waiters of pointers, of keys and of
+ * each generation apart, even when their addresses are the same:
+ * - pointers use `ADDRESS_TAG`.
+ * - keys use `1 << 32` and the high half of the key. The high half is
+ *   also folded into the address, which is what the buckets are picked by,
+ *   so keys that only differ in it are spread out where `usize` is 32 bits wide.
+ * - generations use `2 << 32` and the generation.
+ * - `ParkingLot`s use `3 << 32` and their id, in the backends which
+ *   don't give them bucket tables of their own (see `LotTag`).
+ * - lanes use `4 << 32` and the lane.
+ */
+pub(crate) const ADDRESS_TAG: u64 = 0;
+
+/// The address and tag of `key`.
+#[inline(always)]
+pub(crate) const fn key_parts(key: u64) -> (usize, u64) {
+    (((key >> 32) ^ key) as usize, (1 << 32) | (key >> 32))
+}
+
+/// The tag of `addr` in `generation`.
+#[inline(always)]
+pub(crate) const fn generation_tag(generation: u32) -> u64 {
+    (2 << 32) | generation as u64
+}
+
+/// The tag of `lane` of an address.
+#[inline(always)]
+pub(crate) const fn lane_tag(lane: u8) -> u64 {
+    (4 << 32) | lane as u64
+}
+
+/// The tag of the waiters of a `ParkingLot`. Its id is assigned on
+/// first use, so that lots can still be created in `const`s.
+#[cfg(any(
+    all(loom, feature = "loom-test"),
+    all(miri, feature = "std", not(loom))
+))]
+pub(crate) struct LotTag(core::sync::atomic::AtomicU32);
+
+#[cfg(any(
+    all(loom, feature = "loom-test"),
+    all(miri, feature = "std", not(loom))
+))]
+impl LotTag {
+    pub(crate) const fn new() -> Self {
+        Self(core::sync::atomic::AtomicU32::new(0))
+    }
+
+    pub(crate) fn get(&self) -> u64 {
+        use core::sync::atomic::{AtomicU32, Ordering::Relaxed};
+        static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+        let mut id = self.0.load(Relaxed);
+        if id == 0 {
+            let new = NEXT_ID.fetch_add(1, Relaxed);
+            assert!(new != 0, "sparking-lot-core: too many `ParkingLot`s");
+            id = match self.0.compare_exchange(0, new, Relaxed, Relaxed) {
+                Ok(_) => new,
+                Err(id) => id,
+            };
+        }
+        (3 << 32) | id as u64
+    }
+}
+
 /// Parks the current thread on `addr` until notified,
 /// but only if `expected` returns true.
 ///
@@ -94,27 +413,40 @@ use fake::parking_lot;
 ///
 /// # Safety
 /// - `expected` can't call any functions from this [`crate`],
-/// as this may cause deadlocks or panics.
+///   as this may cause deadlocks or panics. In debug builds
+///   with `std`, such calls always panic.
 /// - Using addresses that you don't own is highly discouraged.
-/// This is because if multiple libraries/modules/anything [`park`]
-/// on the same address without knowledge of each other, it
-/// will cause something that from their perspective looks like
-/// spurious wake-ups, which is likely to break code, since [`park`]
-/// guarantees that no spurious wake-ups will happen.
+///   This is because if multiple libraries/modules/anything [`park`]
+///   on the same address without knowledge of each other, it
+///   will cause something that from their perspective looks like
+///   spurious wake-ups, which is likely to break code, since [`park`]
+///   guarantees that no spurious wake-ups will happen.
 ///
 /// # Notes
 ///
 /// - The memory pointed to by `addr` isn't written to,
-/// it isn't read and no references to it are formed.
+///   it isn't read and no references to it are formed.
+///   Only its address is used, never its provenance, so
+///   it works with [strict provenance](core::ptr#strict-provenance).
 /// - `expected` is called under a lock, which could block
-/// other [`park`], [`unpark_one`], [`unpark_some`] or
-/// [`unpark_all`] calls (even with different `addr`). As such,
-/// `expected` should return quickly.
+///   other [`park`], [`unpark_one`], [`unpark_some`] or
+///   [`unpark_all`] calls (even with different `addr`). As such,
+///   `expected` should return quickly.
 /// - This function ensures that if another thread does
-/// something that would cause `expected` to return false
-/// and only then calls [`unpark_one`], [`unpark_some`] or
-/// [`unpark_all`], [`park`] will either be woken
-/// up or will not sleep.
+///   something that would cause `expected` to return false
+///   and only then calls [`unpark_one`], [`unpark_some`] or
+///   [`unpark_all`], [`park`] will either be woken
+///   up or will not sleep.
+/// - It can be called from thread-local destructors, even
+///   after the thread-local data of this crate was destroyed.
+///
+/// # Panics
+///
+/// If `expected` panics, the panic is propagated and the thread isn't
+/// parked (with `abort-on-panic`, the process is aborted instead). The
+/// lot is left as if [`park`] was never called, so other threads (even
+/// ones parked on `addr`) aren't affected, and later calls don't panic.
+/// The same holds for the other park functions.
 ///
 /// [`park`]: crate::park()
 ///
@@ -127,25 +459,548 @@ use fake::parking_lot;
 /// fn wait_for_event() {
 ///     //SAFETY: remember not to park on WAKE_UP in unrelated functions.
 ///     unsafe {
-///         sparking_lot_core::park(&WAKE_UP as *const _ as *const _, || {
+///         sparking_lot_core::park(&WAKE_UP, || WAKE_UP.load(Relaxed) == false)
+///     }
+/// }
+///
+/// fn notify_event_happened() {
+///     //If these lines are reordered park may miss this notification
+///     WAKE_UP.store(true, Relaxed);
+///     sparking_lot_core::unpark_one(&WAKE_UP);
+/// }
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+pub unsafe fn park(addr: impl AsParkAddr, expected: impl FnOnce() -> bool) {
+    parking_lot::park(addr.park_addr(), expected);
+}
+
+/// Parks the current task on `addr` until notified, but only if `expected`
+/// returns true. The async version of [`park`], available with the `async`
+/// feature.
+///
+/// `expected` is called when the future is first polled. If it returns true,
+/// the task is queued on `addr` with the waker of the [`Context`](core::task::Context)
+/// instead of parking the thread, and the future completes once it's woken by
+/// any of the unpark functions. Async and thread waiters on the same address
+/// share a queue, so the [wake order](crate#wake-order) covers both, and an
+/// unpark function doesn't know which kind it wakes.
+///
+/// Dropping the future before it completes removes the task from the queue,
+/// so it doesn't use up a wake-up.
+///
+/// # Safety
+///
+/// Same as [`park`].
+///
+/// # Notes
+///
+/// - The memory pointed to by `addr` isn't written to,
+///   it isn't read and no references to it are formed.
+/// - If the task is woken while the future is being dropped or polled on
+///   another thread, those wait (spinning) until the waker is taken out,
+///   which is only a few instructions.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+/// static WAKE_UP: AtomicBool = AtomicBool::new(false);
+///
+/// async fn wait_for_event() {
+///     //SAFETY: remember not to park on WAKE_UP in unrelated functions.
+///     unsafe {
+///         sparking_lot_core::park_async(&WAKE_UP as *const _ as *const _, || {
 ///             WAKE_UP.load(Relaxed) == false
 ///         })
+///         .await
 ///     }
 /// }
 ///
 /// fn notify_event_happened() {
-///     //If these lines are reordered park may miss this notification
 ///     WAKE_UP.store(true, Relaxed);
-///     sparking_lot_core::unpark_one(&WAKE_UP as *const _ as *const _)
+///     // wakes threads in `park` and tasks in `park_async` alike
+///     sparking_lot_core::unpark_all(&WAKE_UP as *const _ as *const _);
+/// }
+/// ```
+#[cfg(feature = "async")]
+pub unsafe fn park_async<F: FnOnce() -> bool>(addr: impl AsParkAddr, expected: F) -> ParkFuture<F> {
+    ParkFuture::new(addr.park_addr(), expected)
+}
+
+#[cfg(feature = "async")]
+mod park_future;
+#[cfg(feature = "async")]
+pub use park_future::ParkFuture;
+
+/// Returns how many threads (and tasks) are [`parked`](park()) on `addr`.
+///
+/// The count is advisory: threads may park or be woken right after it's
+/// taken, so it's only useful for debugging and heuristics, such as choosing
+/// between [`unpark_one`] and [`unpark_all`]. Decisions which have to be
+/// exact should be made in the callbacks of the unpark functions instead
+/// (see [`UnparkResult`]).
+///
+/// It locks the bucket of `addr` and walks its whole queue, so it's
+/// as expensive as [`unpark_all`], even if nothing is parked.
+///
+/// # Example
+///
+/// ```
+/// use sparking_lot_core::parked_count;
+///
+/// static ADDR: u8 = 0;
+/// assert_eq!(parked_count(&ADDR as *const _ as *const ()), 0);
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn parked_count(addr: impl AsParkAddr) -> usize {
+    parking_lot::parked_count(addr.park_addr())
+}
+
+/// Returns false if no thread (or task) is [`parked`](park()) on `addr`,
+/// without locking anything, so unparkers can skip the unpark call.
+///
+/// It may return true even if nothing is parked on `addr`: the waiters are
+/// counted per bucket rather than per address, threads stay counted until
+/// they return from `park` after being woken, and threads parked in
+/// [`ParkingLot`]s are counted too. A false is reliable though, as long as the
+/// store which makes `expected` return false comes before the call:
+/// a thread which parks concurrently either sees that store, and doesn't
+/// park, or is already counted.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering::Release};
+///
+/// use sparking_lot_core::{has_waiters, unpark_one};
+///
+/// static READY: AtomicBool = AtomicBool::new(false);
+/// let addr = &READY as *const _ as *const ();
+///
+/// READY.store(true, Release);
+/// if has_waiters(addr) {
+///     unpark_one(addr);
 /// }
 /// ```
 #[cfg_attr(not(loom), inline(always))]
 #[cfg_attr(loom, track_caller)]
-pub unsafe fn park(addr: *const (), expected: impl FnOnce() -> bool) {
-    parking_lot::park(addr, expected)
+pub fn has_waiters(addr: impl AsParkAddr) -> bool {
+    parking_lot::may_have_waiters(addr.park_addr())
+}
+
+/// The token passed to threads woken by functions which don't take one.
+pub const DEFAULT_UNPARK_TOKEN: usize = 0;
+
+/// Like [`park`], but returns the token passed by the unparker,
+/// or `None` if `expected` returned false.
+///
+/// The token is [`DEFAULT_UNPARK_TOKEN`] unless the thread was woken by one of:
+///
+/// - [`unpark_one_with_token`], which passes the token it's given.
+/// - [`unpark_one_with`] and [`unpark_one_fair`], which pass the token returned
+///   by their callback.
+/// - [`unpark_handle`], for threads parked with [`park_with_handle`].
+/// - `RawRwLock` (with the `lock-api` feature), which wakes the writer it hands
+///   the lock to with a token of its own, through [`unpark_one_with`].
+///
+/// It's written before the thread is woken, so it can tell the thread what
+/// happened (e.g. that a lock was handed off to it directly) without any
+/// other atomics.
+///
+/// # Safety
+///
+/// The same as for [`park`].
+///
+/// [`park`]: crate::park()
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+pub unsafe fn park_with_token(
+    addr: impl AsParkAddr,
+    expected: impl FnOnce() -> bool,
+) -> Option<usize> {
+    parking_lot::park(addr.park_addr(), expected)
+}
+
+/// Like [`park_with_token`], but calls `before_sleep` once the thread is
+/// queued on `addr` and the bucket is unlocked, right before it sleeps.
+///
+/// Unpark functions for `addr` which come after `expected` wake the thread,
+/// even if they run before `before_sleep` is done, in which case it doesn't
+/// sleep at all. So a condition variable can unlock the user's mutex in
+/// `before_sleep`: a notifier which locks the mutex after that can't miss
+/// the thread, and unlike in `expected`, the bucket isn't held meanwhile.
+///
+/// `before_sleep` can call the unpark functions of this crate (e.g. to wake
+/// a thread waiting for the mutex), but not the park functions.
+///
+/// # Panics
+///
+/// If `expected` or `before_sleep` panics, the panic is propagated (with
+/// `abort-on-panic`, the process is aborted instead). The thread unqueues
+/// itself first, and if an unparker has already picked it, it waits until
+/// that unparker is done with it, so the wake-up is lost with the panic.
+///
+/// # Safety
+///
+/// The same as for [`park`], and `before_sleep` must not park.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use std::sync::{Mutex, MutexGuard};
+/// use std::thread;
+///
+/// use sparking_lot_core::{park_before_sleep, unpark_all};
+///
+/// struct Condvar(u8);
+///
+/// impl Condvar {
+///     fn wait<'a, T>(&self, guard: MutexGuard<'a, T>, mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+///         // SAFETY: owned address, and neither closure calls this crate
+///         unsafe { park_before_sleep(self as *const _ as *const (), || true, || drop(guard)) };
+///         mutex.lock().unwrap()
+///     }
+///
+///     fn notify_all(&self) {
+///         unpark_all(self as *const _ as *const ());
+///     }
+/// }
+///
+/// static READY: Mutex<bool> = Mutex::new(false);
+/// static CONDVAR: Condvar = Condvar(0);
+///
+/// let waiter = thread::spawn(|| {
+///     let mut ready = READY.lock().unwrap();
+///     while !*ready {
+///         ready = CONDVAR.wait(ready, &READY);
+///     }
+/// });
+/// *READY.lock().unwrap() = true;
+/// CONDVAR.notify_all();
+/// waiter.join().unwrap();
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+pub unsafe fn park_before_sleep(
+    addr: impl AsParkAddr,
+    expected: impl FnOnce() -> bool,
+    before_sleep: impl FnOnce(),
+) -> Option<usize> {
+    parking_lot::park_before_sleep(addr.park_addr(), expected, before_sleep)
+}
+
+/// Like [`park_with_token`], but in two phases: the thread is queued on
+/// `addr` without checking anything, and then `validate` is called with
+/// the bucket unlocked. If it returns true, the thread sleeps, otherwise
+/// it unqueues itself and `None` is returned.
+///
+/// Since the thread is already queued while `validate` runs, the usual rule
+/// is enough: if the change which makes `validate` return false comes before
+/// an unpark function for `addr`, either `validate` sees it, or the thread
+/// is woken. Nothing runs under the bucket lock, so a slow `validate`
+/// doesn't hold up other threads using the bucket, but the thread is queued
+/// for a while even when it doesn't sleep, and unparkers can pick it.
+///
+/// If an unparker picks the thread before it unqueues itself, the wake-up
+/// wins, as in [`park_timeout`]: the thread waits for it and returns its
+/// token, even though `validate` returned false. A woken thread shouldn't
+/// drop the wake-up, since it may have been the only one.
+///
+/// `validate` can call the unpark functions of this crate, but not the park
+/// functions.
+///
+/// This takes a closure rather than being split into a `prepare_park` which
+/// returns a handle and a `commit_park` which consumes it. The queued entry
+/// lives in this call's frame (or the thread's own node) and has to stay put
+/// until it's unqueued. A handle could be moved, leaked with
+/// [`mem::forget`](core::mem::forget) or kept across another park. Any of
+/// those would leave a dangling entry in the queue, and none of them can be
+/// ruled out by the type system. With a closure the entry is unqueued on
+/// every way out, including panics.
+///
+/// # Panics
+///
+/// If `validate` panics, it's handled like a panic of the `before_sleep` of
+/// [`park_before_sleep`]: the thread unqueues itself, waiting for an unparker
+/// which already picked it, and the panic is propagated (with
+/// `abort-on-panic`, the process is aborted instead).
+///
+/// # Safety
+///
+/// The same as for [`park`], and `validate` must not park.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicBool;
+/// use core::sync::atomic::Ordering::{Acquire, Release};
+/// use std::thread;
+///
+/// use sparking_lot_core::{park_then_validate, unpark_all};
+///
+/// static READY: AtomicBool = AtomicBool::new(false);
+/// let addr = (&READY as *const AtomicBool).addr();
+///
+/// let waiter = thread::spawn(move || {
+///     while !READY.load(Acquire) {
+///         // SAFETY: only this example parks on `READY`, and `validate` doesn't call this crate
+///         unsafe { park_then_validate(addr, || !READY.load(Acquire)) };
+///     }
+/// });
+/// READY.store(true, Release);
+/// unpark_all(addr);
+/// waiter.join().unwrap();
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+pub unsafe fn park_then_validate(
+    addr: impl AsParkAddr,
+    validate: impl FnOnce() -> bool,
+) -> Option<usize> {
+    parking_lot::park_then_validate(addr.park_addr(), validate)
+}
+
+/// The result of the unpark functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct UnparkResult {
+    /// The number of threads which were woken.
+    pub unparked: usize,
+    /// Whether threads are still parked on the address. Since it's
+    /// determined with the bucket locked, it can only change because
+    /// of other [`park`] or unpark calls. [`unpark_some`] and
+    /// [`ParkingLot::unpark_some`] don't determine it for a `count` of 0,
+    /// which doesn't lock anything, and return false.
+    ///
+    /// [`park`]: crate::park()
+    pub has_more: bool,
+}
+
+/// The result of [`unpark_requeue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RequeueResult {
+    /// The number of threads which were woken.
+    pub unparked: usize,
+    /// The number of threads which were moved to the other address.
+    pub requeued: usize,
+}
+
+/// The error of [`try_unpark_one`] when the bucket of the address is locked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct WouldBlock;
+
+impl core::fmt::Display for WouldBlock {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("the bucket is locked by another thread")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WouldBlock {}
+
+/// The result of [`park_timeout`] and [`park_until`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParkResult {
+    /// The thread was woken by an unpark function, which passed this
+    /// token (see [`unpark_one_with_token`]).
+    Unparked(usize),
+    /// `expected` returned false, so the thread didn't park.
+    Invalid,
+    /// The timeout (or deadline) passed before the thread was woken.
+    TimedOut,
+}
+
+impl ParkResult {
+    /// Returns true if the thread was woken by an unpark function.
+    #[inline]
+    pub fn is_unparked(self) -> bool {
+        matches!(self, ParkResult::Unparked(_))
+    }
+}
+
+/// Like [`park`], but stops waiting once `timeout` passes.
+///
+/// A thread which times out removes itself from the queue of `addr`,
+/// so it isn't woken (or counted) by later unpark functions. If an
+/// unpark function picks the thread right as it times out, the wake-up
+/// wins and [`ParkResult::Unparked`] is returned, so no wake-up is lost.
+/// There are still no spurious wake-ups: before the timeout passes the
+/// thread only returns if it's unparked.
+///
+/// Only available with `std`, and not with the `freertos`
+/// or `zephyr` parkers.
+///
+/// # Safety
+///
+/// The same as for [`park`].
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering::Acquire};
+/// use std::time::Duration;
+///
+/// use sparking_lot_core::{park_timeout, ParkResult};
+///
+/// static LOCKED: AtomicBool = AtomicBool::new(true);
+///
+/// // SAFETY: nothing else parks on `LOCKED`, which is private
+/// let result = unsafe {
+///     park_timeout(&LOCKED as *const _ as *const _, || LOCKED.load(Acquire), Duration::from_millis(10))
+/// };
+/// assert_eq!(result, ParkResult::TimedOut);
+/// ```
+#[cfg(all(
+    feature = "std",
+    not(any(loom, feature = "freertos", feature = "zephyr"))
+))]
+#[inline(always)]
+#[cfg_attr(feature = "watchdog", track_caller)]
+pub unsafe fn park_timeout(
+    addr: impl AsParkAddr,
+    expected: impl FnOnce() -> bool,
+    timeout: core::time::Duration,
+) -> ParkResult {
+    match std::time::Instant::now().checked_add(timeout) {
+        Some(deadline) => parking_lot::park_until(addr.park_addr(), expected, deadline, |_, _| ()),
+        // too far in the future to ever pass
+        None => match parking_lot::park(addr.park_addr(), expected) {
+            Some(token) => ParkResult::Unparked(token),
+            None => ParkResult::Invalid,
+        },
+    }
+}
+
+/// Like [`park_timeout`], but stops waiting once `deadline` passes.
+///
+/// [`Instant`](std::time::Instant) is monotonic, so code which parks in
+/// a loop (e.g. after waking for something that wasn't meant for it) can
+/// keep the same deadline without the retries adding up. The guarantees
+/// are the same as for [`park_timeout`]: there are no spurious wake-ups
+/// before the deadline passes, and [`ParkResult::TimedOut`] is only
+/// returned once it has. If it already has, `expected` is still called,
+/// but the thread returns right away.
+///
+/// Only available with `std`, and not with the `freertos`
+/// or `zephyr` parkers.
+///
+/// # Safety
+///
+/// The same as for [`park`].
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering::Acquire};
+/// use std::time::{Duration, Instant};
+///
+/// use sparking_lot_core::{park_until, ParkResult};
+///
+/// static LOCKED: AtomicBool = AtomicBool::new(true);
+///
+/// let deadline = Instant::now() + Duration::from_millis(10);
+/// // SAFETY: nothing else parks on `LOCKED`, which is private
+/// let result = unsafe {
+///     park_until(&LOCKED as *const _ as *const _, || LOCKED.load(Acquire), deadline)
+/// };
+/// assert_eq!(result, ParkResult::TimedOut);
+/// assert!(Instant::now() >= deadline);
+/// ```
+#[cfg(all(
+    feature = "std",
+    not(any(loom, feature = "freertos", feature = "zephyr"))
+))]
+#[inline(always)]
+#[cfg_attr(feature = "watchdog", track_caller)]
+pub unsafe fn park_until(
+    addr: impl AsParkAddr,
+    expected: impl FnOnce() -> bool,
+    deadline: std::time::Instant,
+) -> ParkResult {
+    parking_lot::park_until(addr.park_addr(), expected, deadline, |_, _| ())
+}
+
+/// Like [`park_until`], but if the thread times out, `timed_out` is called
+/// while it's being removed from the queue, with the address it was
+/// waiting on and whether it was the last thread waiting there.
+///
+/// Like `expected`, `timed_out` runs with the bucket locked, so no thread can
+/// park on or be unparked from the address in the meantime. This lets a
+/// mutex which keeps a "has waiters" bit clear it when its last waiter
+/// gives up, without racing threads which are about to park. The address
+/// differs from `addr` if the thread was moved by [`unpark_requeue`].
+/// `timed_out` isn't called if the thread is woken or doesn't park.
+///
+/// Only available with `std`, and not with the `freertos`
+/// or `zephyr` parkers.
+///
+/// # Safety
+///
+/// The same as for [`park`], and `timed_out` has the same restrictions
+/// as `expected`.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicU8, Ordering::{Acquire, Relaxed}};
+/// use std::time::{Duration, Instant};
+///
+/// use sparking_lot_core::{park_until_with, ParkResult};
+///
+/// const LOCKED: u8 = 1;
+/// const PARKED: u8 = 2;
+/// static STATE: AtomicU8 = AtomicU8::new(LOCKED);
+///
+/// let deadline = Instant::now() + Duration::from_millis(10);
+/// // SAFETY: nothing else parks on `STATE`, which is private,
+/// // and the callbacks only use atomics
+/// let result = unsafe {
+///     park_until_with(
+///         &STATE as *const _ as *const _,
+///         || STATE.fetch_or(PARKED, Relaxed) & LOCKED != 0,
+///         deadline,
+///         |_, was_last| {
+///             if was_last {
+///                 STATE.fetch_and(!PARKED, Relaxed);
+///             }
+///         },
+///     )
+/// };
+/// assert_eq!(result, ParkResult::TimedOut);
+/// assert_eq!(STATE.load(Acquire), LOCKED);
+/// ```
+#[cfg(all(
+    feature = "std",
+    not(any(loom, feature = "freertos", feature = "zephyr"))
+))]
+#[inline(always)]
+#[cfg_attr(feature = "watchdog", track_caller)]
+pub unsafe fn park_until_with(
+    addr: impl AsParkAddr,
+    expected: impl FnOnce() -> bool,
+    deadline: std::time::Instant,
+    timed_out: impl FnOnce(*const (), bool),
+) -> ParkResult {
+    parking_lot::park_until(addr.park_addr(), expected, deadline, |addr, was_last| {
+        timed_out(core::ptr::without_provenance(addr), was_last)
+    })
 }
 
-/// Wakes one thread [`parked`](park()) on `addr`.
+/// Wakes one thread [`parked`](park()) on `addr`, the one
+/// which parked first (see [wake order](crate#wake-order)).
 ///
 /// Should be called after making the `expected` of
 /// the corresponding [`park`] return false.
@@ -153,15 +1008,15 @@ pub unsafe fn park(addr: *const (), expected: impl FnOnce() -> bool) {
 /// # Notes
 ///
 /// - The memory pointed to by `addr` isn't written to,
-/// it isn't read and no references to it are formed.
+///   it isn't read and no references to it are formed.
 /// - If no thread is waiting on `addr`, no thread is
-/// woken, but it still requires locking, so it's not
-/// recommended to call it without reason.
+///   woken, but it still requires locking, so it's not
+///   recommended to call it without reason.
 /// - This function ensures that if it is called after an
-/// effect, that would cause the `expected` of a call to
-/// [`park`] with the same `addr`, [`park`] will either
-/// be woken, or it will not have gone to sleep and
-/// will return.
+///   effect, that would cause the `expected` of a call to
+///   [`park`] with the same `addr`, [`park`] will either
+///   be woken, or it will not have gone to sleep and
+///   will return.
 ///
 /// [`park`]: crate::park()
 ///
@@ -204,52 +1059,310 @@ pub unsafe fn park(addr: *const (), expected: impl FnOnce() -> bool) {
 /// ```
 #[cfg_attr(not(loom), inline(always))]
 #[cfg_attr(loom, track_caller)]
-pub fn unpark_one(addr: *const ()) {
-    parking_lot::unpark_one(addr);
+pub fn unpark_one(addr: impl AsParkAddr) -> UnparkResult {
+    parking_lot::unpark_one(addr.park_addr(), |_| DEFAULT_UNPARK_TOKEN)
 }
 
-/// Wakes at most `count` threads [`parked`](park()) on `addr`.
+/// Like [`unpark_one`], but always wakes the thread which parked first,
+/// even with the `random-wake` feature.
 ///
-/// Should be called after making the `expected` of
-/// the corresponding [`parks`](park()) return false.
+/// Features are unified across the dependency graph, so `random-wake` may
+/// be enabled by any crate in the process. Primitives which rely on the
+/// [wake order](crate#wake-order) for more than fairness, e.g. hand-off
+/// queues where the woken thread has to be the oldest one, can use this to
+/// keep it. Without `random-wake`, it's the same as [`unpark_one`].
 ///
-/// # Notes
+/// # Example
 ///
-/// - The memory pointed to by `addr` isn't written to,
-/// it isn't read and no references to it are formed.
-/// - If no thread is waiting on `addr`, no thread is
-/// woken, but it still requires locking, so it's not
-/// recommended to call it without reason.
-/// - This function ensures that if it is called after an
-/// effect, that would cause the `expected` of a call to
-/// [`park`] with the same `addr`, [`park`] will either
-/// be woken, or it will not have gone to sleep and
-/// will return.
+/// ```
+/// use core::sync::atomic::AtomicBool;
 ///
-/// [`park`]: crate::park()
+/// use sparking_lot_core::unpark_one_fifo;
+///
+/// static QUEUE: AtomicBool = AtomicBool::new(false);
+/// // nobody is parked on it
+/// assert_eq!(unpark_one_fifo(&QUEUE as *const AtomicBool).unparked, 0);
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_one_fifo(addr: impl AsParkAddr) -> UnparkResult {
+    parking_lot::unpark_one_fifo(addr.park_addr())
+}
+
+/// Like [`unpark_one`], but if the bucket of `addr` is locked, it returns
+/// [`WouldBlock`] without waking a thread instead of waiting for the lock.
+///
+/// Buckets are shared by many addresses, so it can fail even if no other
+/// thread uses `addr`. A caller which skips the wake-up has to retry it
+/// later, e.g. from a path which can afford to wait. With `critical-section`
+/// or `single-core`, locking a bucket never waits, so it always succeeds.
 ///
 /// # Example
 ///
 /// ```
-/// # struct YourTaskQueue;
-/// # struct Task {};
-/// # impl YourTaskQueue {
-/// #     const fn new() -> Self { Self }
-/// #     fn push_task(&self, _: Task) {}
-/// #     fn pop_task(&self) -> Option<Task> { None }
-/// # }
-/// use sparking_lot_core::{park, unpark_some};
+/// use core::sync::atomic::AtomicBool;
 ///
-/// static tasks: YourTaskQueue = YourTaskQueue::new();
+/// use sparking_lot_core::{try_unpark_one, WouldBlock};
 ///
-/// fn add_tasks<T: Iterator<Item = Task>>(new_tasks: T) {
-///     let mut count = 0;
-///     for t in new_tasks {
-///         tasks.push_task(t);
-///         count += 1;
-///     }
-///     unpark_some(&tasks as *const _ as *const _, count);
-/// }
+/// static FLAG: AtomicBool = AtomicBool::new(false);
+/// let addr = (&FLAG as *const AtomicBool).addr();
+///
+/// match try_unpark_one(addr) {
+///     // nothing is parked on `addr`
+///     Ok(result) => assert_eq!(result.unparked, 0),
+///     // the wake-up has to be retried
+///     Err(WouldBlock) => {}
+/// }
+/// ```
+#[cfg_attr(not(loom), inline)]
+#[cfg_attr(loom, track_caller)]
+pub fn try_unpark_one(addr: impl AsParkAddr) -> Result<UnparkResult, WouldBlock> {
+    parking_lot::try_unpark_one(addr.park_addr()).ok_or(WouldBlock)
+}
+
+/// Like [`unpark_one`], but passes `token` to the woken thread, which
+/// gets it from [`park_with_token`] (or [`park_timeout`]).
+///
+/// If no thread is woken, `token` is dropped.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicBool;
+/// use core::sync::atomic::Ordering::Relaxed;
+/// use std::thread;
+///
+/// use sparking_lot_core::{park_with_token, unpark_one_with_token};
+///
+/// static WAITING: AtomicBool = AtomicBool::new(false);
+/// let addr = (&WAITING as *const AtomicBool).addr();
+///
+/// let h = thread::spawn(move || unsafe {
+///     // SAFETY: no calls to sparking_lot_core functions in closure, owned address
+///     park_with_token(addr, || {
+///         WAITING.store(true, Relaxed);
+///         true
+///     })
+/// });
+/// // `expected` runs with the bucket locked, so once
+/// // `WAITING` is set, the thread is already queued
+/// while !WAITING.load(Relaxed) {
+///     thread::yield_now();
+/// }
+/// unpark_one_with_token(addr, 42);
+/// assert_eq!(h.join().unwrap(), Some(42));
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_one_with_token(addr: impl AsParkAddr, token: usize) -> UnparkResult {
+    parking_lot::unpark_one(addr.park_addr(), |_| token)
+}
+
+/// Like [`unpark_one`], but calls `callback` with the bucket of `addr`
+/// still locked, after picking the thread to wake, but before waking it.
+///
+/// `callback` gets the [`UnparkResult`] which will be returned and
+/// returns the token passed to the woken thread (see [`park_with_token`]).
+/// It's called even if no thread is woken, in which case the token is
+/// dropped. Since [`park`]s on `addr` can't start or finish while it
+/// runs, it can update state which depends on whether threads are still
+/// parked, such as a "has waiters" bit, without races. It's synchronized
+/// the same way as `release` in [`unpark_one_release`].
+///
+/// `callback` has the same restrictions as `expected`: it can't call any
+/// functions from this [`crate`] and should return quickly.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicU8;
+/// use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+///
+/// use sparking_lot_core::{park_with_token, unpark_one_with, DEFAULT_UNPARK_TOKEN};
+///
+/// const LOCKED: u8 = 1;
+/// const PARKED: u8 = 2;
+/// const HANDED_OFF: usize = 1;
+///
+/// /// A mutex which hands the lock off to the thread it wakes.
+/// struct FairMutex(AtomicU8);
+///
+/// impl FairMutex {
+///     fn lock(&self) {
+///         let addr = self as *const _ as *const ();
+///         while self.0.compare_exchange(0, LOCKED, Acquire, Relaxed).is_err() {
+///             // SAFETY: no calls to sparking_lot_core functions in closure, owned address
+///             let token = unsafe {
+///                 park_with_token(addr, || {
+///                     self.0
+///                         .fetch_update(Relaxed, Relaxed, |s| (s & LOCKED != 0).then_some(s | PARKED))
+///                         .is_ok()
+///                 })
+///             };
+///             if token == Some(HANDED_OFF) {
+///                 // `unlock` never unlocked it, it's ours now
+///                 return;
+///             }
+///         }
+///     }
+///
+///     fn unlock(&self) {
+///         if self.0.compare_exchange(LOCKED, 0, Release, Relaxed).is_ok() {
+///             return;
+///         }
+///         unpark_one_with(self as *const _ as *const (), |result| {
+///             if result.unparked == 0 {
+///                 self.0.store(0, Release);
+///                 return DEFAULT_UNPARK_TOKEN;
+///             }
+///             // stays locked, only the `PARKED` bit may change
+///             let parked = if result.has_more { PARKED } else { 0 };
+///             self.0.store(LOCKED | parked, Release);
+///             HANDED_OFF
+///         });
+///     }
+/// }
+/// # let m = FairMutex(AtomicU8::new(0));
+/// # m.lock();
+/// # m.unlock();
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_one_with(
+    addr: impl AsParkAddr,
+    callback: impl FnOnce(UnparkResult) -> usize,
+) -> UnparkResult {
+    parking_lot::unpark_one(addr.park_addr(), callback)
+}
+
+/// Like [`unpark_one_with`], but `callback` is also told whether this
+/// wake-up should be fair, for primitives with eventual fairness.
+///
+/// Handing a lock off directly to the woken thread is fair, but slow, since
+/// the lock can't be used until that thread is scheduled. Letting it compete
+/// with running threads (barging) is much faster, but it may starve parked
+/// threads. The lot only decides when to be fair: like in `parking_lot`,
+/// the second argument of `callback` is true once a random timeout (0.5ms on
+/// average) has passed since the last fair wake-up of the bucket (without
+/// `std`, for every 64th wake-up on average instead), and only when a thread
+/// is woken. What being fair means is up to the primitive.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicU8;
+/// use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+///
+/// use sparking_lot_core::{park_with_token, unpark_one_fair, DEFAULT_UNPARK_TOKEN};
+///
+/// const LOCKED: u8 = 1;
+/// const PARKED: u8 = 2;
+/// const HANDED_OFF: usize = 1;
+///
+/// struct Mutex(AtomicU8);
+///
+/// impl Mutex {
+///     fn lock(&self) {
+///         loop {
+///             let state = self.0.load(Relaxed);
+///             if state & LOCKED == 0 {
+///                 if self.0.compare_exchange(state, state | LOCKED, Acquire, Relaxed).is_ok() {
+///                     return;
+///                 }
+///                 continue;
+///             }
+///             let parked = unsafe {
+///                 park_with_token(self as *const _ as *const (), || {
+///                     self.0.fetch_or(PARKED, Relaxed) & LOCKED != 0
+///                 })
+///             };
+///             if parked == Some(HANDED_OFF) {
+///                 return;
+///             }
+///         }
+///     }
+///
+///     fn unlock(&self) {
+///         if self.0.compare_exchange(LOCKED, 0, Release, Relaxed).is_ok() {
+///             return;
+///         }
+///         unpark_one_fair(self as *const _ as *const (), |result, be_fair| {
+///             let parked = if result.has_more { PARKED } else { 0 };
+///             if be_fair {
+///                 // stays locked for the woken thread
+///                 self.0.store(LOCKED | parked, Release);
+///                 HANDED_OFF
+///             } else {
+///                 // the woken thread competes for the lock
+///                 self.0.store(parked, Release);
+///                 DEFAULT_UNPARK_TOKEN
+///             }
+///         });
+///     }
+/// }
+/// # let m = Mutex(AtomicU8::new(0));
+/// # m.lock();
+/// # m.unlock();
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_one_fair(
+    addr: impl AsParkAddr,
+    callback: impl FnOnce(UnparkResult, bool) -> usize,
+) -> UnparkResult {
+    parking_lot::unpark_one_fair(addr.park_addr(), callback)
+}
+
+/// Wakes at most `count` threads [`parked`](park()) on `addr`,
+/// the ones which parked first (see [wake order](crate#wake-order)).
+///
+/// Should be called after making the `expected` of
+/// the corresponding [`parks`](park()) return false.
+///
+/// # Notes
+///
+/// - If `count` is 0, no thread is woken and nothing is locked, so it's
+///   cheap. [`UnparkResult::has_more`] isn't determined then and is always
+///   false, [`has_waiters`] or [`parked_count`] can be used instead.
+/// - The memory pointed to by `addr` isn't written to,
+///   it isn't read and no references to it are formed.
+/// - If no thread is waiting on `addr`, no thread is
+///   woken, but it still requires locking, so it's not
+///   recommended to call it without reason.
+/// - This function ensures that if it is called after an
+///   effect, that would cause the `expected` of a call to
+///   [`park`] with the same `addr`, [`park`] will either
+///   be woken, or it will not have gone to sleep and
+///   will return.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// # struct YourTaskQueue;
+/// # struct Task {};
+/// # impl YourTaskQueue {
+/// #     const fn new() -> Self { Self }
+/// #     fn push_task(&self, _: Task) {}
+/// #     fn pop_task(&self) -> Option<Task> { None }
+/// # }
+/// use sparking_lot_core::{park, unpark_some};
+///
+/// static tasks: YourTaskQueue = YourTaskQueue::new();
+///
+/// fn add_tasks<T: Iterator<Item = Task>>(new_tasks: T) {
+///     let mut count = 0;
+///     for t in new_tasks {
+///         tasks.push_task(t);
+///         count += 1;
+///     }
+///     unpark_some(&tasks as *const _ as *const _, count);
+/// }
 ///
 /// fn get_task() -> Task {
 ///     let mut task = None;
@@ -275,8 +1388,8 @@ pub fn unpark_one(addr: *const ()) {
 /// ```
 #[cfg_attr(not(loom), inline(always))]
 #[cfg_attr(loom, track_caller)]
-pub fn unpark_some(addr: *const (), count: usize) {
-    parking_lot::unpark_some(addr, count);
+pub fn unpark_some(addr: impl AsParkAddr, count: usize) -> UnparkResult {
+    parking_lot::unpark_some(addr.park_addr(), count)
 }
 
 /// Wakes all threads [`parked`](park()) on `addr`.
@@ -287,15 +1400,15 @@ pub fn unpark_some(addr: *const (), count: usize) {
 /// # Notes
 ///
 /// - The memory pointed to by `addr` isn't written to,
-/// it isn't read and no references to it are formed.
+///   it isn't read and no references to it are formed.
 /// - If no thread is waiting on `addr`, no thread is
-/// woken, but it still requires locking, so it's not
-/// recommended to call it without reason.
+///   woken, but it still requires locking, so it's not
+///   recommended to call it without reason.
 /// - This function ensures that if it is called after an
-/// effect, that would cause the `expected` of a call to
-/// [`park`] with the same `addr`, [`park`] will either
-/// be woken, or it will not have gone to sleep and
-/// will return.
+///   effect, that would cause the `expected` of a call to
+///   [`park`] with the same `addr`, [`park`] will either
+///   be woken, or it will not have gone to sleep and
+///   will return.
 ///
 /// [`park`]: crate::park()
 ///
@@ -332,6 +1445,1122 @@ pub fn unpark_some(addr: *const (), count: usize) {
 /// ```
 #[cfg_attr(not(loom), inline(always))]
 #[cfg_attr(loom, track_caller)]
-pub fn unpark_all(addr: *const ()) {
-    parking_lot::unpark_all(addr);
+pub fn unpark_all(addr: impl AsParkAddr) -> UnparkResult {
+    parking_lot::unpark_all(addr.park_addr(), |_| ())
+}
+
+/// Like [`unpark_one`], but calls `release` with the bucket
+/// of `addr` locked, before any thread is woken.
+///
+/// Everything `release` does happens-before every later `expected`
+/// of a [`park`] with the same `addr`, and before the woken thread
+/// returns from [`park`] (see [synchronization](crate#synchronization)).
+/// So if `release` makes `expected` return false, it can do so with
+/// `Relaxed` atomics.
+///
+/// `release` has the same restrictions as `expected`: it can't call any
+/// functions from this [`crate`] and should return quickly.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicBool;
+/// use core::sync::atomic::Ordering::Relaxed;
+///
+/// use sparking_lot_core::{park, unpark_one_release};
+///
+/// static READY: AtomicBool = AtomicBool::new(false);
+///
+/// fn wait() {
+///     // SAFETY: only `READY` is used in the closure, and it's private
+///     unsafe { park(&READY as *const _ as *const _, || !READY.load(Relaxed)) };
+/// }
+///
+/// fn notify() {
+///     unpark_one_release(&READY as *const _ as *const _, || READY.store(true, Relaxed));
+/// }
+/// # notify();
+/// # wait();
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_one_release(addr: impl AsParkAddr, release: impl FnOnce()) -> UnparkResult {
+    parking_lot::unpark_one(addr.park_addr(), |_| {
+        release();
+        DEFAULT_UNPARK_TOKEN
+    })
+}
+
+/// Like [`unpark_some`], but calls `release` first, with
+/// the bucket of `addr` locked.
+///
+/// `release` is synchronized the same way as in [`unpark_one_release`],
+/// and it's called even if `count` is 0. Unlike [`unpark_some`], the bucket
+/// is locked for a `count` of 0 too, and [`UnparkResult::has_more`] is
+/// determined as usual.
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_some_release(
+    addr: impl AsParkAddr,
+    count: usize,
+    release: impl FnOnce(),
+) -> UnparkResult {
+    parking_lot::unpark_some_release(addr.park_addr(), count, release)
+}
+
+/// Like [`unpark_all`], but calls `release` first, with
+/// the bucket of `addr` locked.
+///
+/// `release` is synchronized the same way as in [`unpark_one_release`].
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_all_release(addr: impl AsParkAddr, release: impl FnOnce()) -> UnparkResult {
+    parking_lot::unpark_all(addr.park_addr(), |_| release())
+}
+
+/// Like [`unpark_all`], but calls `callback` with the result while the
+/// bucket of `addr` is still locked, after the threads are taken out of
+/// the queue, but before any of them are woken.
+///
+/// `callback` is called exactly once per call, so it can update state
+/// once for each released batch (e.g. the generation of a barrier), and
+/// it's synchronized the same way as `release` in [`unpark_one_release`].
+/// Like `expected` in [`park`], it must not call other functions of this
+/// crate.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicUsize;
+/// use core::sync::atomic::Ordering::Relaxed;
+///
+/// use sparking_lot_core::unpark_all_and_then;
+///
+/// static RELEASED: AtomicUsize = AtomicUsize::new(0);
+///
+/// let result = unpark_all_and_then(&RELEASED as *const _ as *const _, |result| {
+///     RELEASED.fetch_add(result.unparked, Relaxed);
+/// });
+/// assert_eq!(RELEASED.load(Relaxed), result.unparked);
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_all_and_then(
+    addr: impl AsParkAddr,
+    callback: impl FnOnce(UnparkResult),
+) -> UnparkResult {
+    parking_lot::unpark_all(addr.park_addr(), callback)
+}
+
+/// Like [`park`], but parks on the address of `value`, so that
+/// no pointer casts are needed.
+///
+/// Unlike [`park`], this is safe: `value` is only used for its address
+/// and is never read, and breaking the rules of [`park`] with it only
+/// causes bugs (deadlocks, panics or wake-ups which look spurious), not
+/// undefined behaviour. They should be followed all the same: `expected`
+/// shouldn't call this crate, and `value` should be owned by the caller,
+/// e.g. the atomic `expected` checks.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering::{Acquire, Release}};
+/// use std::thread;
+///
+/// use sparking_lot_core::{park_ref, unpark_all_ref};
+///
+/// static READY: AtomicBool = AtomicBool::new(false);
+///
+/// let waiter = thread::spawn(|| park_ref(&READY, || !READY.load(Acquire)));
+/// READY.store(true, Release);
+/// unpark_all_ref(&READY);
+/// waiter.join().unwrap();
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+pub fn park_ref<T: ?Sized>(value: &T, expected: impl FnOnce() -> bool) {
+    // not `value.park_addr()`, which would use the impl of `T` if it has one
+    parking_lot::park(AsParkAddr::park_addr(&value), expected);
+}
+
+/// Like [`unpark_one`], but wakes a thread parked on `value` with [`park_ref`].
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_one_ref<T: ?Sized>(value: &T) -> UnparkResult {
+    unpark_one(value)
+}
+
+/// Like [`unpark_some`], but wakes threads parked on `value` with [`park_ref`].
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_some_ref<T: ?Sized>(value: &T, count: usize) -> UnparkResult {
+    unpark_some(value, count)
+}
+
+/// Like [`unpark_all`], but wakes the threads parked on `value` with [`park_ref`].
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_all_ref<T: ?Sized>(value: &T) -> UnparkResult {
+    unpark_all(value)
+}
+
+/// Parks the current thread on the address of `atomic` if it's still
+/// `value`, like a futex wait.
+///
+/// This is the usual `expected` of [`park`] without the closure: `atomic`
+/// is loaded once with the bucket locked, so a thread which stores another
+/// value and then calls [`unpark_one`] or [`unpark_all`] on `atomic` can't
+/// be missed. It's safe, since there's no closure which could call this
+/// crate. Like a futex, it can return before the value changes, so it's
+/// called in a loop.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicU32, Ordering::{Acquire, Release}};
+/// use std::thread;
+///
+/// use sparking_lot_core::{park_if_eq, unpark_all};
+///
+/// static STATE: AtomicU32 = AtomicU32::new(0);
+///
+/// let waiter = thread::spawn(|| {
+///     while STATE.load(Acquire) == 0 {
+///         park_if_eq(&STATE, 0);
+///     }
+/// });
+/// STATE.store(1, Release);
+/// unpark_all(&STATE);
+/// waiter.join().unwrap();
+/// ```
+#[cfg(target_has_atomic = "32")]
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+pub fn park_if_eq(atomic: &core::sync::atomic::AtomicU32, value: u32) {
+    parking_lot::park(AsParkAddr::park_addr(&atomic), || {
+        atomic.load(core::sync::atomic::Ordering::Acquire) == value
+    });
+}
+
+/// Like [`park`], but parks the current thread on an integer `key`
+/// instead of an address.
+///
+/// Keys are separate from addresses: [`unpark_one_key`] and
+/// [`unpark_all_key`] only wake threads parked on the same key,
+/// and the pointer based functions never wake them, even if a key
+/// and an address have the same value. They share the buckets with
+/// addresses, so `expected` blocks other calls the same way.
+///
+/// All 64 bits of a key are used whatever the width of `usize`, so
+/// on 32-bit targets keys which only differ in their high half are still
+/// separate, and they're spread over the buckets as well.
+///
+/// # Safety
+///
+/// The same as for [`park`]. Keys are global to the process, so the
+/// advice about addresses applies even more: only use keys from a range
+/// which nothing else parks on (e.g. ones derived from an owned address).
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering::{Acquire, Release}};
+/// use std::thread;
+///
+/// use sparking_lot_core::{park_on_key, unpark_all_key};
+///
+/// const SHARD: u64 = 7;
+/// static READY: AtomicBool = AtomicBool::new(false);
+///
+/// let waiter = thread::spawn(|| {
+///     // SAFETY: nothing else uses `SHARD` as a key
+///     unsafe { park_on_key(SHARD, || !READY.load(Acquire)) };
+/// });
+/// READY.store(true, Release);
+/// unpark_all_key(SHARD);
+/// waiter.join().unwrap();
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+pub unsafe fn park_on_key(key: u64, expected: impl FnOnce() -> bool) {
+    let (addr, tag) = key_parts(key);
+    parking_lot::park_tagged(addr, tag, expected);
+}
+
+/// Like [`unpark_one`], but wakes a thread parked on `key` with [`park_on_key`].
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_one_key(key: u64) -> UnparkResult {
+    let (addr, tag) = key_parts(key);
+    parking_lot::unpark_one_tagged(addr, tag)
+}
+
+/// Like [`unpark_all`], but wakes the threads parked on `key` with [`park_on_key`].
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_all_key(key: u64) -> UnparkResult {
+    let (addr, tag) = key_parts(key);
+    parking_lot::unpark_all_tagged(addr, tag)
+}
+
+/// Like [`park`], but the thread is only woken by [`unpark_one_versioned`]
+/// and [`unpark_all_versioned`] with the same `generation`.
+///
+/// This filters out stale wake-ups when the memory at `addr` is freed and
+/// reused: if its owner bumps the generation whenever the address gets a
+/// new owner, an old owner which still holds the address (or its old
+/// generation) can't wake the new owner's threads. The pointer based
+/// unpark functions don't wake versioned waiters at all, and the versioned
+/// ones don't wake threads parked with [`park`].
+///
+/// # Safety
+///
+/// The same as for [`park`].
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, AtomicU32, Ordering::{Acquire, Release}};
+/// use std::thread;
+///
+/// use sparking_lot_core::{park_versioned, unpark_all_versioned};
+///
+/// static SLOT: AtomicBool = AtomicBool::new(false);
+/// static GENERATION: AtomicU32 = AtomicU32::new(1);
+///
+/// fn addr() -> *const () {
+///     &SLOT as *const _ as *const _
+/// }
+///
+/// let generation = GENERATION.load(Acquire);
+/// let waiter = thread::spawn(move || {
+///     // SAFETY: `SLOT` is private
+///     unsafe { park_versioned(addr(), generation, || !SLOT.load(Acquire)) };
+/// });
+/// // a stale wake-up from the previous owner of the slot is ignored
+/// assert_eq!(unpark_all_versioned(addr(), generation - 1).unparked, 0);
+/// SLOT.store(true, Release);
+/// unpark_all_versioned(addr(), generation);
+/// waiter.join().unwrap();
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+pub unsafe fn park_versioned(
+    addr: impl AsParkAddr,
+    generation: u32,
+    expected: impl FnOnce() -> bool,
+) {
+    parking_lot::park_tagged(addr.park_addr(), generation_tag(generation), expected);
+}
+
+/// Like [`unpark_one`], but only wakes a thread parked on `addr` with
+/// [`park_versioned`] in `generation`.
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_one_versioned(addr: impl AsParkAddr, generation: u32) -> UnparkResult {
+    parking_lot::unpark_one_tagged(addr.park_addr(), generation_tag(generation))
+}
+
+/// Like [`unpark_all`], but only wakes the threads parked on `addr` with
+/// [`park_versioned`] in `generation`.
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_all_versioned(addr: impl AsParkAddr, generation: u32) -> UnparkResult {
+    parking_lot::unpark_all_tagged(addr.park_addr(), generation_tag(generation))
+}
+
+/// Like [`park`], but parks the current thread in one of 256 separate
+/// queues of `addr`, picked by `lane`.
+///
+/// This lets one object have several wait queues (e.g. readers and writers)
+/// without making up addresses for them. The thread is only woken by
+/// [`unpark_one_lane`] and [`unpark_all_lane`] with the same `lane`, and the
+/// pointer based unpark functions don't wake it at all. All the lanes of
+/// an address share its bucket, so `expected` is still called with every
+/// lane of `addr` locked, and so are the callbacks of the other functions.
+///
+/// # Safety
+///
+/// The same as for [`park`].
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicU8, Ordering::{Acquire, Release}};
+/// use std::thread;
+///
+/// use sparking_lot_core::{park_lane, unpark_all_lane};
+///
+/// const READERS: u8 = 0;
+/// const WRITERS: u8 = 1;
+///
+/// static STATE: AtomicU8 = AtomicU8::new(0);
+///
+/// let reader = thread::spawn(|| {
+///     // SAFETY: `STATE` is private
+///     unsafe { park_lane(&STATE, READERS, || STATE.load(Acquire) == 0) };
+/// });
+/// # thread::sleep(std::time::Duration::from_millis(50));
+/// STATE.store(1, Release);
+/// // the reader isn't woken by waking the writers
+/// assert_eq!(unpark_all_lane(&STATE, WRITERS).unparked, 0);
+/// unpark_all_lane(&STATE, READERS);
+/// reader.join().unwrap();
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+pub unsafe fn park_lane(addr: impl AsParkAddr, lane: u8, expected: impl FnOnce() -> bool) {
+    parking_lot::park_tagged(addr.park_addr(), lane_tag(lane), expected);
+}
+
+/// Like [`unpark_one`], but only wakes a thread parked in `lane` of `addr`
+/// with [`park_lane`].
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_one_lane(addr: impl AsParkAddr, lane: u8) -> UnparkResult {
+    parking_lot::unpark_one_tagged(addr.park_addr(), lane_tag(lane))
+}
+
+/// Like [`unpark_all`], but only wakes the threads parked in `lane` of
+/// `addr` with [`park_lane`].
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_all_lane(addr: impl AsParkAddr, lane: u8) -> UnparkResult {
+    parking_lot::unpark_all_tagged(addr.park_addr(), lane_tag(lane))
+}
+
+/// Wakes up to `count` threads [`parked`](park()) on each `addr` in `requests`,
+/// and returns how many were woken in total.
+///
+/// Does the same as calling [`unpark_some`] for every request, but the requests whose
+/// addresses map to the same bucket are handled under one lock (out of every
+/// `usize::BITS` requests), and the threads are only woken once all the buckets are
+/// unlocked. This is cheaper for primitives which
+/// wake waiters of several related addresses at once. [`UnparkBatch`]
+/// collects the requests as they come up.
+///
+/// # Notes
+///
+/// - The requests for each address are handled in order, so threads parked
+///   on the same address are still woken in the [wake order](crate#wake-order).
+///   There is no ordering between addresses.
+/// - The buckets aren't locked all at once, so unlike [`unpark_requeue`], it
+///   isn't atomic: a thread may park on an address whose bucket was already
+///   handled and stay parked.
+/// - The memory pointed to by the addresses isn't written to,
+///   it isn't read and no references to it are formed.
+///
+/// # Example
+///
+/// ```
+/// use sparking_lot_core::unpark_many;
+///
+/// struct RwLock {
+///     readers: u8,
+///     writer: u8,
+/// }
+///
+/// let lock = RwLock { readers: 0, writer: 0 };
+/// // wake all readers and a writer
+/// assert_eq!(unpark_many(&[(&lock.readers, usize::MAX), (&lock.writer, 1)]), 0);
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_many<A: AsParkAddr>(requests: &[(A, usize)]) -> usize {
+    parking_lot::unpark_many(requests)
+}
+
+/// Wakes up to `wake_count` threads [`parked`](park()) on `from`, and
+/// moves up to `requeue_count` of the next ones to `to`, as if they had
+/// parked on `to` instead. Threads are picked in the [wake order](crate#wake-order)
+/// and requeued threads are queued after the ones already parked on `to`.
+///
+/// This is what condition variables need to avoid thundering herds: instead
+/// of waking every waiter just for all but one of them to block on the mutex
+/// again, one is woken and the rest are moved to the mutex address, to be
+/// woken one by one as it's unlocked. Both buckets are locked at once (in a
+/// fixed order, so concurrent requeues can't deadlock), so no thread parked
+/// on either address can miss a wake-up.
+///
+/// Requeueing to the same address leaves the threads where they are, but
+/// they're still counted.
+///
+/// # Notes
+///
+/// - Requeued threads are woken by unpark functions for `to`, with the
+///   token those pass, and [`park_timeout`] still times out for them.
+/// - The memory pointed to by `from` and `to` isn't written to,
+///   it isn't read and no references to it are formed.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::AtomicBool;
+/// use core::sync::atomic::Ordering::{Acquire, Release};
+/// use std::thread;
+///
+/// use sparking_lot_core::{park, unpark_all, unpark_requeue, RequeueResult};
+///
+/// static CONDVAR: AtomicBool = AtomicBool::new(false);
+/// static MUTEX: AtomicBool = AtomicBool::new(false);
+/// let condvar = (&CONDVAR as *const AtomicBool).addr();
+/// let mutex = (&MUTEX as *const AtomicBool).addr();
+///
+/// let waiter = thread::spawn(move || unsafe {
+///     // SAFETY: no calls to sparking_lot_core functions in closure, owned address
+///     park(condvar, || !CONDVAR.load(Acquire));
+/// });
+/// # thread::sleep(std::time::Duration::from_millis(50));
+/// CONDVAR.store(true, Release);
+/// // the thread is moved to `MUTEX` (if it parked already) instead of being woken
+/// let result = unpark_requeue(condvar, mutex, 0, usize::MAX);
+/// assert!(result.unparked == 0 && result.requeued <= 1);
+/// unpark_all(mutex);
+/// waiter.join().unwrap();
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub fn unpark_requeue(
+    from: impl AsParkAddr,
+    to: impl AsParkAddr,
+    wake_count: usize,
+    requeue_count: usize,
+) -> RequeueResult {
+    parking_lot::unpark_requeue(from.park_addr(), to.park_addr(), wake_count, requeue_count)
+}
+
+/// A thread parked with [`park_with_handle`], which [`unpark_handle`]
+/// wakes without searching the queue of its address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParkHandle(parking_lot::Handle);
+
+//SAFETY: it only points to the waiter, which is only accessed with its bucket locked
+unsafe impl Send for ParkHandle {}
+unsafe impl Sync for ParkHandle {}
+
+/// Like [`park_with_token`], but calls `queued` with a handle of the thread
+/// once it's queued on `addr`, which [`unpark_handle`] can wake directly.
+///
+/// This is for primitives which already know which thread they want to
+/// wake, e.g. because they keep their own queue of waiters, so that waking
+/// it doesn't have to search the queue of `addr`, which is shared with the
+/// other addresses of its bucket.
+///
+/// `queued` is called with the bucket of `addr` locked, right after
+/// `expected`, so the same restrictions apply to it.
+///
+/// # Safety
+///
+/// Same as [`park`].
+///
+/// # Notes
+///
+/// - The thread can still be woken by every unpark function for `addr`,
+///   and requeued by [`unpark_requeue`], handles follow requeued threads.
+/// - The memory pointed to by `addr` isn't written to,
+///   it isn't read and no references to it are formed.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::VecDeque;
+/// use std::sync::atomic::{AtomicBool, Ordering::{Acquire, Release}};
+/// use std::sync::Mutex;
+/// use std::thread;
+///
+/// use sparking_lot_core::{park_with_handle, unpark_handle, ParkHandle};
+///
+/// static GO: AtomicBool = AtomicBool::new(false);
+/// static WAITERS: Mutex<VecDeque<ParkHandle>> = Mutex::new(VecDeque::new());
+/// let addr = || &GO as *const _ as *const ();
+///
+/// let waiter = thread::spawn(move || {
+///     // SAFETY: only this example parks on `GO`, and `queued` doesn't call this crate
+///     unsafe {
+///         park_with_handle(addr(), || !GO.load(Acquire), |handle| {
+///             WAITERS.lock().unwrap().push_back(handle)
+///         })
+///     }
+/// });
+/// let handle = loop {
+///     if let Some(handle) = WAITERS.lock().unwrap().pop_front() {
+///         break handle;
+///     }
+///     thread::yield_now();
+/// };
+/// GO.store(true, Release);
+/// // SAFETY: nothing else wakes the waiter, so it's still parked
+/// assert!(unsafe { unpark_handle(handle, 42) });
+/// assert_eq!(waiter.join().unwrap(), Some(42));
+/// ```
+///
+/// [`park`]: crate::park()
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+pub unsafe fn park_with_handle(
+    addr: impl AsParkAddr,
+    expected: impl FnOnce() -> bool,
+    queued: impl FnOnce(ParkHandle),
+) -> Option<usize> {
+    parking_lot::park_with_handle(addr.park_addr(), expected, |handle| {
+        queued(ParkHandle(handle))
+    })
+}
+
+/// Wakes the thread of `handle` with `token`, if it's still parked, in
+/// constant time. Returns false if it was already woken (or requeued and
+/// woken) by another unpark function.
+///
+/// # Safety
+///
+/// The thread of `handle` must not have returned from the [`park_with_handle`]
+/// call `handle` comes from, since the handle points into its stack (or
+/// thread-local storage). Being woken by other unpark functions is fine, as
+/// long as the thread hasn't returned yet, which usually means that every
+/// thread which could wake it has to agree on who does.
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub unsafe fn unpark_handle(handle: ParkHandle, token: usize) -> bool {
+    parking_lot::unpark_handle(handle.0, token)
+}
+
+/// Parks the current thread on every address of `addrs` at once, but only
+/// if `expected` returns true, until any of them is unparked. This is what a
+/// `select` over several conditions needs.
+///
+/// Returns the index in `addrs` of the address the thread was woken through,
+/// and the token it was woken with, or [`None`] if `expected` returned false.
+///
+/// `expected` is called with the buckets of every address locked, so none
+/// of them can be unparked between `expected` and the thread being queued.
+///
+/// # Safety
+///
+/// Same as [`park`].
+///
+/// # Panics
+///
+/// If `addrs` is empty.
+///
+/// # Notes
+///
+/// - The thread is queued on every address separately, so every unpark
+///   function sees it as parked on each of them. Once it's woken through
+///   one, it no longer counts as parked on the others, and unparkers of
+///   those skip it and wake the next thread instead, so no wake-up is lost.
+/// - An address can be in `addrs` more than once.
+/// - Every address needs a waiter of its own, which lives on the stack of
+///   this call, so it uses more stack than [`park`].
+/// - The memory pointed to by the addresses isn't written to,
+///   it isn't read and no references to it are formed.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering::{Acquire, Release}};
+/// use std::thread;
+///
+/// use sparking_lot_core::{park_any, unpark_one};
+///
+/// static READY: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+/// let addr = |idx: usize| &READY[idx] as *const _ as *const ();
+///
+/// thread::scope(|s| {
+///     let waiter = s.spawn(|| {
+///         // SAFETY: only this example parks on `READY`
+///         unsafe {
+///             park_any(&[addr(0), addr(1)], || {
+///                 !READY.iter().any(|ready| ready.load(Acquire))
+///             })
+///         }
+///     });
+///     READY[1].store(true, Release);
+///     unpark_one(addr(1));
+///     // woken through `READY[1]`, unless it saw it set before parking
+///     if let Some((idx, _)) = waiter.join().unwrap() {
+///         assert_eq!(idx, 1);
+///     }
+/// });
+/// ```
+#[cfg_attr(not(loom), inline(always))]
+#[cfg_attr(loom, track_caller)]
+pub unsafe fn park_any<const N: usize>(
+    addrs: &[impl AsParkAddr; N],
+    expected: impl FnOnce() -> bool,
+) -> Option<(usize, usize)> {
+    parking_lot::park_any(&addrs.each_ref().map(|addr| addr.park_addr()), expected)
+}
+
+/// Wakes one thread [`parked`](park()) on `addr`, can be called
+/// from interrupt handlers.
+///
+/// The wake-up isn't performed right away, since that would require taking a
+/// bucket lock, which may be held by the interrupted thread. Instead it's
+/// recorded with a bounded number of lock-free operations and performed later
+/// by the next [`park`], [`unpark_one`], [`unpark_some`] or [`unpark_all`]
+/// call of any thread, or by the parked thread itself with parkers that poll
+/// (the default `no_std` parker and `single-core`).
+///
+/// Returns `false` if too many wake-ups are already deferred, in which
+/// case nothing is recorded.
+///
+/// Only available without `std` on targets with atomic compare and swap
+/// (or with `portable-atomic`).
+///
+/// [`park`]: crate::park()
+#[cfg(all(
+    not(any(loom, feature = "std")),
+    any(target_has_atomic = "ptr", feature = "portable-atomic")
+))]
+#[inline(always)]
+pub fn unpark_one_from_isr(addr: impl AsParkAddr) -> bool {
+    real::isr::unpark_one(addr.park_addr())
+}
+
+/// Wakes all threads [`parked`](park()) on `addr`, can be called
+/// from interrupt handlers.
+///
+/// The wake-up is deferred the same way as in [`unpark_one_from_isr`].
+///
+/// Returns `false` if too many wake-ups are already deferred, in which
+/// case nothing is recorded.
+///
+/// Only available without `std` on targets with atomic compare and swap
+/// (or with `portable-atomic`).
+#[cfg(all(
+    not(any(loom, feature = "std")),
+    any(target_has_atomic = "ptr", feature = "portable-atomic")
+))]
+#[inline(always)]
+pub fn unpark_all_from_isr(addr: impl AsParkAddr) -> bool {
+    real::isr::unpark_all(addr.park_addr())
+}
+
+/// Resets the lot in the child process after `fork`.
+///
+/// After `fork`, only the forking thread exists in the child, but
+/// the threads which were [`parked`](park()) in the parent are still
+/// queued, and bucket locks held by other threads at the time of `fork`
+/// stay locked forever, so the first [`park`] or unpark could deadlock.
+/// This function empties every queue and unlocks every bucket.
+/// [`ParkingLot`]s aren't reset, so the child should create new ones.
+///
+/// # Safety
+///
+/// - Must be called in the child process, before it spawns any threads
+///   or calls any other function from this [`crate`]. The easiest way is
+///   calling it right after `fork` returns 0, or registering it as the
+///   child handler with `pthread_atfork`.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # #[cfg(all(unix, feature = "std"))]
+/// # fn main() {
+/// extern "C" fn reinit_child() {
+///     //SAFETY: called in the child right after `fork`
+///     unsafe { sparking_lot_core::reinit_after_fork() }
+/// }
+///
+/// extern "C" {
+///     fn pthread_atfork(
+///         prepare: Option<extern "C" fn()>,
+///         parent: Option<extern "C" fn()>,
+///         child: Option<extern "C" fn()>,
+///     ) -> i32;
+/// }
+///
+/// unsafe { pthread_atfork(None, None, Some(reinit_child)) };
+/// # }
+/// # #[cfg(not(all(unix, feature = "std")))]
+/// # fn main() {}
+/// ```
+#[cfg(all(unix, feature = "std", not(loom)))]
+#[inline(always)]
+pub unsafe fn reinit_after_fork() {
+    parking_lot::reinit_after_fork()
+}
+
+/// Like [`park`], but `signals` are blocked while the thread is parked.
+///
+/// Signals which arrive while they're blocked stay pending and are
+/// delivered once the old signal mask of the thread is restored, right
+/// before this function returns. This is useful for threads which are
+/// shut down with signals and shouldn't be interrupted while parked.
+///
+/// Only available on Unix with the `signal-mask` feature.
+///
+/// # Safety
+///
+/// Same as [`park`].
+///
+/// # Panics
+///
+/// - if a signal number in `signals` is invalid.
+/// - same as [`park`].
+///
+/// [`park`]: crate::park()
+#[cfg(all(unix, feature = "signal-mask", not(loom)))]
+#[inline(always)]
+#[cfg_attr(feature = "watchdog", track_caller)]
+pub unsafe fn park_with_signals_blocked(
+    addr: impl AsParkAddr,
+    expected: impl FnOnce() -> bool,
+    signals: &[libc::c_int],
+) {
+    let _blocked = real::signal::block(signals);
+    parking_lot::park(addr.park_addr(), expected);
+}
+
+#[cfg(all(feature = "watchdog", not(loom)))]
+pub use real::watchdog::LongHold;
+
+/// Calls `report` whenever [`park`] holds a bucket lock for
+/// longer than `threshold`, replacing the previous watchdog.
+///
+/// The lock is held while `expected` runs, so this points at
+/// slow or blocking `expected` closures. The hold is measured
+/// after the lock is released, so `report` is only called
+/// once `expected` returns. `report` can call functions from
+/// this [`crate`].
+///
+/// Only available with the `watchdog` feature.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// sparking_lot_core::set_watchdog(Duration::from_millis(1), |hold| eprintln!("{hold}"));
+/// ```
+#[cfg(all(feature = "watchdog", not(loom)))]
+pub fn set_watchdog(threshold: core::time::Duration, report: fn(&LongHold)) {
+    real::watchdog::set(threshold, report)
+}
+
+/// Removes the watchdog set with [`set_watchdog`].
+///
+/// Only available with the `watchdog` feature.
+#[cfg(all(feature = "watchdog", not(loom)))]
+pub fn clear_watchdog() {
+    real::watchdog::clear()
+}
+
+#[cfg(all(feature = "instrument", not(loom)))]
+pub use real::instrument::{Event, UnparkKind};
+
+#[cfg(all(feature = "stats", not(loom)))]
+pub use real::stats::Stats;
+
+/// Returns a snapshot of the counters of every park and unpark in the
+/// process, e.g. to tell how often threads actually sleep, or how contended
+/// the buckets are, without an external profiler.
+///
+/// Only available with the `stats` feature, which costs an atomic increment
+/// per park, unpark and bucket lock.
+///
+/// # Example
+///
+/// ```
+/// use sparking_lot_core::{park, stats, unpark_one};
+///
+/// static ADDR: u8 = 0;
+/// let addr = &ADDR as *const _ as *const ();
+///
+/// let before = stats();
+/// // SAFETY: nothing else parks on `ADDR`
+/// unsafe { park(addr, || false) };
+/// unpark_one(addr);
+/// let after = stats();
+/// assert!(after.invalid_parks > before.invalid_parks);
+/// assert!(after.unpark_one_misses > before.unpark_one_misses);
+/// ```
+#[cfg(all(feature = "stats", not(loom)))]
+pub fn stats() -> Stats {
+    real::stats::get()
+}
+
+#[cfg(all(feature = "deadlock-detection", not(loom)))]
+pub use real::deadlock::DeadlockedThread;
+
+/// Records that the current thread holds the resource at `addr`, e.g. a
+/// lock it just acquired, for [`check_deadlock`]. Threads parked on `addr`
+/// are considered to wait for the thread until it calls [`release_resource`].
+///
+/// A thread can hold the same resource more than once (e.g. read locks),
+/// in which case it has to release it as many times.
+///
+/// Only available with the `deadlock-detection` feature.
+#[cfg(all(feature = "deadlock-detection", not(loom)))]
+pub fn acquire_resource(addr: impl AsParkAddr) {
+    real::deadlock::acquire_resource(addr.park_addr())
+}
+
+/// Records that the current thread released the resource at `addr`, which it
+/// declared with [`acquire_resource`]. Does nothing if it doesn't hold it.
+///
+/// Only available with the `deadlock-detection` feature.
+#[cfg(all(feature = "deadlock-detection", not(loom)))]
+pub fn release_resource(addr: impl AsParkAddr) {
+    real::deadlock::release_resource(addr.park_addr())
+}
+
+/// Returns the groups of parked threads which wait on each other, so that
+/// none of them can ever be woken.
+///
+/// A thread [`parked`](park()) on an address waits for every thread which
+/// holds it as a resource (see [`acquire_resource`]). If the threads it
+/// waits for are parked too, and, following those waits, some of them wait
+/// for it, none of them can run, and they're returned as one group. A thread
+/// parked on a resource it holds itself is a group of its own.
+///
+/// Primitives only take part if they declare their resources, like a mutex
+/// would when it's locked and unlocked. Tasks, threads parked on keys and
+/// generations, and threads parked in [`ParkingLot`]s aren't checked.
+/// Every bucket is locked while the parked threads are collected, so it's
+/// meant to run periodically on a background thread, not often.
+///
+/// Only available with the `deadlock-detection` feature, which also clones
+/// an [`Arc`](std::sync::Arc) on every park.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering::{Acquire, Release}};
+/// use std::thread;
+///
+/// use sparking_lot_core::{
+///     acquire_resource, check_deadlock, park, parked_count, release_resource, unpark_all,
+/// };
+///
+/// static LOCKED: AtomicBool = AtomicBool::new(false);
+/// let addr = || &LOCKED as *const _ as *const ();
+///
+/// // a thread locks a lock which isn't reentrant twice
+/// let waiter = thread::spawn(move || {
+///     LOCKED.store(true, Release);
+///     acquire_resource(addr());
+///     // SAFETY: only this example parks on `LOCKED`
+///     unsafe { park(addr(), || LOCKED.load(Acquire)) };
+///     release_resource(addr());
+/// });
+/// while parked_count(addr()) == 0 {
+///     thread::yield_now();
+/// }
+/// let deadlocks = check_deadlock();
+/// assert_eq!(deadlocks.len(), 1);
+/// assert_eq!(deadlocks[0][0].parked_on, addr());
+///
+/// // break it by hand
+/// LOCKED.store(false, Release);
+/// unpark_all(addr());
+/// waiter.join().unwrap();
+/// ```
+#[cfg(all(feature = "deadlock-detection", not(loom)))]
+pub fn check_deadlock() -> Vec<Vec<DeadlockedThread>> {
+    real::deadlock::find_cycles(&real::parking_lot::parked_threads())
+}
+
+/// Grows the bucket table to at least `buckets` buckets (rounded up to a
+/// power of two), and returns how many it has now.
+///
+/// The table starts out with the compile-time size (see `more-concurrency` and
+/// `tiny-footprint`) and `growable-table` only grows it once threads start
+/// parking, for the number of cores and then for the threads which parked,
+/// so applications which know they'll run many more threads can size it
+/// up front instead, e.g. from their configuration at startup. It can be
+/// called at any time: threads which are already parked are moved to the new
+/// table. Tables are never shrunk (or freed), so smaller values do nothing.
+///
+/// Only available with the `growable-table` feature.
+///
+/// # Panics
+///
+/// If `buckets` is bigger than the largest power of two in a `usize`.
+///
+/// # Example
+///
+/// ```
+/// let threads = 512;
+/// assert!(sparking_lot_core::reserve_buckets(threads) >= threads);
+/// ```
+#[cfg(all(feature = "growable-table", not(loom)))]
+pub fn reserve_buckets(buckets: usize) -> usize {
+    real::parking_lot::reserve_buckets(buckets)
+}
+
+mod lot;
+pub use lot::ParkingLot;
+
+mod batch;
+pub use batch::UnparkBatch;
+
+#[cfg(all(not(loom), target_has_atomic = "ptr"))]
+mod wait_queue;
+#[cfg(all(not(loom), target_has_atomic = "ptr"))]
+pub use wait_queue::WaitQueue;
+
+#[cfg(all(not(loom), target_has_atomic = "ptr"))]
+mod semaphore;
+#[cfg(all(not(loom), target_has_atomic = "ptr"))]
+pub use semaphore::Semaphore;
+
+#[cfg(all(not(loom), target_has_atomic = "8"))]
+mod once;
+#[cfg(all(not(loom), target_has_atomic = "8"))]
+pub use once::{Once, OnceState};
+
+#[cfg(all(not(loom), target_has_atomic = "32"))]
+pub mod atomic_wait;
+
+#[cfg(all(
+    feature = "compat",
+    not(any(loom, feature = "freertos", feature = "zephyr"))
+))]
+pub mod compat;
+
+#[cfg(all(feature = "ffi", not(loom)))]
+pub mod ffi;
+
+#[cfg(feature = "barrier")]
+mod barrier;
+#[cfg(feature = "barrier")]
+pub use barrier::{Barrier, Latch};
+
+#[cfg(all(feature = "debug-introspection", not(loom)))]
+mod dump;
+#[cfg(all(feature = "debug-introspection", not(loom)))]
+pub use dump::dump;
+
+#[cfg(all(feature = "stress", not(loom)))]
+mod stress;
+#[cfg(all(feature = "stress", not(loom)))]
+pub use stress::{run_stress, StressConfig, StressReport};
+
+#[cfg(all(feature = "lock-api", not(loom)))]
+mod rwlock;
+#[cfg(all(feature = "lock-api", not(loom)))]
+pub use rwlock::RawRwLock;
+
+/// Calls `hook` on every [`Event`], replacing the previous hook.
+///
+/// Threads report [`Park`](Event::Park) right before they sleep and [`Wake`](Event::Wake)
+/// once they're done sleeping, and every unpark function reports how many threads it
+/// woke with [`Unpark`](Event::Unpark). No bucket locks are held while `hook` runs, so
+/// it can call functions from this [`crate`], but it runs on every call, so it should
+/// be cheap. Tasks waiting in `park_async` aren't reported. The hook is a plain `fn`,
+/// so state, like counters, has to be kept in `static`s.
+///
+/// Only available with the `instrument` feature.
+///
+/// # Example
+///
+/// ```
+/// use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+/// use sparking_lot_core::{set_event_hook, unpark_all, Event};
+///
+/// static UNPARKS: AtomicUsize = AtomicUsize::new(0);
+///
+/// set_event_hook(|event| {
+///     if let Event::Unpark { .. } = event {
+///         UNPARKS.fetch_add(1, Relaxed);
+///     }
+/// });
+/// unpark_all(&UNPARKS as *const _ as *const ());
+/// assert!(UNPARKS.load(Relaxed) >= 1);
+/// ```
+#[cfg(all(feature = "instrument", not(loom)))]
+pub fn set_event_hook(hook: fn(&Event)) {
+    real::instrument::set(hook)
+}
+
+/// Removes the hook set with [`set_event_hook`].
+///
+/// Only available with the `instrument` feature.
+#[cfg(all(feature = "instrument", not(loom)))]
+pub fn clear_event_hook() {
+    real::instrument::clear()
+}
+
+/// A way of blocking threads, for targets none of the built-in parkers
+/// support (RTOSes, bare-metal schedulers, ...). Installed with
+/// [`set_raw_parker`].
+///
+/// Only available with the `custom-parker` feature.
+///
+/// # Safety
+///
+/// Implementations must not lose wake-ups: after `unpark(handle)` is called,
+/// the next (or current) call of `park` on the thread `handle` belongs to
+/// must return. `park` may still return spuriously, the lot rechecks its own
+/// state and parks again.
+#[cfg(feature = "custom-parker")]
+pub unsafe trait RawParker: Sync {
+    /// Returns a handle of the current thread, which is later passed to
+    /// [`unpark`](RawParker::unpark). Called right before parking.
+    fn current(&self) -> usize;
+    /// Blocks the current thread until it's unparked.
+    fn park(&self);
+    /// Unparks the thread of `handle`.
+    ///
+    /// The thread may stop parking (spuriously) right before this is called,
+    /// so it has to cope with threads which aren't parked anymore, or have even
+    /// exited. It's never called from an interrupt handler, but it's called
+    /// with no bucket locks held, so it may call functions from this [`crate`].
+    fn unpark(&self, handle: usize);
+}
+
+/// Installs the [`RawParker`] used by [`park`] and the unpark functions.
+///
+/// Until one is installed, parked threads busy-wait like with the default
+/// `no_std` parker, so it should be installed during start-up. It can only
+/// be installed once, later calls return `false` and don't change it.
+///
+/// Only available with the `custom-parker` feature, which requires disabling `std`.
+///
+/// [`park`]: crate::park()
+///
+/// # Example
+///
+/// ```ignore
+/// struct RtosParker;
+///
+/// unsafe impl sparking_lot_core::RawParker for RtosParker {
+///     fn current(&self) -> usize {
+///         rtos::current_task() as usize
+///     }
+///
+///     fn park(&self) {
+///         rtos::wait_notification();
+///     }
+///
+///     fn unpark(&self, handle: usize) {
+///         rtos::notify(handle as rtos::TaskHandle);
+///     }
+/// }
+///
+/// assert!(sparking_lot_core::set_raw_parker(&RtosParker));
+/// ```
+#[cfg(all(feature = "custom-parker", not(loom)))]
+pub fn set_raw_parker(parker: &'static dyn RawParker) -> bool {
+    real::park::install_raw_parker(parker)
 }
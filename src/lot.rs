@@ -0,0 +1,198 @@
+use crate::{parking_lot, AsParkAddr, UnparkResult};
+
+/// The bucket count of [`ParkingLot`]s by default,
+/// as many as the global table starts with.
+const DEFAULT_BUCKETS: usize = if cfg!(feature = "tiny-footprint") {
+    4
+} else if cfg!(feature = "more-concurrency") {
+    128
+} else {
+    32
+};
+
+/// A parking lot with a bucket table of its own.
+///
+/// The free functions of this crate share one table with every other crate
+/// in the process, so two unrelated subsystems can contend for the same
+/// bucket, and a subsystem which parks on an address it doesn't own sees
+/// the wake-ups of every other user of it. Threads parked in a `ParkingLot`
+/// are only woken by the unpark methods of the same lot, and only contend
+/// for its buckets with its own users.
+///
+/// The table has `BUCKETS` buckets, which must be a power of two. By default
+/// that's as many as the global table starts with (32, 128 with
+/// `more-concurrency` and 4 with `tiny-footprint`), but a lot only used by
+/// a few threads can use less memory, and one used by hundreds can have
+/// less contention, since the table doesn't grow with `growable-table`.
+/// Like the global one, it doesn't allocate.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering::{Acquire, Release}};
+/// use std::thread;
+///
+/// use sparking_lot_core::ParkingLot;
+///
+/// static READY: AtomicBool = AtomicBool::new(false);
+///
+/// fn addr() -> *const () {
+///     &READY as *const _ as *const _
+/// }
+///
+/// let lot = ParkingLot::new();
+/// thread::scope(|s| {
+///     s.spawn(|| {
+///         // SAFETY: only this example parks on `READY`
+///         unsafe { lot.park(addr(), || !READY.load(Acquire)) };
+///     });
+///     READY.store(true, Release);
+///     lot.unpark_all(addr());
+/// });
+///
+/// // 4 buckets for a handful of threads
+/// let small = ParkingLot::<4>::with_buckets();
+/// assert_eq!(small.parked_count(addr()), 0);
+/// ```
+pub struct ParkingLot<const BUCKETS: usize = DEFAULT_BUCKETS> {
+    lot: parking_lot::Lot<BUCKETS>,
+}
+
+impl ParkingLot {
+    /// Creates a lot with the default number of buckets.
+    #[cfg(not(loom))]
+    pub const fn new() -> Self {
+        Self::with_buckets()
+    }
+
+    /// Creates a lot with the default number of buckets.
+    #[cfg(loom)]
+    pub fn new() -> Self {
+        Self::with_buckets()
+    }
+}
+
+impl<const BUCKETS: usize> ParkingLot<BUCKETS> {
+    const POWER_OF_TWO: () = assert!(
+        BUCKETS.is_power_of_two(),
+        "the bucket count of a `ParkingLot` must be a power of two"
+    );
+
+    /// Creates a lot with `BUCKETS` buckets.
+    ///
+    /// # Panics
+    ///
+    /// At compile time, if `BUCKETS` isn't a power of two.
+    #[cfg(not(loom))]
+    pub const fn with_buckets() -> Self {
+        let () = Self::POWER_OF_TWO;
+        Self {
+            lot: parking_lot::Lot::new(),
+        }
+    }
+
+    /// Creates a lot with `BUCKETS` buckets.
+    ///
+    /// # Panics
+    ///
+    /// At compile time, if `BUCKETS` isn't a power of two.
+    #[cfg(loom)]
+    pub fn with_buckets() -> Self {
+        let () = Self::POWER_OF_TWO;
+        Self {
+            lot: parking_lot::Lot::new(),
+        }
+    }
+
+    /// Like [`park`](crate::park()), but parks the thread in this lot.
+    ///
+    /// # Safety
+    ///
+    /// The same as for [`park`](crate::park()).
+    #[cfg_attr(not(loom), inline(always))]
+    #[cfg_attr(any(loom, feature = "watchdog"), track_caller)]
+    pub unsafe fn park(&self, addr: impl AsParkAddr, expected: impl FnOnce() -> bool) {
+        self.lot.park(addr.park_addr(), expected);
+    }
+
+    /// Like [`park_timeout`](crate::park_timeout), but parks the thread in this lot.
+    ///
+    /// Only available with `std`, and not with the `freertos`
+    /// or `zephyr` parkers.
+    ///
+    /// # Safety
+    ///
+    /// The same as for [`park`](crate::park()).
+    #[cfg(all(
+        feature = "std",
+        not(any(loom, feature = "freertos", feature = "zephyr"))
+    ))]
+    #[inline(always)]
+    #[cfg_attr(feature = "watchdog", track_caller)]
+    pub unsafe fn park_timeout(
+        &self,
+        addr: impl AsParkAddr,
+        expected: impl FnOnce() -> bool,
+        timeout: core::time::Duration,
+    ) -> crate::ParkResult {
+        match std::time::Instant::now().checked_add(timeout) {
+            Some(deadline) => self.lot.park_until(addr.park_addr(), expected, deadline),
+            // too far in the future to ever pass
+            None => match self.lot.park(addr.park_addr(), expected) {
+                Some(token) => crate::ParkResult::Unparked(token),
+                None => crate::ParkResult::Invalid,
+            },
+        }
+    }
+
+    /// Like [`unpark_one`](crate::unpark_one), but wakes a thread parked in this lot.
+    #[cfg_attr(not(loom), inline(always))]
+    #[cfg_attr(loom, track_caller)]
+    pub fn unpark_one(&self, addr: impl AsParkAddr) -> UnparkResult {
+        self.lot.unpark_one(addr.park_addr())
+    }
+
+    /// Like [`unpark_some`](crate::unpark_some), but wakes threads parked in this lot.
+    #[cfg_attr(not(loom), inline(always))]
+    #[cfg_attr(loom, track_caller)]
+    pub fn unpark_some(&self, addr: impl AsParkAddr, count: usize) -> UnparkResult {
+        self.lot.unpark_some(addr.park_addr(), count)
+    }
+
+    /// Like [`unpark_all`](crate::unpark_all), but wakes the threads parked in this lot.
+    #[cfg_attr(not(loom), inline(always))]
+    #[cfg_attr(loom, track_caller)]
+    pub fn unpark_all(&self, addr: impl AsParkAddr) -> UnparkResult {
+        self.lot.unpark_all(addr.park_addr())
+    }
+
+    /// Like [`parked_count`](crate::parked_count), but counts the threads parked in this lot.
+    #[cfg_attr(not(loom), inline(always))]
+    #[cfg_attr(loom, track_caller)]
+    pub fn parked_count(&self, addr: impl AsParkAddr) -> usize {
+        self.lot.parked_count(addr.park_addr())
+    }
+
+    /// Like [`dump`](crate::dump()), but writes the addresses threads are parked on in this lot.
+    ///
+    /// Only available with the `debug-introspection` feature.
+    #[cfg(all(feature = "debug-introspection", not(loom)))]
+    pub fn dump(&self, mut out: impl core::fmt::Write) -> core::fmt::Result {
+        self.lot
+            .dump(|addr, tag, count| crate::dump::write_waiters(&mut out, addr, tag, count))
+    }
+}
+
+impl<const BUCKETS: usize> Default for ParkingLot<BUCKETS> {
+    fn default() -> Self {
+        Self::with_buckets()
+    }
+}
+
+impl<const BUCKETS: usize> core::fmt::Debug for ParkingLot<BUCKETS> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ParkingLot")
+            .field("buckets", &BUCKETS)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,853 @@
+#[cfg(not(doc))]
+#[cfg(not(all(miri, feature = "std", not(loom))))]
+compile_error!("[internal error] `mod miri` must be used with miri + feature = std");
+
+/* A simple lot for Miri. All waiters are in one queue behind a `std`
+ * mutex, so nothing depends on which bucket an address hashes to (Miri
+ * randomizes them) and there are no intrusive links or `static` tables
+ * of `UnsafeCell`s, so Miri only has the code that's being tested to
+ * check. Every waiter allocates, which doesn't matter in an interpreter.
+ */
+pub(super) mod parking_lot {
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+    use std::ops::{Deref, DerefMut};
+    use std::sync::atomic::{AtomicBool, Ordering::Relaxed};
+    use std::sync::{Arc, Condvar, Mutex, MutexGuard, TryLockError};
+
+    use crate::{
+        AsParkAddr, LotTag, RequeueResult, UnparkResult, ADDRESS_TAG, DEFAULT_UNPARK_TOKEN,
+    };
+
+    #[cfg(feature = "async")]
+    use core::task::Waker;
+
+    /// Poisoning is ignored, like in the real lot.
+    fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+        mutex.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[derive(Default)]
+    struct State {
+        /// Set once the waiter is unlinked and woken.
+        token: Option<usize>,
+        #[cfg(feature = "async")]
+        waker: Option<Waker>,
+    }
+
+    /// Wakes the unlinked waiters with the default token if the callback
+    /// of an unpark function panics, like the real lot does.
+    struct WakeOnUnwind<'a>(&'a [Arc<Signal>]);
+
+    impl Drop for WakeOnUnwind<'_> {
+        fn drop(&mut self) {
+            for signal in self.0 {
+                signal.wake(DEFAULT_UNPARK_TOKEN);
+            }
+        }
+    }
+
+    /// Dequeues the waiter of a signal if the closure which runs while it's
+    /// queued (e.g. `before_sleep`) panics, like the real lot does.
+    struct DequeueOnUnwind<'a>(&'a Arc<Signal>);
+
+    impl Drop for DequeueOnUnwind<'_> {
+        fn drop(&mut self) {
+            lock_queue().remove(self.0);
+        }
+    }
+
+    /// Calls `f` with the waiter of `signal` queued, see `DequeueOnUnwind`.
+    fn while_queued<R>(signal: &Arc<Signal>, f: impl FnOnce() -> R) -> R {
+        let dequeue = DequeueOnUnwind(signal);
+        let result = f();
+        core::mem::forget(dequeue);
+        result
+    }
+
+    /// How a waiter is woken, shared by it and the queue.
+    #[derive(Default)]
+    struct Signal {
+        state: Mutex<State>,
+        woken: Condvar,
+        /// Only set for the waiters of `park_any`, whose
+        /// thread waits for any of them on this instead.
+        group: Option<Arc<Signal>>,
+        /// Set on a group once an unparker unlinked one of its waiters, with
+        /// the queue locked. The others then aren't parked anymore.
+        claimed: AtomicBool,
+    }
+
+    impl Signal {
+        fn wake(&self, token: usize) {
+            let mut state = lock(&self.state);
+            state.token = Some(token);
+            #[cfg(feature = "async")]
+            let waker = state.waker.take();
+            drop(state);
+            self.woken.notify_one();
+            #[cfg(feature = "async")]
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+            if let Some(group) = &self.group {
+                group.wake(token);
+            }
+        }
+
+        fn wait(&self) -> usize {
+            let mut state = lock(&self.state);
+            loop {
+                if let Some(token) = state.token {
+                    return token;
+                }
+                state = self.woken.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+        }
+
+        #[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+        fn wait_until(&self, deadline: std::time::Instant) -> Option<usize> {
+            let mut state = lock(&self.state);
+            loop {
+                if let Some(token) = state.token {
+                    return Some(token);
+                }
+                let timeout = deadline.checked_duration_since(std::time::Instant::now())?;
+                state = self
+                    .woken
+                    .wait_timeout(state, timeout)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+            }
+        }
+    }
+
+    struct Waiter {
+        addr: usize,
+        tag: u64,
+        signal: Arc<Signal>,
+    }
+
+    impl Waiter {
+        fn is_parked_on(&self, addr: usize, tag: u64) -> bool {
+            self.addr == addr
+                && self.tag == tag
+                && !self
+                    .signal
+                    .group
+                    .as_ref()
+                    .is_some_and(|group| group.claimed.load(Relaxed))
+        }
+
+        /// Called by unparkers when they unlink the waiter to wake it.
+        fn claim(&self) {
+            if let Some(group) = &self.signal.group {
+                group.claimed.store(true, Relaxed);
+            }
+        }
+    }
+
+    struct Queue {
+        /// Oldest first, for every address.
+        waiters: VecDeque<Waiter>,
+        /// When `unpark_one_fair` should be fair next, set on first use.
+        #[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+        fair_deadline: Option<std::time::Instant>,
+        /// Without a clock, every other `unpark_one_fair` is fair.
+        #[cfg(any(feature = "freertos", feature = "zephyr"))]
+        fair: bool,
+    }
+
+    impl Queue {
+        const fn new() -> Self {
+            Self {
+                waiters: VecDeque::new(),
+                #[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+                fair_deadline: None,
+                #[cfg(any(feature = "freertos", feature = "zephyr"))]
+                fair: false,
+            }
+        }
+
+        /// Like the real lot, but with a fixed 0.5ms timeout, because
+        /// Miri's clock doesn't make the random one any more useful.
+        fn be_fair(&mut self) -> bool {
+            #[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+            {
+                let now = std::time::Instant::now();
+                match self.fair_deadline {
+                    Some(deadline) if now < deadline => false,
+                    deadline => {
+                        self.fair_deadline = Some(now + std::time::Duration::from_micros(500));
+                        // the first call only starts the timeout
+                        deadline.is_some()
+                    }
+                }
+            }
+            #[cfg(any(feature = "freertos", feature = "zephyr"))]
+            {
+                self.fair = !self.fair;
+                self.fair
+            }
+        }
+
+        fn has_waiters(&self, addr: usize, tag: u64) -> bool {
+            self.waiters
+                .iter()
+                .any(|waiter| waiter.is_parked_on(addr, tag))
+        }
+
+        /// Moves up to `count` waiters on `addr` to `unlinked`, oldest first.
+        fn unlink(&mut self, addr: usize, tag: u64, count: usize, unlinked: &mut Vec<Arc<Signal>>) {
+            let mut left = count;
+            self.waiters.retain(|waiter| {
+                if left == 0 || !waiter.is_parked_on(addr, tag) {
+                    return true;
+                }
+                waiter.claim();
+                left -= 1;
+                unlinked.push(waiter.signal.clone());
+                false
+            });
+        }
+
+        /// Removes the waiter of `signal`, if an unparker hasn't yet.
+        fn remove(&mut self, signal: &Arc<Signal>) -> Option<Waiter> {
+            let position = self
+                .waiters
+                .iter()
+                .position(|waiter| Arc::ptr_eq(&waiter.signal, signal));
+            self.waiters.remove(position?)
+        }
+    }
+
+    static QUEUE: Mutex<Queue> = Mutex::new(Queue::new());
+
+    thread_local!(static INSIDE: Cell<bool> = const { Cell::new(false) });
+
+    /// The locked queue. Calls from `expected` (or the callbacks) panic
+    /// instead of deadlocking, like in debug builds of the real lot.
+    struct Locked(MutexGuard<'static, Queue>);
+
+    #[track_caller]
+    fn lock_queue() -> Locked {
+        // TLS may already be destroyed, in which case nothing is checked
+        if INSIDE.try_with(|x| x.replace(true)).unwrap_or(false) {
+            panic!("sparking-lot-core functions can't be called from `expected`");
+        }
+        Locked(lock(&QUEUE))
+    }
+
+    /// Like `lock_queue`, but gives up if another thread holds the queue.
+    #[track_caller]
+    fn try_lock_queue() -> Option<Locked> {
+        if INSIDE.try_with(|x| x.replace(true)).unwrap_or(false) {
+            panic!("sparking-lot-core functions can't be called from `expected`");
+        }
+        match QUEUE.try_lock() {
+            Ok(guard) => Some(Locked(guard)),
+            Err(TryLockError::Poisoned(e)) => Some(Locked(e.into_inner())),
+            Err(TryLockError::WouldBlock) => {
+                let _ = INSIDE.try_with(|x| x.set(false));
+                None
+            }
+        }
+    }
+
+    impl Deref for Locked {
+        type Target = Queue;
+
+        fn deref(&self) -> &Queue {
+            &self.0
+        }
+    }
+
+    impl DerefMut for Locked {
+        fn deref_mut(&mut self) -> &mut Queue {
+            &mut self.0
+        }
+    }
+
+    impl Drop for Locked {
+        fn drop(&mut self) {
+            let _ = INSIDE.try_with(|x| x.set(false));
+        }
+    }
+
+    fn enqueue(addr: usize, tag: u64, expected: impl FnOnce() -> bool) -> Option<Arc<Signal>> {
+        enqueue_with(addr, tag, expected, |_| ())
+    }
+
+    /// Like `enqueue`, but calls `queued` with the signal of
+    /// the waiter once it's queued, with the queue still locked.
+    fn enqueue_with(
+        addr: usize,
+        tag: u64,
+        expected: impl FnOnce() -> bool,
+        queued: impl FnOnce(&Arc<Signal>),
+    ) -> Option<Arc<Signal>> {
+        let signal = Arc::new(Signal::default());
+        let mut queue = lock_queue();
+        if !expected() {
+            return None;
+        }
+        queue.waiters.push_back(Waiter {
+            addr,
+            tag,
+            signal: signal.clone(),
+        });
+        queued(&signal);
+        Some(signal)
+    }
+
+    /// A waiter of `park_with_handle`, which is kept alive by its `Arc`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) struct Handle(*const Signal);
+
+    pub(crate) fn park_with_handle(
+        addr: usize,
+        expected: impl FnOnce() -> bool,
+        queued: impl FnOnce(Handle),
+    ) -> Option<usize> {
+        enqueue_with(addr, ADDRESS_TAG, expected, |signal| {
+            queued(Handle(Arc::as_ptr(signal)))
+        })
+        .map(|signal| signal.wait())
+    }
+
+    pub(crate) unsafe fn unpark_handle(handle: Handle, token: usize) -> bool {
+        let mut queue = lock_queue();
+        let position = queue
+            .waiters
+            .iter()
+            .position(|waiter| Arc::as_ptr(&waiter.signal) == handle.0);
+        match position.and_then(|position| queue.waiters.remove(position)) {
+            Some(waiter) => {
+                drop(queue);
+                waiter.signal.wake(token);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn park(addr: usize, expected: impl FnOnce() -> bool) -> Option<usize> {
+        enqueue(addr, ADDRESS_TAG, expected).map(|signal| signal.wait())
+    }
+
+    pub(crate) fn park_any<const N: usize>(
+        addrs: &[usize; N],
+        expected: impl FnOnce() -> bool,
+    ) -> Option<(usize, usize)> {
+        assert!(
+            N != 0,
+            "sparking-lot-core: `park_any` needs at least one address"
+        );
+        let group = Arc::new(Signal::default());
+        let signals: [Arc<Signal>; N] = core::array::from_fn(|_| {
+            Arc::new(Signal {
+                group: Some(group.clone()),
+                ..Signal::default()
+            })
+        });
+        let mut queue = lock_queue();
+        if !expected() {
+            return None;
+        }
+        for (&addr, signal) in addrs.iter().zip(&signals) {
+            queue.waiters.push_back(Waiter {
+                addr,
+                tag: ADDRESS_TAG,
+                signal: signal.clone(),
+            });
+        }
+        drop(queue);
+        group.wait();
+        let mut queue = lock_queue();
+        for signal in &signals {
+            queue.remove(signal);
+        }
+        drop(queue);
+        // the unparkers wake their own signal before the group
+        signals.iter().enumerate().find_map(|(idx, signal)| {
+            let token = lock(&signal.state).token?;
+            Some((idx, token))
+        })
+    }
+
+    pub(crate) fn park_tagged(
+        addr: usize,
+        tag: u64,
+        expected: impl FnOnce() -> bool,
+    ) -> Option<usize> {
+        enqueue(addr, tag, expected).map(|signal| signal.wait())
+    }
+
+    #[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+    pub(crate) fn park_until(
+        addr: usize,
+        expected: impl FnOnce() -> bool,
+        deadline: std::time::Instant,
+        timed_out: impl FnOnce(usize, bool),
+    ) -> crate::ParkResult {
+        park_until_tagged(addr, ADDRESS_TAG, expected, deadline, timed_out)
+    }
+
+    #[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+    fn park_until_tagged(
+        addr: usize,
+        tag: u64,
+        expected: impl FnOnce() -> bool,
+        deadline: std::time::Instant,
+        timed_out: impl FnOnce(usize, bool),
+    ) -> crate::ParkResult {
+        let signal = match enqueue(addr, tag, expected) {
+            Some(signal) => signal,
+            None => return crate::ParkResult::Invalid,
+        };
+        wait_until(signal, deadline, timed_out)
+    }
+
+    pub(crate) fn park_before_sleep(
+        addr: usize,
+        expected: impl FnOnce() -> bool,
+        before_sleep: impl FnOnce(),
+    ) -> Option<usize> {
+        enqueue(addr, ADDRESS_TAG, expected).map(|signal| {
+            while_queued(&signal, before_sleep);
+            signal.wait()
+        })
+    }
+
+    pub(crate) fn park_then_validate(
+        addr: usize,
+        validate: impl FnOnce() -> bool,
+    ) -> Option<usize> {
+        let signal = enqueue(addr, ADDRESS_TAG, || true)?;
+        // an unparker may have dequeued it first, then it has to take the token
+        if !while_queued(&signal, validate) && lock_queue().remove(&signal).is_some() {
+            return None;
+        }
+        Some(signal.wait())
+    }
+
+    #[cfg(all(feature = "compat", not(any(feature = "freertos", feature = "zephyr"))))]
+    pub(crate) fn park_before_sleep_until(
+        addr: usize,
+        expected: impl FnOnce() -> bool,
+        before_sleep: impl FnOnce(),
+        deadline: Option<std::time::Instant>,
+        timed_out: impl FnOnce(usize, bool),
+    ) -> crate::ParkResult {
+        let signal = match enqueue(addr, ADDRESS_TAG, expected) {
+            Some(signal) => signal,
+            None => return crate::ParkResult::Invalid,
+        };
+        while_queued(&signal, before_sleep);
+        match deadline {
+            Some(deadline) => wait_until(signal, deadline, timed_out),
+            None => crate::ParkResult::Unparked(signal.wait()),
+        }
+    }
+
+    /// Waits for the queued `signal` until `deadline`, and then dequeues
+    /// it, unless an unparker already did.
+    #[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+    fn wait_until(
+        signal: Arc<Signal>,
+        deadline: std::time::Instant,
+        timed_out: impl FnOnce(usize, bool),
+    ) -> crate::ParkResult {
+        use crate::ParkResult;
+        if let Some(token) = signal.wait_until(deadline) {
+            return ParkResult::Unparked(token);
+        }
+        let mut queue = lock_queue();
+        if let Some(waiter) = queue.remove(&signal) {
+            timed_out(waiter.addr, !queue.has_waiters(waiter.addr, waiter.tag));
+            return ParkResult::TimedOut;
+        }
+        drop(queue);
+        // an unparker unlinked it first, so it's about to wake it
+        ParkResult::Unparked(signal.wait())
+    }
+
+    pub(crate) fn parked_count(addr: usize) -> usize {
+        parked_count_tagged(addr, ADDRESS_TAG)
+    }
+
+    #[cfg(feature = "debug-introspection")]
+    pub(crate) fn dump(
+        report: impl FnMut(usize, u64, usize) -> core::fmt::Result,
+    ) -> core::fmt::Result {
+        // the waiters of `ParkingLot`s are in the same queue
+        dump_where(|tag| tag >> 32 != 3, report)
+    }
+
+    #[cfg(feature = "debug-introspection")]
+    fn dump_where(
+        is_dumped: impl Fn(u64) -> bool,
+        mut report: impl FnMut(usize, u64, usize) -> core::fmt::Result,
+    ) -> core::fmt::Result {
+        let queue = lock_queue();
+        for (idx, waiter) in queue.waiters.iter().enumerate() {
+            let (addr, tag) = (waiter.addr, waiter.tag);
+            // every address is reported at its first waiter
+            let first = !queue
+                .waiters
+                .iter()
+                .take(idx)
+                .any(|w| w.is_parked_on(addr, tag));
+            if is_dumped(tag) && first {
+                let count = queue
+                    .waiters
+                    .iter()
+                    .filter(|w| w.is_parked_on(addr, tag))
+                    .count();
+                report(addr, tag, count)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn may_have_waiters(addr: usize) -> bool {
+        lock_queue().has_waiters(addr, ADDRESS_TAG)
+    }
+
+    fn parked_count_tagged(addr: usize, tag: u64) -> usize {
+        let queue = lock_queue();
+        queue
+            .waiters
+            .iter()
+            .filter(|waiter| waiter.is_parked_on(addr, tag))
+            .count()
+    }
+
+    fn unpark_one_in(
+        addr: usize,
+        tag: u64,
+        callback: impl FnOnce(UnparkResult, &mut Queue) -> usize,
+    ) -> UnparkResult {
+        unpark_one_locked(lock_queue(), addr, tag, callback)
+    }
+
+    fn unpark_one_locked(
+        mut queue: Locked,
+        addr: usize,
+        tag: u64,
+        callback: impl FnOnce(UnparkResult, &mut Queue) -> usize,
+    ) -> UnparkResult {
+        let mut unlinked = Vec::new();
+        queue.unlink(addr, tag, 1, &mut unlinked);
+        let result = UnparkResult {
+            unparked: unlinked.len(),
+            has_more: queue.has_waiters(addr, tag),
+        };
+        let wake = WakeOnUnwind(&unlinked);
+        let token = callback(result, &mut queue);
+        core::mem::forget(wake);
+        drop(queue);
+        for signal in unlinked {
+            signal.wake(token);
+        }
+        result
+    }
+
+    pub(crate) fn unpark_one(
+        addr: usize,
+        callback: impl FnOnce(UnparkResult) -> usize,
+    ) -> UnparkResult {
+        unpark_one_in(addr, ADDRESS_TAG, |result, _| callback(result))
+    }
+
+    pub(crate) fn try_unpark_one(addr: usize) -> Option<UnparkResult> {
+        let queue = try_lock_queue()?;
+        Some(unpark_one_locked(queue, addr, ADDRESS_TAG, |_, _| {
+            DEFAULT_UNPARK_TOKEN
+        }))
+    }
+
+    /// The queue is always FIFO here.
+    pub(crate) fn unpark_one_fifo(addr: usize) -> UnparkResult {
+        unpark_one_tagged(addr, ADDRESS_TAG)
+    }
+
+    pub(crate) fn unpark_one_tagged(addr: usize, tag: u64) -> UnparkResult {
+        unpark_one_in(addr, tag, |_, _| DEFAULT_UNPARK_TOKEN)
+    }
+
+    pub(crate) fn unpark_one_fair(
+        addr: usize,
+        callback: impl FnOnce(UnparkResult, bool) -> usize,
+    ) -> UnparkResult {
+        unpark_one_in(addr, ADDRESS_TAG, |result, queue| {
+            let be_fair = result.unparked != 0 && queue.be_fair();
+            callback(result, be_fair)
+        })
+    }
+
+    pub(crate) fn unpark_some(addr: usize, count: usize) -> UnparkResult {
+        if count == 0 {
+            // like the real lot, nothing is locked or looked up
+            return UnparkResult::default();
+        }
+        unpark_some_tagged(addr, ADDRESS_TAG, count, || ())
+    }
+
+    pub(crate) fn unpark_some_release(
+        addr: usize,
+        count: usize,
+        release: impl FnOnce(),
+    ) -> UnparkResult {
+        unpark_some_tagged(addr, ADDRESS_TAG, count, release)
+    }
+
+    fn unpark_some_tagged(
+        addr: usize,
+        tag: u64,
+        count: usize,
+        release: impl FnOnce(),
+    ) -> UnparkResult {
+        let mut queue = lock_queue();
+        release();
+        let mut unlinked = Vec::new();
+        queue.unlink(addr, tag, count, &mut unlinked);
+        let result = UnparkResult {
+            unparked: unlinked.len(),
+            has_more: queue.has_waiters(addr, tag),
+        };
+        drop(queue);
+        for signal in unlinked {
+            signal.wake(DEFAULT_UNPARK_TOKEN);
+        }
+        result
+    }
+
+    pub(crate) fn unpark_all(addr: usize, callback: impl FnOnce(UnparkResult)) -> UnparkResult {
+        unpark_all_in(addr, ADDRESS_TAG, callback)
+    }
+
+    pub(crate) fn unpark_all_tagged(addr: usize, tag: u64) -> UnparkResult {
+        unpark_all_in(addr, tag, |_| ())
+    }
+
+    fn unpark_all_in(addr: usize, tag: u64, callback: impl FnOnce(UnparkResult)) -> UnparkResult {
+        let mut queue = lock_queue();
+        let mut unlinked = Vec::new();
+        queue.unlink(addr, tag, usize::MAX, &mut unlinked);
+        let result = UnparkResult {
+            unparked: unlinked.len(),
+            has_more: false,
+        };
+        let wake = WakeOnUnwind(&unlinked);
+        callback(result);
+        core::mem::forget(wake);
+        drop(queue);
+        for signal in unlinked {
+            signal.wake(DEFAULT_UNPARK_TOKEN);
+        }
+        result
+    }
+
+    /// Without buckets there's no contention to isolate, so a
+    /// `ParkingLot` only keeps its waiters apart with its own
+    /// tag, whatever its bucket count.
+    pub(crate) struct Lot<const BUCKETS: usize>(LotTag);
+
+    impl<const BUCKETS: usize> Lot<BUCKETS> {
+        pub(crate) const fn new() -> Self {
+            Self(LotTag::new())
+        }
+
+        pub(crate) fn park(&self, addr: usize, expected: impl FnOnce() -> bool) -> Option<usize> {
+            park_tagged(addr, self.0.get(), expected)
+        }
+
+        #[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+        pub(crate) fn park_until(
+            &self,
+            addr: usize,
+            expected: impl FnOnce() -> bool,
+            deadline: std::time::Instant,
+        ) -> crate::ParkResult {
+            park_until_tagged(addr, self.0.get(), expected, deadline, |_, _| ())
+        }
+
+        pub(crate) fn unpark_one(&self, addr: usize) -> UnparkResult {
+            unpark_one_tagged(addr, self.0.get())
+        }
+
+        pub(crate) fn unpark_all(&self, addr: usize) -> UnparkResult {
+            unpark_all_tagged(addr, self.0.get())
+        }
+
+        pub(crate) fn unpark_some(&self, addr: usize, count: usize) -> UnparkResult {
+            if count == 0 {
+                return UnparkResult::default();
+            }
+            unpark_some_tagged(addr, self.0.get(), count, || ())
+        }
+
+        pub(crate) fn parked_count(&self, addr: usize) -> usize {
+            parked_count_tagged(addr, self.0.get())
+        }
+
+        #[cfg(feature = "debug-introspection")]
+        pub(crate) fn dump(
+            &self,
+            mut report: impl FnMut(usize, u64, usize) -> core::fmt::Result,
+        ) -> core::fmt::Result {
+            let tag = self.0.get();
+            dump_where(
+                |waiter| waiter == tag,
+                |addr, _, count| report(addr, ADDRESS_TAG, count),
+            )
+        }
+    }
+
+    pub(crate) fn unpark_many<A: AsParkAddr>(requests: &[(A, usize)]) -> usize {
+        let mut queue = lock_queue();
+        let mut unlinked = Vec::new();
+        for (addr, count) in requests {
+            queue.unlink(addr.park_addr(), ADDRESS_TAG, *count, &mut unlinked);
+        }
+        drop(queue);
+        let woken = unlinked.len();
+        for signal in unlinked {
+            signal.wake(DEFAULT_UNPARK_TOKEN);
+        }
+        woken
+    }
+
+    pub(crate) fn unpark_requeue(
+        from: usize,
+        to: usize,
+        wake_count: usize,
+        requeue_count: usize,
+    ) -> RequeueResult {
+        unpark_requeue_with(
+            from,
+            to,
+            || (wake_count, requeue_count),
+            |_, _| DEFAULT_UNPARK_TOKEN,
+        )
+    }
+
+    pub(crate) fn unpark_requeue_with(
+        from: usize,
+        to: usize,
+        counts: impl FnOnce() -> (usize, usize),
+        callback: impl FnOnce(RequeueResult, bool) -> usize,
+    ) -> RequeueResult {
+        let mut queue = lock_queue();
+        let (wake_count, requeue_count) = counts();
+        let mut result = RequeueResult::default();
+        let mut unlinked = Vec::new();
+        let mut requeued = Vec::new();
+        queue.waiters.retain(|waiter| {
+            if !waiter.is_parked_on(from, ADDRESS_TAG) {
+                return true;
+            }
+            if result.unparked < wake_count {
+                waiter.claim();
+                result.unparked += 1;
+                unlinked.push(waiter.signal.clone());
+                return false;
+            }
+            if result.requeued == requeue_count {
+                return true;
+            }
+            result.requeued += 1;
+            // requeueing to the same address is a no-op, but still counted
+            if from == to {
+                return true;
+            }
+            requeued.push(waiter.signal.clone());
+            false
+        });
+        // behind the threads which were already parked on `to`
+        queue
+            .waiters
+            .extend(requeued.into_iter().map(|signal| Waiter {
+                addr: to,
+                tag: ADDRESS_TAG,
+                signal,
+            }));
+        let has_more = queue.has_waiters(from, ADDRESS_TAG);
+        let wake = WakeOnUnwind(&unlinked);
+        let token = callback(result, has_more);
+        core::mem::forget(wake);
+        drop(queue);
+        for signal in unlinked {
+            signal.wake(token);
+        }
+        result
+    }
+
+    /// Miri can't `fork`, so this only empties the queue.
+    #[cfg(unix)]
+    pub(crate) unsafe fn reinit_after_fork() {
+        *lock(&QUEUE) = Queue::new();
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) struct AsyncWaiter {
+        signal: Arc<Signal>,
+        registered: AtomicBool,
+    }
+
+    #[cfg(feature = "async")]
+    impl AsyncWaiter {
+        pub(crate) fn new() -> Self {
+            Self {
+                signal: Arc::new(Signal::default()),
+                registered: AtomicBool::new(false),
+            }
+        }
+
+        /// Queues the waiter on `addr` if `expected` returns true.
+        ///
+        /// # Safety
+        ///
+        /// - can only be called once.
+        pub(crate) unsafe fn register(
+            &self,
+            addr: usize,
+            expected: impl FnOnce() -> bool,
+            waker: &Waker,
+        ) -> bool {
+            let mut queue = lock_queue();
+            if !expected() {
+                return false;
+            }
+            lock(&self.signal.state).waker = Some(waker.clone());
+            queue.waiters.push_back(Waiter {
+                addr,
+                tag: ADDRESS_TAG,
+                signal: self.signal.clone(),
+            });
+            self.registered.store(true, Relaxed);
+            true
+        }
+
+        /// Returns true once the waiter was unparked, otherwise
+        /// replaces the waker that unparking it wakes.
+        pub(crate) fn poll(&self, waker: &Waker) -> bool {
+            let mut state = lock(&self.signal.state);
+            if state.token.is_some() {
+                return true;
+            }
+            state.waker = Some(waker.clone());
+            false
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl Drop for AsyncWaiter {
+        fn drop(&mut self) {
+            if self.registered.load(Relaxed) {
+                lock_queue().remove(&self.signal);
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use crate::{park, unpark_all};
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+/// The closure panicked, it can only be run again with `call_once_force`.
+const POISONED: u8 = 3;
+/// Threads are parked on the state, waiting for the running closure.
+const PARKED: u8 = 4;
+
+/// Runs a closure once, like `std::sync::Once`, in a single byte.
+///
+/// Threads which call it while the closure runs park until it's done, and
+/// once it's done, calls only cost an atomic load. If the closure panics,
+/// the `Once` is poisoned: [`call_once`](Once::call_once) panics from then
+/// on, and [`call_once_force`](Once::call_once_force) runs its closure
+/// again, telling it about the poisoning.
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+///
+/// use sparking_lot_core::Once;
+///
+/// static INIT: Once = Once::new();
+///
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| INIT.call_once(|| println!("only printed once")));
+///     }
+/// });
+/// assert!(INIT.is_completed());
+/// ```
+#[derive(Debug, Default)]
+pub struct Once {
+    state: AtomicU8,
+}
+
+/// The state of a [`Once`], given to the closure of
+/// [`call_once_force`](Once::call_once_force).
+#[derive(Debug)]
+pub struct OnceState {
+    poisoned: bool,
+}
+
+impl OnceState {
+    /// Returns whether the `Once` was poisoned by a closure
+    /// which panicked before this one ran.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+}
+
+/// Publishes how the closure ended, poisoning the `Once` if it panicked,
+/// and wakes the threads waiting for it.
+struct Running<'a> {
+    state: &'a AtomicU8,
+    done: u8,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        if self.state.swap(self.done, Release) & PARKED != 0 {
+            unpark_all(self.state);
+        }
+    }
+}
+
+impl Once {
+    /// Creates a `Once` which hasn't run yet.
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(INCOMPLETE),
+        }
+    }
+
+    /// Runs `f` if no closure of this `Once` has completed yet, and
+    /// returns once one has.
+    ///
+    /// # Panics
+    ///
+    /// If the `Once` is poisoned, or becomes poisoned while waiting.
+    #[inline]
+    pub fn call_once(&self, f: impl FnOnce()) {
+        if self.is_completed() {
+            return;
+        }
+        let mut f = Some(f);
+        self.call(false, &mut |_| (f.take().unwrap())());
+    }
+
+    /// Like [`call_once`](Once::call_once), but runs `f` even if the `Once`
+    /// is poisoned, in which case the [`OnceState`] says so.
+    #[inline]
+    pub fn call_once_force(&self, f: impl FnOnce(&OnceState)) {
+        if self.is_completed() {
+            return;
+        }
+        let mut f = Some(f);
+        self.call(true, &mut |state| (f.take().unwrap())(state));
+    }
+
+    /// Returns whether a closure of this `Once` has completed.
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        self.state.load(Acquire) == COMPLETE
+    }
+
+    #[cold]
+    fn call(&self, ignore_poison: bool, f: &mut dyn FnMut(&OnceState)) {
+        loop {
+            let state = self.state.load(Acquire);
+            match state & !PARKED {
+                COMPLETE => return,
+                POISONED if !ignore_poison => {
+                    panic!("sparking-lot-core: `Once` poisoned by a panicking closure")
+                }
+                INCOMPLETE | POISONED => {
+                    if self
+                        .state
+                        .compare_exchange_weak(state, RUNNING, Acquire, Relaxed)
+                        .is_err()
+                    {
+                        continue;
+                    }
+                    let mut running = Running {
+                        state: &self.state,
+                        done: POISONED,
+                    };
+                    f(&OnceState {
+                        poisoned: state == POISONED,
+                    });
+                    running.done = COMPLETE;
+                    return;
+                }
+                _ => {
+                    //SAFETY: `expected` only updates an atomic and the address is private
+                    unsafe {
+                        park(&self.state, || {
+                            self.state
+                                .fetch_update(Relaxed, Relaxed, |state| {
+                                    (state & !PARKED == RUNNING).then_some(state | PARKED)
+                                })
+                                .is_ok()
+                        })
+                    };
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use core::future::Future;
+use core::marker::PhantomPinned;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::parking_lot;
+
+/// The future returned by [`park_async`](crate::park_async).
+///
+/// It's a named type so it can be stored in the futures of async primitives
+/// (e.g. a `LockFuture` of an async mutex) without boxing it. Dropping it
+/// before it completes removes the task from the queue of its address.
+#[must_use = "futures do nothing unless polled"]
+pub struct ParkFuture<F> {
+    waiter: parking_lot::AsyncWaiter,
+    addr: usize,
+    /// Taken by the first poll.
+    expected: Option<F>,
+    done: bool,
+    // `waiter` can't move once it's registered
+    _pinned: PhantomPinned,
+}
+
+impl<F> ParkFuture<F> {
+    pub(crate) fn new(addr: usize, expected: F) -> Self {
+        Self {
+            waiter: parking_lot::AsyncWaiter::new(),
+            addr,
+            expected: Some(expected),
+            done: false,
+            _pinned: PhantomPinned,
+        }
+    }
+}
+
+impl<F: FnOnce() -> bool> Future for ParkFuture<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        //SAFETY: `waiter` isn't moved out of `self`, `expected` isn't pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        assert!(!this.done, "`ParkFuture` polled after completion");
+        let ready = match this.expected.take() {
+            //SAFETY: `waiter` is pinned and only registered once
+            Some(expected) => unsafe { !this.waiter.register(this.addr, expected, cx.waker()) },
+            None => this.waiter.poll(cx.waker()),
+        };
+        this.done = ready;
+        match ready {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        }
+    }
+}
+
+impl<F> core::fmt::Debug for ParkFuture<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ParkFuture")
+            .field("addr", &core::ptr::without_provenance::<()>(self.addr))
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,15 @@
+//! Atomics for the non-`loom` code, which come from `portable-atomic`
+//! with the feature of the same name, so that targets without native
+//! compare and swap (e.g. `thumbv6m`) are supported.
+
+// not every parker needs all of them
+#![allow(unused_imports)]
+
+#[cfg(not(feature = "portable-atomic"))]
+pub(crate) use core::sync::atomic::{
+    fence, AtomicBool, AtomicI32, AtomicPtr, AtomicU16, AtomicU8, AtomicUsize,
+};
+#[cfg(feature = "portable-atomic")]
+pub(crate) use portable_atomic::{
+    fence, AtomicBool, AtomicI32, AtomicPtr, AtomicU16, AtomicU8, AtomicUsize,
+};
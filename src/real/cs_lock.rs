@@ -0,0 +1,59 @@
+//! Bucket locks which enter a critical section of the `critical-section` crate.
+//!
+//! On single-core targets there is nothing to spin against except
+//! interrupt handlers, so a critical section is both smaller than the
+//! spinlock and the only lock which can't deadlock with them.
+
+use core::cell::UnsafeCell;
+use core::ops::Deref;
+
+/// A bucket lock which holds a critical section while it's locked.
+pub(crate) struct Mutex<T> {
+    data: UnsafeCell<T>,
+}
+
+// The data is only accessed inside a critical section.
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub(crate) const fn new(data: T) -> Self {
+        Self {
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        MutexGuard {
+            mutex: self,
+            state: unsafe { critical_section::acquire() },
+        }
+    }
+
+    /// Entering a critical section never waits for another holder.
+    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        Some(self.lock())
+    }
+}
+
+/// Critical sections have to be released in the reverse order they
+/// were acquired in, so guards must be dropped in reverse order too.
+pub(crate) struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+    state: critical_section::RestoreState,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        //SAFETY: the critical section is held while the guard is alive
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        //SAFETY: `state` came from the matching `acquire`
+        unsafe { critical_section::release(self.state) };
+    }
+}
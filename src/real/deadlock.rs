@@ -0,0 +1,161 @@
+//! Finds parked threads which wait on each other, for `deadlock-detection`.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+#[cfg(feature = "static-only")]
+compile_error!("`deadlock-detection` allocates, so it can't be used with `static-only`");
+
+/// A thread and the resources it holds, shared with
+/// the `ThreadData`s it parks with.
+pub(crate) struct ThreadInfo {
+    id: ThreadId,
+    name: Option<String>,
+    resources: Mutex<Vec<usize>>,
+}
+
+impl ThreadInfo {
+    fn resources(&self) -> MutexGuard<'_, Vec<usize>> {
+        self.resources.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+thread_local! {
+    static CURRENT: Arc<ThreadInfo> = {
+        let thread = thread::current();
+        Arc::new(ThreadInfo {
+            id: thread.id(),
+            name: thread.name().map(String::from),
+            resources: Mutex::new(Vec::new()),
+        })
+    };
+}
+
+/// None if the thread-locals of the thread are already destroyed,
+/// in which case its parks aren't checked.
+pub(crate) fn current() -> Option<Arc<ThreadInfo>> {
+    CURRENT.try_with(Arc::clone).ok()
+}
+
+pub(crate) fn acquire_resource(addr: usize) {
+    let _ = CURRENT.try_with(|info| info.resources().push(addr));
+}
+
+pub(crate) fn release_resource(addr: usize) {
+    let _ = CURRENT.try_with(|info| {
+        let mut resources = info.resources();
+        // resources are usually released in the reverse order
+        if let Some(idx) = resources.iter().rposition(|&held| held == addr) {
+            resources.remove(idx);
+        }
+    });
+}
+
+/// A thread found by [`check_deadlock`](crate::check_deadlock).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeadlockedThread {
+    /// The id of the thread.
+    pub thread_id: ThreadId,
+    /// The name of the thread, if it has one.
+    pub name: Option<String>,
+    /// The address the thread is parked on.
+    pub parked_on: *const (),
+    /// The resources the thread holds, in the order they were acquired.
+    pub holds: Vec<*const ()>,
+}
+
+/// Groups the parked threads (and the addresses they're parked on) into the
+/// cycles they form: a thread waits for every thread which holds its address.
+pub(crate) fn find_cycles(parked: &[(Arc<ThreadInfo>, usize)]) -> Vec<Vec<DeadlockedThread>> {
+    let holds: Vec<Vec<usize>> = parked
+        .iter()
+        .map(|(info, _)| info.resources().clone())
+        .collect();
+    let waits_for = |from: usize| {
+        let (addr, holds) = (parked[from].1, &holds);
+        (0..parked.len()).filter(move |&to| holds[to].contains(&addr))
+    };
+
+    // Tarjan's algorithm, the cycles are the strongly connected components
+    // with more than one thread, or with a thread which waits for itself
+    struct Search {
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        next: usize,
+        components: Vec<Vec<usize>>,
+    }
+    fn visit<I: Iterator<Item = usize>>(
+        search: &mut Search,
+        node: usize,
+        waits_for: &impl Fn(usize) -> I,
+    ) {
+        search.index[node] = Some(search.next);
+        search.low[node] = search.next;
+        search.next += 1;
+        search.stack.push(node);
+        search.on_stack[node] = true;
+        for to in waits_for(node) {
+            match search.index[to] {
+                None => {
+                    visit(search, to, waits_for);
+                    search.low[node] = search.low[node].min(search.low[to]);
+                }
+                Some(index) if search.on_stack[to] => {
+                    search.low[node] = search.low[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+        if Some(search.low[node]) == search.index[node] {
+            let mut component = Vec::new();
+            loop {
+                let member = search.stack.pop().unwrap();
+                search.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            search.components.push(component);
+        }
+    }
+
+    let mut search = Search {
+        index: vec![None; parked.len()],
+        low: vec![0; parked.len()],
+        stack: Vec::new(),
+        on_stack: vec![false; parked.len()],
+        next: 0,
+        components: Vec::new(),
+    };
+    for node in 0..parked.len() {
+        if search.index[node].is_none() {
+            visit(&mut search, node, &waits_for);
+        }
+    }
+    search
+        .components
+        .into_iter()
+        .filter(|component| {
+            component.len() > 1 || waits_for(component[0]).any(|to| to == component[0])
+        })
+        .map(|component| {
+            component
+                .into_iter()
+                .rev()
+                .map(|node| {
+                    let (info, addr) = &parked[node];
+                    DeadlockedThread {
+                        thread_id: info.id,
+                        name: info.name.clone(),
+                        parked_on: *addr as *const (),
+                        holds: holds[node].iter().map(|&held| held as *const ()).collect(),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,121 @@
+//! Reports parking and unparking to a user hook, for `instrument`.
+
+use crate::real::atomic::AtomicPtr;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::Ordering::{Acquire, Release};
+
+/// A park or an unpark, passed to the hook set with
+/// [`set_event_hook`](crate::set_event_hook).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// The calling thread is about to sleep on `addr`, since `expected` returned true.
+    /// For [`park_then_validate`](crate::park_then_validate), it's reported once
+    /// the thread is queued, before `validate` runs.
+    Park {
+        /// The address passed to [`park`](crate::park()).
+        addr: *const (),
+    },
+    /// The calling thread stopped sleeping on `addr`. Reported by the same thread
+    /// as the [`Park`](Event::Park) before it, so the time between them is the
+    /// time it spent parked.
+    Wake {
+        /// The address the thread parked on, even if it was requeued since.
+        addr: *const (),
+        /// True if the thread gave up waiting instead of being unparked.
+        timed_out: bool,
+    },
+    /// The calling thread stopped waiting on `addr` without sleeping, since the
+    /// `validate` of [`park_then_validate`](crate::park_then_validate) returned
+    /// false. Reported instead of a [`Wake`](Event::Wake).
+    Invalid {
+        /// The address the thread was queued on.
+        addr: *const (),
+    },
+    /// Threads parked on `addr` were unparked, reported once the bucket of
+    /// `addr` is unlocked.
+    Unpark {
+        /// The address passed to the unpark function.
+        addr: *const (),
+        /// Which unpark function was called.
+        kind: UnparkKind,
+        /// How many threads were unparked, possibly 0.
+        unparked: usize,
+    },
+}
+
+/// The unpark function an [`Event::Unpark`] comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum UnparkKind {
+    /// [`unpark_one`](crate::unpark_one) and its variants which take a
+    /// token, a callback or a `release` closure.
+    One,
+    /// [`unpark_one_fair`](crate::unpark_one_fair).
+    OneFair,
+    /// [`unpark_some`](crate::unpark_some) and
+    /// [`unpark_some_release`](crate::unpark_some_release).
+    Some,
+    /// [`unpark_all`](crate::unpark_all) and
+    /// [`unpark_all_release`](crate::unpark_all_release).
+    All,
+    /// One request of [`unpark_many`](crate::unpark_many).
+    Many,
+    /// [`unpark_requeue`](crate::unpark_requeue), on the `from` address.
+    /// Requeued threads aren't counted as unparked.
+    Requeue,
+    /// [`unpark_handle`](crate::unpark_handle), on the address the thread is parked on.
+    Handle,
+}
+
+static HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+pub(crate) fn set(hook: fn(&Event)) {
+    HOOK.store(hook as *mut (), Release);
+}
+
+pub(crate) fn clear() {
+    HOOK.store(ptr::null_mut(), Release);
+}
+
+#[inline(always)]
+fn report(event: impl FnOnce() -> Event) {
+    let hook = HOOK.load(Acquire);
+    if !hook.is_null() {
+        //SAFETY: only `fn(&Event)`s are stored in `HOOK`
+        let hook = unsafe { mem::transmute::<*mut (), fn(&Event)>(hook) };
+        hook(&event());
+    }
+}
+
+#[inline(always)]
+pub(crate) fn park(addr: usize) {
+    report(|| Event::Park {
+        addr: addr as *const (),
+    });
+}
+
+#[inline(always)]
+pub(crate) fn wake(addr: usize, timed_out: bool) {
+    report(|| Event::Wake {
+        addr: addr as *const (),
+        timed_out,
+    });
+}
+
+#[inline(always)]
+pub(crate) fn invalid(addr: usize) {
+    report(|| Event::Invalid {
+        addr: addr as *const (),
+    });
+}
+
+#[inline(always)]
+pub(crate) fn unpark(addr: usize, kind: UnparkKind, unparked: usize) {
+    report(|| Event::Unpark {
+        addr: addr as *const (),
+        kind,
+        unparked,
+    });
+}
@@ -0,0 +1,93 @@
+//! Deferred unparking for interrupt handlers.
+//!
+//! Interrupt handlers can't take bucket locks (the interrupted thread may
+//! hold them), so they only record the wake-up in a fixed set of slots with
+//! a bounded number of atomic operations. The recorded wake-ups are performed
+//! by the next thread that calls into the lot, or by parked threads whose
+//! parkers poll (the spinning and `single-core` parkers).
+
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use crate::real::atomic::{AtomicBool, AtomicU8, AtomicUsize};
+
+const SLOT_COUNT: usize = 8;
+
+const FREE: u8 = 0;
+const WRITING: u8 = 1;
+const UNPARK_ONE: u8 = 2;
+const UNPARK_ALL: u8 = 3;
+const DRAINING: u8 = 4;
+
+struct Slot {
+    state: AtomicU8,
+    addr: AtomicUsize,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const SLOT_INIT: Slot = Slot {
+    state: AtomicU8::new(FREE),
+    addr: AtomicUsize::new(0),
+};
+static SLOTS: [Slot; SLOT_COUNT] = [SLOT_INIT; SLOT_COUNT];
+static PENDING: AtomicBool = AtomicBool::new(false);
+
+fn defer(addr: usize, kind: u8) -> bool {
+    for slot in &SLOTS {
+        if slot
+            .state
+            .compare_exchange(FREE, WRITING, Acquire, Relaxed)
+            .is_ok()
+        {
+            slot.addr.store(addr, Relaxed);
+            slot.state.store(kind, Release);
+            PENDING.store(true, Release);
+            return true;
+        }
+    }
+    false
+}
+
+pub(crate) fn unpark_one(addr: usize) -> bool {
+    defer(addr, UNPARK_ONE)
+}
+
+pub(crate) fn unpark_all(addr: usize) -> bool {
+    defer(addr, UNPARK_ALL)
+}
+
+/// Performs the deferred wake-ups. Must be called from thread context.
+#[inline(always)]
+pub(crate) fn drain() {
+    if PENDING.load(Relaxed) {
+        drain_slow();
+    }
+}
+
+#[cold]
+fn drain_slow() {
+    /* `defer` sets `PENDING` after publishing the slot, so every slot
+     * published before this swap is visible below. Slots published
+     * later set `PENDING` again.
+     */
+    if !PENDING.swap(false, Acquire) {
+        return;
+    }
+    for slot in &SLOTS {
+        let kind = slot.state.load(Acquire);
+        if (kind == UNPARK_ONE || kind == UNPARK_ALL)
+            && slot
+                .state
+                .compare_exchange(kind, DRAINING, Acquire, Relaxed)
+                .is_ok()
+        {
+            let addr = slot.addr.load(Relaxed);
+            slot.state.store(FREE, Release);
+            // these call `drain` too, but `PENDING` is already cleared
+            if kind == UNPARK_ONE {
+                super::parking_lot::unpark_one(addr, |_| crate::DEFAULT_UNPARK_TOKEN);
+            } else {
+                super::parking_lot::unpark_all(addr, |_| ());
+            }
+        }
+    }
+}
@@ -13,26 +13,54 @@ if #[cfg(loom)] {
             pub(crate) use loom::sync::atomic::{AtomicPtr, AtomicBool};
         }
         else { // default to the old impl
-            pub(crate) use loom::sync::Condvar;
+            pub(crate) use loom::sync::{Condvar, Mutex as CondvarMutex};
         }
 
     }
 }
-else {
+else if #[cfg(feature = "std")] {
     pub(crate) use std::cell::Cell;
-    pub(crate) use std::sync::{Mutex, MutexGuard};
+    pub(crate) use super::word_lock::{Mutex, MutexGuard};
 
     cfg_if! {
 
-        if #[cfg(feature = "thread-parker")] {
+        if #[cfg(any(feature = "freertos", feature = "zephyr", feature = "flag-parker"))] {
+            // the parker doesn't use `std`
+        }
+        else if #[cfg(all(target_family = "wasm", not(target_feature = "atomics")))] {
+            // falls back to the flag parker
+        }
+        else if #[cfg(all(
+            target_arch = "wasm32",
+            target_feature = "atomics",
+            not(feature = "thread-parker"),
+        ))] {
+            // the wasm parker doesn't use `std`
+        }
+        else if #[cfg(feature = "thread-parker")] {
             pub(crate) use std::thread;
             pub(crate) use std::sync::atomic::{AtomicPtr, AtomicBool};
         }
+        else if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            // the futex parker doesn't use `std`
+        }
         else { // default to the old impl
-            pub(crate) use std::sync::Condvar;
+            pub(crate) use std::sync::{Condvar, Mutex as CondvarMutex};
         }
 
     }
 }
+else if #[cfg(feature = "critical-section")] {
+    pub(crate) use core::cell::Cell;
+    pub(crate) use super::cs_lock::{Mutex, MutexGuard};
+}
+else if #[cfg(feature = "single-core")] {
+    pub(crate) use core::cell::Cell;
+    pub(crate) use super::single_core::{Mutex, MutexGuard};
+}
+else { // no_std
+    pub(crate) use core::cell::Cell;
+    pub(crate) use super::spin::{Mutex, MutexGuard};
+}
 
 }
@@ -1,3 +1,35 @@
+#[cfg(not(loom))]
+mod atomic;
+#[cfg(all(feature = "critical-section", not(any(loom, feature = "std"))))]
+mod cs_lock;
+#[cfg(all(feature = "deadlock-detection", not(loom)))]
+pub(crate) mod deadlock;
+#[cfg(all(feature = "instrument", not(loom)))]
+pub(crate) mod instrument;
+#[cfg(all(
+    not(any(loom, feature = "std")),
+    any(target_has_atomic = "ptr", feature = "portable-atomic")
+))]
+pub(crate) mod isr;
 mod loom;
-mod park;
+#[cfg(all(feature = "node-pool", not(loom)))]
+mod node_pool;
+pub(crate) mod park;
 pub(super) mod parking_lot;
+#[cfg(all(unix, feature = "signal-mask", not(loom)))]
+pub(crate) mod signal;
+#[cfg(all(feature = "single-core", not(loom)))]
+mod single_core;
+#[cfg(not(any(
+    loom,
+    feature = "std",
+    feature = "single-core",
+    feature = "critical-section"
+)))]
+mod spin;
+#[cfg(all(feature = "stats", not(loom)))]
+pub(crate) mod stats;
+#[cfg(all(feature = "watchdog", not(loom)))]
+pub(crate) mod watchdog;
+#[cfg(any(all(feature = "std", not(loom)), all(test, loom)))]
+mod word_lock;
@@ -0,0 +1,107 @@
+//! A fixed pool of waiter nodes, for targets without TLS.
+
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use crate::real::atomic::AtomicBool;
+
+/// The number of nodes, read from `SPARKING_LOT_CORE_NODE_POOL`
+/// at compile time. Defaults to 16.
+pub(crate) const POOL_SIZE: usize = match option_env!("SPARKING_LOT_CORE_NODE_POOL") {
+    Some(s) => parse(s),
+    None => 16,
+};
+
+const fn parse(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    assert!(
+        !bytes.is_empty(),
+        "SPARKING_LOT_CORE_NODE_POOL must be a positive integer"
+    );
+    let mut i = 0;
+    let mut n = 0usize;
+    while i < bytes.len() {
+        let digit = bytes[i];
+        assert!(
+            digit.is_ascii_digit(),
+            "SPARKING_LOT_CORE_NODE_POOL must be a positive integer"
+        );
+        n = n * 10 + (digit - b'0') as usize;
+        i += 1;
+    }
+    assert!(
+        n > 0,
+        "SPARKING_LOT_CORE_NODE_POOL must be a positive integer"
+    );
+    n
+}
+
+/// Types which can be stored in a [`NodePool`].
+pub(crate) trait PoolNode {
+    const INIT: Self;
+}
+
+struct Node<T> {
+    in_use: AtomicBool,
+    data: T,
+}
+
+impl<T: PoolNode> Node<T> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        in_use: AtomicBool::new(false),
+        data: T::INIT,
+    };
+}
+
+pub(crate) struct NodePool<T, const N: usize> {
+    nodes: [Node<T>; N],
+}
+
+// Nodes are only accessed by the thread that borrowed them,
+// or by other threads through the borrower's own synchronisation.
+unsafe impl<T, const N: usize> Sync for NodePool<T, N> {}
+
+impl<T: PoolNode, const N: usize> NodePool<T, N> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            nodes: [Node::INIT; N],
+        }
+    }
+
+    /// Marks every node as free.
+    ///
+    /// # Safety
+    ///
+    /// - no node can be borrowed by a thread which still exists.
+    #[cfg(all(unix, feature = "std"))]
+    pub(crate) unsafe fn reset(&self) {
+        for node in &self.nodes {
+            node.in_use.store(false, Relaxed);
+        }
+    }
+
+    /// Borrows a free node, or returns `None` if all of them are in use.
+    pub(crate) fn acquire(&self) -> Option<Borrowed<'_, T>> {
+        self.nodes.iter().find_map(|node| {
+            node.in_use
+                .compare_exchange(false, true, Acquire, Relaxed)
+                .is_ok()
+                .then(|| Borrowed(node))
+        })
+    }
+}
+
+/// Returns the node to the pool when dropped.
+pub(crate) struct Borrowed<'a, T>(&'a Node<T>);
+
+impl<T> Borrowed<'_, T> {
+    pub(crate) fn get(&self) -> &T {
+        &self.0.data
+    }
+}
+
+impl<T> Drop for Borrowed<'_, T> {
+    fn drop(&mut self) {
+        self.0.in_use.store(false, Release);
+    }
+}
@@ -0,0 +1,100 @@
+use core::cell::{Cell, UnsafeCell};
+use core::hint::spin_loop;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use crate::real::atomic::{AtomicBool, AtomicU8};
+use crate::RawParker;
+
+const UNSET: u8 = 0;
+const SETTING: u8 = 1;
+const SET: u8 = 2;
+
+/// The `RawParker` installed with `set_raw_parker`, which can only be set once.
+struct Installed {
+    state: AtomicU8,
+    parker: UnsafeCell<Option<&'static dyn RawParker>>,
+}
+
+//SAFETY: `parker` is only written once, before `state` is set to `SET`.
+unsafe impl Sync for Installed {}
+
+static INSTALLED: Installed = Installed {
+    state: AtomicU8::new(UNSET),
+    parker: UnsafeCell::new(None),
+};
+
+pub(crate) fn install(parker: &'static dyn RawParker) -> bool {
+    if INSTALLED
+        .state
+        .compare_exchange(UNSET, SETTING, Relaxed, Relaxed)
+        .is_err()
+    {
+        return false;
+    }
+    //SAFETY: only this thread got to `SETTING`
+    unsafe { *INSTALLED.parker.get() = Some(parker) };
+    INSTALLED.state.store(SET, Release);
+    true
+}
+
+fn installed() -> Option<&'static dyn RawParker> {
+    if INSTALLED.state.load(Acquire) == SET {
+        //SAFETY: `parker` isn't written after `SET`
+        unsafe { *INSTALLED.parker.get() }
+    } else {
+        None
+    }
+}
+
+/// A parker which blocks with the installed `RawParker`. Until one is
+/// installed, it polls a flag like the default `no_std` parker.
+pub(crate) struct Parker {
+    /// The handle of the parking thread, if a `RawParker` was installed
+    /// when it started parking.
+    handle: Cell<Option<usize>>,
+    notified: AtomicBool,
+}
+
+impl Parker {
+    pub(crate) const fn new() -> Self {
+        Self {
+            handle: Cell::new(None),
+            notified: AtomicBool::new(false),
+        }
+    }
+}
+
+impl super::ParkerT for Parker {
+    const CHEAP_NEW: bool = true;
+
+    fn prepare_park(&self) {
+        self.handle.set(installed().map(|raw| raw.current()));
+        self.notified.store(false, Relaxed);
+    }
+
+    unsafe fn park(&self) {
+        let raw = self.handle.get().and(installed());
+        // `RawParker::park` may return spuriously, so the flag is always rechecked
+        while !self.notified.load(Acquire) {
+            match raw {
+                Some(raw) => raw.park(),
+                None => {
+                    #[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
+                    crate::real::isr::drain();
+                    spin_loop();
+                }
+            }
+        }
+    }
+
+    unsafe fn unpark(this: *const Self) {
+        // `*this` may be destroyed right after `notified` is set
+        let handle = (*this).handle.get();
+        (*this).notified.store(true, Release);
+        if let (Some(handle), Some(raw)) = (handle, installed()) {
+            raw.unpark(handle);
+        }
+    }
+}
+
+unsafe impl Sync for Parker {}
@@ -0,0 +1,155 @@
+use core::ffi::c_void;
+use core::ptr::addr_of;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use core::sync::atomic::{AtomicU32, AtomicUsize};
+
+use super::{std_mutex, ParkerT};
+
+type UlockWait2 = unsafe extern "C" fn(u32, *mut c_void, u64, u64, u64) -> i32;
+type UlockWake = unsafe extern "C" fn(u32, *mut c_void, u64) -> i32;
+
+// from `sys/ulock.h`
+const UL_COMPARE_AND_WAIT: u32 = 1;
+const ULF_NO_ERRNO: u32 = 0x0100_0000;
+
+const UNRESOLVED: usize = 0;
+const UNAVAILABLE: usize = 1;
+
+/* `__ulock_wait2` is only available since macOS 11 (iOS 14), so both
+ * functions are looked up at runtime. `WAKE` is stored before `WAIT`,
+ * which is the one checked.
+ */
+static WAIT: AtomicUsize = AtomicUsize::new(UNRESOLVED);
+static WAKE: AtomicUsize = AtomicUsize::new(UNRESOLVED);
+
+#[cold]
+fn resolve() -> usize {
+    //SAFETY: the names are nul terminated
+    let (wait, wake) = unsafe {
+        (
+            libc::dlsym(libc::RTLD_DEFAULT, c"__ulock_wait2".as_ptr()) as usize,
+            libc::dlsym(libc::RTLD_DEFAULT, c"__ulock_wake".as_ptr()) as usize,
+        )
+    };
+    if wait <= UNAVAILABLE || wake <= UNAVAILABLE {
+        WAIT.store(UNAVAILABLE, Relaxed);
+        return UNAVAILABLE;
+    }
+    WAKE.store(wake, Relaxed);
+    WAIT.store(wait, Release);
+    wait
+}
+
+/// Returns `__ulock_wait2`, if it's available.
+#[inline(always)]
+fn ulock_wait2() -> Option<UlockWait2> {
+    let wait = match WAIT.load(Acquire) {
+        UNRESOLVED => resolve(),
+        wait => wait,
+    };
+    //SAFETY: resolved with `dlsym`
+    (wait != UNAVAILABLE).then(|| unsafe { core::mem::transmute::<usize, UlockWait2>(wait) })
+}
+
+/// Only called after `ulock_wait2` returned `Some`.
+#[inline(always)]
+fn ulock_wake() -> UlockWake {
+    //SAFETY: stored before `WAIT`, which was loaded with `Acquire`
+    unsafe { core::mem::transmute::<usize, UlockWake>(WAKE.load(Relaxed)) }
+}
+
+const EMPTY: u32 = 0;
+const NOTIFIED: u32 = 1;
+
+/// A parker which sleeps with `__ulock_wait2` on its own state. On
+/// Darwin versions without it, the mutex parker is used instead.
+///
+/// The choice is made in `prepare_park`, before the parker can be
+/// unparked, so both sides always agree on it.
+pub(crate) struct Parker {
+    state: AtomicU32,
+    fallback: std_mutex::Parker,
+}
+
+impl Parker {
+    pub(crate) const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(EMPTY),
+            fallback: std_mutex::Parker::new(),
+        }
+    }
+
+    /// Sleeps while the state is `EMPTY`, or until `timeout` (in ns, 0 for
+    /// none) passes. Spurious returns (`EINTR`, `EFAULT`) are fine, the callers
+    /// recheck the state.
+    fn wait(&self, wait: UlockWait2, timeout: u64) {
+        //SAFETY: the address is valid for the call
+        unsafe {
+            wait(
+                UL_COMPARE_AND_WAIT | ULF_NO_ERRNO,
+                &self.state as *const AtomicU32 as *mut c_void,
+                EMPTY.into(),
+                timeout,
+                0,
+            );
+        }
+    }
+
+    fn try_consume(&self) -> bool {
+        self.state
+            .compare_exchange(NOTIFIED, EMPTY, Acquire, Relaxed)
+            .is_ok()
+    }
+}
+
+impl ParkerT for Parker {
+    const CHEAP_NEW: bool = false;
+
+    fn prepare_park(&self) {
+        ulock_wait2();
+    }
+
+    unsafe fn park(&self) {
+        let wait = match ulock_wait2() {
+            Some(wait) => wait,
+            None => return self.fallback.park(),
+        };
+        while !self.try_consume() {
+            self.wait(wait, 0);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    unsafe fn park_until(&self, deadline: std::time::Instant) -> bool {
+        let wait = match ulock_wait2() {
+            Some(wait) => wait,
+            None => return self.fallback.park_until(deadline),
+        };
+        while !self.try_consume() {
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            // at least 1ns, since 0 means no timeout, and saturated
+            let timeout = (deadline - now).as_nanos().clamp(1, u64::MAX.into()) as u64;
+            self.wait(wait, timeout);
+        }
+        true
+    }
+
+    unsafe fn unpark(this: *const Self) {
+        if ulock_wait2().is_none() {
+            return ParkerT::unpark(addr_of!((*this).fallback));
+        }
+        /* After the store the parked thread may return and destroy `*this`,
+         * but `__ulock_wake` only uses the address as a key, so this is fine
+         * even if the memory is reused in the meantime: at worst some other
+         * waiter sees a spurious wake-up, which they have to tolerate.
+         */
+        let state = addr_of!((*this).state);
+        (*state).store(NOTIFIED, Release);
+        ulock_wake()(UL_COMPARE_AND_WAIT | ULF_NO_ERRNO, state as *mut c_void, 0);
+    }
+}
+
+unsafe impl Sync for Parker {}
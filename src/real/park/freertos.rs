@@ -0,0 +1,75 @@
+use core::cell::Cell;
+use core::ffi::c_void;
+use core::ptr;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use crate::real::atomic::AtomicBool;
+
+type TaskHandle = *mut c_void;
+type TickType = u32;
+type BaseType = i32;
+
+/// `eIncrement` in the `eNotifyAction` mapping of the shim.
+const NOTIFY_INCREMENT: u8 = 2;
+
+/* These are the C shims compiled by `freertos-rust`, which wrap the FreeRTOS
+ * macros. Linking against `freertos-rust` (or providing the same symbols)
+ * is required when using the `freertos` feature.
+ */
+extern "C" {
+    fn freertos_rs_get_current_task() -> TaskHandle;
+    fn freertos_rs_task_notify_take(clear_count: u8, wait: TickType) -> u32;
+    fn freertos_rs_task_notify(task: TaskHandle, value: u32, action: u8) -> BaseType;
+    fn freertos_rs_max_wait() -> TickType;
+}
+
+/// A parker based on direct to task notifications.
+///
+/// The notification value (index 0) of the parked task is used as a
+/// counting semaphore, so other code in the same task shouldn't use
+/// it while it's parked.
+pub(crate) struct Parker {
+    task: Cell<TaskHandle>,
+    notified: AtomicBool,
+}
+
+impl Parker {
+    pub(crate) const fn new() -> Self {
+        Self {
+            task: Cell::new(ptr::null_mut()),
+            notified: AtomicBool::new(false),
+        }
+    }
+}
+
+impl super::ParkerT for Parker {
+    const CHEAP_NEW: bool = true;
+
+    fn prepare_park(&self) {
+        self.task.set(unsafe { freertos_rs_get_current_task() });
+        self.notified.store(false, Relaxed);
+    }
+
+    unsafe fn park(&self) {
+        /* Notifications left over from an earlier `unpark`, which lost
+         * the race with the `notified` check, may wake this up early, so
+         * the flag is always rechecked.
+         */
+        while !self.notified.load(Acquire) {
+            freertos_rs_task_notify_take(1, freertos_rs_max_wait());
+        }
+    }
+
+    unsafe fn unpark(this: *const Self) {
+        /* If the parked task has a higher priority than the current one,
+         * it preempts it right after `notified` is set (in SMP ports) or in
+         * `freertos_rs_task_notify` and may destroy `*this`, so the handle
+         * is read beforehand. The task itself outlives its `ThreadData`.
+         */
+        let task = (*this).task.get();
+        (*this).notified.store(true, Release);
+        freertos_rs_task_notify(task, 0, NOTIFY_INCREMENT);
+    }
+}
+
+unsafe impl Sync for Parker {}
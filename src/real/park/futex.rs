@@ -0,0 +1,86 @@
+use core::ptr::{self, addr_of};
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+const EMPTY: u32 = 0;
+const NOTIFIED: u32 = 1;
+
+/// A parker which sleeps with `futex(2)` on its own state.
+///
+/// It doesn't allocate, take any locks or panic, so parking can't unwind
+/// and the nodes can be created on the stack for free.
+pub(crate) struct Parker(AtomicU32);
+
+impl Parker {
+    pub(crate) const fn new() -> Self {
+        Self(AtomicU32::new(EMPTY))
+    }
+
+    /// Sleeps while the state is `EMPTY`, or until `timeout` passes. Spurious
+    /// returns (signals, `EAGAIN`, wakes for reused addresses) are fine, the
+    /// callers recheck the state.
+    fn wait(&self, timeout: Option<&libc::timespec>) {
+        //SAFETY: the futex word is valid for the call
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                &self.0 as *const AtomicU32,
+                libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                EMPTY,
+                timeout.map_or(ptr::null(), |t| t as *const libc::timespec),
+            );
+        }
+    }
+
+    fn try_consume(&self) -> bool {
+        self.0
+            .compare_exchange(NOTIFIED, EMPTY, Acquire, Relaxed)
+            .is_ok()
+    }
+}
+
+impl super::ParkerT for Parker {
+    const CHEAP_NEW: bool = true;
+    const CAN_PANIC: bool = false;
+
+    unsafe fn park(&self) {
+        while !self.try_consume() {
+            self.wait(None);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    unsafe fn park_until(&self, deadline: std::time::Instant) -> bool {
+        while !self.try_consume() {
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            let timeout = deadline - now;
+            let timeout = libc::timespec {
+                // saturate, very long timeouts are just sleeping forever
+                tv_sec: timeout.as_secs().try_into().unwrap_or(libc::time_t::MAX),
+                // always below 1e9, so it fits any c_long
+                tv_nsec: timeout.subsec_nanos() as _,
+            };
+            self.wait(Some(&timeout));
+        }
+        true
+    }
+
+    unsafe fn unpark(this: *const Self) {
+        /* After the store the parked thread may return and destroy `*this`,
+         * but waking a futex only uses its address, so this is fine even if
+         * the memory is reused in the meantime: at worst some other futex
+         * sees a spurious wake-up, which all of them have to tolerate.
+         */
+        let futex = addr_of!((*this).0);
+        (*this).0.store(NOTIFIED, Release);
+        libc::syscall(
+            libc::SYS_futex,
+            futex,
+            libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+            1,
+        );
+    }
+}
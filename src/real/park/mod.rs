@@ -1,24 +1,148 @@
 pub(crate) trait ParkerT {
+    // `static-only` never caches `ThreadData` in TLS
+    #[cfg_attr(feature = "static-only", allow(dead_code))]
     const CHEAP_NEW: bool;
+    /// False if `park` and `park_until` can't panic, in
+    /// which case parking doesn't need panic guards.
+    #[cfg_attr(
+        not(all(feature = "abort-on-panic", not(panic = "abort"))),
+        allow(dead_code)
+    )]
+    const CAN_PANIC: bool = true;
+    /// Must only return after `unpark`, so parkers which sleep in
+    /// syscalls have to retry them if they're interrupted by a
+    /// signal (`EINTR`).
+    ///
     /// # Safety
     ///
     /// - can only be called by one 'owner' thread
     unsafe fn park(&self);
+    /// Like `park`, but gives up once `deadline` passes, in which case it
+    /// returns false. A later `unpark` is then consumed by the next `park`.
+    ///
+    /// # Safety
+    ///
+    /// - can only be called by one 'owner' thread
+    #[cfg(all(
+        feature = "std",
+        not(any(loom, feature = "freertos", feature = "zephyr"))
+    ))]
+    unsafe fn park_until(&self, deadline: std::time::Instant) -> bool;
+    /// Called by the parking thread before `self` becomes
+    /// reachable by unparkers.
+    #[inline(always)]
+    fn prepare_park(&self) {}
     /// # Safety
     ///
     /// - must point to a living `Self`
     unsafe fn unpark(this: *const Self);
 }
 
+const _: () = assert!(
+    cfg!(feature = "thread-parker") as u8
+        + cfg!(feature = "flag-parker") as u8
+        + cfg!(feature = "freertos") as u8
+        + cfg!(feature = "zephyr") as u8
+        + cfg!(feature = "single-core") as u8
+        + cfg!(feature = "custom-parker") as u8
+        <= 1,
+    "only one of the parker features can be enabled"
+);
+
+#[cfg(all(feature = "static-only", feature = "thread-parker"))]
+compile_error!("`thread-parker` may allocate, so it can't be used with `static-only`");
+
+#[cfg(all(feature = "single-core", feature = "std"))]
+compile_error!("`single-core` is for bare-metal systems, disable `std`");
+
+#[cfg(all(feature = "custom-parker", feature = "std"))]
+compile_error!("`custom-parker` replaces the `no_std` parker, disable `std`");
+
+#[cfg(all(feature = "critical-section", feature = "std", not(loom)))]
+compile_error!("`critical-section` only replaces the `no_std` bucket locks, disable `std`");
+
+#[cfg(all(feature = "critical-section", feature = "single-core"))]
+compile_error!(
+    "`single-core` already masks interrupts in the bucket locks, disable `critical-section`"
+);
+
 cfg_if::cfg_if! {
 
-if #[cfg(feature = "thread-parker")] {
+if #[cfg(all(feature = "freertos", not(loom)))] {
+    mod freertos;
+    pub(crate) use freertos::Parker;
+}
+else if #[cfg(all(feature = "zephyr", not(loom)))] {
+    mod zephyr;
+    pub(crate) use zephyr::Parker;
+}
+else if #[cfg(all(feature = "single-core", not(loom)))] {
+    mod wfi;
+    pub(crate) use wfi::Parker;
+}
+else if #[cfg(all(feature = "custom-parker", not(loom)))] {
+    mod custom;
+    pub(crate) use custom::{install as install_raw_parker, Parker};
+}
+else if #[cfg(all(
+    target_arch = "wasm32",
+    target_feature = "atomics",
+    not(any(loom, feature = "flag-parker", feature = "thread-parker")),
+))] {
+    mod wasm;
+    pub(crate) use wasm::Parker;
+}
+else if #[cfg(any(
+    not(any(loom, feature = "std")),
+    all(feature = "flag-parker", not(loom)),
+    // std has no threads here, so nothing could unpark a blocked
+    // thread, and `Thread` handles and `Condvar`s are useless
+    all(not(loom), target_family = "wasm", not(target_feature = "atomics")),
+))] {
+    mod spin;
+    pub(crate) use spin::Parker;
+}
+else if #[cfg(feature = "thread-parker")] {
     mod std_thread;
     pub(crate) use std_thread::Parker;
 }
+else if #[cfg(all(any(target_os = "linux", target_os = "android"), not(loom)))] {
+    mod futex;
+    pub(crate) use futex::Parker;
+}
+else if #[cfg(all(any(target_os = "macos", target_os = "ios"), not(loom)))] {
+    mod darwin;
+    mod std_mutex;
+    pub(crate) use darwin::Parker;
+
+    // the fallback for older versions boxes its `Mutex` and `Condvar`
+    #[cfg(feature = "static-only")]
+    compile_error!("`static-only` with `std` isn't supported on this platform, disable `std`");
+}
+else if #[cfg(all(windows, not(loom)))] {
+    mod std_mutex;
+    mod windows;
+    pub(crate) use windows::Parker;
+}
 else {// default to the old impl
     mod std_mutex;
     pub(crate) use std_mutex::Parker;
+
+    // `std::sync::{Mutex, Condvar}` box their state on the first use on other platforms.
+    #[cfg(all(
+        feature = "static-only",
+        not(loom),
+        not(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "windows",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "dragonfly",
+            target_os = "fuchsia",
+        ))
+    ))]
+    compile_error!("`static-only` with `std` isn't supported on this platform, disable `std`");
 }
 
 }
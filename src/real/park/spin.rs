@@ -0,0 +1,59 @@
+#[cfg(not(feature = "std"))]
+use core::hint::spin_loop as relax;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use crate::real::atomic::AtomicBool;
+#[cfg(feature = "std")]
+use std::thread::yield_now as relax;
+
+/// The parker used without `std` or with `flag-parker`. It never touches
+/// thread handles, it just polls a flag. There is no portable way to put
+/// a thread to sleep in `core`, so without `std` it busy-waits, otherwise
+/// it yields the time slice between polls.
+pub(crate) struct Parker(AtomicBool);
+
+impl Parker {
+    pub(crate) const fn new() -> Self {
+        Self(AtomicBool::new(false))
+    }
+}
+
+impl super::ParkerT for Parker {
+    const CHEAP_NEW: bool = true;
+
+    unsafe fn park(&self) {
+        while self
+            .0
+            .compare_exchange_weak(true, false, Acquire, Relaxed)
+            .is_err()
+        {
+            #[cfg(all(
+                not(feature = "std"),
+                any(target_has_atomic = "ptr", feature = "portable-atomic")
+            ))]
+            crate::real::isr::drain();
+            relax();
+        }
+    }
+
+    #[cfg(feature = "std")]
+    unsafe fn park_until(&self, deadline: std::time::Instant) -> bool {
+        while self
+            .0
+            .compare_exchange_weak(true, false, Acquire, Relaxed)
+            .is_err()
+        {
+            if std::time::Instant::now() >= deadline {
+                return false;
+            }
+            relax();
+        }
+        true
+    }
+
+    unsafe fn unpark(this: *const Self) {
+        // After this store the parked thread may return and destroy `*this`,
+        // so it has to be the last access.
+        (*this).0.store(true, Release);
+    }
+}
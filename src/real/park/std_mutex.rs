@@ -1,4 +1,4 @@
-use crate::real::loom::{Condvar, Mutex};
+use crate::real::loom::{Condvar, CondvarMutex as Mutex};
 pub(crate) struct Parker {
     should_unpark: Mutex<bool>,
     condvar: Condvar,
@@ -54,6 +54,27 @@ impl super::ParkerT for Parker {
         }
     }
 
+    #[cfg(not(loom))]
+    unsafe fn park_until(&self, deadline: std::time::Instant) -> bool {
+        // see the note in `park` about panics
+        let mut should_unpark = self.should_unpark.lock().unwrap();
+        loop {
+            if *should_unpark {
+                *should_unpark = false;
+                return true;
+            }
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            should_unpark = self
+                .condvar
+                .wait_timeout(should_unpark, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
     unsafe fn unpark(this: *const Self) {
         // The dereferences are valid since it's required that
         // `this` is alive when the function begins, and it stays
@@ -39,6 +39,37 @@ impl ParkerT for Parker {
         }
     }
 
+    #[cfg(not(loom))]
+    unsafe fn park_until(&self, deadline: std::time::Instant) -> bool {
+        if self
+            .0
+            .compare_exchange(Self::notified().as_ptr(), ptr::null_mut(), Acquire, Relaxed)
+            .is_ok()
+        {
+            return true;
+        }
+        ParkEvent::with(|event| {
+            let event_ptr = event.get_ref() as *const _ as *mut _;
+            let old = self.0.swap(event_ptr, AcqRel);
+            if old != Self::notified().as_ptr() {
+                debug_assert_eq!(old, ptr::null_mut());
+                if !event.wait_until(deadline)
+                    && self
+                        .0
+                        .compare_exchange(event_ptr, ptr::null_mut(), Relaxed, Relaxed)
+                        .is_ok()
+                {
+                    // withdrawn before any `unpark` saw the event
+                    return false;
+                }
+                // `unpark` took the event, so it's about to signal it
+                event.wait();
+            }
+            self.0.store(ptr::null_mut(), Release);
+            true
+        })
+    }
+
     unsafe fn unpark(this: *const Self) {
         if let Some(event) = NonNull::new((*this).0.swap(Self::notified().as_ptr(), AcqRel)) {
             #[cfg(not(loom))]
@@ -54,8 +85,7 @@ impl ParkerT for Parker {
 impl Parker {
     fn notified() -> NonNull<ParkEvent> {
         static NOTIFIED: u8 = 0;
-        let ret = NonNull::from(&NOTIFIED).cast();
-        ret
+        NonNull::from(&NOTIFIED).cast()
     }
 
     #[cfg(not(loom))]
@@ -93,6 +123,19 @@ impl ParkEvent {
         }
     }
 
+    /// Returns false if `deadline` passed before the event was signaled.
+    #[cfg(not(loom))]
+    fn wait_until(self: Pin<&Self>, deadline: std::time::Instant) -> bool {
+        while !self.signaled.load(Acquire) {
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            thread::park_timeout(deadline - now);
+        }
+        true
+    }
+
     /// # Safety
     ///
     /// - `this` must be alive when called.
@@ -0,0 +1,70 @@
+use core::arch::wasm32::{memory_atomic_notify, memory_atomic_wait32};
+use core::ptr::addr_of;
+use core::sync::atomic::AtomicI32;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+const EMPTY: i32 = 0;
+const NOTIFIED: i32 = 1;
+
+/// A parker which sleeps with `memory.atomic.wait32` on its own state,
+/// the `wasm` equivalent of the futex parker.
+///
+/// It doesn't allocate, take any locks or panic, so parking can't unwind
+/// and the nodes can be created on the stack for free. Browsers don't let
+/// the main thread block, so parking there traps.
+pub(crate) struct Parker(AtomicI32);
+
+impl Parker {
+    pub(crate) const fn new() -> Self {
+        Self(AtomicI32::new(EMPTY))
+    }
+
+    /// Sleeps while the state is `EMPTY`, or until `timeout_ns` passes
+    /// (never if it's negative). Spurious returns are fine, the callers
+    /// recheck the state.
+    fn wait(&self, timeout_ns: i64) {
+        //SAFETY: the state is valid for the call and `atomics` is enabled
+        unsafe { memory_atomic_wait32(self.0.as_ptr(), EMPTY, timeout_ns) };
+    }
+
+    fn try_consume(&self) -> bool {
+        self.0
+            .compare_exchange(NOTIFIED, EMPTY, Acquire, Relaxed)
+            .is_ok()
+    }
+}
+
+impl super::ParkerT for Parker {
+    const CHEAP_NEW: bool = true;
+    const CAN_PANIC: bool = false;
+
+    unsafe fn park(&self) {
+        while !self.try_consume() {
+            self.wait(-1);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    unsafe fn park_until(&self, deadline: std::time::Instant) -> bool {
+        while !self.try_consume() {
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            // saturate, very long timeouts are just sleeping forever
+            self.wait((deadline - now).as_nanos().try_into().unwrap_or(-1));
+        }
+        true
+    }
+
+    unsafe fn unpark(this: *const Self) {
+        /* After the store the parked thread may return and destroy `*this`,
+         * but notifying only uses the address, so this is fine even if the
+         * memory is reused in the meantime: at worst some other waiter sees
+         * a spurious wake-up, which all of them have to tolerate.
+         */
+        let state = addr_of!((*this).0) as *mut i32;
+        (*this).0.store(NOTIFIED, Release);
+        memory_atomic_notify(state, 1);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::real::atomic::AtomicBool;
+use crate::real::single_core::{disable, restore, wait_for_interrupt};
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+/// The parker used with `single-core`. Sleeps with WFI until
+/// an interrupt handler unparks it.
+pub(crate) struct Parker(AtomicBool);
+
+impl Parker {
+    pub(crate) const fn new() -> Self {
+        Self(AtomicBool::new(false))
+    }
+}
+
+impl super::ParkerT for Parker {
+    const CHEAP_NEW: bool = true;
+
+    unsafe fn park(&self) {
+        loop {
+            /* The flag is checked with interrupts masked, so an interrupt
+             * handler can't set it between the check and WFI. The pending
+             * interrupt still wakes the core, and runs after `restore`.
+             */
+            let enabled = disable();
+            debug_assert!(enabled, "`park` called with interrupts disabled");
+            if self.0.load(Acquire) {
+                // reset for reuse (`node-pool`)
+                self.0.store(false, Relaxed);
+                restore(enabled);
+                return;
+            }
+            wait_for_interrupt();
+            restore(enabled);
+            // the interrupt may have deferred wake-ups
+            #[cfg(any(target_has_atomic = "ptr", feature = "portable-atomic"))]
+            crate::real::isr::drain();
+        }
+    }
+
+    unsafe fn unpark(this: *const Self) {
+        (*this).0.store(true, Release);
+    }
+}
@@ -0,0 +1,164 @@
+use core::ffi::c_void;
+use core::ptr::addr_of;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use core::sync::atomic::{AtomicU8, AtomicUsize};
+
+use super::{std_mutex, ParkerT};
+
+type WaitOnAddress = unsafe extern "system" fn(*const c_void, *const c_void, usize, u32) -> i32;
+type WakeByAddressSingle = unsafe extern "system" fn(*const c_void);
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetModuleHandleA(name: *const u8) -> *mut c_void;
+    fn GetProcAddress(module: *mut c_void, name: *const u8) -> *mut c_void;
+}
+
+const INFINITE: u32 = u32::MAX;
+
+const UNRESOLVED: usize = 0;
+const UNAVAILABLE: usize = 1;
+
+/* `WaitOnAddress` and `WakeByAddressSingle` are only available since
+ * Windows 8, so they're looked up at runtime. `WAKE` is stored before
+ * `WAIT`, which is the one checked.
+ */
+static WAIT: AtomicUsize = AtomicUsize::new(UNRESOLVED);
+static WAKE: AtomicUsize = AtomicUsize::new(UNRESOLVED);
+
+#[cold]
+fn resolve() -> usize {
+    //SAFETY: the names are nul terminated
+    let (wait, wake) = unsafe {
+        let module = GetModuleHandleA(c"api-ms-win-core-synch-l1-2-0".as_ptr().cast());
+        if module.is_null() {
+            (UNAVAILABLE, UNAVAILABLE)
+        } else {
+            (
+                GetProcAddress(module, c"WaitOnAddress".as_ptr().cast()) as usize,
+                GetProcAddress(module, c"WakeByAddressSingle".as_ptr().cast()) as usize,
+            )
+        }
+    };
+    if wait <= UNAVAILABLE || wake <= UNAVAILABLE {
+        WAIT.store(UNAVAILABLE, Relaxed);
+        return UNAVAILABLE;
+    }
+    WAKE.store(wake, Relaxed);
+    WAIT.store(wait, Release);
+    wait
+}
+
+/// Returns `WaitOnAddress`, if it's available.
+#[inline(always)]
+fn wait_on_address() -> Option<WaitOnAddress> {
+    let wait = match WAIT.load(Acquire) {
+        UNRESOLVED => resolve(),
+        wait => wait,
+    };
+    //SAFETY: resolved with `GetProcAddress`
+    (wait != UNAVAILABLE).then(|| unsafe { core::mem::transmute::<usize, WaitOnAddress>(wait) })
+}
+
+/// Only called after `wait_on_address` returned `Some`.
+#[inline(always)]
+fn wake_by_address_single() -> WakeByAddressSingle {
+    //SAFETY: stored before `WAIT`, which was loaded with `Acquire`
+    unsafe { core::mem::transmute::<usize, WakeByAddressSingle>(WAKE.load(Relaxed)) }
+}
+
+const EMPTY: u8 = 0;
+const NOTIFIED: u8 = 1;
+
+/// A parker which sleeps with `WaitOnAddress` on its own state. On
+/// Windows versions without it, the mutex parker is used instead.
+///
+/// The choice is made in `prepare_park`, before the parker can be
+/// unparked, so both sides always agree on it.
+pub(crate) struct Parker {
+    state: AtomicU8,
+    fallback: std_mutex::Parker,
+}
+
+impl Parker {
+    pub(crate) const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            fallback: std_mutex::Parker::new(),
+        }
+    }
+
+    /// Sleeps while the state is `EMPTY`, or until `timeout` (in ms) passes.
+    fn wait(&self, wait: WaitOnAddress, timeout: u32) {
+        let compare = EMPTY;
+        //SAFETY: both addresses are valid for the call and of the same size
+        unsafe {
+            wait(
+                &self.state as *const AtomicU8 as *const c_void,
+                &compare as *const u8 as *const c_void,
+                1,
+                timeout,
+            );
+        }
+    }
+
+    fn try_consume(&self) -> bool {
+        self.state
+            .compare_exchange(NOTIFIED, EMPTY, Acquire, Relaxed)
+            .is_ok()
+    }
+}
+
+impl ParkerT for Parker {
+    const CHEAP_NEW: bool = false;
+
+    fn prepare_park(&self) {
+        wait_on_address();
+    }
+
+    unsafe fn park(&self) {
+        let wait = match wait_on_address() {
+            Some(wait) => wait,
+            None => return self.fallback.park(),
+        };
+        // `WaitOnAddress` may return spuriously, so the state is always rechecked
+        while !self.try_consume() {
+            self.wait(wait, INFINITE);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    unsafe fn park_until(&self, deadline: std::time::Instant) -> bool {
+        let wait = match wait_on_address() {
+            Some(wait) => wait,
+            None => return self.fallback.park_until(deadline),
+        };
+        while !self.try_consume() {
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            // rounded up, so it doesn't wake up early, and below `INFINITE`
+            #[allow(clippy::manual_div_ceil)]
+            let timeout = ((deadline - now).as_nanos() + 999_999) / 1_000_000;
+            self.wait(wait, timeout.min(u128::from(INFINITE - 1)) as u32);
+        }
+        true
+    }
+
+    unsafe fn unpark(this: *const Self) {
+        if wait_on_address().is_none() {
+            return ParkerT::unpark(addr_of!((*this).fallback));
+        }
+        /* After the store the parked thread may return and destroy `*this`,
+         * but `WakeByAddressSingle` only uses the address as a key, so this
+         * is fine even if the memory is reused in the meantime: at worst some
+         * other waiter sees a spurious wake-up, which they have to tolerate.
+         */
+        let state = addr_of!((*this).state);
+        (*state).store(NOTIFIED, Release);
+        wake_by_address_single()(state as *const c_void);
+    }
+}
+
+unsafe impl Sync for Parker {}
@@ -0,0 +1,84 @@
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use crate::real::atomic::{AtomicBool, AtomicI32};
+
+/// `struct k_futex`, `atomic_t` is 32 bits on all supported targets.
+#[repr(C)]
+struct KFutex {
+    val: AtomicI32,
+}
+
+/// `k_timeout_t` with `CONFIG_TIMEOUT_64BIT` (the default).
+#[repr(C)]
+struct KTimeout {
+    ticks: i64,
+}
+
+const K_FOREVER: KTimeout = KTimeout { ticks: -1 };
+
+extern "C" {
+    fn k_futex_wait(futex: *const KFutex, expected: i32, timeout: KTimeout) -> i32;
+    fn k_futex_wake(futex: *const KFutex, wake_all: bool) -> i32;
+}
+
+/* Zephyr futexes are kernel objects looked up by address, so waking one
+ * after its memory was freed isn't allowed. A woken thread may destroy its
+ * `Parker` as soon as it observes the notification, so the futexes can't be
+ * a part of it. Instead, parkers are hashed into a small set of static
+ * futexes, where the value is a wake-up sequence number.
+ */
+const FUTEX_COUNT: usize = 8;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const FUTEX_INIT: KFutex = KFutex {
+    val: AtomicI32::new(0),
+};
+static FUTEXES: [KFutex; FUTEX_COUNT] = [FUTEX_INIT; FUTEX_COUNT];
+
+fn futex_for(parker: *const Parker) -> &'static KFutex {
+    // `Parker`s are at least 4 byte aligned, so the low bits are useless.
+    &FUTEXES[(parker.addr() >> 2) % FUTEX_COUNT]
+}
+
+/// A parker based on `k_futex_wait`/`k_futex_wake`.
+pub(crate) struct Parker {
+    notified: AtomicBool,
+}
+
+impl Parker {
+    pub(crate) const fn new() -> Self {
+        Self {
+            notified: AtomicBool::new(false),
+        }
+    }
+}
+
+impl super::ParkerT for Parker {
+    const CHEAP_NEW: bool = true;
+
+    fn prepare_park(&self) {
+        self.notified.store(false, Relaxed);
+    }
+
+    unsafe fn park(&self) {
+        let futex = futex_for(self);
+        loop {
+            let seq = futex.val.load(Acquire);
+            if self.notified.load(Acquire) {
+                return;
+            }
+            // Returns early if `seq` is outdated. The result is ignored,
+            // since the only thing that matters is `notified`.
+            k_futex_wait(futex, seq, K_FOREVER);
+        }
+    }
+
+    unsafe fn unpark(this: *const Self) {
+        let futex = futex_for(this);
+        // `*this` may be destroyed after this store, `futex` is static
+        (*this).notified.store(true, Release);
+        futex.val.fetch_add(1, Release);
+        // other parkers sharing the futex recheck their flag and go back to sleep
+        k_futex_wake(futex, true);
+    }
+}
@@ -1,8 +1,58 @@
 use crate::real::loom::{Cell, Mutex, MutexGuard};
 use crate::real::park::{Parker, ParkerT};
+use crate::{
+    AsParkAddr, ParkResult, RequeueResult, UnparkResult, ADDRESS_TAG, DEFAULT_UNPARK_TOKEN,
+};
+use core::cell::UnsafeCell;
+use core::ops::Deref;
 use core::ptr::{self, addr_of, NonNull};
+use core::sync::atomic::Ordering::Relaxed;
 
-#[cfg(all(not(loom), not(feature = "more-concurrency")))]
+#[cfg(not(loom))]
+use crate::real::atomic::{AtomicPtr, AtomicUsize};
+#[cfg(loom)]
+use loom::sync::atomic::{AtomicPtr, AtomicUsize};
+
+#[cfg(all(feature = "async", not(loom)))]
+use crate::real::atomic::AtomicBool;
+#[cfg(feature = "async")]
+use core::sync::atomic::Ordering::{Acquire, Release};
+#[cfg(feature = "async")]
+use core::task::Waker;
+#[cfg(all(feature = "async", loom))]
+use loom::sync::atomic::AtomicBool;
+
+#[cfg(all(feature = "deadlock-detection", not(loom)))]
+use crate::real::deadlock;
+#[cfg(all(feature = "instrument", not(loom)))]
+use crate::real::instrument::{self, UnparkKind};
+#[cfg(all(
+    not(any(loom, feature = "std")),
+    any(target_has_atomic = "ptr", feature = "portable-atomic")
+))]
+use crate::real::isr::drain as drain_isr_wakes;
+#[cfg(all(feature = "stats", not(loom)))]
+use crate::real::stats;
+#[cfg(all(feature = "deadlock-detection", not(loom)))]
+use std::sync::Arc;
+#[cfg(not(all(
+    not(any(loom, feature = "std")),
+    any(target_has_atomic = "ptr", feature = "portable-atomic")
+)))]
+#[inline(always)]
+fn drain_isr_wakes() {}
+
+#[cfg(all(feature = "tiny-footprint", feature = "more-concurrency"))]
+compile_error!("`tiny-footprint` and `more-concurrency` are mutually exclusive");
+
+#[cfg(all(feature = "growable-table", feature = "static-only"))]
+compile_error!("`growable-table` allocates bigger tables, disable `static-only`");
+
+#[cfg(all(
+    not(loom),
+    not(feature = "more-concurrency"),
+    not(feature = "tiny-footprint")
+))]
 // parking-lot uses a max load factor of 3,
 // so 32(1 << 5) buckets is enough for 96 threads. In
 // the case that more threads use sparking-lot,
@@ -12,10 +62,14 @@ const BUCKET_BITS: usize = 5;
 #[cfg(all(not(loom), feature = "more-concurrency"))]
 // In this case, performs better until 384 threads instead
 const BUCKET_BITS: usize = 7;
+#[cfg(all(not(loom), feature = "tiny-footprint"))]
+// For MCUs, which rarely have more than a few threads
+const BUCKET_BITS: usize = 2;
 #[cfg(loom)]
 // Reduce load for loom
 const BUCKET_BITS: usize = 1;
 
+#[cfg_attr(all(feature = "growable-table", not(test)), allow(dead_code))]
 const BUCKET_COUNT: usize = 1 << BUCKET_BITS;
 
 /* # Note
@@ -30,9 +84,38 @@ const BUCKET_COUNT: usize = 1 << BUCKET_BITS;
  */
 #[repr(C)]
 struct ThreadData {
-    next: Cell<*const ThreadData>,
-    addr: Cell<*const ()>,
+    next: Link,
+    /// The waiter before this one, so it can be unlinked without
+    /// walking the queue. Only valid while it's queued.
+    prev: Link,
+    /// True while it's in a bucket queue. Only accessed with the bucket locked.
+    queued: Cell<bool>,
+    /// Only changed with the bucket locked (both of them when requeueing),
+    /// but read by timed out waiters to find their bucket.
+    addr: AtomicUsize,
+    /// Tells pointers, keys and generations apart, see `ADDRESS_TAG`.
+    /// Set before the waiter is queued and only read with the bucket locked.
+    tag: Cell<u64>,
     parker: Parker,
+    /// Set by the unparker, read by the thread once it's woken.
+    token: Cell<usize>,
+    /// Position in the bucket queue, used to verify FIFO order.
+    #[cfg(debug_assertions)]
+    ticket: Cell<usize>,
+    /// Only set for the waiters of `park_async`, which are woken through it
+    /// instead of `parker`. Only accessed with the bucket locked while queued.
+    #[cfg(feature = "async")]
+    waker: Cell<Option<Waker>>,
+    /// Set by the unparker of an async waiter once it's done with it.
+    #[cfg(feature = "async")]
+    notified: AtomicBool,
+    /// The thread parked with it, for `check_deadlock`. Set before the
+    /// waiter is queued and only read with the bucket locked.
+    #[cfg(all(feature = "deadlock-detection", not(loom)))]
+    thread: Cell<Option<Arc<deadlock::ThreadInfo>>>,
+    /// Only set for the waiters of `park_any`, which are woken through it
+    /// instead of their own parker. Set before the waiter is queued.
+    group: Cell<*const Group>,
 }
 
 impl ThreadData {
@@ -40,8 +123,21 @@ impl ThreadData {
     const fn new() -> Self {
         Self {
             parker: Parker::new(),
-            addr: Cell::new(ptr::null()),
-            next: Cell::new(ptr::null()),
+            addr: AtomicUsize::new(0),
+            tag: Cell::new(ADDRESS_TAG),
+            next: Link::null(),
+            prev: Link::null(),
+            queued: Cell::new(false),
+            token: Cell::new(DEFAULT_UNPARK_TOKEN),
+            #[cfg(debug_assertions)]
+            ticket: Cell::new(0),
+            #[cfg(feature = "async")]
+            waker: Cell::new(None),
+            #[cfg(feature = "async")]
+            notified: AtomicBool::new(false),
+            #[cfg(all(feature = "deadlock-detection", not(loom)))]
+            thread: Cell::new(None),
+            group: Cell::new(ptr::null()),
         }
     }
 
@@ -49,208 +145,2187 @@ impl ThreadData {
     fn new() -> Self {
         Self {
             parker: Parker::new(),
-            addr: Cell::new(ptr::null()),
-            next: Cell::new(ptr::null()),
+            addr: AtomicUsize::new(0),
+            tag: Cell::new(ADDRESS_TAG),
+            next: Link::null(),
+            prev: Link::null(),
+            queued: Cell::new(false),
+            token: Cell::new(DEFAULT_UNPARK_TOKEN),
+            #[cfg(debug_assertions)]
+            ticket: Cell::new(0),
+            #[cfg(feature = "async")]
+            waker: Cell::new(None),
+            #[cfg(feature = "async")]
+            notified: AtomicBool::new(false),
+            #[cfg(all(feature = "deadlock-detection", not(loom)))]
+            thread: Cell::new(None),
+            group: Cell::new(ptr::null()),
+        }
+    }
+
+    /// The waiters of a `park_any` call whose group was claimed through
+    /// another address aren't parked anymore, they only wait for their
+    /// thread to unlink them.
+    ///
+    /// # Safety
+    ///
+    /// - the bucket `self` is queued in must be locked.
+    #[inline(always)]
+    unsafe fn is_parked_on(&self, addr: usize, tag: u64) -> bool {
+        self.addr.load(Relaxed) == addr
+            && self.tag.get() == tag
+            && !Group::is_claimed(self.group.get())
+    }
+
+    /// Called by unparkers before they unlink `this` to wake it. Returns
+    /// false if it's a waiter of `park_any` whose group another unparker
+    /// claimed since `is_parked_on`, then it has to be skipped.
+    ///
+    /// # Safety
+    ///
+    /// - the bucket `this` is queued in must be locked.
+    #[inline(always)]
+    unsafe fn claim(this: *const Self) -> bool {
+        let group = (*this).group.get();
+        group.is_null() || Group::claim(group, this)
+    }
+
+    /// Wakes a waiter which was unlinked by the caller.
+    ///
+    /// # Safety
+    ///
+    /// - `this` must point to a `ThreadData` the caller unlinked and hasn't
+    ///   woken yet. It may be destroyed as soon as this starts waking it.
+    #[inline(always)]
+    unsafe fn unpark(this: *const Self) {
+        #[cfg(feature = "async")]
+        if let Some(waker) = (*this).waker.take() {
+            (*this).notified.store(true, Release);
+            // `*this` may be destroyed now, but `waker` is owned
+            waker.wake();
+            return;
+        }
+        let group = (*this).group.get();
+        if !group.is_null() {
+            // the group was claimed with `this`, so its thread waits for this
+            return ParkerT::unpark((*group).parker);
         }
+        // since ThreadData lives until the thread is
+        // woken and threads sleep before `unpark` is
+        // called, `parker` is alive.
+        ParkerT::unpark(addr_of!((*this).parker));
     }
 }
 
-fn lock_bucket(addr: *const ()) -> MutexGuard<'static, Bucket> {
-    struct Hashtable {
-        buckets: [Mutex<Bucket>; BUCKET_COUNT],
+/// An intrusive queue link. With `hardening`, an encoded copy of the
+/// pointer is kept next to it and checked on every read, so that links
+/// corrupted by other (unsafe) code abort the process instead of waking
+/// or losing arbitrary threads.
+struct Link {
+    ptr: Cell<*const ThreadData>,
+    #[cfg(feature = "hardening")]
+    check: Cell<usize>,
+}
+
+#[cfg(feature = "hardening")]
+const LINK_KEY: usize = 0xA5A5_A5A5_A5A5_A5A5_u64 as usize;
+
+impl Link {
+    #[cfg(not(loom))]
+    const fn null() -> Self {
+        Self {
+            ptr: Cell::new(ptr::null()),
+            #[cfg(feature = "hardening")]
+            check: Cell::new(LINK_KEY),
+        }
     }
 
-    impl Hashtable {
-        #[cfg(not(loom))]
-        const fn new() -> Self {
-            const INIT: Mutex<Bucket> = Mutex::new(Bucket {
-                first: Cell::new(ptr::null()),
-                last: Cell::new(ptr::null()),
-            });
+    #[cfg(loom)]
+    fn null() -> Self {
+        Self {
+            ptr: Cell::new(ptr::null()),
+            #[cfg(feature = "hardening")]
+            check: Cell::new(LINK_KEY),
+        }
+    }
 
-            Self {
-                buckets: [INIT; BUCKET_COUNT],
-            }
+    #[inline(always)]
+    fn get(&self) -> *const ThreadData {
+        let ptr = self.ptr.get();
+        #[cfg(feature = "hardening")]
+        if ptr.addr() ^ LINK_KEY != self.check.get() {
+            corrupted();
         }
+        ptr
+    }
 
-        #[cfg(loom)]
-        fn new() -> Self {
-            Self {
-                buckets: core::array::from_fn(|_| {
-                    Mutex::new(Bucket {
-                        first: Cell::new(ptr::null()),
-                        last: Cell::new(ptr::null()),
-                    })
-                }),
+    #[inline(always)]
+    fn set(&self, ptr: *const ThreadData) {
+        self.ptr.set(ptr);
+        #[cfg(feature = "hardening")]
+        self.check.set(ptr.addr() ^ LINK_KEY);
+    }
+}
+
+#[cfg(feature = "hardening")]
+#[cold]
+#[inline(never)]
+fn corrupted() -> ! {
+    #[cfg(any(loom, feature = "std"))]
+    {
+        use std::io::Write;
+        let _ = std::io::stderr().write_all(b"sparking-lot-core: corrupted waiter queue\n");
+        std::process::abort();
+    }
+    #[cfg(not(any(loom, feature = "std")))]
+    panic!("sparking-lot-core: corrupted waiter queue");
+}
+
+struct Hashtable {
+    // only mutably accessed by `reset`
+    #[cfg(not(all(feature = "growable-table", not(loom))))]
+    buckets: UnsafeCell<[Mutex<Bucket>; BUCKET_COUNT]>,
+    /// Either the `static` first table or a leaked allocation,
+    /// only mutably accessed by `reset`.
+    #[cfg(all(feature = "growable-table", not(loom)))]
+    buckets: *mut [Mutex<Bucket>],
+    #[cfg(all(feature = "growable-table", not(loom)))]
+    bits: usize,
+}
+
+unsafe impl Sync for Hashtable {}
+
+impl Hashtable {
+    #[cfg(not(loom))]
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY_BUCKET: Mutex<Bucket> = Mutex::new(Bucket::new());
+
+    #[cfg(not(any(loom, feature = "growable-table")))]
+    const fn new() -> Self {
+        Self {
+            buckets: UnsafeCell::new([Self::EMPTY_BUCKET; BUCKET_COUNT]),
+        }
+    }
+
+    #[cfg(loom)]
+    fn new() -> Self {
+        Self {
+            buckets: UnsafeCell::new(core::array::from_fn(|_| Mutex::new(Bucket::new()))),
+        }
+    }
+
+    #[cfg(all(feature = "growable-table", not(loom)))]
+    fn with_bits(bits: usize) -> Self {
+        let buckets: Box<[Mutex<Bucket>]> =
+            (0..1 << bits).map(|_| Mutex::new(Bucket::new())).collect();
+        Self {
+            buckets: Box::into_raw(buckets),
+            bits,
+        }
+    }
+
+    #[inline(always)]
+    fn buckets(&self) -> &[Mutex<Bucket>] {
+        //SAFETY: only mutated by `reset`, when nobody else is using the table
+        #[cfg(not(all(feature = "growable-table", not(loom))))]
+        return unsafe { &*self.buckets.get() };
+        #[cfg(all(feature = "growable-table", not(loom)))]
+        return unsafe { &*self.buckets };
+    }
+
+    #[inline(always)]
+    fn bits(&self) -> usize {
+        #[cfg(all(feature = "growable-table", not(loom)))]
+        return self.bits;
+        #[cfg(not(all(feature = "growable-table", not(loom))))]
+        return BUCKET_BITS;
+    }
+
+    /// False once the table has been replaced by a bigger one,
+    /// after which its buckets mustn't be used anymore.
+    #[inline(always)]
+    fn is_current(&self) -> bool {
+        #[cfg(all(feature = "growable-table", not(loom)))]
+        return ptr::eq(self, growth::table());
+        #[cfg(not(all(feature = "growable-table", not(loom))))]
+        return true;
+    }
+
+    #[inline]
+    fn lock_bucket(&self, addr: usize) -> MutexGuard<'_, Bucket> {
+        self.lock_index(self.hash(addr))
+    }
+
+    /// # Note
+    ///
+    /// `idx` must come from `hash`.
+    #[inline]
+    fn lock_index(&self, idx: usize) -> MutexGuard<'_, Bucket> {
+        //SAFETY: guaranteed by the hash function
+        let bucket = unsafe {
+            #[cfg(not(loom))]
+            debug_assert!(idx < 1 << self.bits());
+            #[cfg(loom)]
+            assert!(idx < 1 << self.bits());
+            self.buckets().get_unchecked(idx)
+        };
+        lock(bucket)
+    }
+
+    #[inline]
+    fn try_lock_bucket(&self, addr: usize) -> Option<MutexGuard<'_, Bucket>> {
+        //SAFETY: guaranteed by the hash function
+        try_lock(unsafe { self.buckets().get_unchecked(self.hash(addr)) })
+    }
+
+    /// Replaces every bucket with an empty, unlocked one.
+    ///
+    /// # Safety
+    ///
+    /// - no other thread can be using the table.
+    #[cfg(all(unix, feature = "std", not(loom)))]
+    unsafe fn reset(&self) {
+        /* Locks may be held by threads which don't exist anymore,
+         * so they are overwritten instead of being locked. The old
+         * values are leaked, which is fine in a child process.
+         */
+        #[cfg(not(feature = "growable-table"))]
+        ptr::write(self.buckets.get(), Self::new().buckets.into_inner());
+        #[cfg(feature = "growable-table")]
+        for bucket in &mut *self.buckets {
+            ptr::write(bucket, Mutex::new(Bucket::new()));
+        }
+    }
+
+    #[inline(always)]
+    fn hash(&self, n: usize) -> usize {
+        hash(n, self.bits())
+    }
+}
+
+/* loom tests with checkpoints, can't rely on
+ * addresses, and this allows users to write
+ * `n as *const()` to select buckets, but still
+ * kind of works with addresses with disabled
+ * loom checkpoints.
+ */
+#[cfg(loom)]
+fn hash(n: usize, bits: usize) -> usize {
+    n & ((1 << bits) - 1)
+}
+
+#[cfg(not(loom))]
+fn hash(n: usize, bits: usize) -> usize {
+    #[cfg(target_pointer_width = "16")]
+    return fib_hash::hash16(n as u16, bits);
+    #[cfg(target_pointer_width = "32")]
+    return fib_hash::hash32(n as u32, bits);
+    #[cfg(target_pointer_width = "64")]
+    return fib_hash::hash64(n as u64, bits);
+    #[cfg(target_pointer_width = "128")]
+    return fib_hash::hash128(n as u128, bits);
+}
+
+#[inline]
+fn lock(bucket: &Mutex<Bucket>) -> MutexGuard<'_, Bucket> {
+    /* Poisoning is ignored: foreign code only runs under a bucket lock
+     * between changes to it, so a panic can't leave it inconsistent.
+     * `expected` (and `timed_out`) run before the bucket is modified,
+     * and the callbacks of the unpark functions after the threads to wake
+     * are unlinked, which `WakeOnUnwind` still wakes if they panic.
+     * Only `loom`'s lock poisons, the others don't track panics.
+     */
+    #[cfg(all(feature = "stats", not(loom)))]
+    stats::bucket_lock();
+    #[cfg(loom)]
+    return bucket.lock().unwrap_or_else(|e| e.into_inner());
+    #[cfg(not(loom))]
+    return bucket.lock();
+}
+
+/// Like `lock`, but returns `None` instead of waiting for the lock.
+#[inline]
+fn try_lock(bucket: &Mutex<Bucket>) -> Option<MutexGuard<'_, Bucket>> {
+    #[cfg(loom)]
+    // `loom`'s `try_lock` doesn't poison
+    let guard = bucket.try_lock().ok();
+    #[cfg(not(loom))]
+    let guard = bucket.try_lock();
+    #[cfg(all(feature = "stats", not(loom)))]
+    if guard.is_some() {
+        stats::bucket_lock();
+    }
+    guard
+}
+
+/* Fibonacci hashing: multiplying by 2^width / phi (made odd) and
+ * taking the top `bits` bits spreads close-by addresses, which are
+ * the common case, evenly across the buckets. Since the top bits are
+ * taken, addresses in different buckets stay in different buckets
+ * when the table grows.
+ */
+#[cfg(not(loom))]
+mod fib_hash {
+    #[cfg(any(test, target_pointer_width = "16"))]
+    pub(super) fn hash16(n: u16, bits: usize) -> usize {
+        (n.wrapping_mul(0x9E37) >> (16 - bits)) as usize
+    }
+
+    #[cfg(any(test, target_pointer_width = "32"))]
+    pub(super) fn hash32(n: u32, bits: usize) -> usize {
+        (n.wrapping_mul(0x9E3779B9) >> (32 - bits)) as usize
+    }
+
+    #[cfg(any(test, target_pointer_width = "64"))]
+    pub(super) fn hash64(n: u64, bits: usize) -> usize {
+        (n.wrapping_mul(0x9E3779B97F4A7C15) >> (64 - bits)) as usize
+    }
+
+    #[cfg(any(test, target_pointer_width = "128"))]
+    pub(super) fn hash128(n: u128, bits: usize) -> usize {
+        (n.wrapping_mul(0x9E3779B97F4A7C15F39CC0605CEDC835) >> (128 - bits)) as usize
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::real::parking_lot::{BUCKET_BITS, BUCKET_COUNT};
+
+        /// Hashes `BUCKET_COUNT * 8` addresses, `stride` apart, and checks that
+        /// most buckets are used and none gets more than double its share.
+        fn check(hash: impl Fn(u128) -> usize, base: u128, stride: u128) {
+            const PER_BUCKET: usize = 8;
+            let mut load = [0; BUCKET_COUNT];
+            for i in 0..(BUCKET_COUNT * PER_BUCKET) as u128 {
+                load[hash(base + i * stride)] += 1;
             }
+            let used = load.iter().filter(|&&n| n != 0).count();
+            assert!(
+                used * 4 >= BUCKET_COUNT * 3,
+                "only {used} buckets used with stride {stride}: {load:?}"
+            );
+            assert!(
+                load.iter().all(|&n| n <= PER_BUCKET * 2),
+                "overloaded bucket with stride {stride}: {load:?}"
+            );
         }
 
-        #[inline]
-        fn lock_bucket(&self, addr: *const ()) -> MutexGuard<'_, Bucket> {
-            let idx = Self::hash(addr as usize);
-            //SAFETY: guaranteed by the hash function
-            unsafe {
-                #[cfg(not(loom))]
-                debug_assert!(idx < BUCKET_COUNT);
-                #[cfg(loom)]
-                assert!(idx < BUCKET_COUNT);
-                self.buckets.get_unchecked(idx)
+        fn check_strides(hash: impl Fn(u128) -> usize, base: u128) {
+            for stride in [1, 2, 4, 8, 16, 24, 64] {
+                check(&hash, base, stride);
             }
-            .lock()
-            .unwrap()
         }
 
-        /* loom tests with checkpoints, can't rely on
-         * addresses, and this allows users to write
-         * `n as *const()` to select buckets, but still
-         * kind of works with addresses with disabled
-         * loom checkpoints.
-         */
-        #[cfg(loom)]
-        fn hash(n: usize) -> usize {
-            n & (BUCKET_COUNT - 1)
+        #[test]
+        fn distributes_16() {
+            check_strides(|n| hash16(n as u16, BUCKET_BITS), 0x2100);
         }
 
-        #[cfg(not(loom))]
-        fn hash(n: usize) -> usize {
-            #[cfg(target_pointer_width = "64")]
-            return n.wrapping_mul(0x9E3779B97F4A7C15) >> (64 - BUCKET_BITS);
-            #[cfg(target_pointer_width = "32")]
-            return n.wrapping_mul(0x9E3779B9) >> (32 - BUCKET_BITS);
-            #[cfg(not(any(target_pointer_width = "64", target_pointer_width = "32")))]
-            {
-                // With random addresses has slightly
-                // better bucket coverage than the
-                // hashes above, with close-by ones
-                // it's a lot worse.
-                let mut h = 0;
-                for i in 0..BUCKET_BITS {
-                    h |= (n >> i) & (1 << i);
+        #[test]
+        fn distributes_32() {
+            check_strides(|n| hash32(n as u32, BUCKET_BITS), 0x2000_0400);
+        }
+
+        #[test]
+        fn distributes_64() {
+            check_strides(|n| hash64(n as u64, BUCKET_BITS), 0x7ffd_1234_5000);
+        }
+
+        #[test]
+        fn distributes_128() {
+            check_strides(|n| hash128(n, BUCKET_BITS), 0x7ffd_1234_5000);
+        }
+
+        #[test]
+        fn growing_splits_buckets() {
+            for n in (0..1 << 16).map(|i| 0x7ffd_1234_5000 + i * 8) {
+                for bits in 1..16 {
+                    assert_eq!(hash64(n, bits + 1) >> 1, hash64(n, bits));
                 }
-                h
             }
         }
     }
+}
+
+#[cfg(not(any(loom, feature = "growable-table")))]
+static HASHTABLE: Hashtable = Hashtable::new();
+#[cfg(loom)]
+loom::lazy_static! {
+    static ref HASHTABLE: Hashtable = Hashtable::new();
+}
+
+#[inline(always)]
+fn hashtable() -> &'static Hashtable {
+    #[cfg(all(feature = "growable-table", not(loom)))]
+    return growth::table();
+    #[cfg(not(all(feature = "growable-table", not(loom))))]
+    return &HASHTABLE;
+}
+
+/// The bucket table of a `ParkingLot`, with a power of two of buckets. It
+/// never grows, so unlike in the global table its buckets are always current.
+pub(crate) struct Lot<const BUCKETS: usize> {
+    buckets: [Mutex<Bucket>; BUCKETS],
+}
+
+/*SAFETY: the queues are only accessed with their bucket locked, and a lot
+ * can't be moved or dropped while threads are parked in it, since they
+ * borrow it until they're woken.
+ */
+unsafe impl<const BUCKETS: usize> Send for Lot<BUCKETS> {}
+unsafe impl<const BUCKETS: usize> Sync for Lot<BUCKETS> {}
+
+impl<const BUCKETS: usize> Lot<BUCKETS> {
     #[cfg(not(loom))]
-    static HASHTABLE: Hashtable = Hashtable::new();
+    pub(crate) const fn new() -> Self {
+        Self {
+            buckets: [Hashtable::EMPTY_BUCKET; BUCKETS],
+        }
+    }
+
     #[cfg(loom)]
-    loom::lazy_static! {
-        static ref HASHTABLE: Hashtable = Hashtable::new();
+    pub(crate) fn new() -> Self {
+        Self {
+            buckets: core::array::from_fn(|_| Mutex::new(Bucket::new())),
+        }
+    }
+
+    #[inline(always)]
+    fn table(&self) -> Table<'_> {
+        Table::Lot(&self.buckets)
+    }
+
+    #[cfg_attr(all(feature = "watchdog", not(loom)), track_caller)]
+    pub(crate) fn park(&self, addr: usize, expected: impl FnOnce() -> bool) -> Option<usize> {
+        park_blocking(self.table(), addr, ADDRESS_TAG, expected, |_| ())
+    }
+
+    #[cfg(all(
+        feature = "std",
+        not(any(loom, feature = "freertos", feature = "zephyr"))
+    ))]
+    #[cfg_attr(feature = "watchdog", track_caller)]
+    pub(crate) fn park_until(
+        &self,
+        addr: usize,
+        expected: impl FnOnce() -> bool,
+        deadline: std::time::Instant,
+    ) -> ParkResult {
+        park_until_in(self.table(), addr, expected, deadline, |_, _| ())
+    }
+
+    pub(crate) fn unpark_one(&self, addr: usize) -> UnparkResult {
+        let result = unpark_one_in(self.table(), addr, ADDRESS_TAG, |_, _| DEFAULT_UNPARK_TOKEN);
+        #[cfg(all(feature = "instrument", not(loom)))]
+        instrument::unpark(addr, UnparkKind::One, result.unparked);
+        #[cfg(all(feature = "stats", not(loom)))]
+        stats::unpark_one(result.unparked);
+        result
+    }
+
+    pub(crate) fn unpark_all(&self, addr: usize) -> UnparkResult {
+        unpark_all_in(self.table(), addr, ADDRESS_TAG, |_| ())
+    }
+
+    pub(crate) fn unpark_some(&self, addr: usize, count: usize) -> UnparkResult {
+        if count == 0 {
+            return unpark_none(addr);
+        }
+        unpark_some_in(self.table(), addr, count, || ())
+    }
+
+    pub(crate) fn parked_count(&self, addr: usize) -> usize {
+        parked_count_in(self.table(), addr)
+    }
+
+    #[cfg(feature = "debug-introspection")]
+    pub(crate) fn dump(
+        &self,
+        report: impl FnMut(usize, u64, usize) -> core::fmt::Result,
+    ) -> core::fmt::Result {
+        dump_in(self.table(), report)
+    }
+}
+
+/// The table a waiter is queued in.
+#[derive(Clone, Copy)]
+enum Table<'a> {
+    /// The table of the free functions, which may grow.
+    Global,
+    /// The buckets of a `ParkingLot`.
+    Lot(&'a [Mutex<Bucket>]),
+}
+
+/// With `growable-table`, the table is replaced by a bigger one once there are
+/// more than `LOAD_FACTOR` threads per bucket, like in `parking_lot`. Threads
+/// are counted the first time they park, and the first one also sizes the
+/// table for `available_parallelism`, so big machines don't have to grow
+/// it thread by thread.
+///
+/// The grower locks every bucket of the old table and moves the queues over
+/// before publishing the new one, so a bucket which is still current after
+/// it's been locked can be used. Old tables are leaked, since threads may
+/// still be waiting for their locks.
+#[cfg(all(feature = "growable-table", not(loom)))]
+mod growth {
+    use super::{Bucket, Hashtable, Mutex, MutexGuard, UnsafeCell, BUCKET_BITS, BUCKET_COUNT};
+    use core::ptr;
+    use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+    use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize};
+
+    const LOAD_FACTOR: usize = 3;
+
+    struct InitialBuckets(UnsafeCell<[Mutex<Bucket>; BUCKET_COUNT]>);
+
+    unsafe impl Sync for InitialBuckets {}
+
+    // the first table doesn't allocate, like the fixed one
+    static INITIAL_BUCKETS: InitialBuckets =
+        InitialBuckets(UnsafeCell::new([Hashtable::EMPTY_BUCKET; BUCKET_COUNT]));
+    static INITIAL: Hashtable = Hashtable {
+        buckets: INITIAL_BUCKETS.0.get(),
+        bits: BUCKET_BITS,
+    };
+    static TABLE: AtomicPtr<Hashtable> = AtomicPtr::new(ptr::addr_of!(INITIAL).cast_mut());
+    static THREADS: AtomicUsize = AtomicUsize::new(0);
+    static SIZED: AtomicBool = AtomicBool::new(false);
+
+    #[inline(always)]
+    pub(super) fn table() -> &'static Hashtable {
+        //SAFETY: tables are never freed
+        unsafe { &*TABLE.load(Acquire) }
+    }
+
+    /// Decrements the thread count when the thread exits.
+    struct Registered;
+
+    impl Drop for Registered {
+        fn drop(&mut self) {
+            THREADS.fetch_sub(1, Relaxed);
+        }
+    }
+
+    std::thread_local! {
+        static REGISTERED: Registered = {
+            let mut threads = THREADS.fetch_add(1, Relaxed) + 1;
+            if !SIZED.swap(true, Relaxed) {
+                // the first thread sizes it as if every core ran a thread
+                let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+                threads = threads.max(cores);
+            }
+            grow(threads);
+            Registered
+        };
+    }
+
+    /// Counts the thread on its first call, growing the table if needed.
+    /// Can't be called with a bucket locked.
+    #[inline(always)]
+    pub(super) fn register_thread() {
+        // during TLS destruction the thread isn't counted, which is fine
+        let _ = REGISTERED.try_with(|_| {});
+    }
+
+    #[cold]
+    fn grow(threads: usize) {
+        let mut bits = table().bits;
+        if threads <= (1 << bits) * LOAD_FACTOR {
+            return;
+        }
+        // leave room for the thread count to double
+        while (1 << bits) * LOAD_FACTOR < threads * 2 {
+            bits += 1;
+        }
+        resize(bits);
+    }
+
+    /// Grows the table to at least `1 << bits` buckets.
+    /// Can't be called with a bucket locked.
+    #[cold]
+    pub(super) fn resize(bits: usize) {
+        loop {
+            let old = table();
+            if old.bits >= bits {
+                return;
+            }
+            let len = 1 << old.bits;
+            // in index order, like `lock_bucket_pair`
+            let guards: Vec<MutexGuard<'static, Bucket>> =
+                (0..len).map(|idx| old.lock_index(idx)).collect();
+            if !old.is_current() {
+                // someone else grew it first
+                continue;
+            }
+
+            let new = Hashtable::with_bits(bits);
+            for bucket in &guards {
+                /*SAFETY:
+                 * - sleeping threads can't destroy their ThreadData.
+                 * - every bucket is locked, so threads can't be unlinked by others.
+                 */
+                unsafe {
+                    let mut current = bucket.first.get();
+                    while !current.is_null() {
+                        let next = (*current).next.get();
+                        let addr = (*current).addr.load(Relaxed);
+                        // keeps the order of the old queue
+                        new.lock_bucket(addr).push(&*current);
+                        current = next;
+                    }
+                }
+                bucket.clear();
+            }
+            // unlocking the old buckets publishes the new table to their waiters
+            TABLE.store(Box::into_raw(Box::new(new)), Release);
+            return;
+        }
+    }
+}
+
+/// Grows the table to at least `buckets` buckets, and returns how many it has.
+#[cfg(all(feature = "growable-table", not(loom)))]
+pub(crate) fn reserve_buckets(buckets: usize) -> usize {
+    let buckets = buckets
+        .checked_next_power_of_two()
+        .expect("sparking-lot-core: too many buckets");
+    growth::resize(buckets.trailing_zeros() as usize);
+    1 << growth::table().bits
+}
+
+/// A locked bucket. In debug builds with `std`, the thread is also
+/// marked as being inside the lot until it's dropped, so reentrant
+/// calls (from `expected`) panic instead of deadlocking.
+struct BucketGuard<'a> {
+    bucket: MutexGuard<'a, Bucket>,
+    #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+    _inside: reentrancy::Inside,
+}
+
+impl Deref for BucketGuard<'_> {
+    type Target = Bucket;
+
+    #[inline(always)]
+    fn deref(&self) -> &Bucket {
+        &self.bucket
     }
-    HASHTABLE.lock_bucket(addr)
 }
 
 #[inline(always)]
-fn with_thread_data<R>(f: impl FnOnce(&ThreadData) -> R) -> R {
-    if !Parker::CHEAP_NEW {
-        #[cfg(not(loom))]
-        thread_local!(static THREAD_DATA: ThreadData = const {ThreadData::new()});
-        #[cfg(loom)]
-        loom::thread_local!(static THREAD_DATA: ThreadData = ThreadData::new());
-        match THREAD_DATA.try_with(|x| x as *const _) {
-            Ok(ptr) => unsafe { f(&*ptr) },
-            Err(_) => {
-                let td = ThreadData::new();
-                f(&td)
+fn lock_bucket(table: Table<'_>, addr: usize) -> BucketGuard<'_> {
+    match table {
+        Table::Global => lock_bucket_in_table(addr).1,
+        Table::Lot(buckets) => {
+            #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+            let inside = reentrancy::Inside::enter();
+            let bits = buckets.len().trailing_zeros() as usize;
+            // with one bucket there's nothing to hash, and shifting by the width overflows
+            let idx = if bits == 0 { 0 } else { hash(addr, bits) };
+            BucketGuard {
+                bucket: lock(&buckets[idx]),
+                #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+                _inside: inside,
             }
         }
-    } else {
-        let td = ThreadData::new();
-        f(&td)
     }
 }
 
-pub(crate) fn park(addr: *const (), expected: impl FnOnce() -> bool) {
-    with_thread_data(|thread_data| {
-        let bucket = lock_bucket(addr);
-        if !expected() {
-            return;
+/// Like `lock_bucket`, but also returns the table the bucket is in.
+#[inline(always)]
+fn lock_bucket_in_table(addr: usize) -> (&'static Hashtable, BucketGuard<'static>) {
+    #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+    let inside = reentrancy::Inside::enter();
+    loop {
+        let table = hashtable();
+        let bucket = table.lock_bucket(addr);
+        // the table may have grown while waiting for the lock
+        if table.is_current() {
+            let bucket = BucketGuard {
+                bucket,
+                #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+                _inside: inside,
+            };
+            return (table, bucket);
         }
+    }
+}
 
-        thread_data.next.set(ptr::null());
-        thread_data.addr.set(addr);
+/// Like `lock_bucket_in_table`, but gives up if the bucket is locked.
+#[inline]
+fn try_lock_bucket_in_table(addr: usize) -> Option<BucketGuard<'static>> {
+    #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+    let inside = reentrancy::Inside::enter();
+    loop {
+        let table = hashtable();
+        let bucket = table.try_lock_bucket(addr)?;
+        if table.is_current() {
+            return Some(BucketGuard {
+                bucket,
+                #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+                _inside: inside,
+            });
+        }
+    }
+}
+
+/// Locks the bucket `thread_data` is queued in. Its `addr` can only
+/// change while that bucket is locked, so it's checked after locking.
+#[inline]
+fn lock_bucket_of<'a>(table: Table<'a>, thread_data: &ThreadData) -> BucketGuard<'a> {
+    loop {
+        let addr = thread_data.addr.load(Relaxed);
+        let bucket = lock_bucket(table, addr);
+        if thread_data.addr.load(Relaxed) == addr {
+            return bucket;
+        }
+        // requeued in the meantime
+    }
+}
+
+/// The locked buckets of two addresses, which may be the same bucket.
+struct BucketPair {
+    // locks may restore the interrupt state when unlocked,
+    // so they're unlocked in reverse (declaration) order
+    later: Option<MutexGuard<'static, Bucket>>,
+    earlier: MutexGuard<'static, Bucket>,
+    from_is_earlier: bool,
+    #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+    _inside: reentrancy::Inside,
+}
 
-        if bucket.first.get().is_null() {
-            bucket.first.set(thread_data);
+impl BucketPair {
+    fn from(&self) -> &Bucket {
+        if self.from_is_earlier {
+            &self.earlier
         } else {
-            //SAFETY: last isn't null if head isn't null
-            unsafe {
-                #[cfg(not(loom))]
-                debug_assert!(!bucket.last.get().is_null());
-                #[cfg(loom)]
-                assert!(!bucket.last.get().is_null());
-                &*bucket.last.get()
-            }
-            .next
-            .set(thread_data);
+            self.later.as_deref().unwrap_or(&self.earlier)
         }
-        bucket.last.set(thread_data);
-        // not releasing `bucket` lock before parking would deadlock
-        drop(bucket);
+    }
+
+    fn to(&self) -> &Bucket {
+        if self.from_is_earlier {
+            self.later.as_deref().unwrap_or(&self.earlier)
+        } else {
+            &self.earlier
+        }
+    }
+}
+
+/// Locks the buckets of `from` and `to` in index order, so that
+/// threads locking the same pair of buckets can't deadlock.
+#[inline]
+fn lock_bucket_pair(from: usize, to: usize) -> BucketPair {
+    #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+    let inside = reentrancy::Inside::enter();
+    loop {
+        let table = hashtable();
+        let (from_idx, to_idx) = (table.hash(from), table.hash(to));
+        let (earlier, later) = if from_idx <= to_idx {
+            (from_idx, to_idx)
+        } else {
+            (to_idx, from_idx)
+        };
+        let earlier_guard = table.lock_index(earlier);
+        let later_guard = (later != earlier).then(|| table.lock_index(later));
+        // the table may have grown while waiting for the locks
+        if table.is_current() {
+            return BucketPair {
+                later: later_guard,
+                earlier: earlier_guard,
+                from_is_earlier: from_idx == earlier,
+                #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+                _inside: inside,
+            };
+        }
+    }
+}
+
+/// The locked buckets of several addresses, some of which may share one.
+struct BucketSet<const N: usize> {
+    /// In index order, with `None` after a bucket which is already locked.
+    guards: [Option<MutexGuard<'static, Bucket>>; N],
+    /// Where the guard of the bucket of each address is in `guards`.
+    of: [usize; N],
+    #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+    _inside: reentrancy::Inside,
+}
+
+impl<const N: usize> BucketSet<N> {
+    /// The bucket of the address at `idx`.
+    fn get(&self, idx: usize) -> &Bucket {
+        match &self.guards[self.of[idx]] {
+            Some(bucket) => bucket,
+            None => unreachable!(),
+        }
+    }
+}
 
-        // TODO: remove after implementing `Parker`s which guarantee no panics.
-        let on_panic = {
-            use core::mem::MaybeUninit;
+impl<const N: usize> Drop for BucketSet<N> {
+    fn drop(&mut self) {
+        // locks may restore the interrupt state when unlocked, so in reverse order
+        for guard in self.guards.iter_mut().rev() {
+            drop(guard.take());
+        }
+    }
+}
+
+/// Locks the buckets of `addrs` in index order, like `lock_bucket_pair`.
+fn lock_bucket_set<const N: usize>(addrs: &[usize; N]) -> BucketSet<N> {
+    #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+    let inside = reentrancy::Inside::enter();
+    loop {
+        let table = hashtable();
+        let mut order = addrs.map(|addr| table.hash(addr));
+        order.sort_unstable();
+        let mut guards: [Option<MutexGuard<'static, Bucket>>; N] = core::array::from_fn(|_| None);
+        for (i, &idx) in order.iter().enumerate() {
+            if i == 0 || order[i - 1] != idx {
+                guards[i] = Some(table.lock_index(idx));
+            }
+        }
+        // the table may have grown while waiting for the locks
+        if table.is_current() {
+            return BucketSet {
+                guards,
+                of: addrs.map(|addr| {
+                    let idx = table.hash(addr);
+                    order.partition_point(|&other| other < idx)
+                }),
+                #[cfg(all(debug_assertions, feature = "std", not(loom)))]
+                _inside: inside,
+            };
+        }
+    }
+}
+
+/* How many threads (and tasks) are parked on the addresses hashed to each
+ * slot, so `may_have_waiters` can answer without locking a bucket. Waiters
+ * count themselves before `expected` is called and uncount themselves once
+ * they return, so a slot can only read 0 if nobody is parked on its
+ * addresses. The reverse doesn't hold: slots are shared, woken threads are
+ * still counted until they run, and threads parked in `ParkingLot`s are
+ * counted here too.
+ *
+ * The fences pair up with the ones in `may_have_waiters`: either the waiter
+ * is counted by the time the unparker checks, or the store the unparker made
+ * before checking is seen by `expected`, so it doesn't park.
+ *
+ * `tiny-footprint` counts in `u16`s, which wrap around, so it relies on
+ * fewer than 65536 threads being parked at once.
+ */
+#[cfg(not(loom))]
+mod waiter_count {
+    use super::{hash, BUCKET_BITS, BUCKET_COUNT};
+    use crate::real::atomic::fence;
+    use core::sync::atomic::Ordering::{Relaxed, SeqCst};
+
+    #[cfg(feature = "tiny-footprint")]
+    pub(super) use crate::real::atomic::AtomicU16 as Count;
+    #[cfg(not(feature = "tiny-footprint"))]
+    pub(super) use crate::real::atomic::AtomicUsize as Count;
+
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: Count = Count::new(0);
+    static COUNTS: [Count; BUCKET_COUNT] = [ZERO; BUCKET_COUNT];
+
+    #[inline(always)]
+    fn slot(addr: usize) -> &'static Count {
+        &COUNTS[hash(addr, BUCKET_BITS)]
+    }
+
+    #[inline(always)]
+    pub(super) fn add(addr: usize) {
+        slot(addr).fetch_add(1, Relaxed);
+        fence(SeqCst);
+    }
+
+    #[inline(always)]
+    pub(super) fn remove(addr: usize) {
+        slot(addr).fetch_sub(1, Relaxed);
+    }
+
+    /// Moves `count` waiters from `from` to `to`, with both buckets locked.
+    #[inline(always)]
+    pub(super) fn requeue(from: usize, to: usize, count: usize) {
+        if count != 0 {
+            // truncating is fine, the counts wrap around anyway
+            slot(to).fetch_add(count as _, Relaxed);
+            slot(from).fetch_sub(count as _, Relaxed);
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn may_be_nonzero(addr: usize) -> bool {
+        fence(SeqCst);
+        slot(addr).load(Relaxed) != 0
+    }
+
+    #[cfg(all(unix, feature = "std"))]
+    pub(super) fn reset() {
+        for count in &COUNTS {
+            count.store(0, Relaxed);
+        }
+    }
+}
+
+// loom checks `may_have_waiters` with the buckets locked instead
+#[cfg(loom)]
+mod waiter_count {
+    #[inline(always)]
+    pub(super) fn add(_: usize) {}
+    #[inline(always)]
+    pub(super) fn remove(_: usize) {}
+    #[inline(always)]
+    pub(super) fn requeue(_: usize, _: usize, _: usize) {}
+}
+
+#[cfg(all(debug_assertions, feature = "std", not(loom)))]
+mod reentrancy {
+    use std::cell::Cell;
+
+    thread_local!(static INSIDE: Cell<bool> = const { Cell::new(false) });
+
+    pub(super) struct Inside;
+
+    impl Inside {
+        #[track_caller]
+        pub(super) fn enter() -> Self {
+            // TLS may already be destroyed, in which case nothing is checked
+            if INSIDE.try_with(|x| x.replace(true)).unwrap_or(false) {
+                panic!("sparking-lot-core functions can't be called from `expected`");
+            }
+            Inside
+        }
+    }
+
+    impl Drop for Inside {
+        fn drop(&mut self) {
+            let _ = INSIDE.try_with(|x| x.set(false));
+        }
+    }
+}
+
+/// # Safety
+///
+/// - must be called in the child process after `fork`,
+///   before it calls any other functions of this crate.
+#[cfg(all(unix, feature = "std", not(loom)))]
+pub(crate) unsafe fn reinit_after_fork() {
+    /* The threads which were parked in the parent don't exist in the
+     * child, but they are still queued and the buckets (or pool nodes)
+     * they were using may even be locked or borrowed forever.
+     */
+    hashtable().reset();
+    waiter_count::reset();
+    #[cfg(feature = "node-pool")]
+    pool::POOL.reset();
+}
+
+#[inline(always)]
+fn with_thread_data<R>(f: impl FnOnce(&ThreadData) -> R) -> R {
+    /* Without `std` there is no TLS, so the stack (or `node-pool`)
+     * is used instead. `static-only` avoids TLS too, because registering
+     * the destructor of `THREAD_DATA` may allocate.
+     */
+    #[cfg(not(any(loom, feature = "std")))]
+    const _: () = assert!(Parker::CHEAP_NEW);
+    #[cfg(all(any(loom, feature = "std"), not(feature = "static-only")))]
+    if !Parker::CHEAP_NEW {
+        #[cfg(not(loom))]
+        thread_local!(static THREAD_DATA: ThreadData = const {ThreadData::new()});
+        #[cfg(loom)]
+        loom::thread_local!(static THREAD_DATA: ThreadData = ThreadData::new());
+        return match THREAD_DATA.try_with(|x| x as *const _) {
+            Ok(ptr) => unsafe { f(&*ptr) },
+            Err(_) => {
+                let td = ThreadData::new();
+                f(&td)
+            }
+        };
+    }
+    #[cfg(all(feature = "node-pool", not(loom)))]
+    if let Some(node) = pool::POOL.acquire() {
+        return f(node.get());
+    }
+    let td = ThreadData::new();
+    f(&td)
+}
+
+#[cfg(all(feature = "node-pool", not(loom)))]
+mod pool {
+    use super::ThreadData;
+    use crate::real::node_pool::{NodePool, PoolNode, POOL_SIZE};
+
+    impl PoolNode for ThreadData {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const INIT: Self = ThreadData::new();
+    }
+
+    pub(super) static POOL: NodePool<ThreadData, POOL_SIZE> = NodePool::new();
+}
+
+/// Counts a parking thread as a waiter on `addrs` while `expected` runs.
+/// Dropping it uncounts the thread again, which is how both an invalid park
+/// and a panic in `expected` leave the counts as they found them, so a
+/// panic can't make `may_have_waiters` report waiters forever.
+struct Counted<'a>(&'a [usize]);
+
+impl<'a> Counted<'a> {
+    #[inline(always)]
+    fn add(addrs: &'a [usize]) -> Self {
+        for &addr in addrs {
+            waiter_count::add(addr);
+        }
+        Self(addrs)
+    }
+
+    /// Keeps the thread counted, it's queued now.
+    #[inline(always)]
+    fn keep(self) {
+        core::mem::forget(self);
+    }
+}
+
+impl Drop for Counted<'_> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        for &addr in self.0 {
+            waiter_count::remove(addr);
+        }
+    }
+}
+
+/// Wakes threads an unparker has already unlinked, from `first` to `last`,
+/// if its callback panics before it gets to them. They get the default
+/// token, since the callback never returned one. Forgotten otherwise.
+struct WakeOnUnwind {
+    first: *const ThreadData,
+    last: *const ThreadData,
+}
+
+impl WakeOnUnwind {
+    /// The list ending in `tail`, the `next` of its last thread.
+    fn list(list: &Link, tail: NonNull<Link>) -> Self {
+        Self {
+            first: list.get(),
+            // `ThreadData` is repr(C) and `next` is the first element
+            last: tail.as_ptr() as *const ThreadData,
+        }
+    }
+}
+
+impl Drop for WakeOnUnwind {
+    #[cold]
+    fn drop(&mut self) {
+        let mut current = self.first;
+        /*SAFETY:
+         * - sleeping threads can't destroy their ThreadData until woken.
+         * - the threads are unlinked, so this is the only thread which can wake them.
+         */
+        unsafe {
+            while !current.is_null() {
+                let next = if ptr::eq(current, self.last) {
+                    ptr::null()
+                } else {
+                    (*current).next.get()
+                };
+                (*current).token.set(DEFAULT_UNPARK_TOKEN);
+                ThreadData::unpark(current);
+                current = next;
+            }
+        }
+    }
+}
+
+/// Aborts the process when dropped. Only dropped while unwinding.
+#[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+struct AbortOnDrop;
+
+#[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+impl Drop for AbortOnDrop {
+    #[cold]
+    fn drop(&mut self) {
+        #[cfg(any(loom, feature = "std"))]
+        std::process::abort();
+        // panicking while unwinding aborts
+        #[cfg(not(any(loom, feature = "std")))]
+        panic!("sparking-lot-core: aborting because of a panic in `park`");
+    }
+}
+
+/// Calls `f`, a closure of the caller which runs while the thread is queued,
+/// and aborts if it panics with `abort-on-panic`, like for `expected`.
+#[inline(always)]
+fn guarded<R>(f: impl FnOnce() -> R) -> R {
+    #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+    let abort = AbortOnDrop;
+    let result = f();
+    #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+    core::mem::forget(abort);
+    result
+}
+
+#[cfg_attr(all(feature = "watchdog", not(loom)), track_caller)]
+#[inline(always)]
+pub(crate) fn park(addr: usize, expected: impl FnOnce() -> bool) -> Option<usize> {
+    park_tagged(addr, ADDRESS_TAG, expected)
+}
+
+/// Like `park`, for the waiters of keys and generations, see `ADDRESS_TAG`.
+#[cfg_attr(all(feature = "watchdog", not(loom)), track_caller)]
+#[inline(always)]
+pub(crate) fn park_tagged(addr: usize, tag: u64, expected: impl FnOnce() -> bool) -> Option<usize> {
+    park_blocking(Table::Global, addr, tag, expected, |_| ())
+}
+
+/// A waiter of `park_with_handle`, which `unpark_handle` unlinks directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Handle(*const ThreadData);
+
+#[cfg_attr(all(feature = "watchdog", not(loom)), track_caller)]
+#[inline(always)]
+pub(crate) fn park_with_handle(
+    addr: usize,
+    expected: impl FnOnce() -> bool,
+    queued: impl FnOnce(Handle),
+) -> Option<usize> {
+    park_blocking(Table::Global, addr, ADDRESS_TAG, expected, |thread_data| {
+        queued(Handle(thread_data))
+    })
+}
+
+/// # Safety
+///
+/// - the waiter of `handle` must not have returned from `park_with_handle`.
+pub(crate) unsafe fn unpark_handle(handle: Handle, token: usize) -> bool {
+    drain_isr_wakes();
+    let thread_data = &*handle.0;
+    let bucket = lock_bucket_of(Table::Global, thread_data);
+    // an unparker of its address may have unlinked it first
+    let queued = thread_data.queued.get();
+    if queued {
+        bucket.remove(thread_data);
+        thread_data.token.set(token);
+    }
+    drop(bucket);
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::unpark(
+        thread_data.addr.load(Relaxed),
+        UnparkKind::Handle,
+        queued.into(),
+    );
+    if queued {
+        ThreadData::unpark(thread_data);
+    }
+    queued
+}
+
+#[cfg_attr(all(feature = "watchdog", not(loom)), track_caller)]
+#[inline(always)]
+fn park_blocking(
+    table: Table<'_>,
+    addr: usize,
+    tag: u64,
+    expected: impl FnOnce() -> bool,
+    queued: impl FnOnce(*const ThreadData),
+) -> Option<usize> {
+    //SAFETY: `park` only called on this thread.
+    match park_with(
+        table,
+        addr,
+        tag,
+        expected,
+        queued,
+        |_, _| (),
+        |parker| unsafe {
+            parker.park();
+            Slept::Unparked
+        },
+    ) {
+        ParkResult::Unparked(token) => Some(token),
+        _ => None,
+    }
+}
+
+/// Parks until unparked or until `deadline` passes. If it passes, `timed_out`
+/// is called with the bucket locked, see `park_with`.
+#[cfg(all(
+    feature = "std",
+    not(any(loom, feature = "freertos", feature = "zephyr"))
+))]
+#[cfg_attr(feature = "watchdog", track_caller)]
+#[inline(always)]
+pub(crate) fn park_until(
+    addr: usize,
+    expected: impl FnOnce() -> bool,
+    deadline: std::time::Instant,
+    timed_out: impl FnOnce(usize, bool),
+) -> ParkResult {
+    park_until_in(Table::Global, addr, expected, deadline, timed_out)
+}
+
+#[cfg(all(
+    feature = "std",
+    not(any(loom, feature = "freertos", feature = "zephyr"))
+))]
+#[cfg_attr(feature = "watchdog", track_caller)]
+#[inline(always)]
+fn park_until_in(
+    table: Table<'_>,
+    addr: usize,
+    expected: impl FnOnce() -> bool,
+    deadline: std::time::Instant,
+    timed_out: impl FnOnce(usize, bool),
+) -> ParkResult {
+    //SAFETY: `park_until` only called on this thread.
+    park_with(
+        table,
+        addr,
+        ADDRESS_TAG,
+        expected,
+        |_| (),
+        timed_out,
+        |parker| unsafe { parker.park_until(deadline).into() },
+    )
+}
+
+/// Like `park`, but calls `before_sleep` once the waiter is queued and
+/// the bucket is unlocked.
+#[cfg_attr(all(feature = "watchdog", not(loom)), track_caller)]
+#[inline(always)]
+pub(crate) fn park_before_sleep(
+    addr: usize,
+    expected: impl FnOnce() -> bool,
+    before_sleep: impl FnOnce(),
+) -> Option<usize> {
+    match park_with(
+        Table::Global,
+        addr,
+        ADDRESS_TAG,
+        expected,
+        |_| (),
+        |_, _| (),
+        |parker| {
+            guarded(before_sleep);
+            //SAFETY: `park_before_sleep` only called on this thread.
+            unsafe { parker.park() };
+            Slept::Unparked
+        },
+    ) {
+        ParkResult::Unparked(token) => Some(token),
+        _ => None,
+    }
+}
+
+/// Like `park`, but the waiter is queued unconditionally and `validate` is
+/// called once the bucket is unlocked. If it returns false, the waiter
+/// unlinks itself, unless an unparker already did.
+#[cfg_attr(all(feature = "watchdog", not(loom)), track_caller)]
+#[inline(always)]
+pub(crate) fn park_then_validate(addr: usize, validate: impl FnOnce() -> bool) -> Option<usize> {
+    match park_with(
+        Table::Global,
+        addr,
+        ADDRESS_TAG,
+        || true,
+        |_| (),
+        |_, _| (),
+        |parker| {
+            if !guarded(validate) {
+                return Slept::Invalid;
+            }
+            //SAFETY: `park_then_validate` only called on this thread.
+            unsafe { parker.park() };
+            Slept::Unparked
+        },
+    ) {
+        ParkResult::Unparked(token) => Some(token),
+        _ => None,
+    }
+}
+
+/// Like `park_until`, but calls `before_sleep` once the waiter is queued and
+/// the bucket is unlocked, and only gives up if there's a `deadline`.
+#[cfg(all(
+    feature = "compat",
+    not(any(loom, feature = "freertos", feature = "zephyr"))
+))]
+#[cfg_attr(feature = "watchdog", track_caller)]
+#[inline(always)]
+pub(crate) fn park_before_sleep_until(
+    addr: usize,
+    expected: impl FnOnce() -> bool,
+    before_sleep: impl FnOnce(),
+    deadline: Option<std::time::Instant>,
+    timed_out: impl FnOnce(usize, bool),
+) -> ParkResult {
+    park_with(
+        Table::Global,
+        addr,
+        ADDRESS_TAG,
+        expected,
+        |_| (),
+        timed_out,
+        |parker| {
+            guarded(before_sleep);
+            //SAFETY: `park_before_sleep_until` only called on this thread.
+            unsafe {
+                match deadline {
+                    Some(deadline) => parker.park_until(deadline).into(),
+                    None => {
+                        parker.park();
+                        Slept::Unparked
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// How the `sleep` of `park_with` ended.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Slept {
+    Unparked,
+    /// It gave up waiting, e.g. because of a timeout.
+    TimedOut,
+    /// It didn't sleep, since the `validate` of `park_then_validate` returned false.
+    Invalid,
+}
+
+impl From<bool> for Slept {
+    /// From whether the parker was unparked.
+    #[inline(always)]
+    fn from(unparked: bool) -> Self {
+        if unparked {
+            Slept::Unparked
+        } else {
+            Slept::TimedOut
+        }
+    }
+}
+
+/// Common part of the `park` functions. `queued` is called with the waiter
+/// once it's queued, before the bucket is unlocked. `sleep` parks `parker`
+/// and returns how that ended. If it gave up before being unparked, the
+/// waiter unlinks itself, unless an unparker already did. Then `timed_out` is
+/// called with the bucket still locked, with the address the waiter was queued
+/// on (it may have been requeued) and whether it was the last waiter there.
+#[cfg_attr(all(feature = "watchdog", not(loom)), track_caller)]
+#[inline(always)]
+fn park_with(
+    table: Table<'_>,
+    addr: usize,
+    tag: u64,
+    expected: impl FnOnce() -> bool,
+    queued: impl FnOnce(*const ThreadData),
+    timed_out: impl FnOnce(usize, bool),
+    sleep: impl FnOnce(&Parker) -> Slept,
+) -> ParkResult {
+    #[cfg(all(feature = "watchdog", not(loom)))]
+    let location = core::panic::Location::caller();
+    drain_isr_wakes();
+    #[cfg(all(feature = "growable-table", not(loom)))]
+    growth::register_thread();
+    with_thread_data(|thread_data| {
+        let bucket = lock_bucket(table, addr);
+        #[cfg(all(feature = "watchdog", not(loom)))]
+        let bucket = crate::real::watchdog::Watched::new(bucket, location);
+        let counted = Counted::add(core::slice::from_ref(&addr));
+        #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+        let abort = AbortOnDrop;
+        let expected = expected();
+        #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+        core::mem::forget(abort);
+        #[cfg(all(feature = "stats", not(loom)))]
+        stats::park(expected);
+        if !expected {
+            return ParkResult::Invalid;
+        }
+        counted.keep();
+
+        /* If parking panics, `registration` unlinks `thread_data` when dropped.
+         * Parkers which guarantee no panics (the futex one) don't need the
+         * abort guard below, and for them the unwinding path is dead code.
+         * Panics can't be caught with `panic = "abort"`.
+         */
+        #[cfg(all(feature = "deadlock-detection", not(loom)))]
+        thread_data.thread.set(deadlock::current());
+        //SAFETY: `thread_data` is only linked into one queue at a time
+        let registration =
+            unsafe { Registration::register(table, &bucket, addr, tag, thread_data) };
+        queued(thread_data);
+        // not releasing `bucket` lock before parking would deadlock
+        drop(bucket);
+        #[cfg(all(feature = "instrument", not(loom)))]
+        instrument::park(addr);
+
+        #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+        // not `then_some`, which would build the guard and drop it right away
+        let on_panic = if Parker::CAN_PANIC {
+            Some(AbortOnDrop)
+        } else {
+            None
+        };
+        let slept = sleep(&thread_data.parker);
+        //disengage panic guard
+        #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+        core::mem::forget(on_panic);
+
+        let result = if slept == Slept::Unparked {
+            registration.woken();
+            ParkResult::Unparked(thread_data.token.get())
+        } else if registration.deregister(timed_out) {
+            if slept == Slept::Invalid {
+                ParkResult::Invalid
+            } else {
+                ParkResult::TimedOut
+            }
+        } else {
+            // an unparker unlinked `thread_data` first, so it's about to unpark it
+            //SAFETY: `park` only called on this thread.
+            unsafe { thread_data.parker.park() };
+            ParkResult::Unparked(thread_data.token.get())
+        };
+        // it may have been requeued, but it's no longer queued anywhere
+        waiter_count::remove(thread_data.addr.load(Relaxed));
+        #[cfg(all(feature = "stats", not(loom)))]
+        if result == ParkResult::Invalid {
+            stats::failed_validation();
+        }
+        #[cfg(all(feature = "instrument", not(loom)))]
+        match result {
+            ParkResult::Invalid => instrument::invalid(addr),
+            _ => instrument::wake(addr, result == ParkResult::TimedOut),
+        }
+        result
+    })
+}
+
+/// A waiter's `ThreadData` while it's linked into a bucket queue.
+///
+/// This is the whole lifecycle of a `ThreadData` as a waiter: it's registered
+/// by `park` with the bucket locked, and deregistered by the unparker which
+/// unlinks it, also with the bucket locked, before it unparks it. Only then
+/// can the `ThreadData` be reused or destroyed. Wherever it lives (TLS, the
+/// `node-pool` or the stack of `park`), it outlives its registration, so
+/// parking also works in TLS destructors, even after `THREAD_DATA` is gone.
+///
+/// If parking unwinds, the registration deregisters the `ThreadData` itself
+/// when dropped, since no unparker will. A waiter which times out deregisters
+/// itself too, unless an unparker got to it first.
+struct Registration<'a> {
+    table: Table<'a>,
+    thread_data: &'a ThreadData,
+}
+
+impl<'a> Registration<'a> {
+    /// Appends `thread_data` to the queue of `bucket`.
+    ///
+    /// # Safety
+    ///
+    /// - `thread_data` must not be registered already.
+    /// - `bucket` must be the bucket of `addr` in `table`.
+    #[inline(always)]
+    unsafe fn register(
+        table: Table<'a>,
+        bucket: &Bucket,
+        addr: usize,
+        tag: u64,
+        thread_data: &'a ThreadData,
+    ) -> Self {
+        thread_data.addr.store(addr, Relaxed);
+        thread_data.tag.set(tag);
+        thread_data.token.set(DEFAULT_UNPARK_TOKEN);
+        thread_data.parker.prepare_park();
+        bucket.push(thread_data);
+        Self { table, thread_data }
+    }
+
+    /// Called after `park` returns, when an unparker has
+    /// already deregistered the `ThreadData`.
+    #[inline(always)]
+    fn woken(self) {
+        core::mem::forget(self);
+    }
+
+    /// Deregisters the `ThreadData` if no unparker has done it yet.
+    /// Returns false if one has, in which case it's about to be unparked.
+    /// Otherwise calls `unlinked` before releasing the bucket lock, see `unlink`.
+    fn deregister(self, unlinked: impl FnOnce(usize, bool)) -> bool {
+        let unlinked = self.unlink(unlinked);
+        core::mem::forget(self);
+        unlinked
+    }
+
+    /// Unlinks the `ThreadData` if it's still queued, and then calls
+    /// `unlinked` with the bucket locked, with its address and whether
+    /// no waiters with its address and tag are left.
+    #[cold]
+    fn unlink(&self, unlinked: impl FnOnce(usize, bool)) -> bool {
+        let bucket = lock_bucket_of(self.table, self.thread_data);
+        if !self.thread_data.queued.get() {
+            return false;
+        }
+        //SAFETY: the bucket is locked, and `thread_data` is queued in it
+        unsafe { bucket.remove(self.thread_data) };
+        let addr = self.thread_data.addr.load(Relaxed);
+        let tag = self.thread_data.tag.get();
+        //SAFETY: the bucket is locked, so its queue is valid
+        unlinked(addr, unsafe {
+            !has_waiters(bucket.first_for(addr, tag), addr, tag)
+        });
+        true
+    }
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        let thread_data = self.thread_data;
+        /* An unparker which unlinked the waiter first is about to unpark it,
+         * and the `ThreadData` may be on the stack which is unwinding, so
+         * the thread has to wait for it, like when it times out. The waiters
+         * of `park_any` are woken through the parker of their group, and
+         * only the one whose unparker claimed the group is unlinked by it.
+         */
+        if !self.unlink(|_, _| ()) {
+            let group = thread_data.group.get();
+            //SAFETY: only the thread of the waiter unwinds out of `park`
+            unsafe {
+                if group.is_null() {
+                    thread_data.parker.park();
+                } else {
+                    (*(*group).parker).park();
+                }
+            }
+        }
+        waiter_count::remove(thread_data.addr.load(Relaxed));
+    }
+}
+
+/// Waits for an unparker to finish with a waiter it unlinked.
+#[cfg(feature = "async")]
+#[cold]
+fn spin_until(done: impl Fn() -> bool) {
+    while !done() {
+        #[cfg(loom)]
+        loom::thread::yield_now();
+        #[cfg(all(not(loom), feature = "std"))]
+        std::thread::yield_now();
+        #[cfg(all(not(loom), not(feature = "std")))]
+        core::hint::spin_loop();
+    }
+}
+
+/// Shared by the waiters of one `park_any` call, which are woken through it.
+/// An unparker claims it with the bucket of its waiter locked, before it
+/// unlinks the waiter, and the others skip the rest of its waiters from then
+/// on, so only one of them is unlinked and counted as woken. The thread then
+/// unlinks the rest itself.
+struct Group {
+    parker: *const Parker,
+    /// The waiter whose unparker claimed the group, null until one has.
+    woken: AtomicPtr<ThreadData>,
+}
+
+impl Group {
+    /// # Safety
+    ///
+    /// - `this` must be null or the group of a queued waiter.
+    #[inline(always)]
+    unsafe fn is_claimed(this: *const Self) -> bool {
+        !this.is_null() && !(*this).woken.load(Relaxed).is_null()
+    }
+
+    /// # Safety
+    ///
+    /// - `this` must be the group of `waiter`, whose bucket the caller locked.
+    #[inline]
+    unsafe fn claim(this: *const Self, waiter: *const ThreadData) -> bool {
+        (*this)
+            .woken
+            .compare_exchange(ptr::null_mut(), waiter.cast_mut(), Relaxed, Relaxed)
+            .is_ok()
+    }
+}
+
+/// Parks on every address of `addrs` at once, with one waiter for each,
+/// until one of them is unparked. Returns its index and the token.
+#[inline]
+pub(crate) fn park_any<const N: usize>(
+    addrs: &[usize; N],
+    expected: impl FnOnce() -> bool,
+) -> Option<(usize, usize)> {
+    assert!(
+        N != 0,
+        "sparking-lot-core: `park_any` needs at least one address"
+    );
+    drain_isr_wakes();
+    #[cfg(all(feature = "growable-table", not(loom)))]
+    growth::register_thread();
+    // a waiter for every address, so they can't come from TLS
+    let waiters: [ThreadData; N] = core::array::from_fn(|_| ThreadData::new());
+    let group = Group {
+        parker: &waiters[0].parker,
+        woken: AtomicPtr::new(ptr::null_mut()),
+    };
+    let buckets = lock_bucket_set(addrs);
+    let counted = Counted::add(addrs);
+    #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+    let abort = AbortOnDrop;
+    let expected = expected();
+    #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+    core::mem::forget(abort);
+    #[cfg(all(feature = "stats", not(loom)))]
+    stats::park(expected);
+    if !expected {
+        return None;
+    }
+    counted.keep();
+
+    let registrations: [Registration<'_>; N] = core::array::from_fn(|idx| {
+        waiters[idx].group.set(&group);
+        //SAFETY: every waiter is only registered once, in the bucket of its address
+        unsafe {
+            Registration::register(
+                Table::Global,
+                buckets.get(idx),
+                addrs[idx],
+                ADDRESS_TAG,
+                &waiters[idx],
+            )
+        }
+    });
+    // not releasing the bucket locks before parking would deadlock
+    drop(buckets);
+    #[cfg(all(feature = "instrument", not(loom)))]
+    for &addr in addrs {
+        instrument::park(addr);
+    }
+
+    #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+    // see `park_with`
+    let on_panic = if Parker::CAN_PANIC {
+        Some(AbortOnDrop)
+    } else {
+        None
+    };
+    //SAFETY: `park` only called on this thread.
+    unsafe { waiters[0].parker.park() };
+    #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+    core::mem::forget(on_panic);
+
+    let woken = group.woken.load(Relaxed);
+    let mut index = 0;
+    for (idx, registration) in registrations.into_iter().enumerate() {
+        if ptr::eq(&waiters[idx], woken) {
+            index = idx;
+            registration.woken();
+        } else {
+            // only the unparker which claimed the group unlinks its waiter
+            let unlinked = registration.deregister(|_, _| ());
+            debug_assert!(unlinked);
+        }
+    }
+    for waiter in &waiters {
+        // they may have been requeued
+        waiter_count::remove(waiter.addr.load(Relaxed));
+    }
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::wake(addrs[index], false);
+    Some((index, waiters[index].token.get()))
+}
+
+/// The waiter of a `park_async` future. Its `ThreadData` is woken through
+/// `waker` instead of the parker, so it can't block until it's unparked;
+/// instead `notified` tells it that the unparker is done with it.
+#[cfg(feature = "async")]
+pub(crate) struct AsyncWaiter {
+    thread_data: ThreadData,
+    /// Only accessed by the owner of the future.
+    registered: Cell<bool>,
+}
+
+/*SAFETY: `thread_data` is only accessed with its bucket locked while it's
+ * queued, and by its unparker after it's unlinked, which `notified` orders
+ * before the owner uses it again.
+ */
+#[cfg(feature = "async")]
+unsafe impl Send for AsyncWaiter {}
+#[cfg(feature = "async")]
+unsafe impl Sync for AsyncWaiter {}
+
+#[cfg(feature = "async")]
+impl AsyncWaiter {
+    pub(crate) fn new() -> Self {
+        Self {
+            thread_data: ThreadData::new(),
+            registered: Cell::new(false),
+        }
+    }
+
+    /// Queues the waiter on `addr` if `expected` returns true.
+    ///
+    /// # Safety
+    ///
+    /// - `self` must not move until it's dropped.
+    /// - can only be called once.
+    pub(crate) unsafe fn register(
+        &self,
+        addr: usize,
+        expected: impl FnOnce() -> bool,
+        waker: &Waker,
+    ) -> bool {
+        drain_isr_wakes();
+        let waker = waker.clone();
+        let bucket = lock_bucket(Table::Global, addr);
+        let counted = Counted::add(core::slice::from_ref(&addr));
+        #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+        let abort = AbortOnDrop;
+        let expected = expected();
+        #[cfg(all(feature = "abort-on-panic", not(panic = "abort")))]
+        core::mem::forget(abort);
+        #[cfg(all(feature = "stats", not(loom)))]
+        stats::park(expected);
+        if !expected {
+            return false;
+        }
+        counted.keep();
+        let thread_data = &self.thread_data;
+        thread_data.addr.store(addr, Relaxed);
+        thread_data.token.set(DEFAULT_UNPARK_TOKEN);
+        thread_data.waker.set(Some(waker));
+        bucket.push(thread_data);
+        self.registered.set(true);
+        true
+    }
+
+    /// Returns true once the waiter has been woken, otherwise
+    /// the waker is replaced with `waker` if they differ.
+    pub(crate) fn poll(&self, waker: &Waker) -> bool {
+        if self.thread_data.notified.load(Acquire) {
+            return true;
+        }
+        let bucket = lock_bucket_of(Table::Global, &self.thread_data);
+        if !self.thread_data.queued.get() {
+            drop(bucket);
+            // the unparker unlinked it, so it's about to be notified
+            self.wait_for_notify();
+            return true;
+        }
+        let old = self.thread_data.waker.take();
+        let old = match old {
+            Some(old) if old.will_wake(waker) => {
+                self.thread_data.waker.set(Some(old));
+                None
+            }
+            old => {
+                self.thread_data.waker.set(Some(waker.clone()));
+                old
+            }
+        };
+        drop(bucket);
+        // wakers may run arbitrary code when dropped
+        drop(old);
+        false
+    }
+
+    #[cold]
+    fn wait_for_notify(&self) {
+        spin_until(|| self.thread_data.notified.load(Acquire));
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for AsyncWaiter {
+    fn drop(&mut self) {
+        if !self.registered.get() {
+            return;
+        }
+        if !self.thread_data.notified.load(Acquire) {
+            let registration = Registration {
+                table: Table::Global,
+                thread_data: &self.thread_data,
+            };
+            if !registration.deregister(|_, _| ()) {
+                // the unparker may still be using `thread_data`
+                self.wait_for_notify();
+            } else {
+                // dropping a waker may run arbitrary code, so not with the bucket locked
+                drop(self.thread_data.waker.take());
+            }
+        }
+        waiter_count::remove(self.thread_data.addr.load(Relaxed));
+    }
+}
+
+/// Waiters are only ever appended to a bucket queue and unlinked from it, so
+/// tickets strictly increase along it, which makes wake-ups per-address FIFO.
+///
+/// # Safety
+///
+/// - `current` must be valid, `next` must be valid or null.
+#[inline(always)]
+#[allow(unused_variables)]
+unsafe fn debug_check_fifo(current: *const ThreadData, next: *const ThreadData) {
+    #[cfg(debug_assertions)]
+    if !next.is_null() {
+        let (current, next) = ((*current).ticket.get(), (*next).ticket.get());
+        assert!(
+            (next.wrapping_sub(current) as isize) > 0,
+            "sparking-lot-core: waiter queue out of FIFO order"
+        );
+    }
+}
+
+/// Returns true if a thread in the queue starting at `current` is parked on `addr`.
+///
+/// # Safety
+///
+/// - the bucket of the queue must be locked.
+#[inline(always)]
+unsafe fn has_waiters(mut current: *const ThreadData, addr: usize, tag: u64) -> bool {
+    while !current.is_null() {
+        if (*current).is_parked_on(addr, tag) {
+            return true;
+        }
+        current = (*current).next.get();
+    }
+    false
+}
+
+pub(crate) fn parked_count(addr: usize) -> usize {
+    parked_count_in(Table::Global, addr)
+}
+
+#[cfg(all(feature = "debug-introspection", not(loom)))]
+pub(crate) fn dump(
+    report: impl FnMut(usize, u64, usize) -> core::fmt::Result,
+) -> core::fmt::Result {
+    dump_in(Table::Global, report)
+}
+
+/// Calls `report` with every address and tag waiters are queued on in `table`,
+/// and how many of them there are. The buckets are locked one at a time, so
+/// it isn't a snapshot, and `report` runs with the bucket locked.
+#[cfg(all(feature = "debug-introspection", not(loom)))]
+fn dump_in(
+    table: Table<'_>,
+    mut report: impl FnMut(usize, u64, usize) -> core::fmt::Result,
+) -> core::fmt::Result {
+    drain_isr_wakes();
+    #[cfg(all(debug_assertions, feature = "std"))]
+    let _inside = reentrancy::Inside::enter();
+    let buckets = match table {
+        // a table which grows meanwhile is still valid, its waiters are just moved
+        Table::Global => hashtable().buckets(),
+        Table::Lot(buckets) => buckets,
+    };
+    for bucket in buckets {
+        let bucket = lock(bucket);
+        let first = bucket.first.get();
+        let mut current = first;
+        //SAFETY: the bucket is locked, so its queue is valid
+        unsafe {
+            while !current.is_null() {
+                let (addr, tag) = ((*current).addr.load(Relaxed), (*current).tag.get());
+                // every address is reported at its first waiter
+                let mut earlier = first;
+                while earlier != current && !(*earlier).is_parked_on(addr, tag) {
+                    earlier = (*earlier).next.get();
+                }
+                // waiters which aren't parked anymore are reported with the others
+                if earlier == current && (*current).is_parked_on(addr, tag) {
+                    let mut count = 0;
+                    let mut later = current;
+                    while !later.is_null() {
+                        if (*later).is_parked_on(addr, tag) {
+                            count += 1;
+                        }
+                        later = (*later).next.get();
+                    }
+                    report(addr, tag, count)?;
+                }
+                current = (*current).next.get();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns every thread parked on an address in the global table,
+/// with the address. Every bucket is locked while they're collected,
+/// so none of them can be woken in the meantime.
+#[cfg(all(feature = "deadlock-detection", not(loom)))]
+pub(crate) fn parked_threads() -> Vec<(Arc<deadlock::ThreadInfo>, usize)> {
+    #[cfg(debug_assertions)]
+    let _inside = reentrancy::Inside::enter();
+    loop {
+        let table = hashtable();
+        // in index order, like `lock_bucket_pair`
+        let buckets: Vec<MutexGuard<'_, Bucket>> = table.buckets().iter().map(lock).collect();
+        if !table.is_current() {
+            // the table grew while it was being locked
+            continue;
+        }
+        let mut parked = Vec::new();
+        for bucket in &buckets {
+            let mut current = bucket.first.get();
+            //SAFETY: the bucket is locked, so its queue is valid
+            unsafe {
+                while !current.is_null() {
+                    let thread = (*current).thread.take();
+                    if let (Some(info), ADDRESS_TAG) = (&thread, (*current).tag.get()) {
+                        parked.push((info.clone(), (*current).addr.load(Relaxed)));
+                    }
+                    (*current).thread.set(thread);
+                    current = (*current).next.get();
+                }
+            }
+        }
+        return parked;
+    }
+}
+
+#[cfg(not(loom))]
+#[inline(always)]
+pub(crate) fn may_have_waiters(addr: usize) -> bool {
+    waiter_count::may_be_nonzero(addr)
+}
+
+#[cfg(loom)]
+pub(crate) fn may_have_waiters(addr: usize) -> bool {
+    let bucket = lock_bucket(Table::Global, addr);
+    //SAFETY: the bucket is locked, so its queue is valid
+    unsafe { has_waiters(bucket.first_for(addr, ADDRESS_TAG), addr, ADDRESS_TAG) }
+}
+
+fn parked_count_in(table: Table<'_>, addr: usize) -> usize {
+    drain_isr_wakes();
+    let bucket = lock_bucket(table, addr);
+    let mut count = 0;
+    let mut current = bucket.first_for(addr, ADDRESS_TAG);
+    //SAFETY: the bucket is locked, so its queue is valid
+    unsafe {
+        while !current.is_null() {
+            if (*current).is_parked_on(addr, ADDRESS_TAG) {
+                count += 1;
+            }
+            current = (*current).next.get();
+        }
+    }
+    count
+}
+
+#[inline(always)]
+pub(crate) fn unpark_one(
+    addr: usize,
+    callback: impl FnOnce(UnparkResult) -> usize,
+) -> UnparkResult {
+    let result = unpark_one_in(Table::Global, addr, ADDRESS_TAG, |result, _| {
+        callback(result)
+    });
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::unpark(addr, UnparkKind::One, result.unparked);
+    #[cfg(all(feature = "stats", not(loom)))]
+    stats::unpark_one(result.unparked);
+    result
+}
+
+pub(crate) fn unpark_one_tagged(addr: usize, tag: u64) -> UnparkResult {
+    let result = unpark_one_in(Table::Global, addr, tag, |_, _| DEFAULT_UNPARK_TOKEN);
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::unpark(addr, UnparkKind::One, result.unparked);
+    #[cfg(all(feature = "stats", not(loom)))]
+    stats::unpark_one(result.unparked);
+    result
+}
+
+/// Doesn't drain the wakes queued by interrupt handlers, since that
+/// may have to wait for other buckets. The next unpark does it.
+pub(crate) fn try_unpark_one(addr: usize) -> Option<UnparkResult> {
+    let bucket = try_lock_bucket_in_table(addr)?;
+    let result = unpark_one_locked(bucket, addr, ADDRESS_TAG, |_, _| DEFAULT_UNPARK_TOKEN);
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::unpark(addr, UnparkKind::One, result.unparked);
+    #[cfg(all(feature = "stats", not(loom)))]
+    stats::unpark_one(result.unparked);
+    Some(result)
+}
+
+pub(crate) fn unpark_one_fifo(addr: usize) -> UnparkResult {
+    drain_isr_wakes();
+    let bucket = lock_bucket(Table::Global, addr);
+    let result = unpark_first_locked(bucket, addr, ADDRESS_TAG, |_, _| DEFAULT_UNPARK_TOKEN);
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::unpark(addr, UnparkKind::One, result.unparked);
+    #[cfg(all(feature = "stats", not(loom)))]
+    stats::unpark_one(result.unparked);
+    result
+}
+
+pub(crate) fn unpark_one_fair(
+    addr: usize,
+    callback: impl FnOnce(UnparkResult, bool) -> usize,
+) -> UnparkResult {
+    let result = unpark_one_in(Table::Global, addr, ADDRESS_TAG, |result, bucket| {
+        let be_fair = result.unparked != 0 && bucket.be_fair();
+        callback(result, be_fair)
+    });
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::unpark(addr, UnparkKind::OneFair, result.unparked);
+    #[cfg(all(feature = "stats", not(loom)))]
+    stats::unpark_one(result.unparked);
+    result
+}
+
+fn unpark_one_in(
+    table: Table<'_>,
+    addr: usize,
+    tag: u64,
+    callback: impl FnOnce(UnparkResult, &Bucket) -> usize,
+) -> UnparkResult {
+    drain_isr_wakes();
+    unpark_one_locked(lock_bucket(table, addr), addr, tag, callback)
+}
+
+#[cfg(not(feature = "random-wake"))]
+#[inline(always)]
+fn unpark_one_locked(
+    bucket: BucketGuard<'_>,
+    addr: usize,
+    tag: u64,
+    callback: impl FnOnce(UnparkResult, &Bucket) -> usize,
+) -> UnparkResult {
+    unpark_first_locked(bucket, addr, tag, callback)
+}
+
+/// Wakes the thread which parked on `addr` first, even with `random-wake`.
+fn unpark_first_locked(
+    bucket: BucketGuard<'_>,
+    addr: usize,
+    tag: u64,
+    callback: impl FnOnce(UnparkResult, &Bucket) -> usize,
+) -> UnparkResult {
+    let mut current = bucket.first_for(addr, tag);
+    /*SAFETY:
+     * - sleeping threads can't destroy their ThreadData.
+     * - the bucket is locked, so threads can't be unlinked by others.
+     * So, if `*const ThreadData` isn't null, then it's safe to dereference.
+     */
+    unsafe {
+        while !current.is_null() {
+            let next = (*current).next.get();
+            debug_check_fifo(current, next);
+            if (*current).is_parked_on(addr, tag) && ThreadData::claim(current) {
+                bucket.remove(current);
+                let result = UnparkResult {
+                    unparked: 1,
+                    has_more: has_waiters(next, addr, tag),
+                };
+                let wake = WakeOnUnwind {
+                    first: current,
+                    last: current,
+                };
+                let token = callback(result, &bucket);
+                core::mem::forget(wake);
+                // the thread to wake has been unlinked, release the lock
+                drop(bucket);
 
-            struct OnDrop<F: FnOnce()>(MaybeUninit<F>);
-            impl<F: FnOnce()> Drop for OnDrop<F> {
-                fn drop(&mut self) {
-                    // Always initialised
-                    unsafe { self.0.assume_init_read()() };
-                }
+                (*current).token.set(token);
+                ThreadData::unpark(current);
+                return result;
             }
-            OnDrop(MaybeUninit::new(|| {
-                release(addr, thread_data);
-                // Slight modification of `unpark_one`
-                #[cold]
-                fn release(addr: *const (), thread_data: &ThreadData) {
-                    let bucket = lock_bucket(addr);
-                    let mut current = bucket.first.get();
-                    let mut previous = ptr::null();
-                    /*SAFETY:
-                     * - sleeping threads can't destroy their ThreadData.
-                     * - the bucket is locked, so threads can't be unlinked by others.
-                     * So, if `*const ThreadData` isn't null, then it's safe to dereference.
-                     */
-                    unsafe {
-                        while !current.is_null() {
-                            let next = (*current).next.get();
-                            if ptr::eq(current, thread_data) {
-                                // fix tail if needed, goes first to deduce `previous`
-                                if current == bucket.last.get() {
-                                    bucket.last.set(previous);
-                                }
-                                // remove `current` from the list
-                                if previous.is_null() {
-                                    bucket.first.set(next);
-                                } else {
-                                    (*previous).next.set(next);
-                                }
-
-                                return;
-                            }
-                            previous = current;
-                            current = next;
-                        }
+            current = next;
+        }
+    }
+    callback(UnparkResult::default(), &bucket);
+    UnparkResult::default()
+}
+
+/// Wakes a random thread parked on `addr`, picked with reservoir sampling.
+#[cfg(feature = "random-wake")]
+fn unpark_one_locked(
+    bucket: BucketGuard<'_>,
+    addr: usize,
+    tag: u64,
+    callback: impl FnOnce(UnparkResult, &Bucket) -> usize,
+) -> UnparkResult {
+    /*SAFETY:
+     * - sleeping threads can't destroy their ThreadData.
+     * - the bucket is locked, so threads can't be unlinked by others.
+     * So, if `*const ThreadData` isn't null, then it's safe to dereference.
+     */
+    unsafe {
+        let (chosen, seen) = loop {
+            let mut current = bucket.first_for(addr, tag);
+            let mut chosen = ptr::null::<ThreadData>();
+            let mut seen = 0u32;
+            while !current.is_null() {
+                let next = (*current).next.get();
+                debug_check_fifo(current, next);
+                if (*current).is_parked_on(addr, tag) {
+                    seen += 1;
+                    // the n-th waiter replaces the choice with probability 1/n,
+                    // the product is a random number in `0..seen` in the top half
+                    if (u64::from(bucket.random()) * u64::from(seen)) >> 32 == 0 {
+                        chosen = current;
                     }
                 }
-            }))
+                current = next;
+            }
+            if chosen.is_null() {
+                callback(UnparkResult::default(), &bucket);
+                return UnparkResult::default();
+            }
+            // otherwise it's no longer parked, so the next pass skips it
+            if ThreadData::claim(chosen) {
+                break (chosen, seen);
+            }
+        };
+        bucket.remove(chosen);
+        let result = UnparkResult {
+            unparked: 1,
+            has_more: seen > 1,
         };
+        let wake = WakeOnUnwind {
+            first: chosen,
+            last: chosen,
+        };
+        let token = callback(result, &bucket);
+        core::mem::forget(wake);
+        // the thread to wake has been unlinked, release the lock
+        drop(bucket);
 
-        //SAFETY: `park` only called on this thread.
-        unsafe {
-            thread_data.parker.park();
-        }
+        (*chosen).token.set(token);
+        ThreadData::unpark(chosen);
+        result
+    }
+}
 
-        //disengage panic guard
-        core::mem::forget(on_panic);
-    });
+#[inline(always)]
+pub(crate) fn unpark_all(addr: usize, callback: impl FnOnce(UnparkResult)) -> UnparkResult {
+    unpark_all_in(Table::Global, addr, ADDRESS_TAG, callback)
+}
+
+#[inline(always)]
+pub(crate) fn unpark_all_tagged(addr: usize, tag: u64) -> UnparkResult {
+    unpark_all_in(Table::Global, addr, tag, |_| ())
 }
 
-pub(crate) fn unpark_one(addr: *const ()) {
-    let bucket = lock_bucket(addr);
-    let mut current = bucket.first.get();
-    let mut previous = ptr::null();
+/// Calls `callback` after unlinking the waiters, before releasing the bucket.
+fn unpark_all_in(
+    table: Table<'_>,
+    addr: usize,
+    tag: u64,
+    callback: impl FnOnce(UnparkResult),
+) -> UnparkResult {
+    drain_isr_wakes();
+    let bucket = lock_bucket(table, addr);
+    let mut woken = 0;
+    let mut current = bucket.first_for(addr, tag);
+
+    let unpark_list = Link::null();
+    let mut unpark_list_tail = NonNull::from(&unpark_list);
+
     /*SAFETY:
      * - sleeping threads can't destroy their ThreadData.
      * - the bucket is locked, so threads can't be unlinked by others.
@@ -259,38 +2334,112 @@ pub(crate) fn unpark_one(addr: *const ()) {
     unsafe {
         while !current.is_null() {
             let next = (*current).next.get();
-            if (*current).addr.get() == addr {
-                // fix tail if needed, goes first to deduce `previous`
-                if current == bucket.last.get() {
-                    bucket.last.set(previous);
-                }
-                // remove `current` from the list
-                if previous.is_null() {
-                    bucket.first.set(next);
-                } else {
-                    (*previous).next.set(next);
-                }
-                // the thread to wake has been unlinked, release the lock
-                drop(bucket);
+            debug_check_fifo(current, next);
+            if (*current).is_parked_on(addr, tag) && ThreadData::claim(current) {
+                bucket.remove(current);
 
-                // since ThreadData lives until the thread is
-                // woken and threads sleep before `unpark` is
-                // called, `parker` is alive.
-                ParkerT::unpark(addr_of!((*current).parker));
-                return;
+                unpark_list_tail.as_ref().set(current);
+                unpark_list_tail = NonNull::from(&(*current).next);
+                woken += 1;
             }
-            previous = current;
             current = next;
         }
     }
+    let result = UnparkResult {
+        unparked: woken,
+        has_more: false,
+    };
+    let wake = WakeOnUnwind::list(&unpark_list, unpark_list_tail);
+    callback(result);
+    core::mem::forget(wake);
+    drop(bucket);
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::unpark(addr, UnparkKind::All, woken);
+    #[cfg(all(feature = "stats", not(loom)))]
+    stats::unpark_all(woken);
+
+    let mut current = unpark_list.get();
+    if current.is_null() {
+        return result;
+    }
+    loop {
+        /*SAFETY:
+         * - sleeping threads can't destroy their ThreadData until woken.
+         * - this thread is the only awake thread with access to them.
+         */
+        unsafe {
+            let next = (*current).next.get();
+            ThreadData::unpark(current);
+
+            // `ThreadData` is repr(C) and `next` is the first element, so
+            // (`current` as *const Link) gives the address of `current->next`.
+            if ptr::eq(current as *const Link, unpark_list_tail.as_ptr()) {
+                break;
+            }
+            // now *current may be destroyed, but it's no longer accessed.
+            current = next;
+        };
+    }
+    result
 }
 
-pub(crate) fn unpark_all(addr: *const ()) {
-    let bucket = lock_bucket(addr);
-    let mut current = bucket.first.get();
-    let mut previous = ptr::null();
+pub(crate) fn unpark_some(addr: usize, count: usize) -> UnparkResult {
+    if count == 0 {
+        return unpark_none(addr);
+    }
+    unpark_some_in(Table::Global, addr, count, || ())
+}
+
+pub(crate) fn unpark_some_release(
+    addr: usize,
+    count: usize,
+    release: impl FnOnce(),
+) -> UnparkResult {
+    unpark_some_in(Table::Global, addr, count, release)
+}
+
+/// `unpark_some` with a count of 0, which wakes nobody, so nothing is
+/// locked and `has_more` isn't looked up.
+#[inline(always)]
+fn unpark_none(addr: usize) -> UnparkResult {
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::unpark(addr, UnparkKind::Some, 0);
+    #[cfg(not(all(feature = "instrument", not(loom))))]
+    let _ = addr;
+    #[cfg(all(feature = "stats", not(loom)))]
+    stats::unpark_some(0);
+    UnparkResult::default()
+}
+
+fn unpark_some_in(
+    table: Table<'_>,
+    addr: usize,
+    count: usize,
+    release: impl FnOnce(),
+) -> UnparkResult {
+    drain_isr_wakes();
+    let bucket = lock_bucket(table, addr);
+    release();
+    if count == 0 {
+        let result = UnparkResult {
+            unparked: 0,
+            //SAFETY: the bucket is locked
+            has_more: unsafe {
+                has_waiters(bucket.first_for(addr, ADDRESS_TAG), addr, ADDRESS_TAG)
+            },
+        };
+        drop(bucket);
+        #[cfg(all(feature = "instrument", not(loom)))]
+        instrument::unpark(addr, UnparkKind::Some, 0);
+        #[cfg(all(feature = "stats", not(loom)))]
+        stats::unpark_some(0);
+        return result;
+    }
+    let mut woken = 0;
+    let mut has_more = false;
+    let mut current = bucket.first_for(addr, ADDRESS_TAG);
 
-    let unpark_list = Cell::new(ptr::null::<ThreadData>());
+    let unpark_list = Link::null();
     let mut unpark_list_tail = NonNull::from(&unpark_list);
 
     /*SAFETY:
@@ -301,31 +2450,133 @@ pub(crate) fn unpark_all(addr: *const ()) {
     unsafe {
         while !current.is_null() {
             let next = (*current).next.get();
-            if (*current).addr.get() == addr {
-                // fix tail if needed, goes first to deduce `previous`
-                if current == bucket.last.get() {
-                    bucket.last.set(previous);
-                }
-                // remove `current` from the list
-                if previous.is_null() {
-                    bucket.first.set(next);
-                } else {
-                    (*previous).next.set(next);
-                }
+            debug_check_fifo(current, next);
+            if (*current).is_parked_on(addr, ADDRESS_TAG) && ThreadData::claim(current) {
+                bucket.remove(current);
 
                 unpark_list_tail.as_ref().set(current);
                 unpark_list_tail = NonNull::from(&(*current).next);
-            } else {
-                previous = current;
+
+                woken += 1;
+                if woken == count {
+                    has_more = has_waiters(next, addr, ADDRESS_TAG);
+                    break;
+                }
             }
             current = next;
         }
     }
     drop(bucket);
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::unpark(addr, UnparkKind::Some, woken);
+    #[cfg(all(feature = "stats", not(loom)))]
+    stats::unpark_some(woken);
+
+    let result = UnparkResult {
+        unparked: woken,
+        has_more,
+    };
+    let mut current = unpark_list.get();
+    if current.is_null() {
+        return result;
+    }
+    loop {
+        /*SAFETY:
+         * - sleeping threads can't destroy their ThreadData until woken.
+         * - this thread is the only awake thread with access to them.
+         */
+        unsafe {
+            let next = (*current).next.get();
+            ThreadData::unpark(current);
+
+            // `ThreadData` is repr(C) and `next` is the first element, so
+            // (`current` as *const Link) gives the address of `current->next`.
+            if ptr::eq(current as *const Link, unpark_list_tail.as_ptr()) {
+                break;
+            }
+            // now *current may be destroyed, but it's no longer accessed.
+            current = next;
+        };
+    }
+    result
+}
+
+/// Unlinks up to `count` threads parked on `addr` from the queue of `bucket`
+/// and appends them to the unpark list ending at `tail`. Returns how many.
+///
+/// # Safety
+///
+/// - `bucket` must be locked.
+/// - `tail` must be the tail of an unpark list owned by the caller.
+unsafe fn unlink_waiters(
+    bucket: &Bucket,
+    addr: usize,
+    count: usize,
+    tail: &mut NonNull<Link>,
+) -> usize {
+    let mut unlinked = 0;
+    let mut current = bucket.first_for(addr, ADDRESS_TAG);
+    while unlinked < count && !current.is_null() {
+        let next = (*current).next.get();
+        debug_check_fifo(current, next);
+        if (*current).is_parked_on(addr, ADDRESS_TAG) && ThreadData::claim(current) {
+            bucket.remove(current);
+
+            tail.as_ref().set(current);
+            *tail = NonNull::from(&(*current).next);
+            unlinked += 1;
+        }
+        current = next;
+    }
+    unlinked
+}
+
+pub(crate) fn unpark_many<A: AsParkAddr>(requests: &[(A, usize)]) -> usize {
+    drain_isr_wakes();
+    let mut woken = 0;
+
+    let unpark_list = Link::null();
+    let mut unpark_list_tail = NonNull::from(&unpark_list);
+
+    /* Requests are grouped by the bucket they're in when it's locked. The
+     * table may grow in between, so which requests have been handled is
+     * tracked with a bit mask, one chunk of requests at a time.
+     */
+    for chunk in requests.chunks(usize::BITS as usize) {
+        let mut handled = 0usize;
+        #[cfg(all(feature = "instrument", not(loom)))]
+        let mut unparked = [0; usize::BITS as usize];
+        for (i, (addr, _)) in chunk.iter().enumerate() {
+            if handled & (1 << i) != 0 {
+                continue;
+            }
+            let (table, bucket) = lock_bucket_in_table(addr.park_addr());
+            let idx = table.hash(addr.park_addr());
+            for (j, (addr, count)) in chunk.iter().enumerate().skip(i) {
+                if handled & (1 << j) == 0 && table.hash(addr.park_addr()) == idx {
+                    handled |= 1 << j;
+                    //SAFETY: the bucket is locked and the list is local
+                    let unlinked = unsafe {
+                        unlink_waiters(&bucket, addr.park_addr(), *count, &mut unpark_list_tail)
+                    };
+                    woken += unlinked;
+                    #[cfg(all(feature = "instrument", not(loom)))]
+                    {
+                        unparked[j] = unlinked;
+                    }
+                }
+            }
+        }
+        // every bucket of the chunk is unlocked now
+        #[cfg(all(feature = "instrument", not(loom)))]
+        for ((addr, _), &unparked) in chunk.iter().zip(&unparked) {
+            instrument::unpark(addr.park_addr(), UnparkKind::Many, unparked);
+        }
+    }
 
     let mut current = unpark_list.get();
     if current.is_null() {
-        return;
+        return woken;
     }
     loop {
         /*SAFETY:
@@ -334,68 +2585,111 @@ pub(crate) fn unpark_all(addr: *const ()) {
          */
         unsafe {
             let next = (*current).next.get();
-            // since ThreadData lives until the thread is
-            // woken and threads sleep before `unpark` is
-            // called, `parker` is alive.
-            ParkerT::unpark(addr_of!((*current).parker));
+            ThreadData::unpark(current);
 
             // `ThreadData` is repr(C) and `next` is the first element, so
-            // (`current` as *const Cell<_>) gives the address of `current->next`.
-            if ptr::eq(current as *const Cell<_>, unpark_list_tail.as_ptr()) {
+            // (`current` as *const Link) gives the address of `current->next`.
+            if ptr::eq(current as *const Link, unpark_list_tail.as_ptr()) {
                 break;
             }
             // now *current may be destroyed, but it's no longer accessed.
             current = next;
         };
     }
+    woken
+}
+
+/// Wakes up to `wake_count` threads parked on `from` and moves up to
+/// `requeue_count` of the next ones to the queue of `to`.
+#[inline(always)]
+pub(crate) fn unpark_requeue(
+    from: usize,
+    to: usize,
+    wake_count: usize,
+    requeue_count: usize,
+) -> RequeueResult {
+    unpark_requeue_with(
+        from,
+        to,
+        || (wake_count, requeue_count),
+        |_, _| DEFAULT_UNPARK_TOKEN,
+    )
 }
 
-pub(crate) fn unpark_some(addr: *const (), mut count: usize) {
-    let bucket = lock_bucket(addr);
-    let mut current = bucket.first.get();
-    let mut previous = ptr::null();
+/// Like `unpark_requeue`, but `counts` returns how many threads to wake and
+/// requeue, and `callback` the token of the woken ones, given the result and
+/// whether threads are left on `from`. Both are called with the buckets locked.
+pub(crate) fn unpark_requeue_with(
+    from: usize,
+    to: usize,
+    counts: impl FnOnce() -> (usize, usize),
+    callback: impl FnOnce(RequeueResult, bool) -> usize,
+) -> RequeueResult {
+    drain_isr_wakes();
+    let buckets = lock_bucket_pair(from, to);
+    let (from_bucket, to_bucket) = (buckets.from(), buckets.to());
+    let (wake_count, requeue_count) = counts();
+    let mut result = RequeueResult::default();
+    let mut current = from_bucket.first_for(from, ADDRESS_TAG);
 
-    let unpark_list = Cell::new(ptr::null::<ThreadData>());
+    let unpark_list = Link::null();
     let mut unpark_list_tail = NonNull::from(&unpark_list);
 
     /*SAFETY:
      * - sleeping threads can't destroy their ThreadData.
-     * - the bucket is locked, so threads can't be unlinked by others.
+     * - both buckets are locked, so threads can't be unlinked by others.
      * So, if `*const ThreadData` isn't null, then it's safe to dereference.
      */
     unsafe {
-        while !current.is_null() {
+        while !current.is_null()
+            && (result.unparked < wake_count || result.requeued < requeue_count)
+        {
             let next = (*current).next.get();
-            if (*current).addr.get() == addr {
-                // fix tail if needed, goes first to deduce `previous`
-                if current == bucket.last.get() {
-                    bucket.last.set(previous);
+            debug_check_fifo(current, next);
+            if !(*current).is_parked_on(from, ADDRESS_TAG)
+                || (result.unparked == wake_count && from == to)
+            {
+                // requeueing to the same address is a no-op, but still counted
+                if (*current).is_parked_on(from, ADDRESS_TAG) {
+                    result.requeued += 1;
                 }
-                // remove `current` from the list
-                if previous.is_null() {
-                    bucket.first.set(next);
-                } else {
-                    (*previous).next.set(next);
+                current = next;
+                continue;
+            }
+            if result.unparked < wake_count {
+                if !ThreadData::claim(current) {
+                    current = next;
+                    continue;
                 }
-
+                from_bucket.remove(current);
                 unpark_list_tail.as_ref().set(current);
                 unpark_list_tail = NonNull::from(&(*current).next);
-
-                count -= 1;
-                if count == 0 {
-                    break;
-                }
+                result.unparked += 1;
             } else {
-                previous = current;
+                from_bucket.remove(current);
+                (*current).addr.store(to, Relaxed);
+                to_bucket.push(&*current);
+                result.requeued += 1;
             }
             current = next;
         }
     }
-    drop(bucket);
+    if from != to {
+        waiter_count::requeue(from, to, result.requeued);
+    }
+    //SAFETY: the buckets are locked, so their queues are valid
+    let has_more =
+        unsafe { has_waiters(from_bucket.first_for(from, ADDRESS_TAG), from, ADDRESS_TAG) };
+    let wake = WakeOnUnwind::list(&unpark_list, unpark_list_tail);
+    let token = callback(result, has_more);
+    core::mem::forget(wake);
+    drop(buckets);
+    #[cfg(all(feature = "instrument", not(loom)))]
+    instrument::unpark(from, UnparkKind::Requeue, result.unparked);
 
     let mut current = unpark_list.get();
     if current.is_null() {
-        return;
+        return result;
     }
     loop {
         /*SAFETY:
@@ -404,20 +2698,19 @@ pub(crate) fn unpark_some(addr: *const (), mut count: usize) {
          */
         unsafe {
             let next = (*current).next.get();
-            // since ThreadData lives until the thread is
-            // woken and threads sleep before `unpark` is
-            // called, `parker` is alive.
-            ParkerT::unpark(addr_of!((*current).parker));
+            (*current).token.set(token);
+            ThreadData::unpark(current);
 
             // `ThreadData` is repr(C) and `next` is the first element, so
-            // (`current` as *const Cell<_>) gives the address of `current->next`.
-            if ptr::eq(current as *const Cell<_>, unpark_list_tail.as_ptr()) {
+            // (`current` as *const Link) gives the address of `current->next`.
+            if ptr::eq(current as *const Link, unpark_list_tail.as_ptr()) {
                 break;
             }
             // now *current may be destroyed, but it's no longer accessed.
             current = next;
         };
     }
+    result
 }
 
 // Alignment values taken from crossbeam(https://crates.io/crates/crossbeam/0.8.2)
@@ -440,10 +2733,13 @@ pub(crate) fn unpark_some(addr: *const (), mut count: usize) {
 // - https://github.com/golang/go/blob/3dd58676054223962cd915bb0934d1f9f489d4d2/src/internal/cpu/cpu_ppc64x.go#L9
 // - https://github.com/torvalds/linux/blob/3516bd729358a2a9b090c1905bd2a3fa926e24c6/arch/powerpc/include/asm/cache.h#L26
 #[cfg_attr(
-    any(
-        target_arch = "x86_64",
-        target_arch = "aarch64",
-        target_arch = "powerpc64",
+    all(
+        not(feature = "tiny-footprint"),
+        any(
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "powerpc64",
+        )
     ),
     repr(align(128))
 )]
@@ -457,14 +2753,17 @@ pub(crate) fn unpark_some(addr: *const (), mut count: usize) {
 // - https://github.com/torvalds/linux/blob/3516bd729358a2a9b090c1905bd2a3fa926e24c6/arch/sparc/include/asm/cache.h#L17
 // - https://github.com/torvalds/linux/blob/3516bd729358a2a9b090c1905bd2a3fa926e24c6/arch/hexagon/include/asm/cache.h#L12
 #[cfg_attr(
-    any(
-        target_arch = "arm",
-        target_arch = "mips",
-        target_arch = "mips32r6",
-        target_arch = "mips64",
-        target_arch = "mips64r6",
-        target_arch = "sparc",
-        target_arch = "hexagon",
+    all(
+        not(feature = "tiny-footprint"),
+        any(
+            target_arch = "arm",
+            target_arch = "mips",
+            target_arch = "mips32r6",
+            target_arch = "mips64",
+            target_arch = "mips64r6",
+            target_arch = "sparc",
+            target_arch = "hexagon",
+        )
     ),
     repr(align(32))
 )]
@@ -472,13 +2771,19 @@ pub(crate) fn unpark_some(addr: *const (), mut count: usize) {
 //
 // Sources:
 // - https://github.com/torvalds/linux/blob/3516bd729358a2a9b090c1905bd2a3fa926e24c6/arch/m68k/include/asm/cache.h#L9
-#[cfg_attr(target_arch = "m68k", repr(align(16)))]
+#[cfg_attr(
+    all(not(feature = "tiny-footprint"), target_arch = "m68k"),
+    repr(align(16))
+)]
 // s390x has 256-byte cache line size.
 //
 // Sources:
 // - https://github.com/golang/go/blob/3dd58676054223962cd915bb0934d1f9f489d4d2/src/internal/cpu/cpu_s390x.go#L7
 // - https://github.com/torvalds/linux/blob/3516bd729358a2a9b090c1905bd2a3fa926e24c6/arch/s390/include/asm/cache.h#L13
-#[cfg_attr(target_arch = "s390x", repr(align(256)))]
+#[cfg_attr(
+    all(not(feature = "tiny-footprint"), target_arch = "s390x"),
+    repr(align(256))
+)]
 // x86, wasm, riscv, and sparc64 have 64-byte cache line size.
 //
 // Sources:
@@ -488,8 +2793,11 @@ pub(crate) fn unpark_some(addr: *const (), mut count: usize) {
 // - https://github.com/torvalds/linux/blob/3516bd729358a2a9b090c1905bd2a3fa926e24c6/arch/sparc/include/asm/cache.h#L19
 //
 // All others are assumed to have 64-byte cache line size.
+//
+// `tiny-footprint` drops the padding altogether.
 #[cfg_attr(
     not(any(
+        feature = "tiny-footprint",
         target_arch = "x86_64",
         target_arch = "aarch64",
         target_arch = "powerpc64",
@@ -506,8 +2814,377 @@ pub(crate) fn unpark_some(addr: *const (), mut count: usize) {
     repr(align(64))
 )]
 struct Bucket {
-    first: Cell<*const ThreadData>,
-    last: Cell<*const ThreadData>,
+    first: Link,
+    last: Link,
+    summary: Summary,
+    #[cfg(debug_assertions)]
+    next_ticket: Cell<usize>,
+    /// xorshift32 state, seeded on first use.
+    rng: Cell<u32>,
+    /// When `unpark_one_fair` should be fair next, set on first use.
+    #[cfg(all(
+        feature = "std",
+        not(any(loom, feature = "freertos", feature = "zephyr"))
+    ))]
+    fair_deadline: Cell<Option<std::time::Instant>>,
+}
+
+impl Bucket {
+    #[cfg(not(loom))]
+    const fn new() -> Self {
+        Self {
+            first: Link::null(),
+            last: Link::null(),
+            summary: Summary::new(),
+            #[cfg(debug_assertions)]
+            next_ticket: Cell::new(0),
+            rng: Cell::new(0),
+            #[cfg(all(
+                feature = "std",
+                not(any(loom, feature = "freertos", feature = "zephyr"))
+            ))]
+            fair_deadline: Cell::new(None),
+        }
+    }
+
+    #[cfg(loom)]
+    fn new() -> Self {
+        Self {
+            first: Link::null(),
+            last: Link::null(),
+            summary: Summary::new(),
+            #[cfg(debug_assertions)]
+            next_ticket: Cell::new(0),
+            rng: Cell::new(0),
+            #[cfg(all(
+                feature = "std",
+                not(any(loom, feature = "freertos", feature = "zephyr"))
+            ))]
+            fair_deadline: Cell::new(None),
+        }
+    }
+
+    /// Appends `thread_data` to the queue.
+    ///
+    /// # Safety
+    ///
+    /// - `thread_data` must not be queued.
+    #[inline(always)]
+    unsafe fn push(&self, thread_data: &ThreadData) {
+        self.summary
+            .add(thread_data.addr.load(Relaxed), thread_data.tag.get());
+        thread_data.next.set(ptr::null());
+        thread_data.prev.set(self.last.get());
+        thread_data.queued.set(true);
+        #[cfg(debug_assertions)]
+        {
+            thread_data.ticket.set(self.next_ticket.get());
+            self.next_ticket.set(self.next_ticket.get().wrapping_add(1));
+        }
+
+        if self.first.get().is_null() {
+            self.first.set(thread_data);
+        } else {
+            //SAFETY: last isn't null if head isn't null
+            unsafe {
+                #[cfg(not(loom))]
+                debug_assert!(!self.last.get().is_null());
+                #[cfg(loom)]
+                assert!(!self.last.get().is_null());
+                &*self.last.get()
+            }
+            .next
+            .set(thread_data);
+        }
+        self.last.set(thread_data);
+    }
+
+    /// Unlinks `thread_data` from the queue, in constant time.
+    ///
+    /// # Safety
+    ///
+    /// - `thread_data` must be queued in `self`.
+    #[inline(always)]
+    unsafe fn remove(&self, thread_data: *const ThreadData) {
+        self.summary
+            .remove((*thread_data).addr.load(Relaxed), (*thread_data).tag.get());
+        let (prev, next) = ((*thread_data).prev.get(), (*thread_data).next.get());
+        if prev.is_null() {
+            self.first.set(next);
+        } else {
+            (*prev).next.set(next);
+        }
+        if next.is_null() {
+            self.last.set(prev);
+        } else {
+            (*next).prev.set(prev);
+        }
+        (*thread_data).queued.set(false);
+    }
+
+    /// The first waiter of the queue, or null if the summary shows that
+    /// none of the waiters are parked on `addr` with `tag`, so that looking
+    /// for the waiters of a quiet address doesn't walk the whole queue.
+    #[inline(always)]
+    fn first_for(&self, addr: usize, tag: u64) -> *const ThreadData {
+        if !self.summary.may_contain(addr, tag) {
+            return ptr::null();
+        }
+        self.first.get()
+    }
+
+    /// Forgets every waiter, once they've been moved elsewhere.
+    #[cfg(all(feature = "growable-table", not(loom)))]
+    fn clear(&self) {
+        self.first.set(ptr::null());
+        self.last.set(ptr::null());
+        self.summary.clear();
+    }
+}
+
+/* Every bucket keeps a summary of which addresses its waiters are parked
+ * on: a count per slot of a second hash of the address and tag, which is
+ * independent of the one that picks the bucket. A slot with no waiters
+ * rules out every address hashed to it, so unparking an address nobody
+ * waits on only scans the queue if another waiter shares its slot.
+ *
+ * `tiny-footprint` leaves it out. With a handful of threads the queues are
+ * short enough to scan, and the counts would triple the size of a bucket.
+ */
+#[cfg(not(feature = "tiny-footprint"))]
+const SUMMARY_BITS: u32 = 3;
+#[cfg(not(feature = "tiny-footprint"))]
+const SUMMARY_SLOTS: usize = 1 << SUMMARY_BITS;
+
+/// How many queued waiters hash to each summary slot.
+#[cfg(not(feature = "tiny-footprint"))]
+struct Summary([Cell<u32>; SUMMARY_SLOTS]);
+
+#[cfg(not(feature = "tiny-footprint"))]
+impl Summary {
+    #[cfg(not(loom))]
+    const fn new() -> Self {
+        Self([const { Cell::new(0) }; SUMMARY_SLOTS])
+    }
+
+    #[cfg(loom)]
+    fn new() -> Self {
+        Self(core::array::from_fn(|_| Cell::new(0)))
+    }
+
+    #[inline(always)]
+    fn may_contain(&self, addr: usize, tag: u64) -> bool {
+        self.0[summary_slot(addr, tag)].get() != 0
+    }
+
+    #[inline(always)]
+    fn add(&self, addr: usize, tag: u64) {
+        let slot = &self.0[summary_slot(addr, tag)];
+        slot.set(slot.get() + 1);
+    }
+
+    #[inline(always)]
+    fn remove(&self, addr: usize, tag: u64) {
+        let slot = &self.0[summary_slot(addr, tag)];
+        slot.set(slot.get() - 1);
+    }
+
+    #[cfg(all(feature = "growable-table", not(loom)))]
+    fn clear(&self) {
+        for slot in &self.0 {
+            slot.set(0);
+        }
+    }
+}
+
+/// Takes no space and rules nothing out.
+#[cfg(feature = "tiny-footprint")]
+struct Summary;
+
+#[cfg(feature = "tiny-footprint")]
+impl Summary {
+    const fn new() -> Self {
+        Self
+    }
+
+    #[inline(always)]
+    fn may_contain(&self, _: usize, _: u64) -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn add(&self, _: usize, _: u64) {}
+
+    #[inline(always)]
+    fn remove(&self, _: usize, _: u64) {}
+
+    #[cfg(all(feature = "growable-table", not(loom)))]
+    fn clear(&self) {}
+}
+
+/// The bucket hash keeps the top bits of one multiplication, so another
+/// multiplication alone would be correlated with it. Mixing the high bits
+/// down between two rounds makes the slot depend on all of them instead.
+#[cfg(not(feature = "tiny-footprint"))]
+#[inline(always)]
+fn summary_slot(addr: usize, tag: u64) -> usize {
+    const MUL: u64 = 0xD6E8_FEB8_6659_FD93;
+    let mut mixed = addr as u64 ^ tag;
+    mixed = (mixed ^ (mixed >> 32)).wrapping_mul(MUL);
+    mixed = (mixed ^ (mixed >> 32)).wrapping_mul(MUL);
+    mixed ^= mixed >> 32;
+    (mixed >> (u64::BITS - SUMMARY_BITS)) as usize
+}
+
+#[cfg(all(test, not(loom), not(feature = "tiny-footprint")))]
+mod summary_tests {
+    use super::*;
+
+    /// Checks that the addresses (`stride` apart) of one bucket of a
+    /// table with `bits` bits aren't all summarised in the same few slots.
+    fn check(bits: usize, stride: usize) {
+        const PER_SLOT: usize = 16;
+        let mut load = [0; SUMMARY_SLOTS];
+        let addrs = (0x10_000..).step_by(stride);
+        for addr in addrs
+            .filter(|&addr| hash(addr, bits) == 0)
+            .take(SUMMARY_SLOTS * PER_SLOT)
+        {
+            load[summary_slot(addr, ADDRESS_TAG)] += 1;
+        }
+        assert!(
+            load.iter().all(|&n| n != 0 && n <= PER_SLOT * 2),
+            "uneven slots with {bits} bits and stride {stride}: {load:?}"
+        );
+    }
+
+    #[test]
+    fn spreads_a_bucket() {
+        // every `BUCKET_BITS`, and the sizes `growable-table` grows to
+        for bits in 1..=10 {
+            for stride in [1, 4, 8, 16, 64] {
+                check(bits, stride);
+            }
+        }
+    }
+}
+
+/* What `tiny-footprint` documents a bucket to cost without `std`, in
+ * release builds: a word for the lock (or nothing), two pointers and the
+ * random generator state.
+ */
+#[cfg(all(
+    feature = "tiny-footprint",
+    not(any(loom, feature = "std", feature = "hardening", debug_assertions))
+))]
+const _: () = assert!(
+    core::mem::size_of::<Mutex<Bucket>>() <= core::mem::size_of::<(usize, [*const (); 2], u32)>()
+);
+
+#[cfg(all(test, not(loom)))]
+mod size_tests {
+    use super::*;
+    use core::mem::{align_of, size_of};
+
+    #[test]
+    fn summary() {
+        #[cfg(not(feature = "tiny-footprint"))]
+        assert_eq!(size_of::<Summary>(), SUMMARY_SLOTS * size_of::<u32>());
+        #[cfg(feature = "tiny-footprint")]
+        assert_eq!(size_of::<Summary>(), 0);
+    }
+
+    #[test]
+    fn waiter_count() {
+        #[cfg(not(feature = "tiny-footprint"))]
+        assert_eq!(size_of::<waiter_count::Count>(), size_of::<usize>());
+        #[cfg(feature = "tiny-footprint")]
+        assert_eq!(size_of::<waiter_count::Count>(), size_of::<u16>());
+    }
+
+    #[test]
+    fn bucket() {
+        let mut fields = 2 * size_of::<Link>() + size_of::<Summary>() + size_of::<u32>();
+        #[cfg(debug_assertions)]
+        {
+            fields += size_of::<usize>();
+        }
+        #[cfg(all(feature = "std", not(any(feature = "freertos", feature = "zephyr"))))]
+        {
+            fields += size_of::<Option<std::time::Instant>>();
+        }
+        // without padding the fields only round up to the biggest alignment
+        #[cfg(feature = "tiny-footprint")]
+        assert!(align_of::<Bucket>() <= 8);
+        assert_eq!(
+            size_of::<Bucket>(),
+            fields.next_multiple_of(align_of::<Bucket>())
+        );
+    }
+}
+
+impl Bucket {
+    /// Decides if an `unpark_one_fair` should be fair. Like in `parking_lot`,
+    /// that's after a random timeout of 0.5ms on average, so that unfair
+    /// handoffs, which are faster, are the norm, but nobody starves for long.
+    /// Without a clock, every 64th call is fair on average instead.
+    fn be_fair(&self) -> bool {
+        #[cfg(all(
+            feature = "std",
+            not(any(loom, feature = "freertos", feature = "zephyr"))
+        ))]
+        {
+            let now = std::time::Instant::now();
+            match self.fair_deadline.get() {
+                Some(deadline) if now < deadline => false,
+                deadline => {
+                    let timeout = u64::from(self.random() % 1_000_000);
+                    self.fair_deadline
+                        .set(Some(now + std::time::Duration::from_nanos(timeout)));
+                    // the first call only starts the timeout
+                    deadline.is_some()
+                }
+            }
+        }
+        #[cfg(not(all(
+            feature = "std",
+            not(any(loom, feature = "freertos", feature = "zephyr"))
+        )))]
+        {
+            self.random() & 63 == 0
+        }
+    }
+
+    fn random(&self) -> u32 {
+        let mut x = self.rng.get();
+        if x == 0 {
+            x = Self::seed(self);
+        }
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng.set(x);
+        x
+    }
+
+    /// Never returns 0, which xorshift can't leave.
+    #[cold]
+    fn seed(&self) -> u32 {
+        #[cfg(any(loom, feature = "std"))]
+        {
+            use core::hash::BuildHasher;
+            let seed =
+                std::collections::hash_map::RandomState::new().hash_one(ptr::addr_of!(*self));
+            (seed as u32) | 1
+        }
+        // without `std`, only addresses (and ASLR, if any) vary
+        #[cfg(not(any(loom, feature = "std")))]
+        {
+            let local = 0u8;
+            let seed = ptr::addr_of!(*self).addr() ^ ptr::addr_of!(local).addr().rotate_left(16);
+            (seed as u32).wrapping_mul(0x9E3779B9) | 1
+        }
+    }
 }
 
 unsafe impl Send for Bucket {}
@@ -0,0 +1,40 @@
+//! Blocking signals while parked, for `signal-mask`.
+
+use core::mem::MaybeUninit;
+use core::ptr;
+use libc::{c_int, sigset_t};
+
+/// Restores the old signal mask of the thread when dropped.
+pub(crate) struct Blocked {
+    old: sigset_t,
+}
+
+/// Blocks `signals` for the current thread until the result is dropped.
+///
+/// # Panics
+///
+/// - if a signal number is invalid.
+pub(crate) fn block(signals: &[c_int]) -> Blocked {
+    unsafe {
+        let mut set = MaybeUninit::<sigset_t>::uninit();
+        libc::sigemptyset(set.as_mut_ptr());
+        for &signal in signals {
+            assert!(
+                libc::sigaddset(set.as_mut_ptr(), signal) == 0,
+                "invalid signal number {signal}"
+            );
+        }
+        let mut old = MaybeUninit::<sigset_t>::uninit();
+        // only fails with an invalid `how`
+        libc::pthread_sigmask(libc::SIG_BLOCK, set.as_ptr(), old.as_mut_ptr());
+        Blocked {
+            old: old.assume_init(),
+        }
+    }
+}
+
+impl Drop for Blocked {
+    fn drop(&mut self) {
+        unsafe { libc::pthread_sigmask(libc::SIG_SETMASK, &self.old, ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,110 @@
+//! Interrupt masking for single-core bare-metal systems.
+//!
+//! On these systems the only concurrency comes from interrupt handlers, so
+//! masking interrupts is enough to get exclusive access to a bucket, and it's
+//! the only way to make the lot usable from interrupt handlers.
+
+use core::cell::UnsafeCell;
+use core::ops::Deref;
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+#[cfg(not(any(
+    all(target_arch = "arm", target_feature = "mclass"),
+    target_arch = "riscv32",
+    target_arch = "riscv64",
+)))]
+compile_error!("`single-core` is only supported on Arm Cortex-M and RISC-V");
+
+/// Masks interrupts and returns whether they were enabled before.
+#[inline(always)]
+pub(crate) fn disable() -> bool {
+    let enabled;
+    #[cfg(target_arch = "arm")]
+    unsafe {
+        let primask: u32;
+        core::arch::asm!("mrs {}, PRIMASK", out(reg) primask, options(nomem, nostack, preserves_flags));
+        core::arch::asm!("cpsid i", options(nostack, preserves_flags));
+        enabled = primask & 1 == 0;
+    }
+    #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+    unsafe {
+        // clears mstatus.MIE
+        let mstatus: usize;
+        core::arch::asm!("csrrci {}, mstatus, 8", out(reg) mstatus, options(nostack));
+        enabled = mstatus & 8 != 0;
+    }
+    compiler_fence(SeqCst);
+    enabled
+}
+
+/// Unmasks interrupts if `enabled` (the result of the matching [`disable`]).
+#[inline(always)]
+pub(crate) fn restore(enabled: bool) {
+    compiler_fence(SeqCst);
+    if enabled {
+        #[cfg(target_arch = "arm")]
+        unsafe {
+            core::arch::asm!("cpsie i", options(nostack, preserves_flags));
+        }
+        #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+        unsafe {
+            core::arch::asm!("csrsi mstatus, 8", options(nostack));
+        }
+    }
+}
+
+/// Sleeps until an interrupt is pending. Pending interrupts wake
+/// the core up even when they're masked.
+#[inline(always)]
+pub(crate) fn wait_for_interrupt() {
+    unsafe { core::arch::asm!("wfi", options(nomem, nostack, preserves_flags)) };
+}
+
+/// A bucket lock which masks interrupts while it's held.
+pub(crate) struct Mutex<T> {
+    data: UnsafeCell<T>,
+}
+
+// There is only one core and the data is only accessed with interrupts masked.
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub(crate) const fn new(data: T) -> Self {
+        Self {
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        let enabled = disable();
+        MutexGuard {
+            mutex: self,
+            enabled,
+        }
+    }
+
+    /// Masking interrupts never waits for another holder.
+    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        Some(self.lock())
+    }
+}
+
+pub(crate) struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+    enabled: bool,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        //SAFETY: interrupts are masked while the guard is alive
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        restore(self.enabled);
+    }
+}
@@ -0,0 +1,67 @@
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ops::Deref;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use crate::real::atomic::AtomicBool;
+
+/// A minimal spinlock, used for the buckets when `std` isn't available.
+///
+/// The critical sections of the parking lot are short (as long as `expected`
+/// returns quickly), so spinning is acceptable there.
+pub(crate) struct Mutex<T> {
+    locked: AtomicBool,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub(crate) const fn new(data: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Acquire, Relaxed)
+            .is_err()
+        {
+            // only read while contended to avoid bouncing the cache line
+            while self.locked.load(Relaxed) {
+                spin_loop();
+            }
+        }
+        MutexGuard { mutex: self }
+    }
+
+    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Acquire, Relaxed)
+            .is_ok()
+            // dropping a guard unlocks, so it's only built on success
+            .then(|| MutexGuard { mutex: self })
+    }
+}
+
+pub(crate) struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        //SAFETY: the lock is held while the guard is alive
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.locked.store(false, Release);
+    }
+}
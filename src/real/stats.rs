@@ -0,0 +1,95 @@
+//! Counts parks and unparks, for `stats`.
+
+use crate::real::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// A snapshot of the counters, returned by [`stats`](crate::stats).
+///
+/// Every counter only grows (wrapping around on overflow), and they're
+/// read one at a time, so they may not add up exactly while other threads
+/// park and unpark. Subtracting an earlier snapshot gives the activity
+/// between the two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct Stats {
+    /// Calls which called `expected` to park a thread (or task), including
+    /// the ones counted in `invalid_parks`.
+    pub parks: usize,
+    /// Parks which didn't sleep, because `expected` returned false.
+    pub invalid_parks: usize,
+    /// Calls to [`park_then_validate`](crate::park_then_validate) which didn't
+    /// sleep, because `validate` returned false. They're counted in `parks`,
+    /// but not in `invalid_parks`.
+    pub failed_validations: usize,
+    /// Calls to [`unpark_one`](crate::unpark_one) (and its variants) which woke a thread.
+    pub unpark_one_hits: usize,
+    /// Calls to [`unpark_one`](crate::unpark_one) (and its variants) which found nothing to wake.
+    pub unpark_one_misses: usize,
+    /// Threads woken by [`unpark_all`](crate::unpark_all) and
+    /// [`unpark_all_release`](crate::unpark_all_release).
+    pub unpark_all_woken: usize,
+    /// Threads woken by [`unpark_some`](crate::unpark_some) and
+    /// [`unpark_some_release`](crate::unpark_some_release).
+    pub unpark_some_woken: usize,
+    /// Bucket lock acquisitions, by every function of the crate.
+    pub bucket_locks: usize,
+}
+
+static PARKS: AtomicUsize = AtomicUsize::new(0);
+static INVALID_PARKS: AtomicUsize = AtomicUsize::new(0);
+static FAILED_VALIDATIONS: AtomicUsize = AtomicUsize::new(0);
+static UNPARK_ONE_HITS: AtomicUsize = AtomicUsize::new(0);
+static UNPARK_ONE_MISSES: AtomicUsize = AtomicUsize::new(0);
+static UNPARK_ALL_WOKEN: AtomicUsize = AtomicUsize::new(0);
+static UNPARK_SOME_WOKEN: AtomicUsize = AtomicUsize::new(0);
+static BUCKET_LOCKS: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn get() -> Stats {
+    Stats {
+        parks: PARKS.load(Relaxed),
+        invalid_parks: INVALID_PARKS.load(Relaxed),
+        failed_validations: FAILED_VALIDATIONS.load(Relaxed),
+        unpark_one_hits: UNPARK_ONE_HITS.load(Relaxed),
+        unpark_one_misses: UNPARK_ONE_MISSES.load(Relaxed),
+        unpark_all_woken: UNPARK_ALL_WOKEN.load(Relaxed),
+        unpark_some_woken: UNPARK_SOME_WOKEN.load(Relaxed),
+        bucket_locks: BUCKET_LOCKS.load(Relaxed),
+    }
+}
+
+#[inline(always)]
+pub(crate) fn park(valid: bool) {
+    PARKS.fetch_add(1, Relaxed);
+    if !valid {
+        INVALID_PARKS.fetch_add(1, Relaxed);
+    }
+}
+
+#[inline(always)]
+pub(crate) fn failed_validation() {
+    FAILED_VALIDATIONS.fetch_add(1, Relaxed);
+}
+
+#[inline(always)]
+pub(crate) fn unpark_one(unparked: usize) {
+    if unparked != 0 {
+        UNPARK_ONE_HITS.fetch_add(1, Relaxed);
+    } else {
+        UNPARK_ONE_MISSES.fetch_add(1, Relaxed);
+    }
+}
+
+#[inline(always)]
+pub(crate) fn unpark_all(unparked: usize) {
+    UNPARK_ALL_WOKEN.fetch_add(unparked, Relaxed);
+}
+
+#[inline(always)]
+pub(crate) fn unpark_some(unparked: usize) {
+    UNPARK_SOME_WOKEN.fetch_add(unparked, Relaxed);
+}
+
+#[inline(always)]
+pub(crate) fn bucket_lock() {
+    BUCKET_LOCKS.fetch_add(1, Relaxed);
+}
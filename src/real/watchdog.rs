@@ -0,0 +1,103 @@
+//! Reports bucket locks held for too long by `park`, for `watchdog`.
+
+use core::mem::{self, ManuallyDrop};
+use core::ops::Deref;
+use core::panic::Location;
+use core::ptr;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::atomic::{AtomicPtr, AtomicU64};
+use std::time::{Duration, Instant};
+
+/// A bucket lock which was held for longer than the threshold
+/// set with [`set_watchdog`](crate::set_watchdog).
+#[derive(Debug, Clone, Copy)]
+pub struct LongHold {
+    /// Where [`park`](crate::park()) was called.
+    pub location: &'static Location<'static>,
+    /// How long the bucket lock was held, mostly by `expected`.
+    pub held_for: Duration,
+}
+
+impl core::fmt::Display for LongHold {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "`park` at {} held a bucket lock for {:?}, which blocks unrelated addresses",
+            self.location, self.held_for
+        )
+    }
+}
+
+static REPORT: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+static THRESHOLD_NANOS: AtomicU64 = AtomicU64::new(u64::MAX);
+
+pub(crate) fn set(threshold: Duration, report: fn(&LongHold)) {
+    let nanos = u64::try_from(threshold.as_nanos()).unwrap_or(u64::MAX);
+    THRESHOLD_NANOS.store(nanos, Relaxed);
+    REPORT.store(report as *mut (), Release);
+}
+
+pub(crate) fn clear() {
+    REPORT.store(ptr::null_mut(), Release);
+}
+
+struct Start {
+    at: Instant,
+    location: &'static Location<'static>,
+    report: fn(&LongHold),
+}
+
+/// Wraps a bucket guard and reports if it was held for too long.
+pub(crate) struct Watched<G> {
+    guard: ManuallyDrop<G>,
+    // `None` if the watchdog isn't set
+    start: Option<Start>,
+}
+
+impl<G> Watched<G> {
+    #[inline(always)]
+    pub(crate) fn new(guard: G, location: &'static Location<'static>) -> Self {
+        let report = REPORT.load(Acquire);
+        let start = if report.is_null() {
+            None
+        } else {
+            //SAFETY: only `fn(&LongHold)`s are stored in `REPORT`
+            let report = unsafe { mem::transmute::<*mut (), fn(&LongHold)>(report) };
+            Some(Start {
+                at: Instant::now(),
+                location,
+                report,
+            })
+        };
+        Self {
+            guard: ManuallyDrop::new(guard),
+            start,
+        }
+    }
+}
+
+impl<G> Deref for Watched<G> {
+    type Target = G;
+
+    #[inline(always)]
+    fn deref(&self) -> &G {
+        &self.guard
+    }
+}
+
+impl<G> Drop for Watched<G> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        //SAFETY: never used again, unlocks before `report` runs
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        if let Some(start) = &self.start {
+            let held_for = start.at.elapsed();
+            if held_for.as_nanos() > u128::from(THRESHOLD_NANOS.load(Relaxed)) {
+                (start.report)(&LongHold {
+                    location: start.location,
+                    held_for,
+                });
+            }
+        }
+    }
+}
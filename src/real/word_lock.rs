@@ -0,0 +1,397 @@
+use core::cell::UnsafeCell;
+use core::ops::Deref;
+use core::ptr;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+#[cfg(not(loom))]
+use crate::real::atomic::{fence, AtomicUsize};
+use crate::real::loom::Cell;
+use crate::real::park::{Parker, ParkerT};
+#[cfg(loom)]
+use loom::sync::atomic::{fence, AtomicUsize};
+
+const LOCKED: usize = 1;
+/// A thread is fixing up the queue to wake its oldest waiter.
+const QUEUE_LOCKED: usize = 2;
+const QUEUE_MASK: usize = !(LOCKED | QUEUE_LOCKED);
+
+/// The number of spin rounds before queueing, each twice as long as the
+/// last, read from `SPARKING_LOT_CORE_SPIN` at compile time. Defaults to 6.
+/// Bucket critical sections are short, so under moderate contention the
+/// lock is usually released before a thread would even be asleep.
+#[cfg(not(loom))]
+const SPIN_LIMIT: u32 = match option_env!("SPARKING_LOT_CORE_SPIN") {
+    Some(s) => parse(s),
+    None => 6,
+};
+// every spin is a branch loom explores
+#[cfg(loom)]
+const SPIN_LIMIT: u32 = 1;
+
+#[cfg(not(loom))]
+const fn parse(s: &str) -> u32 {
+    let bytes = s.as_bytes();
+    assert!(
+        !bytes.is_empty(),
+        "SPARKING_LOT_CORE_SPIN must be an integer from 0 to 16"
+    );
+    let mut i = 0;
+    let mut n = 0u32;
+    while i < bytes.len() {
+        let digit = bytes[i];
+        assert!(
+            digit.is_ascii_digit() && n <= 16,
+            "SPARKING_LOT_CORE_SPIN must be an integer from 0 to 16"
+        );
+        n = n * 10 + (digit - b'0') as u32;
+        i += 1;
+    }
+    // 16 rounds already spin 2^16 - 1 times in total
+    assert!(
+        n <= 16,
+        "SPARKING_LOT_CORE_SPIN must be an integer from 0 to 16"
+    );
+    n
+}
+
+/// A word-sized lock, used for the buckets with `std`, in the style of
+/// WebKit's `WordLock`.
+///
+/// The state is the locked bit, a bit for the queue, and a pointer to
+/// the most recently queued waiter. Contended threads spin briefly while
+/// nobody is queued, and then push a node from their stack to the front
+/// of the queue and sleep on its parker. Unlocks wake the oldest waiter,
+/// which then competes for the lock again, so there is no handoff and
+/// no poisoning.
+pub(crate) struct Mutex<T> {
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+/* The queue is a list from the newest waiter (the head in `state`) to the
+ * oldest. New waiters only set `next` before pushing themselves, so pushes
+ * don't need the queue lock. Whoever holds the queue lock walks the new
+ * nodes to fill in `prev`, until it finds a node which has `queue_tail`
+ * set, and caches the tail in the head, so the oldest waiter can be taken
+ * off the end in O(1).
+ */
+#[repr(align(4))]
+struct Waiter {
+    parker: Parker,
+    queue_tail: Cell<*const Waiter>,
+    prev: Cell<*const Waiter>,
+    next: Cell<*const Waiter>,
+}
+
+const _: () = assert!(core::mem::align_of::<Waiter>() > !QUEUE_MASK);
+
+impl<T> Mutex<T> {
+    #[cfg(not(loom))]
+    pub(crate) const fn new(data: T) -> Self {
+        Self {
+            state: AtomicUsize::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    #[cfg(loom)]
+    pub(crate) fn new(data: T) -> Self {
+        Self {
+            state: AtomicUsize::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        if self
+            .state
+            .compare_exchange_weak(0, LOCKED, Acquire, Relaxed)
+            .is_err()
+        {
+            self.lock_slow();
+        }
+        MutexGuard { mutex: self }
+    }
+
+    /// Locks the mutex if it isn't locked, without spinning or queueing.
+    #[inline]
+    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        let mut state = self.state.load(Relaxed);
+        while state & LOCKED == 0 {
+            match self
+                .state
+                .compare_exchange_weak(state, state | LOCKED, Acquire, Relaxed)
+            {
+                Ok(_) => return Some(MutexGuard { mutex: self }),
+                Err(actual) => state = actual,
+            }
+        }
+        None
+    }
+
+    #[cold]
+    fn lock_slow(&self) {
+        let mut spins = 0;
+        let mut state = self.state.load(Relaxed);
+        loop {
+            // barging is allowed, even past queued threads
+            if state & LOCKED == 0 {
+                match self
+                    .state
+                    .compare_exchange_weak(state, state | LOCKED, Acquire, Relaxed)
+                {
+                    Ok(_) => return,
+                    Err(actual) => state = actual,
+                }
+                continue;
+            }
+            if state & QUEUE_MASK == 0 && spins < SPIN_LIMIT {
+                spin(spins);
+                spins += 1;
+                state = self.state.load(Relaxed);
+                continue;
+            }
+            state = self.wait(state);
+            spins = 0;
+        }
+    }
+
+    /// Queues the thread and sleeps until it's woken by an unlock, unless
+    /// `state` changes first. Returns the new state either way.
+    fn wait(&self, state: usize) -> usize {
+        let waiter = Waiter {
+            parker: Parker::new(),
+            queue_tail: Cell::new(ptr::null()),
+            prev: Cell::new(ptr::null()),
+            next: Cell::new(ptr::null()),
+        };
+        waiter.parker.prepare_park();
+        let head = (state & QUEUE_MASK) as *const Waiter;
+        if head.is_null() {
+            // the only waiter is its own tail
+            waiter.queue_tail.set(&waiter);
+        } else {
+            waiter.next.set(head);
+        }
+        let queued = (state & !QUEUE_MASK) | ptr::addr_of!(waiter) as usize;
+        if let Err(actual) = self
+            .state
+            .compare_exchange_weak(state, queued, Release, Relaxed)
+        {
+            return actual;
+        }
+        /* `waiter` is reachable by unlockers until it's unparked, so unwinding
+         * out of here would leave them a dangling pointer.
+         */
+        // not `then_some`, which would build the guard and drop it right away
+        let abort = if Parker::CAN_PANIC {
+            Some(AbortOnDrop)
+        } else {
+            None
+        };
+        //SAFETY: the parker belongs to this thread
+        unsafe { waiter.parker.park() };
+        core::mem::forget(abort);
+        self.state.load(Relaxed)
+    }
+
+    /// # Safety
+    ///
+    /// The lock must be held by the caller.
+    #[inline]
+    unsafe fn unlock(&self) {
+        let state = self.state.fetch_sub(LOCKED, Release);
+        if state & QUEUE_LOCKED == 0 && state & QUEUE_MASK != 0 {
+            self.unlock_slow();
+        }
+    }
+
+    #[cold]
+    fn unlock_slow(&self) {
+        let mut state = self.state.load(Relaxed);
+        loop {
+            // someone else is already waking a thread, or there's nobody to wake
+            if state & QUEUE_LOCKED != 0 || state & QUEUE_MASK == 0 {
+                return;
+            }
+            match self
+                .state
+                .compare_exchange_weak(state, state | QUEUE_LOCKED, Acquire, Relaxed)
+            {
+                Ok(_) => break,
+                Err(actual) => state = actual,
+            }
+        }
+
+        'queue: loop {
+            let head = (state & QUEUE_MASK) as *const Waiter;
+            //SAFETY: queued waiters stay alive until they're unparked, which
+            // only the holder of the queue lock does
+            let tail = unsafe { link_queue(head) };
+
+            // the next unlock will wake someone
+            if state & LOCKED != 0 {
+                match self.state.compare_exchange_weak(
+                    state,
+                    state & !QUEUE_LOCKED,
+                    Release,
+                    Relaxed,
+                ) {
+                    Ok(_) => return,
+                    Err(actual) => state = actual,
+                }
+                // pairs with the `Release` of the new waiters
+                fence(Acquire);
+                continue;
+            }
+
+            //SAFETY: see above
+            let new_tail = unsafe { (*tail).prev.get() };
+            if new_tail.is_null() {
+                // `tail` is the only waiter, so the queue becomes empty
+                loop {
+                    match self
+                        .state
+                        .compare_exchange_weak(state, state & LOCKED, Release, Relaxed)
+                    {
+                        Ok(_) => break,
+                        Err(actual) => state = actual,
+                    }
+                    if state & QUEUE_MASK != (head as usize) {
+                        // a new waiter was pushed, so `tail` isn't alone anymore
+                        fence(Acquire);
+                        continue 'queue;
+                    }
+                }
+            } else {
+                //SAFETY: see above
+                unsafe { (*head).queue_tail.set(new_tail) };
+                self.state.fetch_and(!QUEUE_LOCKED, Release);
+            }
+
+            //SAFETY: `tail` is off the queue, so only this thread can wake it
+            unsafe { Parker::unpark(ptr::addr_of!((*tail).parker)) };
+            return;
+        }
+    }
+}
+
+/// Fills in `prev` for the waiters pushed since the queue was last
+/// linked, caches the tail in `head` and returns it.
+///
+/// # Safety
+///
+/// The caller must hold the queue lock and `head` must be the queue.
+unsafe fn link_queue(head: *const Waiter) -> *const Waiter {
+    let mut current = head;
+    loop {
+        let tail = (*current).queue_tail.get();
+        if !tail.is_null() {
+            (*head).queue_tail.set(tail);
+            return tail;
+        }
+        let next = (*current).next.get();
+        (*next).prev.set(current);
+        current = next;
+    }
+}
+
+#[inline]
+fn spin(spins: u32) {
+    #[cfg(not(loom))]
+    for _ in 0..1 << spins {
+        core::hint::spin_loop();
+    }
+    #[cfg(loom)]
+    {
+        let _ = spins;
+        loom::hint::spin_loop();
+    }
+}
+
+struct AbortOnDrop;
+
+impl Drop for AbortOnDrop {
+    #[cold]
+    fn drop(&mut self) {
+        std::process::abort();
+    }
+}
+
+pub(crate) struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        //SAFETY: the lock is held while the guard is alive
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        //SAFETY: the guard holds the lock
+        unsafe { self.mutex.unlock() };
+    }
+}
+
+#[cfg(all(test, loom))]
+mod tests {
+    use super::Mutex;
+    use loom::cell::Cell;
+    use loom::thread;
+    use std::sync::Arc;
+
+    #[test]
+    fn excludes() {
+        loom::model(|| {
+            let mutex = Arc::new(Mutex::new(Cell::new(0)));
+            let h = {
+                let mutex = mutex.clone();
+                thread::spawn(move || {
+                    let count = mutex.lock();
+                    count.set(count.get() + 1);
+                })
+            };
+            {
+                let count = mutex.lock();
+                count.set(count.get() + 1);
+            }
+            h.join().unwrap();
+            assert_eq!(mutex.lock().get(), 2);
+        });
+    }
+
+    #[test]
+    fn wakes_queued() {
+        // a third thread is needed to queue, which makes the full model too big
+        let mut model = loom::model::Builder::new();
+        model.preemption_bound = Some(3);
+        model.check(|| {
+            let mutex = Arc::new(Mutex::new(Cell::new(0)));
+            let handles: Vec<_> = (0..2)
+                .map(|_| {
+                    let mutex = mutex.clone();
+                    thread::spawn(move || {
+                        let count = mutex.lock();
+                        count.set(count.get() + 1);
+                    })
+                })
+                .collect();
+            {
+                let count = mutex.lock();
+                count.set(count.get() + 1);
+            }
+            for h in handles {
+                h.join().unwrap();
+            }
+            assert_eq!(mutex.lock().get(), 3);
+        });
+    }
+}
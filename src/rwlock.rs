@@ -0,0 +1,226 @@
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use lock_api::RawRwLock as _;
+
+use crate::{park_lane, park_with_token, unpark_all_lane, unpark_one_with, DEFAULT_UNPARK_TOKEN};
+
+/// Held exclusively.
+const WRITER: usize = 1;
+/// Writers are parked on the address of the state.
+const WRITERS_PARKED: usize = 2;
+/// Readers are parked in `READERS_LANE` of the address of the state.
+const READERS_PARKED: usize = 4;
+/// The rest of the state counts the readers.
+const ONE_READER: usize = 8;
+const READERS: usize = !(ONE_READER - 1);
+
+const READERS_LANE: u8 = 1;
+/// The lock wasn't released, it was handed to the woken writer.
+const HANDED_OFF: usize = 1;
+
+/// A [`lock_api::RawRwLock`] which parks its waiters in the global lot,
+/// for [`lock_api::RwLock`]s.
+///
+/// It's a word in size and only touches the lot when threads have to wait.
+/// Writers park on the address of the lock and readers in one of its
+/// [lanes](crate::park_lane), so they're woken separately. Writers are
+/// preferred: once one is waiting, new readers wait too, so a stream of
+/// readers can't starve writers. A writer hands the lock directly to the
+/// next waiting writer, and readers are only let in once no writers wait.
+///
+/// Only available with the `lock-api` feature.
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+///
+/// type RwLock<T> = lock_api::RwLock<sparking_lot_core::RawRwLock, T>;
+///
+/// static COUNT: RwLock<usize> = RwLock::new(0);
+///
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| *COUNT.write() += 1);
+///         s.spawn(|| assert!(*COUNT.read() <= 4));
+///     }
+/// });
+/// assert_eq!(*COUNT.read(), 4);
+/// ```
+pub struct RawRwLock {
+    state: AtomicUsize,
+}
+
+impl RawRwLock {
+    #[cold]
+    fn lock_shared_slow(&self) {
+        while !self.try_lock_shared() {
+            // SAFETY: no calls to sparking_lot_core functions in closure, owned address
+            unsafe {
+                park_lane(&self.state, READERS_LANE, || {
+                    self.state
+                        .fetch_update(Relaxed, Relaxed, |state| {
+                            (state & (WRITER | WRITERS_PARKED) != 0)
+                                .then_some(state | READERS_PARKED)
+                        })
+                        .is_ok()
+                })
+            };
+        }
+    }
+
+    #[cold]
+    fn lock_exclusive_slow(&self) {
+        while !self.try_lock_exclusive() {
+            // SAFETY: no calls to sparking_lot_core functions in closure, owned address
+            let token = unsafe {
+                park_with_token(&self.state, || {
+                    self.state
+                        .fetch_update(Relaxed, Relaxed, |state| {
+                            (state & (WRITER | READERS) != 0).then_some(state | WRITERS_PARKED)
+                        })
+                        .is_ok()
+                })
+            };
+            if token == Some(HANDED_OFF) {
+                // `unlock_exclusive` never unlocked it, it's ours now
+                return;
+            }
+        }
+    }
+
+    /// Wakes a parked writer, handing it the lock with `handoff`, and
+    /// returns whether there was one.
+    fn wake_writer(&self, handoff: bool) -> bool {
+        let result = unpark_one_with(&self.state, |result| {
+            // the parked bits only change with the bucket locked
+            if result.unparked != 0 && handoff {
+                let parked = if result.has_more { WRITERS_PARKED } else { 0 };
+                let readers_parked = self.state.load(Relaxed) & READERS_PARKED;
+                // stays locked for the woken writer
+                self.state.store(WRITER | parked | readers_parked, Relaxed);
+                return HANDED_OFF;
+            }
+            if !result.has_more {
+                // a barging writer may have locked it meanwhile
+                self.state.fetch_and(!WRITERS_PARKED, Relaxed);
+            }
+            DEFAULT_UNPARK_TOKEN
+        });
+        result.unparked != 0
+    }
+
+    /// Wakes every parked reader.
+    fn wake_readers(&self) {
+        if self.state.fetch_and(!READERS_PARKED, Relaxed) & READERS_PARKED != 0 {
+            unpark_all_lane(&self.state, READERS_LANE);
+        }
+    }
+
+    #[cold]
+    fn unlock_shared_slow(&self) {
+        if !self.wake_writer(false) {
+            self.wake_readers();
+        }
+    }
+
+    #[cold]
+    fn unlock_exclusive_slow(&self) {
+        if self.state.load(Relaxed) & WRITERS_PARKED != 0 && self.wake_writer(true) {
+            return;
+        }
+        // nobody to hand it to, so the readers get it
+        if self.state.fetch_and(!(WRITER | READERS_PARKED), Release) & READERS_PARKED != 0 {
+            unpark_all_lane(&self.state, READERS_LANE);
+        }
+    }
+}
+
+unsafe impl lock_api::RawRwLock for RawRwLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        state: AtomicUsize::new(0),
+    };
+
+    type GuardMarker = lock_api::GuardSend;
+
+    #[inline]
+    fn lock_shared(&self) {
+        if !self.try_lock_shared() {
+            self.lock_shared_slow();
+        }
+    }
+
+    #[inline]
+    fn try_lock_shared(&self) -> bool {
+        let mut state = self.state.load(Relaxed);
+        // readers let parked writers go first
+        while state & (WRITER | WRITERS_PARKED) == 0 {
+            let new = state
+                .checked_add(ONE_READER)
+                .expect("sparking-lot-core: too many readers");
+            match self
+                .state
+                .compare_exchange_weak(state, new, Acquire, Relaxed)
+            {
+                Ok(_) => return true,
+                Err(new) => state = new,
+            }
+        }
+        false
+    }
+
+    #[inline]
+    unsafe fn unlock_shared(&self) {
+        let state = self.state.fetch_sub(ONE_READER, Release);
+        // readers only wait for writers
+        if state & READERS == ONE_READER && state & WRITERS_PARKED != 0 {
+            self.unlock_shared_slow();
+        }
+    }
+
+    #[inline]
+    fn lock_exclusive(&self) {
+        if !self.try_lock_exclusive() {
+            self.lock_exclusive_slow();
+        }
+    }
+
+    #[inline]
+    fn try_lock_exclusive(&self) -> bool {
+        let mut state = self.state.load(Relaxed);
+        // it may be free with parked bits left over
+        while state & (WRITER | READERS) == 0 {
+            match self
+                .state
+                .compare_exchange_weak(state, state | WRITER, Acquire, Relaxed)
+            {
+                Ok(_) => return true,
+                Err(new) => state = new,
+            }
+        }
+        false
+    }
+
+    #[inline]
+    unsafe fn unlock_exclusive(&self) {
+        if self
+            .state
+            .compare_exchange(WRITER, 0, Release, Relaxed)
+            .is_err()
+        {
+            self.unlock_exclusive_slow();
+        }
+    }
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        self.state.load(Relaxed) & (WRITER | READERS) != 0
+    }
+
+    #[inline]
+    fn is_locked_exclusive(&self) -> bool {
+        self.state.load(Relaxed) & WRITER != 0
+    }
+}
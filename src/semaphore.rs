@@ -0,0 +1,87 @@
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+
+use crate::{has_waiters, park, unpark_some};
+
+/// A counting semaphore: threads take permits with
+/// [`acquire`](Semaphore::acquire), waiting while there are none, and give
+/// them back with [`release`](Semaphore::release).
+///
+/// It's a word in size, and threads only touch the lot when they have to
+/// wait or wake someone. Releasing `n` permits wakes up to `n` waiters with
+/// [`unpark_some`], which then compete for the permits with threads which
+/// didn't wait, so it isn't fair, but permits are never left unused while
+/// threads wait for them.
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+///
+/// use sparking_lot_core::Semaphore;
+///
+/// // at most 2 threads in the section at once
+/// static SLOTS: Semaphore = Semaphore::new(2);
+///
+/// thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             SLOTS.acquire();
+///             // ...
+///             SLOTS.release(1);
+///         });
+///     }
+/// });
+/// assert_eq!(SLOTS.available(), 2);
+/// ```
+#[derive(Debug, Default)]
+pub struct Semaphore {
+    permits: AtomicUsize,
+}
+
+impl Semaphore {
+    /// Creates a semaphore with `permits` permits.
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            permits: AtomicUsize::new(permits),
+        }
+    }
+
+    /// Takes a permit, blocking the current thread until one is available.
+    pub fn acquire(&self) {
+        while !self.try_acquire() {
+            //SAFETY: `expected` only loads an atomic and the address is private
+            unsafe { park(&self.permits, || self.permits.load(Relaxed) == 0) };
+        }
+    }
+
+    /// Takes a permit if one is available, without blocking,
+    /// and returns whether it did.
+    pub fn try_acquire(&self) -> bool {
+        self.permits
+            .fetch_update(Acquire, Relaxed, |permits| permits.checked_sub(1))
+            .is_ok()
+    }
+
+    /// Gives back `n` permits, waking up to `n` threads waiting for them.
+    ///
+    /// # Panics
+    ///
+    /// If there would be more than `usize::MAX` permits.
+    pub fn release(&self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        self.permits
+            .fetch_update(Release, Relaxed, |permits| permits.checked_add(n))
+            .expect("sparking-lot-core: too many permits");
+        if has_waiters(&self.permits) {
+            unpark_some(&self.permits, n);
+        }
+    }
+
+    /// Returns how many permits are available right now.
+    pub fn available(&self) -> usize {
+        self.permits.load(Relaxed)
+    }
+}
@@ -0,0 +1,165 @@
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use core::sync::atomic::{AtomicBool, AtomicUsize};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What [`run_stress`] does. Build it from [`StressConfig::default`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StressConfig {
+    /// How many threads park and unpark. At least 1.
+    pub threads: usize,
+    /// How many addresses the threads pick from. At least 1. Fewer
+    /// addresses mean longer queues, more means more buckets in use.
+    pub addresses: usize,
+    /// The percentage (0 to 100) of operations which are unparks,
+    /// the rest park until the address is notified.
+    pub unpark_percent: u8,
+    /// How long the threads keep going.
+    pub duration: Duration,
+}
+
+impl Default for StressConfig {
+    /// 8 threads on 4 addresses for 100ms, with half of the operations unparking.
+    fn default() -> Self {
+        Self {
+            threads: 8,
+            addresses: 4,
+            unpark_percent: 50,
+            duration: Duration::from_millis(100),
+        }
+    }
+}
+
+/// What happened during [`run_stress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct StressReport {
+    /// Park calls which slept until they were unparked.
+    pub parked: usize,
+    /// Park calls which didn't sleep, because the address was notified
+    /// between picking it and parking (or the run was over).
+    pub invalid: usize,
+    /// Calls to [`unpark_one`](crate::unpark_one).
+    pub unparks: usize,
+    /// Threads woken by those calls.
+    pub woken: usize,
+    /// How long it took, including stopping the threads.
+    pub elapsed: Duration,
+}
+
+impl StressReport {
+    /// Park and unpark calls per second.
+    pub fn ops_per_sec(&self) -> f64 {
+        (self.parked + self.invalid + self.unparks) as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Runs the park and unpark mix described by `config` on its own addresses,
+/// to see how the lot behaves (e.g. with or without `more-concurrency`) under
+/// a workload like the application's.
+///
+/// Every thread repeatedly picks a random address and either notifies it and
+/// calls [`unpark_one`](crate::unpark_one), or parks until it's notified.
+/// Once `config.duration` passes, the parked threads are woken and joined.
+///
+/// Only available with the `stress` feature.
+///
+/// # Panics
+///
+/// If `config.threads` or `config.addresses` is 0, or
+/// `config.unpark_percent` is more than 100.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use sparking_lot_core::{run_stress, StressConfig};
+///
+/// let mut config = StressConfig::default();
+/// config.threads = 4;
+/// config.duration = Duration::from_millis(10);
+/// let report = run_stress(&config);
+/// assert!(report.woken <= report.unparks);
+/// ```
+pub fn run_stress(config: &StressConfig) -> StressReport {
+    assert!(config.threads != 0, "`threads` must be at least 1");
+    assert!(config.addresses != 0, "`addresses` must be at least 1");
+    assert!(
+        config.unpark_percent <= 100,
+        "`unpark_percent` can't be more than 100"
+    );
+    // the events of every address, a waiter parks until its event changes
+    let events: Vec<AtomicUsize> = (0..config.addresses).map(|_| AtomicUsize::new(0)).collect();
+    let stop = AtomicBool::new(false);
+    let start = Instant::now();
+    let mut report = thread::scope(|s| {
+        let workers: Vec<_> = (0..config.threads)
+            .map(|n| {
+                let (events, stop) = (&events, &stop);
+                s.spawn(move || work(config, events, stop, n))
+            })
+            .collect();
+        thread::sleep(config.duration);
+        stop.store(true, Release);
+        // `stop` is checked in `expected`, so nobody parks after this
+        for event in &events {
+            crate::unpark_all(addr(event));
+        }
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .fold(StressReport::default(), |total, report| StressReport {
+                parked: total.parked + report.parked,
+                invalid: total.invalid + report.invalid,
+                unparks: total.unparks + report.unparks,
+                woken: total.woken + report.woken,
+                elapsed: Duration::ZERO,
+            })
+    });
+    report.elapsed = start.elapsed();
+    report
+}
+
+fn addr(event: &AtomicUsize) -> *const () {
+    event as *const _ as *const _
+}
+
+fn work(
+    config: &StressConfig,
+    events: &[AtomicUsize],
+    stop: &AtomicBool,
+    n: usize,
+) -> StressReport {
+    let mut report = StressReport::default();
+    // xorshift32, seeded differently for every thread
+    let mut rng = (n as u32).wrapping_mul(0x9E37_79B9) | 1;
+    let mut random = move || {
+        rng ^= rng << 13;
+        rng ^= rng >> 17;
+        rng ^= rng << 5;
+        rng
+    };
+    while !stop.load(Relaxed) {
+        let event = &events[random() as usize % events.len()];
+        if random() % 100 < u32::from(config.unpark_percent) {
+            event.fetch_add(1, Release);
+            report.unparks += 1;
+            report.woken += crate::unpark_one(addr(event)).unparked;
+        } else {
+            let seen = event.load(Acquire);
+            //SAFETY: `expected` only uses atomics, and `events` is private to `run_stress`
+            let parked = unsafe {
+                crate::park_with_token(addr(event), || {
+                    !stop.load(Acquire) && event.load(Acquire) == seen
+                })
+            };
+            match parked {
+                Some(_) => report.parked += 1,
+                None => report.invalid += 1,
+            }
+        }
+    }
+    report
+}
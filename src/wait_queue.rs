@@ -0,0 +1,91 @@
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::{Acquire, Release};
+
+use crate::{has_waiters, park, unpark_all, unpark_one};
+
+/// A queue of threads waiting for a condition, like a condition variable
+/// without a mutex of its own.
+///
+/// [`wait`](WaitQueue::wait) runs `guard_release` to let other threads
+/// change the condition (e.g. by unlocking a mutex) and sleeps until it's
+/// notified. Notifications which come after `guard_release` started are
+/// never missed, so the usual pattern of checking the condition under a
+/// lock and waiting works without raw addresses or `unsafe`. Like other
+/// condition variables, `wait` may return without a notification, so the
+/// condition has to be checked again in a loop.
+///
+/// It's a word in size and parks on its own address. Notifying it when
+/// nobody waits doesn't lock a bucket (see [`has_waiters`]).
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Mutex;
+/// use std::thread;
+///
+/// use sparking_lot_core::WaitQueue;
+///
+/// static READY: Mutex<bool> = Mutex::new(false);
+/// static QUEUE: WaitQueue = WaitQueue::new();
+///
+/// let waiter = thread::spawn(|| {
+///     let mut ready = READY.lock().unwrap();
+///     while !*ready {
+///         QUEUE.wait(|| drop(ready));
+///         ready = READY.lock().unwrap();
+///     }
+/// });
+/// *READY.lock().unwrap() = true;
+/// QUEUE.notify_all();
+/// waiter.join().unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct WaitQueue {
+    /// Bumped by every notification, so waiters can tell
+    /// if one came after they started waiting.
+    notifications: AtomicUsize,
+}
+
+impl WaitQueue {
+    /// Creates a queue nobody waits on.
+    pub const fn new() -> Self {
+        Self {
+            notifications: AtomicUsize::new(0),
+        }
+    }
+
+    /// Calls `guard_release` and blocks the current thread until it's
+    /// notified with [`notify_one`](WaitQueue::notify_one) or
+    /// [`notify_all`](WaitQueue::notify_all), or it wakes up spuriously.
+    ///
+    /// Notifications which come after `guard_release` started wake the
+    /// thread, even if they come before it sleeps. `guard_release` runs
+    /// with no locks held, so it can do anything, including unlocking
+    /// primitives built on this crate.
+    pub fn wait(&self, guard_release: impl FnOnce()) {
+        let seen = self.notifications.load(Acquire);
+        guard_release();
+        //SAFETY: `expected` only loads an atomic and the address is private
+        unsafe {
+            park(&self.notifications, || {
+                self.notifications.load(Acquire) == seen
+            })
+        };
+    }
+
+    /// Wakes one thread blocked in [`wait`](WaitQueue::wait), if there are any.
+    pub fn notify_one(&self) {
+        self.notifications.fetch_add(1, Release);
+        if has_waiters(&self.notifications) {
+            unpark_one(&self.notifications);
+        }
+    }
+
+    /// Wakes every thread blocked in [`wait`](WaitQueue::wait).
+    pub fn notify_all(&self) {
+        self.notifications.fetch_add(1, Release);
+        if has_waiters(&self.notifications) {
+            unpark_all(&self.notifications);
+        }
+    }
+}
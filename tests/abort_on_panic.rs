@@ -0,0 +1,33 @@
+#![cfg(all(feature = "abort-on-panic", not(loom)))]
+
+use std::process::Command;
+
+use sparking_lot_core as slc;
+
+const CHILD: &str = "SPARKING_LOT_CORE_ABORT_CHILD";
+
+#[test]
+fn expected_panic_aborts() {
+    if std::env::var_os(CHILD).is_some() {
+        static ADDR: u8 = 0;
+        unsafe {
+            slc::park(&ADDR as *const _ as *const _, || {
+                panic!("`expected` panicked")
+            })
+        };
+        // only reached if the panic didn't abort
+        return;
+    }
+    // aborting would kill the test harness, so rerun this test in a child process
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "expected_panic_aborts", "--test-threads=1"])
+        .env(CHILD, "1")
+        .status()
+        .unwrap();
+    assert!(!status.success());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(status.signal(), Some(6 /* SIGABRT */));
+    }
+}
@@ -0,0 +1,54 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering::Relaxed;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core::atomic_wait::{wait, wake_all, wake_one};
+
+#[test]
+fn wait_returns_if_changed() {
+    let atomic = AtomicU32::new(1);
+    // would block forever if it parked
+    wait(&atomic, 0);
+}
+
+#[test]
+fn wake_one_wakes_one() {
+    let atomic = AtomicU32::new(0);
+    thread::scope(|s| {
+        let waiter = s.spawn(|| {
+            while atomic.load(Relaxed) == 0 {
+                wait(&atomic, 0);
+            }
+        });
+        // give the waiter time to actually go to sleep
+        thread::sleep(Duration::from_millis(50));
+        atomic.store(1, Relaxed);
+        wake_one(&atomic);
+        waiter.join().unwrap();
+    });
+}
+
+#[test]
+fn wake_all_wakes_all() {
+    let atomic = AtomicU32::new(0);
+    thread::scope(|s| {
+        let waiters: Vec<_> = (0..3)
+            .map(|_| {
+                s.spawn(|| {
+                    while atomic.load(Relaxed) == 0 {
+                        wait(&atomic, 0);
+                    }
+                })
+            })
+            .collect();
+        thread::sleep(Duration::from_millis(50));
+        atomic.store(1, Relaxed);
+        wake_all(&atomic);
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+    });
+}
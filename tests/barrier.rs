@@ -0,0 +1,62 @@
+#![cfg(all(feature = "barrier", feature = "std", not(loom)))]
+
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core::{Barrier, Latch};
+
+#[test]
+fn latch_releases_waiters() {
+    let latch = Latch::new(2);
+    thread::scope(|s| {
+        let waiters: Vec<_> = (0..3).map(|_| s.spawn(|| latch.wait())).collect();
+        // give the waiters time to actually go to sleep
+        thread::sleep(Duration::from_millis(50));
+        latch.count_down();
+        assert!(!latch.is_released());
+        latch.count_down();
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+    });
+    // it stays released
+    latch.wait();
+    assert!(Latch::new(0).is_released());
+}
+
+#[test]
+#[should_panic = "counted down after its release"]
+fn latch_counted_down_too_often() {
+    let latch = Latch::new(1);
+    latch.count_down();
+    latch.count_down();
+}
+
+#[test]
+fn barrier_is_reusable() {
+    const THREADS: usize = 4;
+    const ROUNDS: usize = 100;
+    let barrier = Barrier::new(THREADS);
+    let leaders = AtomicUsize::new(0);
+    let arrived = AtomicUsize::new(0);
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| {
+                for round in 0..ROUNDS {
+                    arrived.fetch_add(1, Relaxed);
+                    if barrier.wait() {
+                        leaders.fetch_add(1, Relaxed);
+                    }
+                    // everyone arrived, and nobody left for the next round yet
+                    assert_eq!(arrived.load(Relaxed), (round + 1) * THREADS);
+                    barrier.wait();
+                }
+            });
+        }
+    });
+    assert_eq!(leaders.load(Relaxed), ROUNDS);
+    // a barrier for one thread never blocks
+    assert!(Barrier::new(0).wait());
+}
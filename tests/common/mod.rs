@@ -0,0 +1,32 @@
+//! Fixtures shared by the integration tests. Every test crate only uses
+//! some of them.
+#![allow(dead_code)]
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Acquire;
+use std::thread;
+
+use sparking_lot_core::{self as slc, UnparkResult};
+
+/// The address the tests park on for `flag`.
+pub fn addr(flag: &AtomicBool) -> *const () {
+    flag as *const _ as *const _
+}
+
+/// Parks a thread on `wake_up` until it's set.
+pub fn spawn_waiter(wake_up: &'static AtomicBool) -> thread::JoinHandle<()> {
+    thread::spawn(move || unsafe {
+        slc::park(addr(wake_up), || !wake_up.load(Acquire));
+    })
+}
+
+/// Spins until exactly `count` threads are queued on `flag`.
+pub fn wait_for_waiters(flag: &AtomicBool, count: usize) {
+    while slc::parked_count(addr(flag)) != count {
+        thread::yield_now();
+    }
+}
+
+pub const fn result(unparked: usize, has_more: bool) -> UnparkResult {
+    UnparkResult { unparked, has_more }
+}
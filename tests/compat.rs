@@ -0,0 +1,193 @@
+#![cfg(all(feature = "compat", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sparking_lot_core as slc;
+use sparking_lot_core::compat::{
+    park, unpark_all, unpark_one, unpark_requeue, ParkResult, RequeueOp, UnparkToken,
+    DEFAULT_PARK_TOKEN, DEFAULT_UNPARK_TOKEN,
+};
+
+use common::wait_for_waiters;
+
+fn key(flag: &'static AtomicBool) -> usize {
+    (flag as *const AtomicBool).addr()
+}
+
+fn spawn_waiter(wake_up: &'static AtomicBool) -> thread::JoinHandle<ParkResult> {
+    thread::spawn(move || unsafe {
+        park(
+            key(wake_up),
+            || !wake_up.load(Acquire),
+            || {},
+            |_, _| {},
+            DEFAULT_PARK_TOKEN,
+            None,
+        )
+    })
+}
+
+#[test]
+fn invalid() {
+    static KEY: AtomicBool = AtomicBool::new(false);
+    let result = unsafe {
+        park(
+            key(&KEY),
+            || false,
+            || unreachable!(),
+            |_, _| unreachable!(),
+            DEFAULT_PARK_TOKEN,
+            None,
+        )
+    };
+    assert_eq!(result, ParkResult::Invalid);
+}
+
+#[test]
+fn times_out() {
+    static KEY: AtomicBool = AtomicBool::new(false);
+    let mut timed_out = None;
+    let timeout = Instant::now() + Duration::from_millis(10);
+    let result = unsafe {
+        park(
+            key(&KEY),
+            || true,
+            || {},
+            |key, was_last| timed_out = Some((key, was_last)),
+            DEFAULT_PARK_TOKEN,
+            Some(timeout),
+        )
+    };
+    assert_eq!(result, ParkResult::TimedOut);
+    assert_eq!(timed_out, Some((key(&KEY), true)));
+}
+
+#[test]
+fn before_sleep_can_unpark() {
+    static KEY: AtomicBool = AtomicBool::new(false);
+    // the thread is already queued, so it wakes itself
+    let result = unsafe {
+        park(
+            key(&KEY),
+            || true,
+            || assert_eq!(slc::unpark_one(key(&KEY)).unparked, 1),
+            |_, _| {},
+            DEFAULT_PARK_TOKEN,
+            None,
+        )
+    };
+    assert_eq!(result, ParkResult::Unparked(DEFAULT_UNPARK_TOKEN));
+}
+
+#[test]
+fn unpark_one_passes_token() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiter = spawn_waiter(&WAKE_UP);
+    wait_for_waiters(&WAKE_UP, 1);
+    WAKE_UP.store(true, Release);
+    let result = unsafe {
+        unpark_one(key(&WAKE_UP), |result| {
+            assert_eq!(result.unparked_threads, 1);
+            assert!(!result.have_more_threads);
+            UnparkToken(42)
+        })
+    };
+    assert_eq!(result.unparked_threads, 1);
+    assert_eq!(
+        waiter.join().unwrap(),
+        ParkResult::Unparked(UnparkToken(42))
+    );
+}
+
+#[test]
+fn unpark_all_passes_token() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiters: Vec<_> = (0..3).map(|_| spawn_waiter(&WAKE_UP)).collect();
+    wait_for_waiters(&WAKE_UP, 3);
+    WAKE_UP.store(true, Release);
+    assert_eq!(unsafe { unpark_all(key(&WAKE_UP), UnparkToken(7)) }, 3);
+    for waiter in waiters {
+        assert_eq!(waiter.join().unwrap(), ParkResult::Unparked(UnparkToken(7)));
+    }
+}
+
+#[test]
+fn requeue() {
+    static FROM: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    let waiters: Vec<_> = (0..3).map(|_| spawn_waiter(&FROM)).collect();
+    wait_for_waiters(&FROM, 3);
+    FROM.store(true, Release);
+    let result = unsafe {
+        unpark_requeue(
+            key(&FROM),
+            key(&TO),
+            || RequeueOp::UnparkOneRequeueRest,
+            |op, result| {
+                assert_eq!(op, RequeueOp::UnparkOneRequeueRest);
+                assert_eq!((result.unparked_threads, result.requeued_threads), (1, 2));
+                assert!(!result.have_more_threads);
+                UnparkToken(1)
+            },
+        )
+    };
+    assert_eq!((result.unparked_threads, result.requeued_threads), (1, 2));
+    assert_eq!(slc::parked_count(key(&TO)), 2);
+    assert_eq!(unsafe { unpark_all(key(&TO), UnparkToken(2)) }, 2);
+    let mut tokens: Vec<_> = waiters.into_iter().map(|w| w.join().unwrap()).collect();
+    tokens.sort_by_key(|result| match result {
+        ParkResult::Unparked(token) => token.0,
+        _ => usize::MAX,
+    });
+    assert_eq!(
+        tokens,
+        [1, 2, 2].map(|token| ParkResult::Unparked(UnparkToken(token)))
+    );
+}
+
+#[test]
+fn requeue_one_leaves_the_rest() {
+    static FROM: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    let waiters: Vec<_> = (0..2).map(|_| spawn_waiter(&FROM)).collect();
+    wait_for_waiters(&FROM, 2);
+    FROM.store(true, Release);
+    let result = unsafe {
+        unpark_requeue(
+            key(&FROM),
+            key(&TO),
+            || RequeueOp::RequeueOne,
+            |_, result| {
+                assert!(result.have_more_threads);
+                DEFAULT_UNPARK_TOKEN
+            },
+        )
+    };
+    assert_eq!((result.unparked_threads, result.requeued_threads), (0, 1));
+    assert!(result.have_more_threads);
+    assert_eq!(slc::unpark_all(key(&FROM)).unparked, 1);
+    assert_eq!(slc::unpark_all(key(&TO)).unparked, 1);
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+}
+
+#[test]
+fn abort_skips_callback() {
+    static FROM: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    let result = unsafe {
+        unpark_requeue(
+            key(&FROM),
+            key(&TO),
+            || RequeueOp::Abort,
+            |_, _| unreachable!(),
+        )
+    };
+    assert_eq!((result.unparked_threads, result.requeued_threads), (0, 0));
+}
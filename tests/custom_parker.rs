@@ -0,0 +1,61 @@
+#![cfg(all(feature = "custom-parker", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use core::sync::atomic::{AtomicBool, AtomicUsize};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use sparking_lot_core::{self as slc, RawParker};
+
+use common::addr;
+
+/// Parks with `std`, counting the calls.
+struct CountingParker {
+    parks: AtomicUsize,
+    unparks: AtomicUsize,
+}
+
+thread_local!(static HANDLE: &'static Thread = Box::leak(Box::new(thread::current())));
+
+unsafe impl RawParker for CountingParker {
+    fn current(&self) -> usize {
+        // leaked, so unparking exited threads is fine
+        HANDLE.with(|handle| *handle as *const Thread as usize)
+    }
+
+    fn park(&self) {
+        self.parks.fetch_add(1, Relaxed);
+        thread::park();
+    }
+
+    fn unpark(&self, handle: usize) {
+        self.unparks.fetch_add(1, Relaxed);
+        unsafe { &*(handle as *const Thread) }.unpark();
+    }
+}
+
+static PARKER: CountingParker = CountingParker {
+    parks: AtomicUsize::new(0),
+    unparks: AtomicUsize::new(0),
+};
+
+#[test]
+fn blocks_with_installed_parker() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    assert!(slc::set_raw_parker(&PARKER));
+    // it can only be installed once
+    assert!(!slc::set_raw_parker(&PARKER));
+
+    let h = thread::spawn(|| unsafe {
+        slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+    });
+    // give the waiter time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 1);
+    h.join().unwrap();
+    assert!(PARKER.parks.load(Relaxed) >= 1);
+    assert_eq!(PARKER.unparks.load(Relaxed), 1);
+}
@@ -0,0 +1,89 @@
+#![cfg(all(feature = "deadlock-detection", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread::{self, ThreadId};
+
+use sparking_lot_core as slc;
+
+use common::{addr, wait_for_waiters};
+
+/// Holds `held` and parks on `wanted` until it's released.
+fn spawn_waiter(held: &'static AtomicBool, wanted: &'static AtomicBool) -> thread::JoinHandle<()> {
+    held.store(true, Release);
+    thread::spawn(move || {
+        slc::acquire_resource(addr(held));
+        unsafe { slc::park(addr(wanted), || wanted.load(Acquire)) };
+        slc::release_resource(addr(held));
+    })
+}
+
+/// The deadlock `thread` is in, other tests may have deadlocks of their own.
+fn deadlock_of(thread: ThreadId) -> Option<Vec<slc::DeadlockedThread>> {
+    slc::check_deadlock()
+        .into_iter()
+        .find(|deadlock| deadlock.iter().any(|waiter| waiter.thread_id == thread))
+}
+
+fn release(flag: &'static AtomicBool) {
+    flag.store(false, Release);
+    slc::unpark_all(addr(flag));
+}
+
+#[test]
+fn finds_cycles() {
+    static A: AtomicBool = AtomicBool::new(false);
+    static B: AtomicBool = AtomicBool::new(false);
+    let first = spawn_waiter(&A, &B);
+    let second = spawn_waiter(&B, &A);
+    wait_for_waiters(&A, 1);
+    wait_for_waiters(&B, 1);
+
+    let deadlock = deadlock_of(first.thread().id()).expect("no deadlock found");
+    assert_eq!(deadlock.len(), 2);
+    let second_waiter = deadlock
+        .iter()
+        .find(|waiter| waiter.thread_id == second.thread().id())
+        .unwrap();
+    assert_eq!(second_waiter.parked_on, addr(&A));
+    assert_eq!(second_waiter.holds, [addr(&B)]);
+
+    release(&A);
+    release(&B);
+    first.join().unwrap();
+    second.join().unwrap();
+}
+
+#[test]
+fn running_holders_arent_deadlocked() {
+    static HELD: AtomicBool = AtomicBool::new(false);
+    static OTHER: AtomicBool = AtomicBool::new(false);
+    HELD.store(true, Release);
+    slc::acquire_resource(addr(&HELD));
+    let waiter = spawn_waiter(&OTHER, &HELD);
+    wait_for_waiters(&HELD, 1);
+    assert!(deadlock_of(waiter.thread().id()).is_none());
+
+    slc::release_resource(addr(&HELD));
+    release(&HELD);
+    release(&OTHER);
+    waiter.join().unwrap();
+}
+
+#[test]
+fn released_resources_arent_held() {
+    static LOCK: AtomicBool = AtomicBool::new(false);
+    LOCK.store(true, Release);
+    let waiter = thread::spawn(|| {
+        slc::acquire_resource(addr(&LOCK));
+        slc::release_resource(addr(&LOCK));
+        unsafe { slc::park(addr(&LOCK), || LOCK.load(Acquire)) };
+    });
+    wait_for_waiters(&LOCK, 1);
+    assert!(deadlock_of(waiter.thread().id()).is_none());
+
+    release(&LOCK);
+    waiter.join().unwrap();
+}
@@ -0,0 +1,87 @@
+#![cfg(all(feature = "std", feature = "debug-introspection", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+
+use sparking_lot_core as slc;
+
+use common::{addr, wait_for_waiters};
+
+fn dump() -> String {
+    let mut out = String::new();
+    slc::dump(&mut out).unwrap();
+    out
+}
+
+#[test]
+fn lists_waiters() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiters: Vec<_> = (0..2)
+        .map(|_| {
+            thread::spawn(|| unsafe {
+                slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+            })
+        })
+        .collect();
+    wait_for_waiters(&WAKE_UP, 2);
+    let line = format!("{:#x}: 2 parked\n", addr(&WAKE_UP) as usize);
+    assert!(dump().contains(&line), "{}", dump());
+
+    WAKE_UP.store(true, Release);
+    slc::unpark_all(addr(&WAKE_UP));
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+    assert!(!dump().contains(&line));
+}
+
+#[test]
+fn lists_keys_and_generations() {
+    const KEY: u64 = 0x1234_5678_9abc_def0;
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let key = thread::spawn(|| unsafe {
+        slc::park_on_key(KEY, || !WAKE_UP.load(Acquire));
+    });
+    let versioned = thread::spawn(|| unsafe {
+        slc::park_versioned(addr(&WAKE_UP), 7, || !WAKE_UP.load(Acquire));
+    });
+    let generation = format!("{:#x} (generation 7): 1 parked\n", addr(&WAKE_UP) as usize);
+    loop {
+        let out = dump();
+        if out.contains("key 0x123456789abcdef0: 1 parked\n") && out.contains(&generation) {
+            break;
+        }
+        thread::yield_now();
+    }
+
+    WAKE_UP.store(true, Release);
+    slc::unpark_all_key(KEY);
+    slc::unpark_all_versioned(addr(&WAKE_UP), 7);
+    key.join().unwrap();
+    versioned.join().unwrap();
+}
+
+#[test]
+fn lots_are_listed_separately() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let lot = slc::ParkingLot::new();
+    let line = format!("{:#x}: 1 parked\n", addr(&WAKE_UP) as usize);
+    thread::scope(|s| {
+        s.spawn(|| unsafe {
+            lot.park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+        });
+        while lot.parked_count(addr(&WAKE_UP)) == 0 {
+            thread::yield_now();
+        }
+        let mut out = String::new();
+        lot.dump(&mut out).unwrap();
+        assert_eq!(out, line);
+        assert!(!dump().contains(&line));
+
+        WAKE_UP.store(true, Release);
+        lot.unpark_all(addr(&WAKE_UP));
+    });
+}
@@ -0,0 +1,90 @@
+#![cfg(all(feature = "ffi", feature = "std", not(loom)))]
+
+use core::ffi::c_void;
+use core::ptr;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+
+use sparking_lot_core::ffi::{
+    slc_has_waiters, slc_park, slc_park_timeout, slc_park_with_token, slc_unpark_all,
+    slc_unpark_one, slc_unpark_one_with_token, SlcParkResult,
+};
+
+fn addr(flag: &'static AtomicBool) -> *const c_void {
+    flag as *const _ as *const _
+}
+
+/// `expected` for a context pointing to an `AtomicBool`,
+/// which parks until it's set.
+unsafe extern "C" fn not_set(context: *mut c_void) -> bool {
+    !(*(context as *const AtomicBool)).load(Acquire)
+}
+
+fn context(flag: &'static AtomicBool) -> *mut c_void {
+    addr(flag).cast_mut()
+}
+
+fn wait_for_waiter(flag: &'static AtomicBool) {
+    while !slc_has_waiters(addr(flag)) {
+        thread::yield_now();
+    }
+}
+
+#[test]
+fn invalid() {
+    static SET: AtomicBool = AtomicBool::new(true);
+    assert!(!unsafe { slc_park(addr(&SET), Some(not_set), context(&SET)) });
+}
+
+#[test]
+fn park_and_unpark() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiter =
+        thread::spawn(|| unsafe { slc_park(addr(&WAKE_UP), Some(not_set), context(&WAKE_UP)) });
+    wait_for_waiter(&WAKE_UP);
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc_unpark_one(addr(&WAKE_UP)), 1);
+    assert!(waiter.join().unwrap());
+}
+
+#[test]
+fn null_expected_parks() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiter = thread::spawn(|| unsafe { slc_park(addr(&WAKE_UP), None, ptr::null_mut()) });
+    wait_for_waiter(&WAKE_UP);
+    assert_eq!(slc_unpark_all(addr(&WAKE_UP)), 1);
+    assert!(waiter.join().unwrap());
+}
+
+#[test]
+fn token() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiter = thread::spawn(|| {
+        let mut token = 0;
+        let parked = unsafe {
+            slc_park_with_token(addr(&WAKE_UP), Some(not_set), context(&WAKE_UP), &mut token)
+        };
+        (parked, token)
+    });
+    wait_for_waiter(&WAKE_UP);
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc_unpark_one_with_token(addr(&WAKE_UP), 42), 1);
+    assert_eq!(waiter.join().unwrap(), (true, 42));
+}
+
+#[test]
+fn timeout() {
+    static NEVER: AtomicBool = AtomicBool::new(false);
+    let result = unsafe {
+        slc_park_timeout(
+            addr(&NEVER),
+            Some(not_set),
+            context(&NEVER),
+            10_000_000,
+            ptr::null_mut(),
+        )
+    };
+    assert_eq!(result, SlcParkResult::TimedOut);
+    assert!(!slc_has_waiters(addr(&NEVER)));
+}
@@ -0,0 +1,79 @@
+#![cfg(all(feature = "std", not(feature = "random-wake"), not(loom)))]
+
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+const WAITERS: usize = 4;
+
+/// Parks `WAITERS` threads on `woken` one by one, so that they park
+/// in order of their ids. Each records its id in `order` when woken.
+fn spawn_waiters(
+    woken: &'static AtomicUsize,
+    order: &'static Mutex<Vec<usize>>,
+) -> Vec<thread::JoinHandle<()>> {
+    (0..WAITERS)
+        .map(|id| {
+            let h = thread::spawn(move || {
+                // each thread waits until `woken` passes its id
+                unsafe {
+                    slc::park(woken as *const _ as *const _, || woken.load(Acquire) <= id);
+                }
+                order.lock().unwrap().push(id);
+            });
+            // give the waiter time to actually go to sleep
+            thread::sleep(Duration::from_millis(50));
+            h
+        })
+        .collect()
+}
+
+#[test]
+fn unpark_one_is_fifo() {
+    static WOKEN: AtomicUsize = AtomicUsize::new(0);
+    static ORDER: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+    let handles = spawn_waiters(&WOKEN, &ORDER);
+    for i in 1..=WAITERS {
+        WOKEN.store(i, Release);
+        slc::unpark_one(&WOKEN as *const _ as *const _);
+        while ORDER.lock().unwrap().len() != i {
+            thread::yield_now();
+        }
+    }
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert_eq!(*ORDER.lock().unwrap(), (0..WAITERS).collect::<Vec<_>>());
+}
+
+#[test]
+fn unpark_some_is_fifo() {
+    static WOKEN: AtomicUsize = AtomicUsize::new(0);
+    static ORDER: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+    let handles = spawn_waiters(&WOKEN, &ORDER);
+    // wakes the first half, the rest must still be parked
+    WOKEN.store(WAITERS / 2, Release);
+    assert_eq!(
+        slc::unpark_some(&WOKEN as *const _ as *const _, WAITERS / 2),
+        slc::UnparkResult {
+            unparked: WAITERS / 2,
+            has_more: true
+        }
+    );
+    while ORDER.lock().unwrap().len() != WAITERS / 2 {
+        thread::yield_now();
+    }
+    let mut first_half = ORDER.lock().unwrap().clone();
+    first_half.sort_unstable();
+    assert_eq!(first_half, (0..WAITERS / 2).collect::<Vec<_>>());
+
+    WOKEN.store(WAITERS, Release);
+    slc::unpark_all(&WOKEN as *const _ as *const _);
+    for h in handles {
+        h.join().unwrap();
+    }
+}
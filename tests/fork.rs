@@ -0,0 +1,49 @@
+#![cfg(all(unix, feature = "std", not(any(loom, miri))))]
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+
+use sparking_lot_core as slc;
+
+#[test]
+fn reinit_after_fork_unlocks_buckets() {
+    static ADDR: u8 = 0;
+    static IN_EXPECTED: AtomicBool = AtomicBool::new(false);
+    static DONE: AtomicBool = AtomicBool::new(false);
+    let addr = &ADDR as *const _ as *const ();
+
+    // holds the bucket lock of `ADDR` while the process forks
+    let h = thread::spawn(|| unsafe {
+        slc::park(&ADDR as *const _ as *const (), || {
+            IN_EXPECTED.store(true, Release);
+            while !DONE.load(Acquire) {
+                std::hint::spin_loop();
+            }
+            false
+        });
+    });
+    while !IN_EXPECTED.load(Acquire) {
+        thread::yield_now();
+    }
+
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        unsafe {
+            // a deadlock kills the child instead of hanging the test
+            libc::alarm(5);
+            slc::reinit_after_fork();
+            slc::park(addr, || false);
+            slc::unpark_one(addr);
+            slc::unpark_all(addr);
+            libc::_exit(0);
+        }
+    }
+
+    DONE.store(true, Release);
+    h.join().unwrap();
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+}
@@ -0,0 +1,95 @@
+#![cfg(all(feature = "growable-table", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Release;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, spawn_waiter};
+
+#[test]
+fn waiters_survive_growth() {
+    static EARLY: AtomicBool = AtomicBool::new(false);
+    let early: Vec<_> = (0..8).map(|_| spawn_waiter(&EARLY)).collect();
+    // give the waiters time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(slc::parked_count(addr(&EARLY)), 8);
+
+    // enough threads to grow the table a few times
+    let late: &'static [AtomicBool] = (0..512)
+        .map(|_| AtomicBool::new(false))
+        .collect::<Vec<_>>()
+        .leak();
+    let handles: Vec<_> = late.iter().map(spawn_waiter).collect();
+    thread::sleep(Duration::from_millis(200));
+
+    // the early waiters were moved to the new table, still in order
+    assert_eq!(slc::parked_count(addr(&EARLY)), 8);
+    EARLY.store(true, Release);
+    assert_eq!(slc::unpark_all(addr(&EARLY)).unparked, 8);
+    for h in early {
+        h.join().unwrap();
+    }
+
+    for flag in late {
+        flag.store(true, Release);
+        slc::unpark_one(addr(flag));
+    }
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn unpark_many_while_growing() {
+    let flags: &'static [AtomicBool] = (0..256)
+        .map(|_| AtomicBool::new(false))
+        .collect::<Vec<_>>()
+        .leak();
+    let handles: Vec<_> = flags.iter().map(spawn_waiter).collect();
+    thread::sleep(Duration::from_millis(200));
+    let requests: Vec<_> = flags
+        .iter()
+        .map(|flag| {
+            flag.store(true, Release);
+            (addr(flag), 1)
+        })
+        .collect();
+    let mut woken = slc::unpark_many(&requests);
+    // waiters which weren't asleep yet wake up by themselves
+    for h in handles {
+        h.join().unwrap();
+    }
+    woken += slc::unpark_many(&requests);
+    assert!(woken <= flags.len());
+}
+
+#[test]
+fn reserved_buckets_keep_waiters() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+
+    let buckets = slc::reserve_buckets(1000);
+    assert!(buckets >= 1024 && buckets.is_power_of_two());
+    // it never shrinks
+    assert!(slc::reserve_buckets(1) >= buckets);
+
+    assert_eq!(slc::parked_count(addr(&WAKE_UP)), 1);
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 1);
+    h.join().unwrap();
+}
+
+#[test]
+fn sized_for_the_cores() {
+    static NEVER: AtomicBool = AtomicBool::new(false);
+    // registers the thread, without parking it
+    unsafe { slc::park(addr(&NEVER), || false) };
+    let cores = thread::available_parallelism().unwrap().get();
+    assert!(slc::reserve_buckets(1) * 3 >= cores * 2);
+}
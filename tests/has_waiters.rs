@@ -0,0 +1,82 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, wait_for_waiters};
+
+/// Waiters are counted per bucket, so the waiters of another
+/// test could make `has_waiters` return true for this one.
+fn serial() -> MutexGuard<'static, ()> {
+    static SERIAL: Mutex<()> = Mutex::new(());
+    SERIAL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[test]
+fn sees_parked_threads() {
+    let _serial = serial();
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    assert!(!slc::has_waiters(addr(&WAKE_UP)));
+    let handle = thread::spawn(|| unsafe {
+        slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+    });
+    wait_for_waiters(&WAKE_UP, 1);
+    assert!(slc::has_waiters(addr(&WAKE_UP)));
+
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    handle.join().unwrap();
+    assert!(!slc::has_waiters(addr(&WAKE_UP)));
+}
+
+#[test]
+fn invalid_parks_arent_counted() {
+    let _serial = serial();
+    static FLAG: AtomicBool = AtomicBool::new(false);
+    unsafe { slc::park(addr(&FLAG), || false) };
+    assert!(!slc::has_waiters(addr(&FLAG)));
+}
+
+#[test]
+fn requeued_waiters_move() {
+    let _serial = serial();
+    static FROM: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    let handle = thread::spawn(|| unsafe {
+        slc::park(addr(&FROM), || !TO.load(Acquire));
+    });
+    wait_for_waiters(&FROM, 1);
+    slc::unpark_requeue(addr(&FROM), addr(&TO), 0, 1);
+    assert!(slc::has_waiters(addr(&TO)));
+
+    TO.store(true, Release);
+    slc::unpark_all(addr(&TO));
+    handle.join().unwrap();
+    assert!(!slc::has_waiters(addr(&FROM)));
+    assert!(!slc::has_waiters(addr(&TO)));
+}
+
+#[test]
+fn no_lost_wake_ups() {
+    let _serial = serial();
+    static FLAG: AtomicBool = AtomicBool::new(false);
+    for _ in 0..100 {
+        FLAG.store(false, Release);
+        let handle = thread::spawn(|| unsafe {
+            slc::park(addr(&FLAG), || !FLAG.load(Acquire));
+        });
+        thread::sleep(Duration::from_micros(10));
+        FLAG.store(true, Release);
+        if slc::has_waiters(addr(&FLAG)) {
+            slc::unpark_all(addr(&FLAG));
+        }
+        handle.join().unwrap();
+    }
+}
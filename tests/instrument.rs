@@ -0,0 +1,129 @@
+#![cfg(all(feature = "instrument", feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Release;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core::{self as slc, Event, UnparkKind};
+
+use common::{addr, spawn_waiter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recorded {
+    Park,
+    Wake { timed_out: bool },
+    Invalid,
+    Unpark { kind: UnparkKind, unparked: usize },
+}
+
+static EVENTS: Mutex<Vec<(usize, Recorded)>> = Mutex::new(Vec::new());
+
+fn record(event: &Event) {
+    let recorded = match *event {
+        Event::Park { addr } => (addr.addr(), Recorded::Park),
+        Event::Wake { addr, timed_out } => (addr.addr(), Recorded::Wake { timed_out }),
+        Event::Invalid { addr } => (addr.addr(), Recorded::Invalid),
+        Event::Unpark {
+            addr,
+            kind,
+            unparked,
+        } => (addr.addr(), Recorded::Unpark { kind, unparked }),
+        _ => return,
+    };
+    EVENTS.lock().unwrap().push(recorded);
+}
+
+/// The events on `flag`, tests run in parallel so there may be others.
+fn events(flag: &AtomicBool) -> Vec<Recorded> {
+    slc::set_event_hook(record);
+    EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|&&(event_addr, _)| event_addr == addr(flag).addr())
+        .map(|&(_, event)| event)
+        .collect()
+}
+
+#[test]
+fn park_and_unpark_one() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    slc::set_event_hook(record);
+    let h = spawn_waiter(&WAKE_UP);
+    // give the waiter time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    h.join().unwrap();
+
+    let events = events(&WAKE_UP);
+    assert_eq!(events.len(), 3, "{events:?}");
+    assert_eq!(events[0], Recorded::Park);
+    // the woken thread may report before the unparker
+    assert!(events.contains(&Recorded::Wake { timed_out: false }));
+    assert!(events.contains(&Recorded::Unpark {
+        kind: UnparkKind::One,
+        unparked: 1
+    }));
+}
+
+#[test]
+fn timeout() {
+    static NEVER: AtomicBool = AtomicBool::new(false);
+    slc::set_event_hook(record);
+    let result = unsafe { slc::park_timeout(addr(&NEVER), || true, Duration::from_millis(10)) };
+    assert!(!result.is_unparked());
+    assert_eq!(
+        events(&NEVER),
+        [Recorded::Park, Recorded::Wake { timed_out: true }]
+    );
+}
+
+#[test]
+fn invalid_park_is_not_reported() {
+    static FLAG: AtomicBool = AtomicBool::new(false);
+    slc::set_event_hook(record);
+    unsafe { slc::park(addr(&FLAG), || false) };
+    assert_eq!(events(&FLAG), []);
+}
+
+#[test]
+fn failed_validation() {
+    static FLAG: AtomicBool = AtomicBool::new(false);
+    slc::set_event_hook(record);
+    assert_eq!(
+        unsafe { slc::park_then_validate(addr(&FLAG), || false) },
+        None
+    );
+    assert_eq!(events(&FLAG), [Recorded::Park, Recorded::Invalid]);
+}
+
+#[test]
+fn unparks_report_their_kind() {
+    static A: AtomicBool = AtomicBool::new(false);
+    static B: AtomicBool = AtomicBool::new(false);
+    slc::set_event_hook(record);
+    slc::unpark_all(addr(&A));
+    slc::unpark_some(addr(&A), 2);
+    slc::unpark_some(addr(&A), 0);
+    slc::unpark_one_fair(addr(&A), |_, _| 0);
+    slc::unpark_requeue(addr(&A), addr(&B), 1, 1);
+    slc::unpark_many(&[(addr(&A), 1), (addr(&B), 1)]);
+    let unpark = |kind| Recorded::Unpark { kind, unparked: 0 };
+    assert_eq!(
+        events(&A),
+        [
+            unpark(UnparkKind::All),
+            unpark(UnparkKind::Some),
+            unpark(UnparkKind::Some),
+            unpark(UnparkKind::OneFair),
+            unpark(UnparkKind::Requeue),
+            unpark(UnparkKind::Many),
+        ]
+    );
+    assert_eq!(events(&B), [unpark(UnparkKind::Many)]);
+}
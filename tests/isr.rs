@@ -0,0 +1,36 @@
+#![cfg(all(not(feature = "std"), not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Release;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, spawn_waiter};
+
+// Parked threads poll without `std`, so they perform deferred wake-ups themselves.
+
+#[test]
+fn unpark_one_from_isr() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert!(slc::unpark_one_from_isr(addr(&WAKE_UP)));
+    h.join().unwrap();
+}
+
+#[test]
+fn unpark_all_from_isr() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h1 = spawn_waiter(&WAKE_UP);
+    let h2 = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert!(slc::unpark_all_from_isr(addr(&WAKE_UP)));
+    h1.join().unwrap();
+    h2.join().unwrap();
+}
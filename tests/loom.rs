@@ -28,6 +28,47 @@ mod basic {
         });
     }
 
+    #[test]
+    fn park_then_validate() {
+        loom::model(|| {
+            let arc = Arc::new(AtomicUsize::new(0));
+
+            let h = {
+                let arc = arc.clone();
+                thread::spawn(move || {
+                    arc.store(1, Relaxed);
+                    slc::unpark_one(0 as *const ());
+                })
+            };
+            while arc.load(Relaxed) == 0 {
+                unsafe { slc::park_then_validate(0 as *const (), || arc.load(Relaxed) == 0) };
+            }
+            h.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn unpark_one_with_token() {
+        loom::model(|| {
+            let queued = Arc::new(AtomicUsize::new(0));
+            let h = {
+                let queued = queued.clone();
+                thread::spawn(move || unsafe {
+                    slc::park_with_token(0 as *const (), || {
+                        queued.store(1, Relaxed);
+                        true
+                    })
+                })
+            };
+            // `expected` runs with the bucket locked
+            while queued.load(Relaxed) == 0 {
+                thread::yield_now();
+            }
+            slc::unpark_one_with_token(0 as *const (), 7);
+            assert_eq!(h.join().unwrap(), Some(7));
+        });
+    }
+
     #[test]
     fn unpark_some() {
         loom::model(|| {
@@ -77,6 +118,257 @@ mod basic {
             h2.join().unwrap();
         });
     }
+
+    #[test]
+    fn unpark_requeue() {
+        loom::model(|| {
+            let arc = Arc::new(AtomicUsize::new(0));
+
+            let h = {
+                let arc = arc.clone();
+                thread::spawn(move || unsafe {
+                    slc::park(0 as *const (), || arc.load(Relaxed) == 0)
+                })
+            };
+            arc.store(1, Relaxed);
+            // a parked waiter is moved to `1`, not woken
+            let result = slc::unpark_requeue(0 as *const (), 1 as *const (), 0, 1);
+            assert_eq!(result.unparked, 0);
+            assert_eq!(slc::unpark_all(1 as *const ()).unparked, result.requeued);
+            h.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn park_any() {
+        loom::model(|| {
+            let arc = Arc::new(AtomicUsize::new(0));
+
+            // both addresses are unparked at the same time
+            let handles: Vec<_> = (0..2)
+                .map(|idx| {
+                    let arc = arc.clone();
+                    thread::spawn(move || {
+                        arc.store(1, Relaxed);
+                        slc::unpark_one_with_token(idx as *const (), idx);
+                    })
+                })
+                .collect();
+            let woken = unsafe {
+                slc::park_any(&[0 as *const (), 1 as *const ()], || arc.load(Relaxed) == 0)
+            };
+            if let Some((idx, token)) = woken {
+                assert_eq!(idx, token);
+            }
+            for h in handles {
+                h.join().unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn parking_lot() {
+        loom::model(|| {
+            let arc = Arc::new(AtomicUsize::new(0));
+            let lot = Arc::new(slc::ParkingLot::new());
+
+            let h = {
+                let (arc, lot) = (arc.clone(), lot.clone());
+                thread::spawn(move || unsafe {
+                    lot.park(0 as *const (), || arc.load(Relaxed) == 0)
+                })
+            };
+            arc.store(1, Relaxed);
+            // the global lot has no waiters on `0`
+            assert_eq!(slc::unpark_one(0 as *const ()).unparked, 0);
+            lot.unpark_one(0 as *const ());
+            h.join().unwrap();
+        });
+    }
+}
+
+#[cfg(feature = "async")]
+mod park_async {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use std::task::Wake;
+
+    #[test]
+    fn unpark_one() {
+        loom::model(|| {
+            let arc = Arc::new(AtomicUsize::new(0));
+
+            let h = {
+                let arc = arc.clone();
+                thread::spawn(move || {
+                    arc.store(1, Relaxed);
+                    slc::unpark_one(0 as *const ());
+                })
+            };
+            block_on(unsafe { slc::park_async(0 as *const (), || arc.load(Relaxed) == 0) });
+            h.join().unwrap();
+        });
+    }
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn thread_waker() -> Waker {
+        Waker::from(Arc::new(ThreadWaker(thread::current())))
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = thread_waker();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn drop_races_unpark() {
+        loom::model(|| {
+            let h = thread::spawn(|| slc::unpark_one(0 as *const ()).unparked);
+            {
+                let mut future = pin!(unsafe { slc::park_async(0 as *const (), || true) });
+                let waker = thread_waker();
+                let mut cx = Context::from_waker(&waker);
+                let _ = future.as_mut().poll(&mut cx);
+            }
+            h.join().unwrap();
+            // the dropped future isn't queued anymore
+            assert_eq!(slc::unpark_one(0 as *const ()).unparked, 0);
+        });
+    }
+}
+
+#[cfg(feature = "barrier")]
+mod barrier {
+    use super::*;
+    use loom::cell::UnsafeCell;
+
+    #[test]
+    fn latch() {
+        loom::model(|| {
+            let latch = Arc::new(slc::Latch::new(2));
+            let data = Arc::new(UnsafeCell::new(0));
+            let h = {
+                let (latch, data) = (latch.clone(), data.clone());
+                thread::spawn(move || {
+                    data.with_mut(|x| unsafe { *x = 1 });
+                    latch.count_down();
+                })
+            };
+            latch.count_down();
+            latch.wait();
+            // the write happens-before the latch is released
+            assert_eq!(data.with(|x| unsafe { *x }), 1);
+            h.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn barrier() {
+        loom::model(|| {
+            let barrier = Arc::new(slc::Barrier::new(2));
+            let h = {
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    let first = barrier.wait();
+                    let second = barrier.wait();
+                    (first, second)
+                })
+            };
+            let first = barrier.wait();
+            let second = barrier.wait();
+            let (other_first, other_second) = h.join().unwrap();
+            // one leader per round
+            assert!(first != other_first);
+            assert!(second != other_second);
+        });
+    }
+}
+
+/// `release` closures only need `Relaxed` atomics, the lot synchronizes them.
+mod release {
+    use super::*;
+    use loom::cell::UnsafeCell;
+
+    #[test]
+    fn unpark_one_release() {
+        loom::model(|| {
+            let data = Arc::new(UnsafeCell::new(0));
+            let ready = Arc::new(AtomicUsize::new(0));
+
+            let h = {
+                let (data, ready) = (data.clone(), ready.clone());
+                thread::spawn(move || {
+                    data.with_mut(|x| unsafe { *x = 1 });
+                    slc::unpark_one_release(0 as *const (), || ready.store(1, Relaxed));
+                })
+            };
+            unsafe { slc::park(0 as *const (), || ready.load(Relaxed) == 0) };
+            assert_eq!(data.with(|x| unsafe { *x }), 1);
+            h.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn unpark_one_with() {
+        loom::model(|| {
+            let data = Arc::new(UnsafeCell::new(0));
+            let ready = Arc::new(AtomicUsize::new(0));
+
+            let h = {
+                let (data, ready) = (data.clone(), ready.clone());
+                thread::spawn(move || unsafe {
+                    let token = slc::park_with_token(0 as *const (), || ready.load(Relaxed) == 0);
+                    if token == Some(1) {
+                        // handed off, `ready` wasn't even read
+                        assert_eq!(data.with(|x| *x), 1);
+                    }
+                })
+            };
+            slc::unpark_one_with(0 as *const (), |result| {
+                data.with_mut(|x| unsafe { *x = 1 });
+                ready.store(1, Relaxed);
+                result.unparked
+            });
+            h.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn unpark_all_release() {
+        loom::model(|| {
+            let data = Arc::new(UnsafeCell::new(0));
+            let ready = Arc::new(AtomicUsize::new(0));
+
+            let h = {
+                let (data, ready) = (data.clone(), ready.clone());
+                thread::spawn(move || unsafe {
+                    slc::park(0 as *const (), || ready.load(Relaxed) == 0);
+                    assert_eq!(data.with(|x| *x), 1);
+                })
+            };
+            data.with_mut(|x| unsafe { *x = 1 });
+            slc::unpark_all_release(0 as *const (), || ready.store(1, Relaxed));
+            unsafe { slc::park(0 as *const (), || ready.load(Relaxed) == 0) };
+            assert_eq!(data.with(|x| unsafe { *x }), 1);
+            h.join().unwrap();
+        });
+    }
 }
 
 fn spawn_waiter(addr: usize, arc: Arc<AtomicUsize>) -> thread::JoinHandle<()> {
@@ -270,9 +562,9 @@ impl MagicParkToken {
     /// thread or move to the new one.
     ///
     /// - In the case it chooses to continue on the main thread, it will be guaranteed
-    /// to not let the new thread park, testing no parking.
+    ///   to not let the new thread park, testing no parking.
     /// - In the case that execution is moved to the new thread means it gets to a loom
-    /// mutex, where it can choose:
+    ///   mutex, where it can choose:
     /// 1. move to the main thread, not parking once again.
     /// 2. continue on the new thread, guaranteeing it will park.
     ///
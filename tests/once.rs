@@ -0,0 +1,66 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+use core::sync::atomic::{AtomicBool, AtomicUsize};
+use core::sync::atomic::Ordering::Relaxed;
+use std::panic;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core::Once;
+
+#[test]
+fn runs_once() {
+    let once = Once::new();
+    let calls = AtomicUsize::new(0);
+    thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                once.call_once(|| {
+                    // keep the others waiting for a bit
+                    thread::sleep(Duration::from_millis(20));
+                    calls.fetch_add(1, Relaxed);
+                });
+                // nobody returns before it's done
+                assert_eq!(calls.load(Relaxed), 1);
+            });
+        }
+    });
+    assert!(once.is_completed());
+}
+
+#[test]
+fn panics_poison_it() {
+    let once = Once::new();
+    let result = panic::catch_unwind(|| once.call_once(|| panic!("init failed")));
+    assert!(result.is_err());
+    assert!(!once.is_completed());
+    // `call_once` refuses to run again
+    assert!(panic::catch_unwind(|| once.call_once(|| {})).is_err());
+
+    let mut poisoned = false;
+    once.call_once_force(|state| poisoned = state.is_poisoned());
+    assert!(poisoned);
+    assert!(once.is_completed());
+    // and it's no longer poisoned
+    once.call_once(|| unreachable!());
+}
+
+#[test]
+fn waiters_see_the_poisoning() {
+    let once = Once::new();
+    let running = AtomicBool::new(false);
+    thread::scope(|s| {
+        let runner = s.spawn(|| {
+            once.call_once(|| {
+                running.store(true, Relaxed);
+                thread::sleep(Duration::from_millis(50));
+                panic!("init failed");
+            })
+        });
+        while !running.load(Relaxed) {
+            thread::yield_now();
+        }
+        assert!(panic::catch_unwind(|| once.call_once(|| {})).is_err());
+        assert!(runner.join().is_err());
+    });
+}
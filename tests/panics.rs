@@ -0,0 +1,152 @@
+#![cfg(all(feature = "std", not(feature = "abort-on-panic"), not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::panic::catch_unwind;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, spawn_waiter, wait_for_waiters};
+
+fn panic_in_expected(addr: *const ()) {
+    let addr = addr as usize;
+    let res = catch_unwind(|| unsafe {
+        slc::park(addr as *const (), || panic!("`expected` panicked"));
+    });
+    assert!(res.is_err());
+}
+
+fn assert_uncounted(addr: *const ()) {
+    // other tests may park on addresses sharing the count for a while,
+    // but a thread counted by the panicking park would stay forever
+    let mut tries = 0;
+    while slc::has_waiters(addr) {
+        tries += 1;
+        assert!(tries < 100, "the panicking thread is still counted");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn expected_panic_doesnt_poison() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    panic_in_expected(addr(&WAKE_UP));
+    // the bucket is still usable
+    unsafe { slc::park(addr(&WAKE_UP), || false) };
+    let h = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    h.join().unwrap();
+}
+
+#[test]
+fn expected_panic_keeps_waiters() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    panic_in_expected(addr(&WAKE_UP));
+    // the panicking thread isn't queued, so this wakes the waiter
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    h.join().unwrap();
+}
+
+#[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+#[test]
+fn expected_panic_in_park_timeout() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let res = catch_unwind(|| unsafe {
+        slc::park_timeout(
+            addr(&WAKE_UP),
+            || panic!("`expected` panicked"),
+            Duration::from_secs(1),
+        )
+    });
+    assert!(res.is_err());
+    let result = unsafe { slc::park_timeout(addr(&WAKE_UP), || true, Duration::from_millis(1)) };
+    assert_eq!(result, slc::ParkResult::TimedOut);
+}
+
+#[test]
+fn callback_panic_still_wakes() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = thread::spawn(|| unsafe {
+        slc::park_with_token(addr(&WAKE_UP), || !WAKE_UP.load(Acquire))
+    });
+    wait_for_waiters(&WAKE_UP, 1);
+    WAKE_UP.store(true, Release);
+    let res =
+        catch_unwind(|| slc::unpark_one_with(addr(&WAKE_UP), |_| panic!("callback panicked")));
+    assert!(res.is_err());
+    assert_eq!(h.join().unwrap(), Some(slc::DEFAULT_UNPARK_TOKEN));
+    // the bucket is still usable
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 0);
+}
+
+#[test]
+fn expected_panic_isnt_counted() {
+    static ADDR: AtomicBool = AtomicBool::new(false);
+    panic_in_expected(addr(&ADDR));
+    assert_uncounted(addr(&ADDR));
+}
+
+#[test]
+fn before_sleep_panic_waits_for_unparker() {
+    static ADDR: AtomicBool = AtomicBool::new(false);
+    static UNLINKED: AtomicBool = AtomicBool::new(false);
+    let unparker = thread::spawn(|| {
+        wait_for_waiters(&ADDR, 1);
+        slc::unpark_one_with(addr(&ADDR), |_| {
+            UNLINKED.store(true, Release);
+            // give the panic time to unwind, if it doesn't wait
+            thread::sleep(Duration::from_millis(20));
+            slc::DEFAULT_UNPARK_TOKEN
+        })
+    });
+    let res = catch_unwind(|| unsafe {
+        slc::park_before_sleep(
+            addr(&ADDR),
+            || true,
+            || {
+                while !UNLINKED.load(Acquire) {
+                    thread::yield_now();
+                }
+                panic!("`before_sleep` panicked");
+            },
+        )
+    });
+    assert!(res.is_err());
+    assert_eq!(unparker.join().unwrap().unparked, 1);
+    assert_uncounted(addr(&ADDR));
+}
+
+#[test]
+fn validate_panic_waits_for_unparker() {
+    static ADDR: AtomicBool = AtomicBool::new(false);
+    static UNLINKED: AtomicBool = AtomicBool::new(false);
+    let unparker = thread::spawn(|| {
+        wait_for_waiters(&ADDR, 1);
+        slc::unpark_one_with(addr(&ADDR), |_| {
+            UNLINKED.store(true, Release);
+            // give the panic time to unwind, if it doesn't wait
+            thread::sleep(Duration::from_millis(20));
+            slc::DEFAULT_UNPARK_TOKEN
+        })
+    });
+    let res = catch_unwind(|| unsafe {
+        slc::park_then_validate(addr(&ADDR), || {
+            while !UNLINKED.load(Acquire) {
+                thread::yield_now();
+            }
+            panic!("`validate` panicked");
+        })
+    });
+    assert!(res.is_err());
+    assert_eq!(unparker.join().unwrap().unparked, 1);
+    assert_uncounted(addr(&ADDR));
+}
@@ -0,0 +1,44 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+use core::ptr::NonNull;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+
+use sparking_lot_core::{self as slc, AsParkAddr};
+
+#[test]
+fn every_form_is_the_same_address() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let addr = (&WAKE_UP as *const AtomicBool).park_addr();
+    assert_eq!((&WAKE_UP as *const AtomicBool).cast_mut().park_addr(), addr);
+    assert_eq!(NonNull::from(&WAKE_UP).park_addr(), addr);
+    assert_eq!((&WAKE_UP).park_addr(), addr);
+    assert_eq!(addr.park_addr(), addr);
+
+    // parked through an integer, woken through a reference
+    let waiter = thread::spawn(move || unsafe { slc::park(addr, || !WAKE_UP.load(Acquire)) });
+    while slc::parked_count(NonNull::from(&WAKE_UP)) == 0 {
+        thread::yield_now();
+    }
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one(&WAKE_UP).unparked, 1);
+    waiter.join().unwrap();
+}
+
+#[test]
+fn references_to_integers_use_their_address() {
+    static VALUE: usize = 12;
+    let addr = (&VALUE as *const usize).park_addr();
+    assert_ne!(addr, VALUE);
+    assert_eq!(AsParkAddr::park_addr(&&VALUE), addr);
+
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiter = thread::spawn(|| slc::park_ref(&VALUE, || !WAKE_UP.load(Acquire)));
+    while slc::parked_count(addr) == 0 {
+        thread::yield_now();
+    }
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_all_ref(&VALUE).unparked, 1);
+    waiter.join().unwrap();
+}
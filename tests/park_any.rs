@@ -0,0 +1,124 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+
+use sparking_lot_core as slc;
+
+use common::{addr, wait_for_waiters};
+
+/// Parks a thread on both flags, and returns once it's queued.
+fn spawn_waiter(
+    first: &'static AtomicBool,
+    second: &'static AtomicBool,
+) -> thread::JoinHandle<Option<(usize, usize)>> {
+    let handle = thread::spawn(move || unsafe {
+        slc::park_any(&[addr(first), addr(second)], || {
+            !first.load(Acquire) && !second.load(Acquire)
+        })
+    });
+    wait_for_waiters(second, 1);
+    handle
+}
+
+#[test]
+fn woken_through_either_address() {
+    static FIRST: AtomicBool = AtomicBool::new(false);
+    static SECOND: AtomicBool = AtomicBool::new(false);
+    let waiter = spawn_waiter(&FIRST, &SECOND);
+    assert_eq!(slc::parked_count(addr(&FIRST)), 1);
+    SECOND.store(true, Release);
+    assert_eq!(slc::unpark_one_with_token(addr(&SECOND), 7).unparked, 1);
+    assert_eq!(waiter.join().unwrap(), Some((1, 7)));
+    // it's no longer parked on the other one
+    assert_eq!(slc::parked_count(addr(&FIRST)), 0);
+    assert_eq!(slc::unpark_one(addr(&FIRST)).unparked, 0);
+}
+
+#[test]
+fn invalid_park() {
+    static FIRST: AtomicBool = AtomicBool::new(false);
+    static SECOND: AtomicBool = AtomicBool::new(false);
+    let result = unsafe { slc::park_any(&[addr(&FIRST), addr(&SECOND)], || false) };
+    assert_eq!(result, None);
+    assert_eq!(slc::parked_count(addr(&FIRST)), 0);
+    assert_eq!(slc::parked_count(addr(&SECOND)), 0);
+}
+
+#[test]
+fn racing_unparkers() {
+    static FIRST: AtomicBool = AtomicBool::new(false);
+    static SECOND: AtomicBool = AtomicBool::new(false);
+    for _ in 0..100 {
+        FIRST.store(false, Release);
+        SECOND.store(false, Release);
+        let waiter = spawn_waiter(&FIRST, &SECOND);
+        FIRST.store(true, Release);
+        SECOND.store(true, Release);
+        let other = thread::spawn(|| slc::unpark_one_with_token(addr(&SECOND), 2));
+        let first = slc::unpark_one_with_token(addr(&FIRST), 1);
+        let second = other.join().unwrap();
+        // only the one which woke it counts it
+        assert_eq!(first.unparked + second.unparked, 1);
+        match waiter.join().unwrap() {
+            Some((0, 1)) => assert_eq!(first.unparked, 1),
+            Some((1, 2)) => assert_eq!(second.unparked, 1),
+            result => panic!("unexpected wake-up: {result:?}"),
+        }
+        assert_eq!(slc::parked_count(addr(&FIRST)), 0);
+        assert_eq!(slc::parked_count(addr(&SECOND)), 0);
+    }
+}
+
+#[test]
+fn losing_unparker_wakes_the_next_waiter() {
+    static FIRST: AtomicBool = AtomicBool::new(false);
+    static SECOND: AtomicBool = AtomicBool::new(false);
+    for _ in 0..100 {
+        FIRST.store(false, Release);
+        SECOND.store(false, Release);
+        let waiter = spawn_waiter(&FIRST, &SECOND);
+        let plain = thread::spawn(|| unsafe { slc::park_with_token(addr(&SECOND), || true) });
+        wait_for_waiters(&SECOND, 2);
+        FIRST.store(true, Release);
+        SECOND.store(true, Release);
+        let other = thread::spawn(|| slc::unpark_one_with_token(addr(&SECOND), 2));
+        let first = slc::unpark_one_with_token(addr(&FIRST), 1);
+        let second = other.join().unwrap();
+        match waiter.join().unwrap() {
+            Some((0, 1)) => {
+                assert_eq!(first.unparked, 1);
+                // the unparker of `SECOND` skipped it and took the next waiter
+                assert_eq!(second.unparked, 1);
+            }
+            Some((1, 2)) => {
+                assert_eq!(first.unparked, 0);
+                assert_eq!(second.unparked, 1);
+                assert_eq!(slc::unpark_one_with_token(addr(&SECOND), 2).unparked, 1);
+            }
+            result => panic!("unexpected wake-up: {result:?}"),
+        }
+        assert_eq!(slc::parked_count(addr(&FIRST)), 0);
+        assert_eq!(slc::parked_count(addr(&SECOND)), 0);
+        assert_eq!(plain.join().unwrap(), Some(2));
+    }
+}
+
+#[test]
+fn follows_requeued_waiters() {
+    static FIRST: AtomicBool = AtomicBool::new(false);
+    static SECOND: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    let waiter = spawn_waiter(&FIRST, &SECOND);
+    assert_eq!(
+        slc::unpark_requeue(addr(&FIRST), addr(&TO), 0, usize::MAX).requeued,
+        1
+    );
+    FIRST.store(true, Release);
+    assert_eq!(slc::unpark_one_with_token(addr(&TO), 3).unparked, 1);
+    assert_eq!(waiter.join().unwrap(), Some((0, 3)));
+    assert_eq!(slc::parked_count(addr(&SECOND)), 0);
+}
@@ -0,0 +1,172 @@
+#![cfg(all(feature = "async", feature = "std", not(loom)))]
+
+mod common;
+
+use core::future::Future;
+use core::pin::pin;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use core::task::{Context, Poll, Waker};
+use std::sync::{Arc, Mutex};
+use std::task::Wake;
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::addr;
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+fn spawn_task(wake_up: &'static AtomicBool) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        block_on(unsafe { slc::park_async(addr(wake_up), || !wake_up.load(Acquire)) })
+    })
+}
+
+#[test]
+fn not_expected() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(true);
+    block_on(unsafe { slc::park_async(addr(&WAKE_UP), || !WAKE_UP.load(Acquire)) });
+}
+
+#[test]
+fn unpark_one() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = spawn_task(&WAKE_UP);
+    // give the task time to actually be queued
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 1);
+    h.join().unwrap();
+}
+
+#[test]
+fn threads_and_tasks_share_a_queue() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    static ORDER: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+    let thread = thread::spawn(|| {
+        unsafe { slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire)) };
+        ORDER.lock().unwrap().push("thread");
+    });
+    thread::sleep(Duration::from_millis(50));
+    let task = thread::spawn(|| {
+        block_on(unsafe { slc::park_async(addr(&WAKE_UP), || !WAKE_UP.load(Acquire)) });
+        ORDER.lock().unwrap().push("task");
+    });
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 1);
+    thread.join().unwrap();
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 1);
+    task.join().unwrap();
+    assert_eq!(*ORDER.lock().unwrap(), ["thread", "task"]);
+}
+
+#[test]
+fn unpark_all_wakes_both() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let task = spawn_task(&WAKE_UP);
+    let thread = thread::spawn(|| unsafe {
+        slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+    });
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_all(addr(&WAKE_UP)).unparked, 2);
+    task.join().unwrap();
+    thread.join().unwrap();
+}
+
+#[test]
+fn dropping_dequeues() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    {
+        let mut future = pin!(unsafe { slc::park_async(addr(&WAKE_UP), || true) });
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+    }
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 0);
+}
+
+#[test]
+fn futures_can_be_named() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    // like the future of an async primitive which parks
+    struct Wait {
+        park: slc::ParkFuture<fn() -> bool>,
+    }
+    fn expected() -> bool {
+        !WAKE_UP.load(Acquire)
+    }
+    let wait = Wait {
+        park: unsafe { slc::park_async(addr(&WAKE_UP), expected as fn() -> bool) },
+    };
+    // not registered until it's polled, so it can still be moved to another thread
+    let h = thread::spawn(move || block_on(wait.park));
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 1);
+    h.join().unwrap();
+}
+
+#[test]
+#[should_panic = "polled after completion"]
+fn polling_after_completion_panics() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(true);
+    let mut future = pin!(unsafe { slc::park_async(addr(&WAKE_UP), || false) });
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    assert!(future.as_mut().poll(&mut cx).is_ready());
+    let _ = future.as_mut().poll(&mut cx);
+}
+
+#[test]
+fn requeued_tasks_are_woken() {
+    static FROM: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    let h = spawn_task(&FROM);
+    thread::sleep(Duration::from_millis(50));
+    FROM.store(true, Release);
+    assert_eq!(
+        slc::unpark_requeue(addr(&FROM), addr(&TO), 0, 1).requeued,
+        1
+    );
+    assert_eq!(slc::unpark_all(addr(&TO)).unparked, 1);
+    h.join().unwrap();
+}
+
+#[cfg(not(feature = "abort-on-panic"))]
+#[test]
+fn expected_panic_doesnt_poison() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let res = std::panic::catch_unwind(|| {
+        block_on(unsafe { slc::park_async(addr(&WAKE_UP), || panic!("`expected` panicked")) })
+    });
+    assert!(res.is_err());
+    // the task wasn't queued and the bucket is still usable
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 0);
+    let h = spawn_task(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 1);
+    h.join().unwrap();
+}
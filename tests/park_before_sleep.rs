@@ -0,0 +1,57 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use std::sync::Mutex;
+use std::thread;
+
+use sparking_lot_core as slc;
+
+use common::addr;
+
+#[test]
+fn runs_once_queued() {
+    static ADDR: AtomicBool = AtomicBool::new(false);
+    // the thread is already queued, so it wakes itself
+    let token = unsafe {
+        slc::park_before_sleep(
+            addr(&ADDR),
+            || true,
+            || {
+                assert_eq!(slc::parked_count(addr(&ADDR)), 1);
+                assert_eq!(slc::unpark_one_with_token(addr(&ADDR), 3).unparked, 1);
+            },
+        )
+    };
+    assert_eq!(token, Some(3));
+}
+
+#[test]
+fn skipped_if_invalid() {
+    static ADDR: AtomicBool = AtomicBool::new(false);
+    let token = unsafe { slc::park_before_sleep(addr(&ADDR), || false, || unreachable!()) };
+    assert_eq!(token, None);
+}
+
+#[test]
+fn releasing_a_lock_misses_no_notifications() {
+    static CONDVAR: AtomicBool = AtomicBool::new(false);
+    static COUNT: Mutex<usize> = Mutex::new(0);
+    const ROUNDS: usize = 1000;
+    let waiter = thread::spawn(|| {
+        let mut count = COUNT.lock().unwrap();
+        for round in 0..ROUNDS {
+            while *count == round {
+                // SAFETY: only this test parks on `CONDVAR`, and `expected` doesn't call this crate
+                unsafe { slc::park_before_sleep(addr(&CONDVAR), || true, || drop(count)) };
+                count = COUNT.lock().unwrap();
+            }
+        }
+    });
+    for _ in 0..ROUNDS {
+        *COUNT.lock().unwrap() += 1;
+        slc::unpark_one(addr(&CONDVAR));
+    }
+    waiter.join().unwrap();
+}
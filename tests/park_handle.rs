@@ -0,0 +1,68 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::mpsc;
+use std::thread;
+
+use sparking_lot_core::{self as slc, ParkHandle};
+
+use common::addr;
+
+/// Parks a thread on `wake_up`, and returns its handle once it's queued.
+fn spawn_waiter(wake_up: &'static AtomicBool) -> (ParkHandle, thread::JoinHandle<Option<usize>>) {
+    let (tx, rx) = mpsc::sync_channel(1);
+    let handle = thread::spawn(move || unsafe {
+        slc::park_with_handle(
+            addr(wake_up),
+            || !wake_up.load(Acquire),
+            |handle| tx.send(handle).unwrap(),
+        )
+    });
+    (rx.recv().unwrap(), handle)
+}
+
+#[test]
+fn wakes_the_chosen_thread() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiters: Vec<_> = (0..3).map(|_| spawn_waiter(&WAKE_UP)).collect();
+    WAKE_UP.store(true, Release);
+    let mut waiters = waiters.into_iter();
+    let (_, first) = waiters.next().unwrap();
+    let (middle, second) = waiters.next().unwrap();
+    let (_, third) = waiters.next().unwrap();
+
+    assert!(unsafe { slc::unpark_handle(middle, 7) });
+    assert_eq!(second.join().unwrap(), Some(7));
+    // the others are still queued (in which order `random-wake` picks them is up to it)
+    assert_eq!(slc::parked_count(addr(&WAKE_UP)), 2);
+    assert!(slc::unpark_one_with_token(addr(&WAKE_UP), 1).unparked != 0);
+    assert!(slc::unpark_one_with_token(addr(&WAKE_UP), 2).unparked != 0);
+    let mut tokens = [first.join().unwrap(), third.join().unwrap()];
+    tokens.sort();
+    assert_eq!(tokens, [Some(1), Some(2)]);
+}
+
+#[test]
+fn follows_requeued_threads() {
+    static FROM: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    let (handle, waiter) = spawn_waiter(&FROM);
+    FROM.store(true, Release);
+    assert_eq!(
+        slc::unpark_requeue(addr(&FROM), addr(&TO), 0, usize::MAX).requeued,
+        1
+    );
+    assert!(unsafe { slc::unpark_handle(handle, 3) });
+    assert_eq!(waiter.join().unwrap(), Some(3));
+    assert_eq!(slc::parked_count(addr(&TO)), 0);
+}
+
+#[test]
+fn invalid_park_never_calls_queued() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(true);
+    let token = unsafe { slc::park_with_handle(addr(&WAKE_UP), || false, |_| unreachable!()) };
+    assert_eq!(token, None);
+}
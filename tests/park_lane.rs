@@ -0,0 +1,58 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, result};
+
+fn spawn_waiter(wake_up: &'static AtomicBool, lane: u8) -> thread::JoinHandle<()> {
+    thread::spawn(move || unsafe {
+        slc::park_lane(addr(wake_up), lane, || !wake_up.load(Acquire));
+    })
+}
+
+#[test]
+fn lanes_are_separate() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let readers = [spawn_waiter(&WAKE_UP, 0), spawn_waiter(&WAKE_UP, 0)];
+    let writer = spawn_waiter(&WAKE_UP, 1);
+    // give the waiters time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one_lane(addr(&WAKE_UP), 2), result(0, false));
+    assert_eq!(slc::unpark_one_lane(addr(&WAKE_UP), 1), result(1, false));
+    writer.join().unwrap();
+    assert_eq!(slc::unpark_all_lane(addr(&WAKE_UP), 0), result(2, false));
+    for reader in readers {
+        reader.join().unwrap();
+    }
+}
+
+#[test]
+fn separate_from_unlaned() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let laned = spawn_waiter(&WAKE_UP, 0);
+    let versioned = thread::spawn(|| unsafe {
+        slc::park_versioned(addr(&WAKE_UP), 0, || !WAKE_UP.load(Acquire));
+    });
+    let plain = thread::spawn(|| unsafe {
+        slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+    });
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_all(addr(&WAKE_UP)), result(1, false));
+    plain.join().unwrap();
+    assert_eq!(
+        slc::unpark_all_versioned(addr(&WAKE_UP), 0),
+        result(1, false)
+    );
+    versioned.join().unwrap();
+    assert_eq!(slc::unpark_all_lane(addr(&WAKE_UP), 0), result(1, false));
+    laned.join().unwrap();
+}
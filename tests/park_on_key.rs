@@ -0,0 +1,59 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::result;
+
+fn spawn_waiter(key: u64, wake_up: &'static AtomicBool) -> thread::JoinHandle<()> {
+    thread::spawn(move || unsafe {
+        slc::park_on_key(key, || !wake_up.load(Acquire));
+    })
+}
+
+#[test]
+fn unpark_one_key() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h1 = spawn_waiter(1, &WAKE_UP);
+    let h2 = spawn_waiter(1, &WAKE_UP);
+    // give the waiters time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one_key(1), result(1, true));
+    assert_eq!(slc::unpark_one_key(1), result(1, false));
+    h1.join().unwrap();
+    h2.join().unwrap();
+}
+
+#[test]
+fn keys_and_addresses_dont_collide() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let key = &WAKE_UP as *const _ as usize as u64;
+    let h = spawn_waiter(key, &WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    let addr = key as usize as *const ();
+    assert_eq!(slc::parked_count(addr), 0);
+    assert_eq!(slc::unpark_all(addr), result(0, false));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_all_key(key), result(1, false));
+    h.join().unwrap();
+}
+
+#[test]
+fn high_bits_are_kept() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    const LOW: u64 = 2;
+    const HIGH: u64 = LOW | 1 << 40;
+    let h = spawn_waiter(HIGH, &WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_all_key(LOW), result(0, false));
+    assert_eq!(slc::unpark_all_key(HIGH), result(1, false));
+    h.join().unwrap();
+}
@@ -0,0 +1,66 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+use core::sync::atomic::{AtomicBool, AtomicU32};
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+
+use sparking_lot_core as slc;
+
+#[test]
+fn same_address_as_pointers() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiter = thread::spawn(|| slc::park_ref(&WAKE_UP, || !WAKE_UP.load(Acquire)));
+    let addr = &WAKE_UP as *const _ as *const ();
+    while slc::parked_count(addr) == 0 {
+        thread::yield_now();
+    }
+    WAKE_UP.store(true, Release);
+    // the pointer based functions see the same waiter
+    assert_eq!(slc::unpark_one(addr).unparked, 1);
+    waiter.join().unwrap();
+}
+
+#[test]
+fn unsized_values() {
+    static FLAGS: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+    let flags: &'static [AtomicBool] = &FLAGS;
+    let waiter = thread::spawn(move || slc::park_ref(flags, || !flags[1].load(Acquire)));
+    while slc::parked_count(&FLAGS as *const _ as *const ()) == 0 {
+        thread::yield_now();
+    }
+    FLAGS[1].store(true, Release);
+    // a slice parks on the address of its first element
+    assert_eq!(slc::unpark_all_ref(&FLAGS[0]).unparked, 1);
+    waiter.join().unwrap();
+}
+
+#[test]
+fn nobody_parked() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    assert_eq!(slc::unpark_one_ref(&WAKE_UP).unparked, 0);
+    assert_eq!(slc::unpark_some_ref(&WAKE_UP, 2).unparked, 0);
+    assert_eq!(slc::unpark_all_ref(&WAKE_UP).unparked, 0);
+}
+
+#[test]
+fn park_if_eq_changed() {
+    static STATE: AtomicU32 = AtomicU32::new(1);
+    // would block forever if it parked
+    slc::park_if_eq(&STATE, 0);
+}
+
+#[test]
+fn park_if_eq_woken() {
+    static STATE: AtomicU32 = AtomicU32::new(0);
+    let waiter = thread::spawn(|| {
+        while STATE.load(Acquire) == 0 {
+            slc::park_if_eq(&STATE, 0);
+        }
+    });
+    while !slc::has_waiters(&STATE) {
+        thread::yield_now();
+    }
+    STATE.store(1, Release);
+    assert_eq!(slc::unpark_all_ref(&STATE).unparked, 1);
+    waiter.join().unwrap();
+}
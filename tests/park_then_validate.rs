@@ -0,0 +1,57 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+
+use sparking_lot_core as slc;
+
+fn addr(value: &'static AtomicUsize) -> *const () {
+    value as *const _ as *const _
+}
+
+#[test]
+fn invalid_unqueues() {
+    static ADDR: AtomicUsize = AtomicUsize::new(0);
+    let token = unsafe {
+        slc::park_then_validate(addr(&ADDR), || {
+            // already queued, without the bucket locked
+            assert_eq!(slc::parked_count(addr(&ADDR)), 1);
+            false
+        })
+    };
+    assert_eq!(token, None);
+    assert_eq!(slc::parked_count(addr(&ADDR)), 0);
+    assert!(!slc::has_waiters(addr(&ADDR)));
+}
+
+#[test]
+fn wake_up_wins() {
+    static ADDR: AtomicUsize = AtomicUsize::new(0);
+    let token = unsafe {
+        slc::park_then_validate(addr(&ADDR), || {
+            assert_eq!(slc::unpark_one_with_token(addr(&ADDR), 5).unparked, 1);
+            false
+        })
+    };
+    assert_eq!(token, Some(5));
+}
+
+#[test]
+fn misses_no_wake_ups() {
+    static ROUND: AtomicUsize = AtomicUsize::new(0);
+    const ROUNDS: usize = 1000;
+    let waiter = thread::spawn(|| {
+        for round in 0..ROUNDS {
+            while ROUND.load(Acquire) == round {
+                // SAFETY: only this test parks on `ROUND`, and `validate` doesn't call this crate
+                unsafe { slc::park_then_validate(addr(&ROUND), || ROUND.load(Acquire) == round) };
+            }
+        }
+    });
+    for round in 1..=ROUNDS {
+        ROUND.store(round, Release);
+        slc::unpark_one(addr(&ROUND));
+    }
+    waiter.join().unwrap();
+}
@@ -0,0 +1,169 @@
+#![cfg(all(
+    feature = "std",
+    not(any(loom, feature = "freertos", feature = "zephyr"))
+))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sparking_lot_core::{self as slc, ParkResult};
+
+use common::addr;
+
+fn park_timeout(wake_up: &'static AtomicBool, timeout: Duration) -> ParkResult {
+    unsafe { slc::park_timeout(addr(wake_up), || !wake_up.load(Acquire), timeout) }
+}
+
+#[test]
+fn times_out() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let start = Instant::now();
+    assert_eq!(
+        park_timeout(&WAKE_UP, Duration::from_millis(50)),
+        ParkResult::TimedOut
+    );
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    // the thread unlinked itself
+    assert_eq!(slc::unpark_some(addr(&WAKE_UP), 1).unparked, 0);
+}
+
+#[test]
+fn invalid() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(true);
+    assert_eq!(
+        park_timeout(&WAKE_UP, Duration::from_secs(60)),
+        ParkResult::Invalid
+    );
+}
+
+#[test]
+fn unparked_before_timeout() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = thread::spawn(|| park_timeout(&WAKE_UP, Duration::from_secs(60)));
+    // give the waiter time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    assert_eq!(
+        h.join().unwrap(),
+        ParkResult::Unparked(slc::DEFAULT_UNPARK_TOKEN)
+    );
+}
+
+#[test]
+fn huge_timeout() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = thread::spawn(|| park_timeout(&WAKE_UP, Duration::MAX));
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    assert_eq!(
+        h.join().unwrap(),
+        ParkResult::Unparked(slc::DEFAULT_UNPARK_TOKEN)
+    );
+}
+
+#[test]
+fn timed_out_thread_does_not_take_wake_ups() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let timed = thread::spawn(|| park_timeout(&WAKE_UP, Duration::from_millis(10)));
+    let untimed = thread::spawn(|| unsafe {
+        slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+    });
+    assert_eq!(timed.join().unwrap(), ParkResult::TimedOut);
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    untimed.join().unwrap();
+}
+
+#[test]
+fn no_lost_wake_ups_when_racing_the_timeout() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    for i in 0..200 {
+        let h = thread::spawn(|| unsafe {
+            slc::park_timeout(addr(&WAKE_UP), || true, Duration::from_micros(500))
+        });
+        thread::sleep(Duration::from_micros(i % 3 * 250));
+        let woken = slc::unpark_some(addr(&WAKE_UP), 1).unparked;
+        let result = h.join().unwrap();
+        assert_eq!(woken == 1, result.is_unparked(), "{result:?}");
+    }
+}
+
+#[test]
+fn deadline_is_kept_across_retries() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let deadline = Instant::now() + Duration::from_millis(50);
+    // parking again with the same deadline doesn't wait any longer
+    for _ in 0..3 {
+        let result =
+            unsafe { slc::park_until(addr(&WAKE_UP), || !WAKE_UP.load(Acquire), deadline) };
+        assert_eq!(result, ParkResult::TimedOut);
+    }
+    assert!(Instant::now() >= deadline);
+    assert!(deadline.elapsed() < Duration::from_secs(1));
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)).unparked, 0);
+}
+
+#[test]
+fn unparked_before_deadline() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let h = thread::spawn(move || unsafe {
+        slc::park_until(addr(&WAKE_UP), || !WAKE_UP.load(Acquire), deadline)
+    });
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    assert_eq!(
+        h.join().unwrap(),
+        ParkResult::Unparked(slc::DEFAULT_UNPARK_TOKEN)
+    );
+}
+
+fn park_until_with(wake_up: &'static AtomicBool, deadline: Instant) -> (ParkResult, Option<bool>) {
+    let mut was_last = None;
+    let result = unsafe {
+        slc::park_until_with(
+            addr(wake_up),
+            || !wake_up.load(Acquire),
+            deadline,
+            |timed_out_on, last| {
+                assert_eq!(timed_out_on, addr(wake_up));
+                was_last = Some(last);
+            },
+        )
+    };
+    (result, was_last)
+}
+
+#[test]
+fn timed_out_reports_the_last_waiter() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let now = Instant::now();
+    let later = thread::spawn(move || park_until_with(&WAKE_UP, now + Duration::from_millis(200)));
+    // give the other waiter time to park
+    thread::sleep(Duration::from_millis(50));
+    let first = park_until_with(&WAKE_UP, now + Duration::from_millis(100));
+    assert_eq!(first, (ParkResult::TimedOut, Some(false)));
+    assert_eq!(later.join().unwrap(), (ParkResult::TimedOut, Some(true)));
+}
+
+#[test]
+fn timed_out_isnt_called_when_unparked() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let h = thread::spawn(move || park_until_with(&WAKE_UP, deadline));
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    assert_eq!(
+        h.join().unwrap(),
+        (ParkResult::Unparked(slc::DEFAULT_UNPARK_TOKEN), None)
+    );
+}
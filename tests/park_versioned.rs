@@ -0,0 +1,58 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, result};
+
+fn spawn_waiter(wake_up: &'static AtomicBool, generation: u32) -> thread::JoinHandle<()> {
+    thread::spawn(move || unsafe {
+        slc::park_versioned(addr(wake_up), generation, || !wake_up.load(Acquire));
+    })
+}
+
+#[test]
+fn stale_generations_are_ignored() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = spawn_waiter(&WAKE_UP, 2);
+    // give the waiter time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(
+        slc::unpark_one_versioned(addr(&WAKE_UP), 1),
+        result(0, false)
+    );
+    assert_eq!(
+        slc::unpark_all_versioned(addr(&WAKE_UP), 3),
+        result(0, false)
+    );
+    WAKE_UP.store(true, Release);
+    assert_eq!(
+        slc::unpark_one_versioned(addr(&WAKE_UP), 2),
+        result(1, false)
+    );
+    h.join().unwrap();
+}
+
+#[test]
+fn separate_from_unversioned() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let versioned = spawn_waiter(&WAKE_UP, 0);
+    let plain = thread::spawn(|| unsafe {
+        slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+    });
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_all(addr(&WAKE_UP)), result(1, false));
+    plain.join().unwrap();
+    assert_eq!(
+        slc::unpark_all_versioned(addr(&WAKE_UP), 0),
+        result(1, false)
+    );
+    versioned.join().unwrap();
+}
@@ -0,0 +1,38 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Release;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, spawn_waiter};
+
+#[test]
+fn counts_waiters() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    static OTHER: AtomicBool = AtomicBool::new(false);
+    assert_eq!(slc::parked_count(addr(&WAKE_UP)), 0);
+    let handles: Vec<_> = (0..3).map(|_| spawn_waiter(&WAKE_UP)).collect();
+    let other = spawn_waiter(&OTHER);
+    // give the waiters time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(slc::parked_count(addr(&WAKE_UP)), 3);
+    assert_eq!(slc::parked_count(addr(&OTHER)), 1);
+
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    assert_eq!(slc::parked_count(addr(&WAKE_UP)), 2);
+    slc::unpark_all(addr(&WAKE_UP));
+    assert_eq!(slc::parked_count(addr(&WAKE_UP)), 0);
+    for h in handles {
+        h.join().unwrap();
+    }
+
+    OTHER.store(true, Release);
+    slc::unpark_all(addr(&OTHER));
+    other.join().unwrap();
+}
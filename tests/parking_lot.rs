@@ -0,0 +1,103 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+
+use sparking_lot_core::{self as slc, ParkingLot};
+
+use common::{addr, result};
+
+fn wait_for_waiters(lot: &ParkingLot, wake_up: &AtomicBool, count: usize) {
+    while lot.parked_count(addr(wake_up)) != count {
+        thread::yield_now();
+    }
+}
+
+#[test]
+fn lots_are_isolated() {
+    let (lot, other) = (ParkingLot::new(), ParkingLot::new());
+    let wake_up = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| unsafe { lot.park(addr(&wake_up), || !wake_up.load(Acquire)) });
+        wait_for_waiters(&lot, &wake_up, 1);
+        assert_eq!(slc::parked_count(addr(&wake_up)), 0);
+        assert_eq!(slc::unpark_all(addr(&wake_up)), result(0, false));
+        assert_eq!(other.unpark_all(addr(&wake_up)), result(0, false));
+        wake_up.store(true, Release);
+        assert_eq!(lot.unpark_one(addr(&wake_up)), result(1, false));
+    });
+}
+
+#[test]
+fn global_waiters_arent_woken_by_lots() {
+    let lot = ParkingLot::new();
+    let wake_up = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| unsafe { slc::park(addr(&wake_up), || !wake_up.load(Acquire)) });
+        while slc::parked_count(addr(&wake_up)) != 1 {
+            thread::yield_now();
+        }
+        assert_eq!(lot.parked_count(addr(&wake_up)), 0);
+        assert_eq!(lot.unpark_one(addr(&wake_up)), result(0, false));
+        wake_up.store(true, Release);
+        assert_eq!(slc::unpark_one(addr(&wake_up)), result(1, false));
+    });
+}
+
+#[test]
+fn unpark_some_and_all() {
+    let lot = ParkingLot::new();
+    let wake_up = AtomicBool::new(false);
+    thread::scope(|s| {
+        for _ in 0..3 {
+            s.spawn(|| unsafe { lot.park(addr(&wake_up), || !wake_up.load(Acquire)) });
+        }
+        wait_for_waiters(&lot, &wake_up, 3);
+        wake_up.store(true, Release);
+        assert_eq!(lot.unpark_some(addr(&wake_up), 2), result(2, true));
+        assert_eq!(lot.unpark_all(addr(&wake_up)), result(1, false));
+    });
+}
+
+#[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+#[test]
+fn park_timeout_unlinks() {
+    use std::time::Duration;
+
+    let lot = ParkingLot::new();
+    let wake_up = AtomicBool::new(false);
+    let parked = unsafe { lot.park_timeout(addr(&wake_up), || true, Duration::from_millis(10)) };
+    assert_eq!(parked, slc::ParkResult::TimedOut);
+    assert_eq!(lot.unpark_one(addr(&wake_up)), result(0, false));
+}
+
+fn park_and_unpark_each<const BUCKETS: usize>() {
+    let lot = ParkingLot::<BUCKETS>::with_buckets();
+    // more addresses than buckets, so some of them share one
+    let flags: [AtomicBool; 8] = Default::default();
+    thread::scope(|s| {
+        for flag in &flags {
+            let lot = &lot;
+            s.spawn(move || unsafe { lot.park(addr(flag), || !flag.load(Acquire)) });
+        }
+        for flag in &flags {
+            while lot.parked_count(addr(flag)) != 1 {
+                thread::yield_now();
+            }
+        }
+        for flag in &flags {
+            flag.store(true, Release);
+            assert_eq!(lot.unpark_all(addr(flag)), result(1, false));
+        }
+    });
+}
+
+#[test]
+fn bucket_counts() {
+    park_and_unpark_each::<1>();
+    park_and_unpark_each::<4>();
+    park_and_unpark_each::<256>();
+}
@@ -0,0 +1,74 @@
+#![cfg(all(feature = "std", feature = "random-wake", not(loom)))]
+
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+const WAITERS: usize = 4;
+
+/// Parks `WAITERS` threads on `woken` in order of their ids, then wakes
+/// them one at a time with `unpark` and returns the wake order.
+fn wake_order(
+    woken: &'static AtomicUsize,
+    order: &'static Mutex<Vec<usize>>,
+    unpark: fn(*const ()) -> slc::UnparkResult,
+) -> Vec<usize> {
+    woken.store(0, Release);
+    order.lock().unwrap().clear();
+    let handles: Vec<_> = (0..WAITERS)
+        .map(|id| {
+            let h = thread::spawn(move || {
+                unsafe {
+                    slc::park(woken as *const _ as *const _, || woken.load(Acquire) == 0);
+                }
+                order.lock().unwrap().push(id);
+            });
+            // the next thread only parks once this one is queued
+            while slc::parked_count(woken as *const _ as *const ()) != id + 1 {
+                thread::sleep(Duration::from_millis(1));
+            }
+            h
+        })
+        .collect();
+    woken.store(1, Release);
+    for i in 1..=WAITERS {
+        unpark(woken as *const _ as *const _);
+        while order.lock().unwrap().len() != i {
+            thread::yield_now();
+        }
+    }
+    for h in handles {
+        h.join().unwrap();
+    }
+    order.lock().unwrap().clone()
+}
+
+#[test]
+fn unpark_one_is_random() {
+    static WOKEN: AtomicUsize = AtomicUsize::new(0);
+    static ORDER: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+    let fifo: Vec<_> = (0..WAITERS).collect();
+    // each round is FIFO with a probability of 1/24
+    let mut all_fifo = true;
+    for _ in 0..5 {
+        let mut order = wake_order(&WOKEN, &ORDER, slc::unpark_one);
+        all_fifo &= order == fifo;
+        order.sort_unstable();
+        assert_eq!(order, fifo);
+    }
+    assert!(!all_fifo);
+}
+
+#[test]
+fn unpark_one_fifo_is_fifo() {
+    static WOKEN: AtomicUsize = AtomicUsize::new(0);
+    static ORDER: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+    let fifo: Vec<_> = (0..WAITERS).collect();
+    for _ in 0..5 {
+        assert_eq!(wake_order(&WOKEN, &ORDER, slc::unpark_one_fifo), fifo);
+    }
+}
@@ -0,0 +1,66 @@
+#![cfg(all(feature = "lock-api", feature = "std", not(loom)))]
+
+use std::thread;
+use std::time::Duration;
+
+use lock_api::RawRwLock as _;
+use sparking_lot_core::RawRwLock;
+
+type RwLock<T> = lock_api::RwLock<RawRwLock, T>;
+
+#[test]
+fn readers_share_writers_dont() {
+    let lock = RwLock::new(());
+    let first = lock.read();
+    let second = lock.read();
+    assert!(lock.try_write().is_none());
+    drop((first, second));
+    let write = lock.write();
+    assert!(lock.try_read().is_none());
+    assert!(lock.try_write().is_none());
+    drop(write);
+    assert!(!unsafe { lock.raw() }.is_locked());
+}
+
+#[test]
+fn waiting_writers_block_new_readers() {
+    let lock = RwLock::new(0);
+    thread::scope(|s| {
+        let read = lock.read();
+        let writer = s.spawn(|| *lock.write() += 1);
+        while !lock.is_locked_exclusive() && lock.try_read().is_some() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        // the parked writer goes first
+        let reader = s.spawn(|| *lock.read());
+        thread::sleep(Duration::from_millis(50));
+        drop(read);
+        writer.join().unwrap();
+        assert_eq!(reader.join().unwrap(), 1);
+    });
+}
+
+#[test]
+fn contended() {
+    const THREADS: usize = 8;
+    const ROUNDS: usize = 1000;
+    let lock = RwLock::new((0, 0));
+    thread::scope(|s| {
+        for i in 0..THREADS {
+            let lock = &lock;
+            s.spawn(move || {
+                for _ in 0..ROUNDS {
+                    if i % 2 == 0 {
+                        let mut pair = lock.write();
+                        pair.0 += 1;
+                        pair.1 += 1;
+                    } else {
+                        let pair = lock.read();
+                        assert_eq!(pair.0, pair.1);
+                    }
+                }
+            });
+        }
+    });
+    assert_eq!(*lock.read(), (THREADS / 2 * ROUNDS, THREADS / 2 * ROUNDS));
+}
@@ -0,0 +1,52 @@
+#![cfg(all(
+    debug_assertions,
+    feature = "std",
+    not(feature = "abort-on-panic"),
+    not(loom)
+))]
+
+use std::panic::catch_unwind;
+
+use sparking_lot_core as slc;
+
+static A: u8 = 0;
+static B: u8 = 0;
+
+fn addr(x: &'static u8) -> *const () {
+    x as *const _ as *const _
+}
+
+fn assert_panics(f: impl FnOnce() + std::panic::UnwindSafe) {
+    let err = catch_unwind(f).unwrap_err();
+    let msg = err.downcast_ref::<&str>().unwrap();
+    assert!(msg.contains("can't be called from `expected`"));
+}
+
+#[test]
+fn park_in_expected() {
+    assert_panics(|| unsafe {
+        slc::park(addr(&A), || {
+            slc::park(addr(&B), || false);
+            false
+        })
+    });
+    // the lot is still usable
+    unsafe { slc::park(addr(&A), || false) };
+}
+
+#[test]
+fn unpark_in_expected() {
+    assert_panics(|| unsafe {
+        slc::park(addr(&A), || {
+            slc::unpark_one(addr(&B));
+            false
+        })
+    });
+    assert_panics(|| unsafe {
+        slc::park(addr(&A), || {
+            slc::unpark_all(addr(&A));
+            false
+        })
+    });
+    slc::unpark_one(addr(&A));
+}
@@ -0,0 +1,53 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core::Semaphore;
+
+#[test]
+fn try_acquire_takes_permits() {
+    let semaphore = Semaphore::new(2);
+    assert!(semaphore.try_acquire());
+    assert!(semaphore.try_acquire());
+    assert!(!semaphore.try_acquire());
+    semaphore.release(3);
+    assert_eq!(semaphore.available(), 3);
+}
+
+#[test]
+fn release_wakes_waiters() {
+    let semaphore = Semaphore::new(0);
+    thread::scope(|s| {
+        let waiters: Vec<_> = (0..3).map(|_| s.spawn(|| semaphore.acquire())).collect();
+        // give the waiters time to actually go to sleep
+        thread::sleep(Duration::from_millis(50));
+        semaphore.release(3);
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+    });
+    assert_eq!(semaphore.available(), 0);
+}
+
+#[test]
+fn limits_concurrency() {
+    const PERMITS: usize = 3;
+    let semaphore = Semaphore::new(PERMITS);
+    let inside = AtomicUsize::new(0);
+    thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    semaphore.acquire();
+                    assert!(inside.fetch_add(1, Relaxed) < PERMITS);
+                    inside.fetch_sub(1, Relaxed);
+                    semaphore.release(1);
+                }
+            });
+        }
+    });
+    assert_eq!(semaphore.available(), PERMITS);
+}
@@ -0,0 +1,53 @@
+#![cfg(all(unix, feature = "signal-mask", not(loom)))]
+
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+fn is_blocked(signal: libc::c_int) -> bool {
+    unsafe {
+        let mut set = MaybeUninit::<libc::sigset_t>::uninit();
+        libc::pthread_sigmask(libc::SIG_BLOCK, ptr::null(), set.as_mut_ptr());
+        libc::sigismember(set.as_ptr(), signal) == 1
+    }
+}
+
+#[test]
+fn blocks_while_parked() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    static BLOCKED_IN_PARK: AtomicBool = AtomicBool::new(false);
+    let addr = &WAKE_UP as *const _ as *const ();
+    let h = thread::spawn(|| {
+        assert!(!is_blocked(libc::SIGUSR1));
+        unsafe {
+            slc::park_with_signals_blocked(
+                &WAKE_UP as *const _ as *const _,
+                || {
+                    BLOCKED_IN_PARK.store(is_blocked(libc::SIGUSR1), Relaxed);
+                    !WAKE_UP.load(Acquire)
+                },
+                &[libc::SIGUSR1, libc::SIGUSR2],
+            );
+        }
+        // the old mask is restored
+        assert!(!is_blocked(libc::SIGUSR1));
+        assert!(!is_blocked(libc::SIGUSR2));
+    });
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr);
+    h.join().unwrap();
+    assert!(BLOCKED_IN_PARK.load(Relaxed));
+}
+
+#[test]
+#[should_panic]
+fn invalid_signal() {
+    static ADDR: u8 = 0;
+    unsafe { slc::park_with_signals_blocked(&ADDR as *const _ as *const _, || false, &[-1]) };
+}
@@ -0,0 +1,109 @@
+#![cfg(all(feature = "static-only", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::addr;
+
+/// Aborts when a thread inside [`forbid_allocations`] allocates.
+///
+/// Unwinding out of an allocator is UB, so it can't actually panic.
+struct PanickingAlloc;
+
+thread_local!(static FORBIDDEN: Cell<bool> = const { Cell::new(false) });
+
+fn forbid_allocations<R>(f: impl FnOnce() -> R) -> R {
+    FORBIDDEN.with(|x| x.set(true));
+    let ret = f();
+    FORBIDDEN.with(|x| x.set(false));
+    ret
+}
+
+fn check() {
+    if FORBIDDEN.with(|x| x.replace(false)) {
+        use std::io::Write;
+        let _ = std::io::stderr().write_all(b"sparking-lot-core allocated in `static-only` mode\n");
+        std::process::abort();
+    }
+}
+
+unsafe impl GlobalAlloc for PanickingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        check();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        check();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        check();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: PanickingAlloc = PanickingAlloc;
+
+fn spawn_waiter(wake_up: &'static AtomicBool) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        forbid_allocations(|| unsafe {
+            slc::park(addr(wake_up), || !wake_up.load(Acquire));
+        });
+    })
+}
+
+#[test]
+fn unpark_one() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = spawn_waiter(&WAKE_UP);
+    // give the waiter time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    forbid_allocations(|| {
+        WAKE_UP.store(true, Release);
+        slc::unpark_one(addr(&WAKE_UP));
+    });
+    h.join().unwrap();
+}
+
+#[test]
+fn unpark_some() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h1 = spawn_waiter(&WAKE_UP);
+    let h2 = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    forbid_allocations(|| {
+        WAKE_UP.store(true, Release);
+        slc::unpark_some(addr(&WAKE_UP), 2);
+    });
+    h1.join().unwrap();
+    h2.join().unwrap();
+}
+
+#[test]
+fn unpark_all() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h1 = spawn_waiter(&WAKE_UP);
+    let h2 = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    forbid_allocations(|| {
+        WAKE_UP.store(true, Release);
+        slc::unpark_all(addr(&WAKE_UP));
+    });
+    h1.join().unwrap();
+    h2.join().unwrap();
+}
@@ -0,0 +1,71 @@
+#![cfg(all(feature = "std", feature = "stats", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Release;
+
+use sparking_lot_core as slc;
+
+use common::{addr, spawn_waiter, wait_for_waiters};
+
+#[test]
+fn counts_parks() {
+    static FLAG: AtomicBool = AtomicBool::new(false);
+    let before = slc::stats();
+    unsafe { slc::park(addr(&FLAG), || false) };
+    let after = slc::stats();
+    assert!(after.parks > before.parks);
+    assert!(after.invalid_parks > before.invalid_parks);
+    assert!(after.bucket_locks > before.bucket_locks);
+}
+
+#[test]
+fn counts_failed_validations() {
+    static FLAG: AtomicBool = AtomicBool::new(false);
+    let before = slc::stats();
+    unsafe { slc::park_then_validate(addr(&FLAG), || false) };
+    let after = slc::stats();
+    assert!(after.parks > before.parks);
+    assert!(after.failed_validations > before.failed_validations);
+}
+
+#[test]
+fn counts_unpark_one() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiter = spawn_waiter(&WAKE_UP);
+    wait_for_waiters(&WAKE_UP, 1);
+
+    let before = slc::stats();
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    waiter.join().unwrap();
+    slc::unpark_one(addr(&WAKE_UP));
+    let after = slc::stats();
+    assert!(after.unpark_one_hits > before.unpark_one_hits);
+    assert!(after.unpark_one_misses > before.unpark_one_misses);
+}
+
+#[test]
+fn counts_woken_threads() {
+    static ALL: AtomicBool = AtomicBool::new(false);
+    static SOME: AtomicBool = AtomicBool::new(false);
+    let waiters: Vec<_> = (0..3)
+        .map(|_| spawn_waiter(&ALL))
+        .chain((0..2).map(|_| spawn_waiter(&SOME)))
+        .collect();
+    wait_for_waiters(&ALL, 3);
+    wait_for_waiters(&SOME, 2);
+
+    let before = slc::stats();
+    ALL.store(true, Release);
+    SOME.store(true, Release);
+    slc::unpark_all(addr(&ALL));
+    slc::unpark_some(addr(&SOME), 2);
+    let after = slc::stats();
+    assert!(after.unpark_all_woken - before.unpark_all_woken >= 3);
+    assert!(after.unpark_some_woken - before.unpark_some_woken >= 2);
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+}
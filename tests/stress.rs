@@ -0,0 +1,53 @@
+#![cfg(all(feature = "stress", not(loom)))]
+
+use std::time::Duration;
+
+use sparking_lot_core::{run_stress, StressConfig};
+
+fn config(threads: usize, addresses: usize, unpark_percent: u8) -> StressConfig {
+    let mut config = StressConfig::default();
+    config.threads = threads;
+    config.addresses = addresses;
+    config.unpark_percent = unpark_percent;
+    config.duration = Duration::from_millis(50);
+    config
+}
+
+#[test]
+fn mixed() {
+    let report = run_stress(&config(8, 4, 50));
+    assert!(report.parked + report.invalid != 0);
+    assert!(report.unparks != 0);
+    // only the parks which slept can be woken, and only once
+    assert!(report.woken <= report.parked);
+    assert!(report.woken <= report.unparks);
+    assert!(report.elapsed >= Duration::from_millis(50));
+}
+
+#[test]
+fn one_address() {
+    let report = run_stress(&config(16, 1, 10));
+    assert!(report.woken <= report.parked);
+}
+
+/// Nothing unparks until the end, so every thread parks at most once and is woken by the shutdown.
+#[test]
+fn only_parks() {
+    let report = run_stress(&config(4, 2, 0));
+    assert_eq!(report.unparks, 0);
+    assert!(report.parked + report.invalid <= 4);
+}
+
+/// Nobody ever parks, so nobody is woken.
+#[test]
+fn only_unparks() {
+    let report = run_stress(&config(4, 2, 100));
+    assert_eq!(report.parked + report.invalid, 0);
+    assert_eq!(report.woken, 0);
+}
+
+#[test]
+#[should_panic]
+fn no_threads() {
+    run_stress(&config(0, 1, 50));
+}
@@ -0,0 +1,126 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+//! Parking and unparking from TLS destructors, which may run before
+//! or after the destructor of the `ThreadData` cached in TLS.
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::addr;
+
+/// Parks on `wake_up` when dropped.
+struct ParkOnDrop(&'static AtomicBool);
+
+impl Drop for ParkOnDrop {
+    fn drop(&mut self) {
+        let wake_up = self.0;
+        unsafe { slc::park(addr(wake_up), || !wake_up.load(Acquire)) };
+    }
+}
+
+/// Wakes up everyone parked on `wake_up` when dropped.
+struct UnparkOnDrop(&'static AtomicBool);
+
+impl Drop for UnparkOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Release);
+        slc::unpark_all(addr(self.0));
+    }
+}
+
+fn wake_later(wake_up: &'static AtomicBool) {
+    // give the waiter time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    wake_up.store(true, Release);
+    slc::unpark_all(addr(wake_up));
+}
+
+#[test]
+fn park_in_tls_dtor_before_first_park() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    thread_local!(static PARK: ParkOnDrop = const { ParkOnDrop(&WAKE_UP) });
+    let h = thread::spawn(|| {
+        PARK.with(|_| ());
+    });
+    wake_later(&WAKE_UP);
+    h.join().unwrap();
+}
+
+#[test]
+fn park_in_tls_dtor_registered_before_thread_data() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    static OTHER: AtomicBool = AtomicBool::new(true);
+    thread_local!(static PARK: ParkOnDrop = const { ParkOnDrop(&WAKE_UP) });
+    let h = thread::spawn(|| {
+        PARK.with(|_| ());
+        // caches `ThreadData` in TLS, if the parker does that
+        unsafe { slc::park(addr(&OTHER), || false) };
+    });
+    wake_later(&WAKE_UP);
+    h.join().unwrap();
+}
+
+#[test]
+fn park_in_tls_dtor_registered_after_thread_data() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    static OTHER: AtomicBool = AtomicBool::new(true);
+    thread_local!(static PARK: ParkOnDrop = const { ParkOnDrop(&WAKE_UP) });
+    let h = thread::spawn(|| {
+        unsafe { slc::park(addr(&OTHER), || false) };
+        PARK.with(|_| ());
+    });
+    wake_later(&WAKE_UP);
+    h.join().unwrap();
+}
+
+#[test]
+fn unpark_in_tls_dtor() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    thread_local!(static UNPARK: UnparkOnDrop = const { UnparkOnDrop(&WAKE_UP) });
+    let waiter = thread::spawn(|| unsafe {
+        slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+    });
+    thread::sleep(Duration::from_millis(50));
+    thread::spawn(|| UNPARK.with(|_| ())).join().unwrap();
+    waiter.join().unwrap();
+}
+
+#[test]
+fn park_and_unpark_in_tls_dtors() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    thread_local!(static PARK: ParkOnDrop = const { ParkOnDrop(&WAKE_UP) });
+    thread_local!(static UNPARK: UnparkOnDrop = const { UnparkOnDrop(&WAKE_UP) });
+    let waiter = thread::spawn(|| PARK.with(|_| ()));
+    thread::sleep(Duration::from_millis(50));
+    thread::spawn(|| UNPARK.with(|_| ())).join().unwrap();
+    waiter.join().unwrap();
+}
+
+#[test]
+fn threads_exit_after_being_woken() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let waiters: Vec<_> = (0..8)
+        .map(|_| {
+            thread::spawn(|| unsafe {
+                slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+            })
+        })
+        .collect();
+    wake_later(&WAKE_UP);
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+    // the exited threads must not be linked into the bucket anymore
+    static OTHER: AtomicBool = AtomicBool::new(false);
+    let h = thread::spawn(|| unsafe {
+        slc::park(addr(&OTHER), || !OTHER.load(Acquire));
+    });
+    wake_later(&OTHER);
+    h.join().unwrap();
+}
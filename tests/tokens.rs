@@ -0,0 +1,92 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, wait_for_waiters};
+
+fn spawn_waiter(waiting: &'static AtomicBool) -> thread::JoinHandle<Option<usize>> {
+    thread::spawn(move || unsafe { slc::park_with_token(addr(waiting), || true) })
+}
+
+#[test]
+fn passes_token() {
+    static WAITING: AtomicBool = AtomicBool::new(false);
+    let h = spawn_waiter(&WAITING);
+    wait_for_waiters(&WAITING, 1);
+    slc::unpark_one_with_token(addr(&WAITING), 42);
+    assert_eq!(h.join().unwrap(), Some(42));
+}
+
+#[test]
+fn tokens_go_to_their_threads() {
+    static WAITING: AtomicBool = AtomicBool::new(false);
+    let h1 = spawn_waiter(&WAITING);
+    wait_for_waiters(&WAITING, 1);
+    let h2 = spawn_waiter(&WAITING);
+    wait_for_waiters(&WAITING, 2);
+    slc::unpark_one_with_token(addr(&WAITING), 1);
+    slc::unpark_one_with_token(addr(&WAITING), 2);
+    let mut tokens = [h1.join().unwrap(), h2.join().unwrap()];
+    tokens.sort();
+    assert_eq!(tokens, [Some(1), Some(2)]);
+}
+
+#[test]
+fn default_token() {
+    static WAITING: AtomicBool = AtomicBool::new(false);
+    let h1 = spawn_waiter(&WAITING);
+    wait_for_waiters(&WAITING, 1);
+    let h2 = spawn_waiter(&WAITING);
+    wait_for_waiters(&WAITING, 2);
+    slc::unpark_one(addr(&WAITING));
+    slc::unpark_all(addr(&WAITING));
+    assert_eq!(h1.join().unwrap(), Some(slc::DEFAULT_UNPARK_TOKEN));
+    assert_eq!(h2.join().unwrap(), Some(slc::DEFAULT_UNPARK_TOKEN));
+}
+
+#[test]
+fn token_is_reset() {
+    static WAITING: AtomicBool = AtomicBool::new(false);
+    let h = thread::spawn(|| {
+        let mut tokens = [None; 2];
+        for token in &mut tokens {
+            *token = unsafe { slc::park_with_token(addr(&WAITING), || true) };
+        }
+        tokens
+    });
+    wait_for_waiters(&WAITING, 1);
+    slc::unpark_one_with_token(addr(&WAITING), 7);
+    wait_for_waiters(&WAITING, 1);
+    slc::unpark_one(addr(&WAITING));
+    assert_eq!(
+        h.join().unwrap(),
+        [Some(7), Some(slc::DEFAULT_UNPARK_TOKEN)]
+    );
+}
+
+#[test]
+fn invalid() {
+    static WAITING: AtomicBool = AtomicBool::new(false);
+    assert_eq!(
+        unsafe { slc::park_with_token(addr(&WAITING), || false) },
+        None
+    );
+}
+
+#[cfg(not(any(feature = "freertos", feature = "zephyr")))]
+#[test]
+fn park_timeout_gets_token() {
+    static WAITING: AtomicBool = AtomicBool::new(false);
+    let h = thread::spawn(|| unsafe {
+        slc::park_timeout(addr(&WAITING), || true, Duration::from_secs(60))
+    });
+    wait_for_waiters(&WAITING, 1);
+    slc::unpark_one_with_token(addr(&WAITING), 3);
+    assert_eq!(h.join().unwrap(), slc::ParkResult::Unparked(3));
+}
@@ -0,0 +1,56 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::mpsc;
+use std::thread;
+
+use sparking_lot_core::{self as slc, UnparkResult, WouldBlock};
+
+use common::{addr, wait_for_waiters};
+
+#[test]
+fn wakes_a_parked_thread() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = thread::spawn(|| unsafe {
+        slc::park_with_token(addr(&WAKE_UP), || !WAKE_UP.load(Acquire))
+    });
+    wait_for_waiters(&WAKE_UP, 1);
+    WAKE_UP.store(true, Release);
+    // other tests may hold the bucket for a moment
+    let result = loop {
+        match slc::try_unpark_one(addr(&WAKE_UP)) {
+            Ok(result) => break result,
+            Err(WouldBlock) => thread::yield_now(),
+        }
+    };
+    assert_eq!(
+        result,
+        UnparkResult {
+            unparked: 1,
+            has_more: false
+        }
+    );
+    assert_eq!(h.join().unwrap(), Some(slc::DEFAULT_UNPARK_TOKEN));
+}
+
+#[test]
+fn fails_while_the_bucket_is_locked() {
+    static ADDR: AtomicBool = AtomicBool::new(false);
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    // the callback runs with the bucket locked
+    let h = thread::spawn(move || {
+        slc::unpark_one_with(addr(&ADDR), |_| {
+            locked_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            slc::DEFAULT_UNPARK_TOKEN
+        })
+    });
+    locked_rx.recv().unwrap();
+    assert_eq!(slc::try_unpark_one(addr(&ADDR)), Err(WouldBlock));
+    release_tx.send(()).unwrap();
+    assert_eq!(h.join().unwrap(), UnparkResult::default());
+}
@@ -0,0 +1,79 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+
+use sparking_lot_core::{self as slc, UnparkBatch};
+
+use common::{addr, wait_for_waiters};
+
+fn spawn_waiters(wake_up: &'static AtomicBool, count: usize) -> Vec<thread::JoinHandle<()>> {
+    let waiters = (0..count)
+        .map(|_| {
+            thread::spawn(move || unsafe {
+                slc::park(addr(wake_up), || !wake_up.load(Acquire));
+            })
+        })
+        .collect();
+    wait_for_waiters(wake_up, count);
+    waiters
+}
+
+#[test]
+fn wakes_nothing_until_flushed() {
+    static A: AtomicBool = AtomicBool::new(false);
+    static B: AtomicBool = AtomicBool::new(false);
+    let waiters: Vec<_> = [spawn_waiters(&A, 3), spawn_waiters(&B, 4)]
+        .into_iter()
+        .flatten()
+        .collect();
+    A.store(true, Release);
+    B.store(true, Release);
+
+    let mut batch = UnparkBatch::<8>::new();
+    batch.unpark_one(addr(&A));
+    batch.unpark_some(addr(&B), 3);
+    batch.unpark_one(addr(&A));
+    assert_eq!(batch.len(), 3);
+    assert_eq!(slc::parked_count(addr(&A)), 3);
+    assert_eq!(slc::parked_count(addr(&B)), 4);
+
+    assert_eq!(batch.flush(), 5);
+    assert!(batch.is_empty());
+    assert_eq!(slc::parked_count(addr(&A)), 1);
+    assert_eq!(slc::parked_count(addr(&B)), 1);
+
+    batch.unpark_all(addr(&A));
+    batch.unpark_all(addr(&B));
+    assert_eq!(batch.flush(), 2);
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+}
+
+#[test]
+fn full_batch_flushes_first() {
+    static A: AtomicBool = AtomicBool::new(false);
+    static B: AtomicBool = AtomicBool::new(false);
+    let waiters: Vec<_> = [spawn_waiters(&A, 1), spawn_waiters(&B, 1)]
+        .into_iter()
+        .flatten()
+        .collect();
+    A.store(true, Release);
+    B.store(true, Release);
+
+    let mut batch = UnparkBatch::<1>::new();
+    batch.unpark_one(addr(&A));
+    batch.unpark_one(addr(&B));
+    assert_eq!(slc::parked_count(addr(&A)), 0);
+    assert_eq!(slc::parked_count(addr(&B)), 1);
+    assert_eq!(batch.len(), 1);
+    // dropping it wakes the rest
+    drop(batch);
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+}
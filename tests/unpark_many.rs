@@ -0,0 +1,61 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Release;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, spawn_waiter};
+
+#[test]
+fn no_requests() {
+    assert_eq!(slc::unpark_many::<usize>(&[]), 0);
+}
+
+#[test]
+fn nobody_parked() {
+    static A: AtomicBool = AtomicBool::new(false);
+    static B: AtomicBool = AtomicBool::new(false);
+    assert_eq!(
+        slc::unpark_many(&[(addr(&A), 1), (addr(&B), usize::MAX)]),
+        0
+    );
+}
+
+#[test]
+fn wakes_several_addresses() {
+    static A: AtomicBool = AtomicBool::new(false);
+    static B: AtomicBool = AtomicBool::new(false);
+    let handles: Vec<_> = (0..2)
+        .map(|_| spawn_waiter(&A))
+        .chain((0..3).map(|_| spawn_waiter(&B)))
+        .collect();
+    // give the waiters time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    A.store(true, Release);
+    B.store(true, Release);
+    assert_eq!(slc::unpark_many(&[(addr(&A), 1), (addr(&B), 2)]), 3);
+    // the rest are still parked
+    assert_eq!(slc::unpark_all(addr(&A)).unparked, 1);
+    assert_eq!(slc::unpark_all(addr(&B)).unparked, 1);
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn repeated_addresses_add_up() {
+    static A: AtomicBool = AtomicBool::new(false);
+    let handles: Vec<_> = (0..3).map(|_| spawn_waiter(&A)).collect();
+    thread::sleep(Duration::from_millis(50));
+    A.store(true, Release);
+    assert_eq!(slc::unpark_many(&[(addr(&A), 1), (addr(&A), 1)]), 2);
+    assert_eq!(slc::unpark_all(addr(&A)).unparked, 1);
+    for h in handles {
+        h.join().unwrap();
+    }
+}
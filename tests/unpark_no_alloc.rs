@@ -0,0 +1,109 @@
+#![cfg(all(feature = "std", not(any(loom, miri))))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Release;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, spawn_waiter};
+
+/// Counts the allocations made by the current thread.
+struct CountingAlloc;
+
+thread_local!(static ALLOCATIONS: Cell<usize> = const { Cell::new(0) });
+
+fn count() {
+    // can fail during TLS destruction, those allocations aren't interesting
+    let _ = ALLOCATIONS.try_with(|x| x.set(x.get() + 1));
+}
+
+fn allocations_in(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+#[test]
+fn no_waiters() {
+    static NOBODY: AtomicBool = AtomicBool::new(false);
+    let allocations = allocations_in(|| {
+        slc::unpark_one(addr(&NOBODY));
+        slc::unpark_some(addr(&NOBODY), 2);
+        slc::unpark_all(addr(&NOBODY));
+    });
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn unpark_one() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h = spawn_waiter(&WAKE_UP);
+    // give the waiter time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    let allocations = allocations_in(|| {
+        WAKE_UP.store(true, Release);
+        slc::unpark_one(addr(&WAKE_UP));
+    });
+    h.join().unwrap();
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn unpark_some() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h1 = spawn_waiter(&WAKE_UP);
+    let h2 = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    let allocations = allocations_in(|| {
+        WAKE_UP.store(true, Release);
+        slc::unpark_some(addr(&WAKE_UP), 2);
+    });
+    h1.join().unwrap();
+    h2.join().unwrap();
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn unpark_all() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h1 = spawn_waiter(&WAKE_UP);
+    let h2 = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    let allocations = allocations_in(|| {
+        WAKE_UP.store(true, Release);
+        slc::unpark_all(addr(&WAKE_UP));
+    });
+    h1.join().unwrap();
+    h2.join().unwrap();
+    assert_eq!(allocations, 0);
+}
@@ -0,0 +1,68 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Release;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core::{self as slc, UnparkResult, DEFAULT_UNPARK_TOKEN};
+
+use common::{addr, spawn_waiter};
+
+#[test]
+fn nobody_parked_is_never_fair() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    for _ in 0..2 {
+        let mut called = false;
+        let result = slc::unpark_one_fair(addr(&WAKE_UP), |result, be_fair| {
+            assert_eq!(result, UnparkResult::default());
+            assert!(!be_fair);
+            called = true;
+            DEFAULT_UNPARK_TOKEN
+        });
+        assert!(called);
+        assert_eq!(result.unparked, 0);
+        thread::sleep(Duration::from_millis(2));
+    }
+}
+
+#[test]
+fn fair_after_the_timeout() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let mut fair = Vec::new();
+    for _ in 0..3 {
+        let h = spawn_waiter(&WAKE_UP);
+        // also much longer than the fairness timeout
+        thread::sleep(Duration::from_millis(50));
+        slc::unpark_one_fair(addr(&WAKE_UP), |result, be_fair| {
+            assert_eq!(result.unparked, 1);
+            fair.push(be_fair);
+            DEFAULT_UNPARK_TOKEN
+        });
+        h.join().unwrap();
+    }
+    // the first one may only start the timeout of the bucket
+    assert_eq!(fair[1..], [true, true]);
+}
+
+#[test]
+fn not_always_fair() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let handles: Vec<_> = (0..8).map(|_| spawn_waiter(&WAKE_UP)).collect();
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    let mut fair = 0;
+    for _ in 0..8 {
+        slc::unpark_one_fair(addr(&WAKE_UP), |_, be_fair| {
+            fair += usize::from(be_fair);
+            DEFAULT_UNPARK_TOKEN
+        });
+    }
+    // back to back wake-ups are mostly within the timeout
+    assert!(fair < 8);
+    for h in handles {
+        h.join().unwrap();
+    }
+}
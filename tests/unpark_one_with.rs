@@ -0,0 +1,81 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core::{self as slc, UnparkResult};
+
+use common::addr;
+
+fn spawn_waiter(wake_up: &'static AtomicBool) -> thread::JoinHandle<Option<usize>> {
+    thread::spawn(move || unsafe { slc::park_with_token(addr(wake_up), || !wake_up.load(Acquire)) })
+}
+
+#[test]
+fn callback_without_waiters() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let mut called = None;
+    let result = slc::unpark_one_with(addr(&WAKE_UP), |result| {
+        called = Some(result);
+        1
+    });
+    assert_eq!(result, UnparkResult::default());
+    assert_eq!(called, Some(UnparkResult::default()));
+}
+
+#[test]
+fn callback_sees_result_and_passes_token() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h1 = spawn_waiter(&WAKE_UP);
+    let h2 = spawn_waiter(&WAKE_UP);
+    // give the waiters time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+
+    let mut seen = [None; 2];
+    for (i, seen) in seen.iter_mut().enumerate() {
+        let result = slc::unpark_one_with(addr(&WAKE_UP), |result| {
+            *seen = Some(result);
+            // the woken thread can't return before the bucket is unlocked
+            WAKE_UP.store(true, Relaxed);
+            10 + i
+        });
+        assert_eq!(Some(result), *seen);
+    }
+    assert_eq!(
+        seen,
+        [
+            Some(UnparkResult {
+                unparked: 1,
+                has_more: true
+            }),
+            Some(UnparkResult {
+                unparked: 1,
+                has_more: false
+            }),
+        ]
+    );
+    let mut tokens = [h1.join().unwrap(), h2.join().unwrap()];
+    tokens.sort();
+    assert_eq!(tokens, [Some(10), Some(11)]);
+}
+
+#[test]
+fn callback_runs_before_waking() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    static CALLED: AtomicBool = AtomicBool::new(false);
+    let h = thread::spawn(|| {
+        unsafe { slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire)) };
+        CALLED.load(Relaxed)
+    });
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    slc::unpark_one_with(addr(&WAKE_UP), |_| {
+        CALLED.store(true, Relaxed);
+        slc::DEFAULT_UNPARK_TOKEN
+    });
+    assert!(h.join().unwrap());
+}
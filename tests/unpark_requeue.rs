@@ -0,0 +1,97 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Release;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core::{self as slc, ParkResult, RequeueResult};
+
+use common::{addr, spawn_waiter};
+
+const fn result(unparked: usize, requeued: usize) -> RequeueResult {
+    RequeueResult { unparked, requeued }
+}
+
+#[test]
+fn nobody_parked() {
+    static FROM: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    assert_eq!(
+        slc::unpark_requeue(addr(&FROM), addr(&TO), 1, usize::MAX),
+        result(0, 0)
+    );
+}
+
+#[test]
+fn wakes_then_requeues() {
+    static FROM: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    let handles: Vec<_> = (0..3).map(|_| spawn_waiter(&FROM)).collect();
+    // give the waiters time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    FROM.store(true, Release);
+    assert_eq!(
+        slc::unpark_requeue(addr(&FROM), addr(&TO), 1, usize::MAX),
+        result(1, 2)
+    );
+    // the requeued threads are parked on `TO` now
+    assert_eq!(slc::unpark_all(addr(&FROM)).unparked, 0);
+    assert_eq!(slc::unpark_all(addr(&TO)).unparked, 2);
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn requeue_count_is_respected() {
+    static FROM: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    let handles: Vec<_> = (0..3).map(|_| spawn_waiter(&FROM)).collect();
+    thread::sleep(Duration::from_millis(50));
+    FROM.store(true, Release);
+    assert_eq!(
+        slc::unpark_requeue(addr(&FROM), addr(&TO), 0, 2),
+        result(0, 2)
+    );
+    assert_eq!(slc::unpark_all(addr(&FROM)).unparked, 1);
+    assert_eq!(slc::unpark_all(addr(&TO)).unparked, 2);
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn same_address() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let handles: Vec<_> = (0..2).map(|_| spawn_waiter(&WAKE_UP)).collect();
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(
+        slc::unpark_requeue(addr(&WAKE_UP), addr(&WAKE_UP), 1, usize::MAX),
+        result(1, 1)
+    );
+    assert_eq!(slc::unpark_all(addr(&WAKE_UP)).unparked, 1);
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn requeued_waiters_can_time_out() {
+    static FROM: AtomicBool = AtomicBool::new(false);
+    static TO: AtomicBool = AtomicBool::new(false);
+    let h = thread::spawn(|| unsafe {
+        slc::park_timeout(addr(&FROM), || true, Duration::from_millis(200))
+    });
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(
+        slc::unpark_requeue(addr(&FROM), addr(&TO), 0, usize::MAX),
+        result(0, 1)
+    );
+    assert_eq!(h.join().unwrap(), ParkResult::TimedOut);
+    // it must have removed itself from `TO`'s queue
+    assert_eq!(slc::unpark_all(addr(&TO)).unparked, 0);
+}
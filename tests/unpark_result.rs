@@ -0,0 +1,91 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::{addr, result, spawn_waiter};
+
+#[test]
+fn nobody_parked() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)), result(0, false));
+    assert_eq!(slc::unpark_some(addr(&WAKE_UP), 2), result(0, false));
+    assert_eq!(slc::unpark_all(addr(&WAKE_UP)), result(0, false));
+}
+
+#[test]
+fn unpark_one_reports_remaining() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let h1 = spawn_waiter(&WAKE_UP);
+    let h2 = spawn_waiter(&WAKE_UP);
+    // give the waiters time to actually go to sleep
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)), result(1, true));
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)), result(1, false));
+    h1.join().unwrap();
+    h2.join().unwrap();
+}
+
+#[test]
+fn unpark_all_counts() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    let handles: Vec<_> = (0..3).map(|_| spawn_waiter(&WAKE_UP)).collect();
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_all(addr(&WAKE_UP)), result(3, false));
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[test]
+fn other_addresses_are_not_counted() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    static OTHER: AtomicBool = AtomicBool::new(false);
+    let h = spawn_waiter(&WAKE_UP);
+    let other = spawn_waiter(&OTHER);
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(slc::unpark_one(addr(&WAKE_UP)), result(1, false));
+    h.join().unwrap();
+    OTHER.store(true, Release);
+    assert_eq!(slc::unpark_all(addr(&OTHER)), result(1, false));
+    other.join().unwrap();
+}
+
+#[test]
+fn unpark_all_and_then_runs_before_waking() {
+    use core::sync::atomic::AtomicUsize;
+    use core::sync::atomic::Ordering::Relaxed;
+
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    static GENERATION: AtomicUsize = AtomicUsize::new(0);
+    let handles: Vec<_> = (0..3)
+        .map(|_| {
+            thread::spawn(|| {
+                unsafe { slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire)) };
+                assert_eq!(GENERATION.load(Relaxed), 1);
+            })
+        })
+        .collect();
+    thread::sleep(Duration::from_millis(50));
+    let mut calls = 0;
+    let res = slc::unpark_all_and_then(addr(&WAKE_UP), |res| {
+        assert_eq!(res, result(3, false));
+        WAKE_UP.store(true, Release);
+        GENERATION.fetch_add(1, Relaxed);
+        calls += 1;
+    });
+    assert_eq!(res, result(3, false));
+    assert_eq!(calls, 1);
+    for h in handles {
+        h.join().unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Release;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core::{self as slc, UnparkResult};
+
+use common::{addr, spawn_waiter};
+
+#[test]
+fn zero_wakes_nobody() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    assert_eq!(slc::unpark_some(addr(&WAKE_UP), 0), UnparkResult::default());
+    let h = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    // `has_more` isn't determined without the lock
+    assert_eq!(slc::unpark_some(addr(&WAKE_UP), 0), UnparkResult::default());
+    let mut released = false;
+    assert_eq!(
+        slc::unpark_some_release(addr(&WAKE_UP), 0, || released = true),
+        UnparkResult {
+            unparked: 0,
+            has_more: true
+        }
+    );
+    assert!(released);
+    assert!(!h.is_finished());
+    assert_eq!(
+        slc::unpark_some(addr(&WAKE_UP), 1),
+        UnparkResult {
+            unparked: 1,
+            has_more: false
+        }
+    );
+    h.join().unwrap();
+}
+
+#[test]
+fn zero_doesnt_lock() {
+    static ADDR: AtomicBool = AtomicBool::new(false);
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    // the callback runs with the bucket locked
+    let h = thread::spawn(move || {
+        slc::unpark_one_with(addr(&ADDR), |_| {
+            locked_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            slc::DEFAULT_UNPARK_TOKEN
+        })
+    });
+    locked_rx.recv().unwrap();
+    assert_eq!(slc::unpark_some(addr(&ADDR), 0), UnparkResult::default());
+    let lot = slc::ParkingLot::new();
+    assert_eq!(lot.unpark_some(addr(&ADDR), 0), UnparkResult::default());
+    release_tx.send(()).unwrap();
+    h.join().unwrap();
+}
+
+#[test]
+fn returns_woken_count() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    assert_eq!(slc::unpark_some(addr(&WAKE_UP), 3), UnparkResult::default());
+    let h1 = spawn_waiter(&WAKE_UP);
+    let h2 = spawn_waiter(&WAKE_UP);
+    thread::sleep(Duration::from_millis(50));
+    WAKE_UP.store(true, Release);
+    assert_eq!(
+        slc::unpark_some(addr(&WAKE_UP), usize::MAX),
+        UnparkResult {
+            unparked: 2,
+            has_more: false
+        }
+    );
+    h1.join().unwrap();
+    h2.join().unwrap();
+}
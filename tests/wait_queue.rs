@@ -0,0 +1,58 @@
+#![cfg(all(feature = "std", not(loom)))]
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core::WaitQueue;
+
+#[test]
+fn notify_one_wakes_a_waiter() {
+    let ready = Mutex::new(false);
+    let queue = WaitQueue::new();
+    thread::scope(|s| {
+        let waiter = s.spawn(|| {
+            let mut guard = ready.lock().unwrap();
+            while !*guard {
+                queue.wait(|| drop(guard));
+                guard = ready.lock().unwrap();
+            }
+        });
+        thread::sleep(Duration::from_millis(50));
+        *ready.lock().unwrap() = true;
+        queue.notify_one();
+        waiter.join().unwrap();
+    });
+}
+
+#[test]
+fn notifications_during_guard_release_are_kept() {
+    let queue = WaitQueue::new();
+    // the notification comes after `guard_release` started, but before
+    // the thread sleeps, so `wait` returns instead of blocking forever
+    queue.wait(|| queue.notify_one());
+    queue.wait(|| queue.notify_all());
+}
+
+#[test]
+fn notify_all_wakes_every_waiter() {
+    let count = Mutex::new(0);
+    let go = Mutex::new(false);
+    let queue = WaitQueue::new();
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                let mut guard = go.lock().unwrap();
+                while !*guard {
+                    queue.wait(|| drop(guard));
+                    guard = go.lock().unwrap();
+                }
+                *count.lock().unwrap() += 1;
+            });
+        }
+        thread::sleep(Duration::from_millis(50));
+        *go.lock().unwrap() = true;
+        queue.notify_all();
+    });
+    assert_eq!(*count.lock().unwrap(), 4);
+}
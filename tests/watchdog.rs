@@ -0,0 +1,44 @@
+#![cfg(all(feature = "watchdog", not(loom)))]
+
+mod common;
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use sparking_lot_core as slc;
+
+use common::addr;
+
+static HOLDS: Mutex<Vec<slc::LongHold>> = Mutex::new(Vec::new());
+
+#[test]
+fn reports_slow_expected() {
+    static WAKE_UP: AtomicBool = AtomicBool::new(false);
+    slc::set_watchdog(Duration::from_millis(20), |hold| {
+        HOLDS.lock().unwrap().push(*hold)
+    });
+    let line = line!() + 3;
+    let h = thread::spawn(|| unsafe {
+        // doesn't park, but still holds the bucket lock for `expected`
+        slc::park(addr(&WAKE_UP), || {
+            thread::sleep(Duration::from_millis(50));
+            false
+        });
+        // fast, parks and gets woken up
+        slc::park(addr(&WAKE_UP), || !WAKE_UP.load(Acquire));
+    });
+    thread::sleep(Duration::from_millis(100));
+    WAKE_UP.store(true, Release);
+    slc::unpark_one(addr(&WAKE_UP));
+    h.join().unwrap();
+    slc::clear_watchdog();
+
+    let holds = HOLDS.lock().unwrap();
+    assert_eq!(holds.len(), 1, "{holds:?}");
+    assert_eq!(holds[0].location.file(), file!());
+    assert_eq!(holds[0].location.line(), line);
+    assert!(holds[0].held_for >= Duration::from_millis(50));
+}